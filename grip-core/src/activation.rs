@@ -0,0 +1,154 @@
+use crate::config::{self, PROJECT_DIR};
+use crate::error::Result;
+use crate::path::{fish_escape, Shell};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Holds the project bin directory `grip env` put on PATH, so it can be taken
+/// off again on leaving the project
+pub const ACTIVE_VAR: &str = "GRIP_ACTIVE_PROJECT";
+
+/// The bin directory of the nearest project install in `dir` or its parents,
+/// if any. The user install is never taken for one, even under `GRIP_HOME=~/.grip`
+pub fn project_bin_dir(dir: &Path) -> Option<PathBuf> {
+    let user_root = config::data_dir().ok().and_then(|dir| dir.canonicalize().ok());
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_DIR))
+        .filter(|root| root.join("bin").is_dir())
+        .find(|root| root.canonicalize().ok() != user_root)
+        .map(|root| root.join("bin"))
+}
+
+/// The environment a shell in `dir` should have: PATH without the previously
+/// activated project's bin directory, and with the current one's in front
+#[derive(Debug, PartialEq, Eq)]
+pub struct Activation {
+    pub path: Vec<PathBuf>,
+    pub active: Option<PathBuf>,
+}
+
+impl Activation {
+    pub fn new(dir: &Path, current_path: &OsString, previous: Option<&Path>) -> Self {
+        let active = project_bin_dir(dir);
+        let mut path: Vec<PathBuf> = std::env::split_paths(current_path)
+            .filter(|entry| Some(entry.as_path()) != previous && Some(entry) != active.as_ref())
+            .collect();
+        if let Some(active) = &active {
+            path.insert(0, active.clone());
+        }
+        Self { path, active }
+    }
+
+    /// Reads PATH and what was activated before from the environment
+    pub fn from_env() -> Result<Self> {
+        let previous = std::env::var_os(ACTIVE_VAR).filter(|v| !v.is_empty()).map(PathBuf::from);
+        let current_path = std::env::var_os("PATH").unwrap_or_default();
+        Ok(Self::new(&std::env::current_dir()?, &current_path, previous.as_deref()))
+    }
+
+    /// Whether the environment already is this one, in which case there is
+    /// nothing for the shell to do
+    pub fn is_current(&self) -> bool {
+        let previous = std::env::var_os(ACTIVE_VAR).filter(|v| !v.is_empty()).map(PathBuf::from);
+        let current_path = std::env::var_os("PATH").unwrap_or_default();
+        previous == self.active && std::env::split_paths(&current_path).eq(self.path.iter().cloned())
+    }
+
+    /// Statements that set PATH and [`ACTIVE_VAR`] in `shell`. Nushell can't
+    /// evaluate generated code, so its hook loads the JSON form instead
+    pub fn script(&self, shell: Shell) -> String {
+        let joined = std::env::join_paths(&self.path)
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let active = self.active.as_ref().map(|dir| dir.to_string_lossy().into_owned());
+        match shell {
+            Shell::Bash | Shell::Zsh => {
+                let quote = |s: &str| format!("'{}'", s.replace('\'', r"'\''"));
+                let mut script = format!("export PATH={}\n", quote(&joined));
+                match active {
+                    Some(active) => script.push_str(&format!("export {}={}\n", ACTIVE_VAR, quote(&active))),
+                    None => script.push_str(&format!("unset {}\n", ACTIVE_VAR)),
+                }
+                script
+            }
+            Shell::Fish => {
+                let entries: Vec<String> = self
+                    .path
+                    .iter()
+                    .map(|dir| format!("'{}'", fish_escape(&dir.to_string_lossy())))
+                    .collect();
+                let mut script = format!("set -gx PATH {}\n", entries.join(" "));
+                match active {
+                    Some(active) => script.push_str(&format!("set -gx {} '{}'\n", ACTIVE_VAR, fish_escape(&active))),
+                    None => script.push_str(&format!("set -e {}\n", ACTIVE_VAR)),
+                }
+                script
+            }
+            Shell::PowerShell => {
+                let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+                let mut script = format!("$env:PATH = {}\n", quote(&joined));
+                match active {
+                    Some(active) => script.push_str(&format!("$env:{} = {}\n", ACTIVE_VAR, quote(&active))),
+                    None => script.push_str(&format!("Remove-Item Env:{} -ErrorAction SilentlyContinue\n", ACTIVE_VAR)),
+                }
+                script
+            }
+            Shell::Nu => format!("{}\n", self.to_json()),
+        }
+    }
+
+    /// The variables to set, as `load-env` takes them
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "PATH": self.path,
+            ACTIVE_VAR: self.active.as_ref().map(|dir| dir.to_string_lossy().into_owned()).unwrap_or_default(),
+        })
+    }
+}
+
+/// Code for the shell's startup file that runs `grip env` before each prompt,
+/// so changing into a project puts its tools on PATH and leaving takes them off
+pub fn hook_script(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => r#"_grip_hook() {
+  local status=$?
+  eval "$(grip env --shell bash)"
+  return $status
+}
+if [[ ";${PROMPT_COMMAND[*]:-};" != *";_grip_hook;"* ]]; then
+  PROMPT_COMMAND="_grip_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
+"#
+        .to_string(),
+        Shell::Zsh => r#"_grip_hook() {
+  eval "$(grip env --shell zsh)"
+}
+typeset -ag precmd_functions
+if (( ! ${precmd_functions[(I)_grip_hook]} )); then
+  precmd_functions=(_grip_hook $precmd_functions)
+fi
+"#
+        .to_string(),
+        Shell::Fish => r#"function __grip_hook --on-event fish_prompt
+    grip env --shell fish | source
+end
+"#
+        .to_string(),
+        Shell::PowerShell => r#"if (-not $global:__GripPrompt) {
+    $global:__GripPrompt = $function:prompt
+    function global:prompt {
+        grip env --shell powershell | Out-String | Invoke-Expression
+        & $global:__GripPrompt
+    }
+}
+"#
+        .to_string(),
+        Shell::Nu => r#"$env.config = ($env.config | upsert hooks.pre_prompt (
+    ($env.config.hooks.pre_prompt? | default []) | append {||
+        grip env --shell nu | from json | load-env
+    }
+))
+"#
+        .to_string(),
+    }
+}
@@ -0,0 +1,99 @@
+use crate::error::{GripError, Result};
+use crate::package::{Asset, Release};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// OIDC issuer of the certificates GitHub Actions workflows sign with
+pub const GITHUB_ACTIONS_ISSUER: &str = "https://token.actions.githubusercontent.com";
+
+/// How an installed asset's provenance was verified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Attestation {
+    /// A Sigstore bundle published with the release, checked with `cosign`
+    #[serde(rename = "cosign")]
+    Cosign,
+    /// A GitHub artifact attestation, checked with `gh attestation verify`
+    #[serde(rename = "github")]
+    GitHub,
+}
+
+impl std::fmt::Display for Attestation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Attestation::Cosign => write!(f, "cosign"),
+            Attestation::GitHub => write!(f, "github"),
+        }
+    }
+}
+
+/// Finds the Sigstore bundle published for `asset_name`
+pub fn find_bundle_asset<'a>(release: &'a Release, asset_name: &str) -> Option<&'a Asset> {
+    let names = [
+        format!("{}.sigstore.json", asset_name),
+        format!("{}.sigstore", asset_name),
+        format!("{}.bundle", asset_name),
+    ];
+
+    names.iter().find_map(|name| {
+        release
+            .assets
+            .iter()
+            .find(|a| a.name.eq_ignore_ascii_case(name))
+    })
+}
+
+/// The certificate identity expected of assets built by a GitHub repository's
+/// workflows
+pub fn github_identity(host: &str, repo: &str) -> String {
+    format!("^https://{}/{}/", host.replace('.', "\\."), repo)
+}
+
+/// Checks a Sigstore bundle for `file` with `cosign verify-blob`
+pub fn verify_cosign(file: &Path, bundle: &[u8], identity: &str, issuer: &str) -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let bundle_file = dir.path().join("asset.sigstore.json");
+    std::fs::write(&bundle_file, bundle)?;
+
+    let output = Command::new("cosign")
+        .arg("verify-blob")
+        .arg("--bundle")
+        .arg(&bundle_file)
+        .args(["--certificate-identity-regexp", identity])
+        .args(["--certificate-oidc-issuer", issuer])
+        .arg(file)
+        .output()
+        .map_err(|e| GripError::SignatureError(format!("failed to run cosign: {}", e)))?;
+
+    check_output("cosign", file, output)
+}
+
+/// Checks GitHub's artifact attestations for `file` with `gh attestation verify`
+pub fn verify_github(file: &Path, host: &str, repo: &str, token: Option<&str>) -> Result<()> {
+    let mut command = Command::new("gh");
+    command
+        .args(["attestation", "verify"])
+        .arg(file)
+        .args(["--repo", repo, "--hostname", host]);
+    if let Some(token) = token {
+        command.env("GH_TOKEN", token);
+    }
+
+    let output = command
+        .output()
+        .map_err(|e| GripError::SignatureError(format!("failed to run gh: {}", e)))?;
+
+    check_output("gh", file, output)
+}
+
+fn check_output(tool: &str, file: &Path, output: std::process::Output) -> Result<()> {
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(GripError::SignatureError(format!(
+        "{} rejected {}: {}",
+        tool,
+        file.display(),
+        String::from_utf8_lossy(&output.stderr).trim()
+    )))
+}
@@ -0,0 +1,84 @@
+use crate::error::{GripError, Result};
+use crate::registry::provider;
+use crate::state::PackageState;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Version of the bundle format written by `grip export`
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// A set of installed packages, as written by `grip export` and installed by
+/// `grip import` to provision another machine
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Bundle {
+    #[serde(default = "default_format")]
+    pub format: u32,
+    #[serde(default)]
+    pub packages: Vec<BundledPackage>,
+}
+
+fn default_format() -> u32 {
+    BUNDLE_FORMAT_VERSION
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledPackage {
+    pub name: String,
+    /// The active version's tag
+    pub version: String,
+    /// What to install: a registry package name, a repository spec such as
+    /// `gh:owner/repo`, or a direct `url:` or `file:` source
+    pub source: String,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl Bundle {
+    /// The active version of every installed package, sorted by name
+    pub fn from_state(state: &PackageState) -> Self {
+        let mut packages: Vec<BundledPackage> = state
+            .list_packages()
+            .into_iter()
+            .map(|(name, installed)| {
+                let source = match &installed.repository {
+                    Some(repository)
+                        if provider::is_repository_spec(repository)
+                            || provider::is_direct_source(repository) =>
+                    {
+                        repository.clone()
+                    }
+                    _ => name.clone(),
+                };
+                BundledPackage {
+                    name: name.clone(),
+                    version: installed.version.clone(),
+                    source,
+                    pinned: installed.pinned,
+                }
+            })
+            .collect();
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            format: BUNDLE_FORMAT_VERSION,
+            packages,
+        }
+    }
+
+    /// Reads a bundle from a file, or from stdin when `path` is `-`
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = utils::read_input(path)?;
+        let bundle: Self = serde_json::from_str(&content)
+            .map_err(|e| GripError::ConfigError(format!("Failed to parse {}: {}", path.display(), e)))?;
+        if bundle.format > BUNDLE_FORMAT_VERSION {
+            return Err(GripError::ConfigError(format!(
+                "{} uses bundle format {}, but this grip only understands up to {}; upgrade grip",
+                path.display(),
+                bundle.format,
+                BUNDLE_FORMAT_VERSION
+            )));
+        }
+        Ok(bundle)
+    }
+}
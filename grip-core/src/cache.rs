@@ -0,0 +1,165 @@
+use crate::checksum;
+use crate::config::CacheSettings;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ENTRY_FILE: &str = "entry.json";
+
+/// Downloaded release assets, kept so reinstalls don't fetch them again.
+/// Each URL gets a directory named after its sha256 holding the file and an
+/// `entry.json` recording the file's own digest
+pub struct AssetCache {
+    dir: PathBuf,
+    settings: CacheSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub url: String,
+    pub file_name: String,
+    pub sha256: String,
+    pub size: u64,
+    /// Unix timestamps, in seconds
+    pub created: u64,
+    pub last_used: u64,
+}
+
+impl AssetCache {
+    pub fn new(data_dir: &Path, settings: CacheSettings) -> Self {
+        Self {
+            dir: data_dir.join("cache"),
+            settings,
+        }
+    }
+
+    fn entry_dir(&self, url: &str) -> PathBuf {
+        self.dir.join(checksum::sha256_hex(url.as_bytes()))
+    }
+
+    fn read_entry(dir: &Path) -> Option<CacheEntry> {
+        let content = std::fs::read_to_string(dir.join(ENTRY_FILE)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn write_entry(dir: &Path, entry: &CacheEntry) -> Result<()> {
+        std::fs::write(dir.join(ENTRY_FILE), serde_json::to_string_pretty(entry)?)?;
+        Ok(())
+    }
+
+    /// Local files are already on disk, so only remote assets are cached
+    fn is_cacheable(&self, url: &str) -> bool {
+        self.settings.enabled && !url.starts_with("file://")
+    }
+
+    fn is_expired(&self, entry: &CacheEntry) -> bool {
+        self.settings.max_age_days > 0
+            && now().saturating_sub(entry.last_used) > self.settings.max_age_days * 24 * 60 * 60
+    }
+
+    /// Returns the cached copy of `url` if it is present, unexpired and intact
+    pub fn get(&self, url: &str) -> Option<(PathBuf, CacheEntry)> {
+        if !self.is_cacheable(url) {
+            return None;
+        }
+
+        let dir = self.entry_dir(url);
+        let mut entry = Self::read_entry(&dir).filter(|e| e.url == url && !self.is_expired(e))?;
+        let path = dir.join(&entry.file_name);
+
+        // A truncated or tampered file is dropped rather than installed
+        if checksum::sha256_file(&path).ok()? != entry.sha256 {
+            let _ = std::fs::remove_dir_all(&dir);
+            return None;
+        }
+
+        entry.last_used = now();
+        let _ = Self::write_entry(&dir, &entry);
+        Some((path, entry))
+    }
+
+    /// Copies a verified download into the cache, then evicts old entries
+    pub fn put(&self, url: &str, file: &Path, sha256: &str) -> Result<()> {
+        if !self.is_cacheable(url) {
+            return Ok(());
+        }
+
+        let file_name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "asset".to_string());
+        let dir = self.entry_dir(url);
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+        std::fs::create_dir_all(&dir)?;
+        std::fs::copy(file, dir.join(&file_name))?;
+
+        let now = now();
+        Self::write_entry(
+            &dir,
+            &CacheEntry {
+                url: url.to_string(),
+                file_name,
+                sha256: sha256.to_string(),
+                size: std::fs::metadata(file)?.len(),
+                created: now,
+                last_used: now,
+            },
+        )?;
+
+        self.enforce_limits()
+    }
+
+    /// Every cached asset, most recently used first
+    pub fn list(&self) -> Result<Vec<CacheEntry>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for dir in std::fs::read_dir(&self.dir)? {
+            if let Some(entry) = Self::read_entry(&dir?.path()) {
+                entries.push(entry);
+            }
+        }
+        entries.sort_by_key(|e| std::cmp::Reverse(e.last_used));
+        Ok(entries)
+    }
+
+    /// Empties the cache, returning how many entries and bytes were removed
+    pub fn clean(&self) -> Result<(usize, u64)> {
+        let entries = self.list()?;
+        let freed = entries.iter().map(|e| e.size).sum();
+        if self.dir.exists() {
+            std::fs::remove_dir_all(&self.dir)?;
+        }
+        Ok((entries.len(), freed))
+    }
+
+    /// Drops expired entries, then the least recently used ones until the
+    /// cache fits in its size limit
+    fn enforce_limits(&self) -> Result<()> {
+        let mut entries = self.list()?;
+        let max_size = self.settings.max_size_mb * 1024 * 1024;
+        let mut total: u64 = entries.iter().map(|e| e.size).sum();
+
+        while let Some(oldest) = entries.last() {
+            if !self.is_expired(oldest) && (max_size == 0 || total <= max_size) {
+                break;
+            }
+            total -= oldest.size;
+            std::fs::remove_dir_all(self.entry_dir(&oldest.url))?;
+            entries.pop();
+        }
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,138 @@
+use crate::package::Release;
+use crate::resolver;
+use colored::Colorize;
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+static BOLD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\*\*([^*]+)\*\*|__([^_]+)__").unwrap());
+static CODE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"`([^`]+)`").unwrap());
+static LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"!?\[([^\]]*)\]\(([^)\s]+)[^)]*\)").unwrap());
+static COMMENT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<!--.*?-->").unwrap());
+
+/// The releases after `from`, up to and including `to` (the newest when
+/// unset), newest first. Drafts are left out, and prereleases too unless `to`
+/// is one. Tags that aren't versions are placed by their order in `releases`,
+/// which providers list newest first
+pub fn between<'a>(releases: &'a [Release], from: Option<&str>, to: Option<&str>) -> Vec<&'a Release> {
+    let target_is_pre = to
+        .and_then(|to| releases.iter().find(|r| r.tag_name == to))
+        .is_some_and(|r| r.prerelease);
+    let candidates: Vec<&Release> = releases
+        .iter()
+        .filter(|r| !r.draft && (!r.prerelease || target_is_pre))
+        .collect();
+
+    let parsed_from = from.map(resolver::parse_tag);
+    let parsed_to = to.map(resolver::parse_tag);
+    let all_parse = matches!(parsed_from, None | Some(Some(_)))
+        && matches!(parsed_to, None | Some(Some(_)))
+        && candidates.iter().all(|r| resolver::parse_tag(&r.tag_name).is_some());
+
+    if all_parse {
+        let from = parsed_from.flatten();
+        let to = parsed_to.flatten();
+        let mut selected: Vec<&Release> = candidates
+            .into_iter()
+            .filter(|r| {
+                let version = resolver::parse_tag(&r.tag_name);
+                from.as_ref().is_none_or(|from| version.as_ref() > Some(from))
+                    && to.as_ref().is_none_or(|to| version.as_ref() <= Some(to))
+            })
+            .collect();
+        selected.sort_by_key(|r| std::cmp::Reverse(resolver::parse_tag(&r.tag_name)));
+        return selected;
+    }
+
+    let start = to
+        .and_then(|to| candidates.iter().position(|r| r.tag_name == to))
+        .unwrap_or(0);
+    let end = from
+        .and_then(|from| candidates.iter().position(|r| r.tag_name == from))
+        .unwrap_or(candidates.len());
+    candidates.get(start..end).unwrap_or_default().to_vec()
+}
+
+/// Release notes laid out for a terminal, one release after another
+pub fn render_releases(releases: &[&Release]) -> String {
+    let mut out = String::new();
+    for release in releases {
+        let date = release
+            .published_at
+            .as_deref()
+            .and_then(|date| date.get(..10))
+            .map(|date| format!(" ({})", date))
+            .unwrap_or_default();
+        out.push_str(&format!("{}{}\n", release.tag_name.bold().green(), date.dimmed()));
+        match release.body.as_deref().map(str::trim).filter(|body| !body.is_empty()) {
+            Some(body) => out.push_str(&render(body)),
+            None => out.push_str(&format!("    {}\n", "No release notes".dimmed())),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Renders markdown for a terminal, indented: headings in bold, lists with
+/// bullets, code dimmed and links as their text followed by the URL
+pub fn render(markdown: &str) -> String {
+    let markdown = COMMENT.replace_all(markdown, "");
+    let mut out = String::new();
+    let mut in_code = false;
+    let mut blank = true;
+    for line in markdown.lines() {
+        let line = line.trim_end();
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            out.push_str(&format!("        {}\n", line.dimmed()));
+            blank = false;
+            continue;
+        }
+        if trimmed.is_empty() {
+            if !blank {
+                out.push('\n');
+            }
+            blank = true;
+            continue;
+        }
+        blank = false;
+
+        let indent = line.len() - trimmed.len();
+        let rendered = if let Some(heading) = trimmed.strip_prefix('#') {
+            inline(heading.trim_start_matches('#').trim()).bold().to_string()
+        } else if let Some(item) = ["- ", "* ", "+ "].iter().find_map(|bullet| trimmed.strip_prefix(bullet)) {
+            format!("{}• {}", " ".repeat(indent), inline(item))
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            format!("│ {}", inline(quote.trim_start())).dimmed().to_string()
+        } else if trimmed.chars().all(|c| matches!(c, '-' | '*' | '_' | '=')) {
+            continue;
+        } else {
+            format!("{}{}", " ".repeat(indent), inline(trimmed))
+        };
+        out.push_str(&format!("    {}\n", rendered));
+    }
+    while out.ends_with("\n\n") {
+        out.pop();
+    }
+    out
+}
+
+fn inline(text: &str) -> String {
+    let text = LINK.replace_all(text, |c: &Captures| {
+        let label = &c[1];
+        let url = &c[2];
+        if label.is_empty() || label == url {
+            url.underline().to_string()
+        } else {
+            format!("{} {}", label, format!("({})", url).dimmed())
+        }
+    });
+    let text = CODE.replace_all(&text, |c: &Captures| c[1].cyan().to_string());
+    BOLD.replace_all(&text, |c: &Captures| {
+        c.get(1).or(c.get(2)).map_or("", |m| m.as_str()).bold().to_string()
+    })
+    .into_owned()
+}
@@ -0,0 +1,149 @@
+use crate::error::Result;
+use crate::package::{Asset, Release};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::path::Path;
+
+// Release-wide checksum files, in order of preference
+const CHECKSUM_FILES: &[&str] = &[
+    "sha256sums",
+    "sha256sums.txt",
+    "sha256sum.txt",
+    "checksums.txt",
+    "checksums.sha256",
+    "checksums",
+];
+
+/// Computes the hex-encoded sha256 digest of a file
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = StreamingSha256::default();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finish())
+}
+
+/// Computes the hex-encoded blake3 digest of a file
+pub fn blake3_file(path: &Path) -> Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(std::fs::File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Copies a file, computing its sha256 digest on the way rather than reading
+/// it again afterwards
+pub fn copy_with_sha256(from: &Path, to: &Path) -> Result<String> {
+    let mut source = std::fs::File::open(from)?;
+    let mut destination = std::fs::File::create(to)?;
+    let mut hasher = StreamingSha256::default();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = source.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        destination.write_all(&buffer[..read])?;
+    }
+
+    Ok(hasher.finish())
+}
+
+/// A sha256 digest fed a chunk at a time, as a download streams in
+#[derive(Default)]
+pub struct StreamingSha256(Sha256);
+
+impl StreamingSha256 {
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// The hex-encoded digest of everything fed in
+    pub fn finish(self) -> String {
+        self.0.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Computes the hex-encoded sha256 digest of a byte string
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Finds the release asset holding the sha256 digest of `asset_name`, preferring
+/// a per-asset `.sha256` file over a release-wide checksum list
+pub fn find_checksum_asset<'a>(release: &'a Release, asset_name: &str) -> Option<&'a Asset> {
+    let per_asset = [
+        format!("{}.sha256", asset_name),
+        format!("{}.sha256sum", asset_name),
+    ];
+
+    release
+        .assets
+        .iter()
+        .find(|a| per_asset.iter().any(|name| a.name.eq_ignore_ascii_case(name)))
+        .or_else(|| {
+            CHECKSUM_FILES.iter().find_map(|file| {
+                release
+                    .assets
+                    .iter()
+                    .find(|a| a.name.eq_ignore_ascii_case(file))
+            })
+        })
+        .or_else(|| {
+            release.assets.iter().find(|a| {
+                let name = a.name.to_lowercase();
+                name.contains("checksums") || name.contains("sha256sums")
+            })
+        })
+}
+
+/// Extracts the digest for `asset_name` from the contents of a checksum file.
+///
+/// Understands `sha256sum` output (`<digest>  <file>` or `<digest> *<file>`),
+/// BSD-style `SHA256 (<file>) = <digest>` lines, and files containing only a digest.
+pub fn parse_checksum(content: &str, asset_name: &str) -> Option<String> {
+    let is_digest = |s: &str| s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit());
+
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(rest) = line.strip_prefix("SHA256 (") {
+            if let Some((file, digest)) = rest.split_once(") = ") {
+                if file == asset_name && is_digest(digest.trim()) {
+                    return Some(digest.trim().to_lowercase());
+                }
+            }
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let (Some(digest), file) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        if !is_digest(digest) {
+            continue;
+        }
+
+        match file {
+            Some(file) => {
+                let file = file.trim_start_matches('*').trim_start_matches("./");
+                if file == asset_name {
+                    return Some(digest.to_lowercase());
+                }
+            }
+            None if content.lines().filter(|l| !l.trim().is_empty()).count() == 1 => {
+                return Some(digest.to_lowercase());
+            }
+            None => {}
+        }
+    }
+
+    None
+}
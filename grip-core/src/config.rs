@@ -0,0 +1,1113 @@
+use serde::{Deserialize, Serialize};
+use crate::error::{bail, GripError, Result};
+use crate::package::asset_matcher::Libc;
+use crate::storage::StateBackend;
+use crate::ui;
+use base64::Engine;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Registry {
+    pub name: String,
+    pub url: String,
+    pub priority: i32,
+    /// Credentials for the registry's host, used for the index and its assets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<RegistryAuth>,
+    /// Minisign public key the registry's index must be signed with, as the
+    /// bare base64 key or the contents of a `minisign.pub` file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+impl Registry {
+    /// A registry to add to the config. Relative `file://` directories are
+    /// made absolute, since they would otherwise resolve against whatever
+    /// directory grip later runs in
+    pub fn new(name: &str, url: &str, priority: i32, auth: Option<RegistryAuth>) -> Result<Self> {
+        let url = match url.strip_prefix("file://") {
+            Some(path) => format!("file://{}", std::fs::canonicalize(path)?.display()),
+            None => url.to_string(),
+        };
+        Ok(Self {
+            name: name.to_string(),
+            url,
+            priority,
+            auth,
+            public_key: None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RegistryAuth {
+    Bearer { token: String },
+    Basic { username: String, password: String },
+    /// A shell command whose trimmed stdout is used as a bearer token
+    Command { command: String },
+}
+
+impl RegistryAuth {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RegistryAuth::Bearer { .. } => "bearer",
+            RegistryAuth::Basic { .. } => "basic",
+            RegistryAuth::Command { .. } => "command",
+        }
+    }
+
+    /// Builds the `Authorization` header value, running the token command if needed
+    pub fn header_value(&self) -> Result<String> {
+        match self {
+            RegistryAuth::Bearer { token } => Ok(format!("Bearer {}", token)),
+            RegistryAuth::Basic { username, password } => Ok(format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password))
+            )),
+            RegistryAuth::Command { command } => {
+                let output = if cfg!(windows) {
+                    std::process::Command::new("cmd").args(["/C", command]).output()?
+                } else {
+                    std::process::Command::new("sh").args(["-c", command]).output()?
+                };
+
+                let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                if !output.status.success() || token.is_empty() {
+                    return Err(GripError::RegistryError(format!(
+                        "Auth command `{}` did not print a token",
+                        command
+                    )));
+                }
+                Ok(format!("Bearer {}", token))
+            }
+        }
+    }
+}
+
+/// Where a registry's package definitions come from
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistrySource {
+    /// A directory on disk, read in place
+    Local(PathBuf),
+    /// A git repository cloned into the data directory
+    Git(String),
+}
+
+impl Registry {
+    /// Interprets the URL: `file://` paths, `git+<url>` remotes, or a bare
+    /// `host/owner/repo` that is cloned over HTTPS
+    pub fn source(&self) -> RegistrySource {
+        if let Some(path) = self.url.strip_prefix("file://") {
+            RegistrySource::Local(PathBuf::from(path))
+        } else if let Some(url) = self.url.strip_prefix("git+") {
+            RegistrySource::Git(url.to_string())
+        } else if self.url.contains("://") {
+            RegistrySource::Git(self.url.clone())
+        } else {
+            RegistrySource::Git(format!("https://{}.git", self.url))
+        }
+    }
+
+    /// The host that credentials are sent to, if the source has one
+    pub fn host(&self) -> Option<String> {
+        match self.source() {
+            RegistrySource::Git(url) => reqwest::Url::parse(&url)
+                .ok()
+                .and_then(|u| u.host_str().map(str::to_string)),
+            RegistrySource::Local(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Settings {
+    pub install_dir: String,
+    pub auto_update: bool,
+    /// Concurrent downloads when installing several packages
+    #[serde(default = "default_jobs")]
+    pub jobs: usize,
+    /// Refuse to install assets without a signature verified against a key
+    /// from the registry
+    #[serde(default)]
+    pub require_signatures: bool,
+    /// Refuse registries whose index isn't signed with a key from the config
+    #[serde(default)]
+    pub require_signed_registries: bool,
+    /// Check Sigstore attestations for every package, not only those whose
+    /// registry entry opts in
+    #[serde(default)]
+    pub verify_attestations: bool,
+    /// Strip the macOS quarantine attribute from installed executables, so
+    /// Gatekeeper doesn't block them on first run
+    #[serde(default = "default_true")]
+    pub remove_quarantine: bool,
+    /// SPDX ids of licenses to refuse installing, where a trailing `*`
+    /// matches any suffix, e.g. `AGPL-*`
+    #[serde(default)]
+    pub denied_licenses: Vec<String>,
+    /// Make shims scripts that ask grip which version to run, so
+    /// `.grip-versions` and `.tool-versions` files can pin one per directory
+    #[serde(default)]
+    pub version_shims: bool,
+    /// Refuse archives that unpack to more than this many megabytes, which
+    /// guards against decompression bombs; 0 means no limit
+    #[serde(default = "default_max_extracted_size_mb")]
+    pub max_extracted_size_mb: u64,
+    /// Hard link identical installed files to a single read-only copy, on
+    /// Unix; `grip gc` removes copies nothing uses any more
+    #[serde(default = "default_true")]
+    pub dedupe_files: bool,
+    /// Add directories installs need to the user's PATH, in shell startup
+    /// files or the Windows registry; off leaves PATH to the user
+    #[serde(default = "default_true")]
+    pub modify_path: bool,
+    /// Count how often and when each package is run, in `usage.json`, for
+    /// `grip stats` and `grip prune --unused`. Nothing is ever sent anywhere
+    #[serde(default)]
+    pub usage_stats: bool,
+    /// Where the package state and history are kept: `json` files, or a
+    /// `sqlite` database that `list`, `which`, `verify` and `history` query
+    #[serde(default)]
+    pub state_backend: StateBackend,
+}
+
+fn default_jobs() -> usize {
+    4
+}
+
+fn default_max_extracted_size_mb() -> u64 {
+    4096
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Limits for the downloaded asset cache; a limit of 0 means no limit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheSettings {
+    pub enabled: bool,
+    pub max_size_mb: u64,
+    /// Entries unused for this long are evicted
+    pub max_age_days: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_size_mb: 2048,
+            max_age_days: 30,
+        }
+    }
+}
+
+/// How automatic asset selection chooses between builds that all run on this
+/// platform. A project's `grip.json` can override these per package
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssetPreferences {
+    /// `musl` or `gnu`: which libc's builds to prefer on Linux
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub libc: Option<Libc>,
+    /// Prefer builds named `static`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefer_static: Option<bool>,
+    /// Match assets for this architecture instead of the machine's, e.g.
+    /// `x86_64` to run Intel builds on Apple Silicon
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
+    /// Whether x86_64 builds count on Apple Silicon when there is no native
+    /// one; they run through Rosetta
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rosetta: Option<bool>,
+    /// Globs of asset names to choose only when nothing else fits, e.g. `*.deb`
+    pub avoid: Vec<String>,
+}
+
+impl AssetPreferences {
+    /// These preferences with those `overrides` sets taking precedence;
+    /// `avoid` globs from both apply
+    pub fn merged(&self, overrides: &AssetPreferences) -> AssetPreferences {
+        AssetPreferences {
+            libc: overrides.libc.or(self.libc),
+            prefer_static: overrides.prefer_static.or(self.prefer_static),
+            arch: overrides.arch.clone().or_else(|| self.arch.clone()),
+            rosetta: overrides.rosetta.or(self.rosetta),
+            avoid: self.avoid.iter().chain(&overrides.avoid).cloned().collect(),
+        }
+    }
+}
+
+/// Background checks for newer releases of installed packages, off unless
+/// `interval` is set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateCheckSettings {
+    /// How often to check, e.g. `24h` or `7d`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
+}
+
+impl UpdateCheckSettings {
+    /// The interval between checks, or `None` when checking is off
+    pub fn interval(&self) -> Result<Option<std::time::Duration>> {
+        let Some(interval) = &self.interval else {
+            return Ok(None);
+        };
+        match crate::utils::parse_duration(interval) {
+            Some(duration) if duration.is_zero() => Ok(None),
+            Some(duration) => Ok(Some(duration)),
+            None => Err(GripError::ConfigError(format!(
+                "Invalid update_check.interval '{}'; use a number with s, m, h, d or w, e.g. 24h",
+                interval
+            ))),
+        }
+    }
+}
+
+/// Commands run after installs. The user's own hooks always run; steps a
+/// registry declares need confirmation unless `trust_registry` is set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookSettings {
+    /// Run registry-declared post-install steps without asking
+    pub trust_registry: bool,
+    /// Commands run after every install
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub post_install: Vec<String>,
+    /// Commands run after installing the named package
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub packages: HashMap<String, Vec<String>>,
+}
+
+/// Retry and timeout policy for network calls
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// Extra attempts after a transient failure
+    pub retries: u32,
+    /// Per-request timeout; for downloads, the longest wait for the next chunk
+    pub timeout_secs: u64,
+    /// Delay before the first retry, doubled on each further attempt
+    pub backoff_ms: u64,
+    /// Proxy for every request, e.g. `http://proxy.corp:3128`; without it the
+    /// `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` variables apply
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// PEM file of extra root certificates, for proxies that intercept TLS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<PathBuf>,
+    /// How long to wait for a connection, separately from `timeout_secs`
+    pub connect_timeout_secs: u64,
+    /// Idle connections kept open per host for later requests to reuse
+    pub pool_max_idle_per_host: usize,
+    /// How long an idle connection is kept before it is closed
+    pub pool_idle_timeout_secs: u64,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            timeout_secs: 30,
+            backoff_ms: 500,
+            proxy: None,
+            ca_bundle: None,
+            connect_timeout_secs: 30,
+            pool_max_idle_per_host: 8,
+            pool_idle_timeout_secs: 90,
+        }
+    }
+}
+
+impl NetworkSettings {
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.timeout_secs)
+    }
+
+    /// Exponential backoff with up to 50% jitter, so parallel installs don't
+    /// retry in lockstep
+    pub fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let base = self.backoff_ms.saturating_mul(1 << (attempt.saturating_sub(1)).min(10));
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() as u64)
+            .unwrap_or(0);
+        let jitter = if base == 0 { 0 } else { nanos % (base / 2 + 1) };
+        std::time::Duration::from_millis(base + jitter)
+    }
+}
+
+/// Where packages are installed and recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// The user's install root, shared by every project
+    User,
+    /// `./.grip` in the current directory, for tools pinned to a project
+    Project,
+}
+
+/// The directory project-scoped installs live in
+pub const PROJECT_DIR: &str = ".grip";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Config {
+    pub registries: Vec<Registry>,
+    pub default_registry: String,
+    pub cache_ttl: u64,
+    pub settings: Settings,
+    /// Root for installed packages, their shims and the package state, in
+    /// place of the data directory; `GRIP_HOME` takes precedence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub install_dir: Option<String>,
+    #[serde(default)]
+    pub cache: CacheSettings,
+    #[serde(default)]
+    pub network: NetworkSettings,
+    #[serde(default)]
+    pub hooks: HookSettings,
+    #[serde(default)]
+    pub assets: AssetPreferences,
+    #[serde(default)]
+    pub update_check: UpdateCheckSettings,
+    /// Token sent to the GitHub API; `GITHUB_TOKEN` or `GH_TOKEN` take precedence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_token: Option<String>,
+    /// API tokens for other release hosts, e.g. `gitlab.com` or a private instance
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tokens: HashMap<String, String>,
+    /// OSV API `grip audit` queries, for a mirror of https://api.osv.dev
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub osv_url: Option<String>,
+    /// GitHub API that github.com repositories are looked up through, in place
+    /// of https://api.github.com, e.g. a caching proxy or a mock server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_api_url: Option<String>,
+    /// What the user's own config file held, so saving writes back only the
+    /// user's changes and not settings from the other layers
+    #[serde(skip)]
+    layers: Layers,
+}
+
+#[derive(Debug, Default)]
+struct Layers {
+    /// The user's config file
+    file: PathBuf,
+    /// The user's config file as read
+    user: Value,
+    /// The merged config as loaded, to tell what changed before saving
+    loaded: Value,
+}
+
+/// One place settings are read from, lowest precedence first
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigSource {
+    /// `system`, `user`, `project` or `environment`
+    pub layer: &'static str,
+    /// The file, or the variable for an environment override
+    pub location: String,
+    /// Whether the file exists and was read
+    pub loaded: bool,
+}
+
+/// The project config, found in the current directory or its parents
+pub const PROJECT_CONFIG: &str = ".grip.toml";
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            registries: vec![Registry {
+                name: "default".to_string(),
+                url: "github.com/Grip-Packages/Grip-Packages".to_string(),
+                priority: 100,
+                auth: None,
+                public_key: None,
+            }],
+            default_registry: "github.com/Grip-Packages/Grip-Packages".to_string(),
+            cache_ttl: 3600,
+            settings: Settings::default(),
+            install_dir: None,
+            cache: CacheSettings::default(),
+            network: NetworkSettings::default(),
+            hooks: HookSettings::default(),
+            assets: AssetPreferences::default(),
+            update_check: UpdateCheckSettings::default(),
+            github_token: None,
+            tokens: HashMap::new(),
+            osv_url: None,
+            github_api_url: None,
+            layers: Layers::default(),
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            install_dir: "$HOME/.local/bin".to_string(),
+            auto_update: true,
+            jobs: default_jobs(),
+            require_signatures: false,
+            require_signed_registries: false,
+            verify_attestations: false,
+            remove_quarantine: true,
+            denied_licenses: Vec::new(),
+            version_shims: false,
+            max_extracted_size_mb: default_max_extracted_size_mb(),
+            dedupe_files: true,
+            modify_path: true,
+            usage_stats: false,
+            state_backend: StateBackend::Json,
+        }
+    }
+}
+
+impl Config {
+    /// Returns the GitHub token from the environment or the config file
+    pub fn github_token(&self) -> Option<String> {
+        ["GITHUB_TOKEN", "GH_TOKEN"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|token| !token.is_empty())
+            .or_else(|| self.github_token.clone())
+    }
+
+    /// Returns the per-host tokens, with `GITLAB_TOKEN` filling in for gitlab.com
+    /// and `GH_ENTERPRISE_TOKEN` for the GitHub Enterprise host named by `GH_HOST`
+    pub fn host_tokens(&self) -> HashMap<String, String> {
+        let env = |var: &str| std::env::var(var).ok().filter(|v| !v.is_empty());
+
+        let mut tokens = self.tokens.clone();
+        if let Some(token) = env("GITLAB_TOKEN") {
+            tokens.insert("gitlab.com".to_string(), token);
+        }
+        if let (Some(host), Some(token)) = (env("GH_HOST"), env("GH_ENTERPRISE_TOKEN")) {
+            tokens.insert(host, token);
+        }
+        tokens
+    }
+
+    /// Where packages for `scope` are installed and recorded
+    pub fn install_root(&self, scope: Scope, data_dir: &Path) -> Result<PathBuf> {
+        match (scope, &self.install_dir) {
+            (Scope::Project, _) => Ok(std::env::current_dir()?.join(PROJECT_DIR)),
+            (Scope::User, Some(dir)) if std::env::var_os("GRIP_HOME").is_none() => {
+                Ok(PathBuf::from(crate::utils::expand_path(dir)))
+            }
+            (Scope::User, _) => Ok(data_dir.to_path_buf()),
+        }
+    }
+
+    /// The system-wide config, for machines managed as a fleet;
+    /// `GRIP_SYSTEM_CONFIG` names another file
+    pub fn system_path() -> PathBuf {
+        if let Some(path) = std::env::var_os("GRIP_SYSTEM_CONFIG") {
+            return PathBuf::from(path);
+        }
+        if cfg!(windows) {
+            PathBuf::from(std::env::var_os("ProgramData").unwrap_or_else(|| "C:\\ProgramData".into()))
+                .join("grip")
+                .join("config.toml")
+        } else {
+            PathBuf::from("/etc/grip/config.toml")
+        }
+    }
+
+    /// The nearest `.grip.toml` in the current directory or its parents
+    pub fn project_path() -> Option<PathBuf> {
+        let dir = std::env::current_dir().ok()?;
+        dir.ancestors()
+            .map(|dir| dir.join(PROJECT_CONFIG))
+            .find(|path| path.is_file())
+    }
+
+    /// Where the user's config file lives: the file `GRIP_CONFIG` names, or
+    /// `config.toml` when there is one, otherwise `registries.json`
+    pub fn path() -> Result<PathBuf> {
+        if let Some(path) = std::env::var_os("GRIP_CONFIG").filter(|path| !path.is_empty()) {
+            return Ok(PathBuf::from(path));
+        }
+        let dir = dirs::config_dir()
+            .ok_or_else(|| GripError::ConfigError("Failed to get config directory".to_string()))?
+            .join("grip");
+        let toml = dir.join("config.toml");
+        Ok(if toml.exists() { toml } else { dir.join("registries.json") })
+    }
+
+    /// Reads the config in layers, each overriding the one before: grip's
+    /// defaults, the system config, the user's, the project's `.grip.toml`
+    /// and `GRIP_*` environment variables. Registries are merged by name, so
+    /// each layer can add registries or change ones from an earlier layer.
+    /// `user_path` is the user's config file, created when missing
+    pub fn load(user_path: &Path) -> Result<Self> {
+        let config = Self::read(user_path)?;
+        if !user_path.exists() {
+            write_layer(user_path, &config.layers.user)?;
+        }
+        Ok(config)
+    }
+
+    /// Reads the config like [`Config::load`], but leaves a missing user
+    /// config file for [`Config::save`] to create
+    pub fn read(user_path: &Path) -> Result<Self> {
+        let system = read_layer(&Self::system_path())?;
+
+        let user = match read_layer(user_path)? {
+            Some(user) => user,
+            // With a system config the user's starts empty, so grip's
+            // defaults written into it don't mask the system's settings
+            None if system.is_some() => Value::Object(Map::new()),
+            None => serde_json::to_value(Config::default())?,
+        };
+        let project = match Self::project_path() {
+            Some(path) => read_layer(&path)?,
+            None => None,
+        };
+
+        let mut layered = Value::Object(Map::new());
+        for layer in [system, Some(user.clone()), project].into_iter().flatten() {
+            merge(&mut layered, layer, true);
+        }
+        let mut merged = serde_json::to_value(Config::default())?;
+        merge(&mut merged, layered, false);
+        let overrides = env_layer(&merged);
+        merge(&mut merged, overrides, true);
+
+        let mut config: Config = serde_json::from_value(merged)
+            .map_err(|e| GripError::ConfigError(format!("Invalid config: {}", e)))?;
+        config.layers = Layers {
+            file: user_path.to_path_buf(),
+            user,
+            loaded: serde_json::to_value(&config)?,
+        };
+        Ok(config)
+    }
+
+    /// The user's config file this config was loaded from
+    pub fn file(&self) -> &Path {
+        &self.layers.file
+    }
+
+    /// Writes what changed since loading into the user's config file
+    pub fn save(&self) -> Result<()> {
+        let mut user = self.layers.user.clone();
+        apply_changes(&mut user, &self.layers.loaded, &serde_json::to_value(self)?);
+        write_layer(self.file(), &user)
+    }
+
+    /// The files and environment variables settings were read from, lowest
+    /// precedence first
+    pub fn sources(&self) -> Result<Vec<ConfigSource>> {
+        let file = |layer, path: PathBuf| ConfigSource {
+            layer,
+            loaded: path.is_file(),
+            location: path.display().to_string(),
+        };
+        let mut sources = vec![file("system", Self::system_path()), file("user", self.file().to_path_buf())];
+        match Self::project_path() {
+            Some(path) => sources.push(file("project", path)),
+            None => sources.push(ConfigSource {
+                layer: "project",
+                location: PROJECT_CONFIG.to_string(),
+                loaded: false,
+            }),
+        }
+
+        let config = serde_json::to_value(self)?;
+        for (var, path, _) in env_overrides(&config) {
+            let known = path.first().is_some_and(|p| p == "registries") || lookup_path(&config, &path).is_some();
+            if known {
+                sources.push(ConfigSource {
+                    layer: "environment",
+                    location: var,
+                    loaded: true,
+                });
+            }
+        }
+        Ok(sources)
+    }
+
+    /// Looks up a setting by its dotted key, e.g. `network.retries`
+    pub fn get(&self, key: &str) -> Result<Value> {
+        check_settable(key)?;
+        let config = serde_json::to_value(self)?;
+        lookup(&config, key)
+            .cloned()
+            .ok_or_else(|| GripError::Usage(format!("Config key '{}' is unset or unknown", key)))
+    }
+
+    /// Changes a setting by its dotted key. The value is read as JSON when it
+    /// parses as such (so `true`, `8` and `null` work), and as a string
+    /// otherwise or when the setting already holds a string. `null` unsets
+    /// optional settings
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        check_settable(key)?;
+        let unknown = || GripError::Usage(format!("Unknown config key '{}'", key));
+
+        let mut config = serde_json::to_value(&*self)?;
+        let (parent, field) = match key.rsplit_once('.') {
+            Some((parent, field)) => (lookup_mut(&mut config, parent).ok_or_else(unknown)?, field),
+            None => (&mut config, key),
+        };
+        let parent = parent.as_object_mut().ok_or_else(unknown)?;
+
+        let value = match (parent.get(field), serde_json::from_str::<Value>(value)) {
+            (_, Ok(Value::Null)) => Value::Null,
+            (Some(Value::String(_)), _) | (_, Err(_)) => Value::String(value.to_string()),
+            (_, Ok(parsed)) => parsed,
+        };
+        let clears = value.is_null();
+        parent.insert(field.to_string(), value);
+
+        let mut updated: Config = serde_json::from_value(config)
+            .map_err(|e| GripError::Usage(format!("Invalid value for '{}': {}", key, e)))?;
+        updated.layers = std::mem::take(&mut self.layers);
+        // Unknown keys are dropped by serde, so a typo would otherwise be a silent no-op
+        if !clears && lookup(&serde_json::to_value(&updated)?, key).is_none() {
+            return Err(unknown());
+        }
+        updated.update_check.interval().map_err(|e| GripError::Usage(e.to_string()))?;
+        *self = updated;
+        Ok(())
+    }
+
+    /// Every setting as a dotted key and its value, with tokens masked.
+    /// Registries are left out; `grip registry list` shows them
+    pub fn entries(&self) -> Result<Vec<(String, Value)>> {
+        let mut config = serde_json::to_value(self)?;
+        if let Some(config) = config.as_object_mut() {
+            config.remove("registries");
+        }
+        let mut entries = Vec::new();
+        flatten("", &config, &mut entries);
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        for (key, value) in &mut entries {
+            if key == "github_token" || key.starts_with("tokens.") {
+                *value = Value::String("********".to_string());
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Registries carry structure and secrets that `grip registry` manages
+fn check_settable(key: &str) -> Result<()> {
+    if key == "registries" || key.starts_with("registries.") {
+        return Err(GripError::Usage(
+            "Registries are managed with `grip registry add/remove`".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn lookup<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.').try_fold(value, |value, field| value.get(field))
+}
+
+fn lookup_path<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, field| value.get(field))
+}
+
+/// Reads a config file as JSON, or as TOML when it is named `*.toml`.
+/// `None` when it doesn't exist
+fn read_layer(path: &Path) -> Result<Option<Value>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(path)?;
+    parse_layer(&content, is_toml(path))
+        .map(Some)
+        .map_err(|e| GripError::ConfigError(format!("Failed to parse {}: {}", path.display(), e)))
+}
+
+fn parse_layer(content: &str, toml: bool) -> std::result::Result<Value, String> {
+    if toml {
+        toml::from_str(content).map_err(|e| e.to_string())
+    } else {
+        serde_json::from_str(content).map_err(|e| e.to_string())
+    }
+}
+
+fn write_layer(path: &Path, value: &Value) -> Result<()> {
+    let content = if is_toml(path) {
+        // TOML has no null, and unset settings are simply left out
+        let mut value = value.clone();
+        strip_nulls(&mut value);
+        let table = toml::Value::try_from(value)
+            .map_err(|e| GripError::ConfigError(format!("Failed to write {}: {}", path.display(), e)))?;
+        toml::to_string_pretty(&table)
+            .map_err(|e| GripError::ConfigError(format!("Failed to write {}: {}", path.display(), e)))?
+    } else {
+        serde_json::to_string_pretty(value)?
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "toml")
+}
+
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            fields.retain(|_, value| !value.is_null());
+            fields.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+/// Lays `overlay` over `base`, field by field. Registries are matched by
+/// name when `registries_by_name` is set and replaced wholesale otherwise
+fn merge(base: &mut Value, overlay: Value, registries_by_name: bool) {
+    let (Value::Object(base), Value::Object(overlay)) = (&mut *base, &overlay) else {
+        *base = overlay;
+        return;
+    };
+    for (key, value) in overlay.clone() {
+        match base.get_mut(&key) {
+            Some(Value::Array(registries)) if key == "registries" && registries_by_name => {
+                for registry in value.as_array().into_iter().flatten() {
+                    let existing = registries
+                        .iter_mut()
+                        .find(|r| r.get("name").is_some() && r.get("name") == registry.get("name"));
+                    match existing {
+                        Some(existing) => merge(existing, registry.clone(), false),
+                        None => registries.push(registry.clone()),
+                    }
+                }
+            }
+            Some(existing) if existing.is_object() && value.is_object() => {
+                merge(existing, value, registries_by_name)
+            }
+            _ => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Variables grip reads for itself or sets for hooks and plugins, which
+/// would otherwise be taken for settings, as `GRIP_INSTALL_DIR` would
+const RESERVED_VARS: &[&str] = &[
+    "GRIP_HOME",
+    "GRIP_LOG",
+    "GRIP_OFFLINE",
+    "GRIP_SYSTEM_CONFIG",
+    "GRIP_PACKAGE",
+    "GRIP_VERSION",
+    "GRIP_INSTALL_DIR",
+    "GRIP_EXECUTABLE",
+    "GRIP_DATA_DIR",
+    "GRIP_BIN_DIR",
+    "GRIP_STATE",
+    "GRIP_OUTPUT",
+    "GRIP_CONFIG",
+    "GRIP_ACTIVE_PROJECT",
+];
+
+/// `GRIP_<KEY>` variables, with `__` between the parts of a dotted key, as
+/// `(variable, key path, value)`: `GRIP_NETWORK__RETRIES` sets
+/// `network.retries`. `GRIP_REGISTRY__<NAME>__<FIELD>` sets a field of the
+/// registry named `<name>`, adding it when there is none
+fn env_overrides(config: &Value) -> Vec<(String, Vec<String>, String)> {
+    let mut vars: Vec<(String, String)> = std::env::vars_os()
+        .filter_map(|(var, value)| Some((var.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(var, _)| var.starts_with("GRIP_") && !RESERVED_VARS.contains(&var.as_str()))
+        .collect();
+    vars.sort();
+    vars.into_iter()
+        .filter_map(|(var, value)| {
+            let path: Vec<String> = var["GRIP_".len()..].split("__").map(str::to_lowercase).collect();
+            if path.iter().any(String::is_empty) {
+                return None;
+            }
+            let path = match path.first().map(String::as_str) {
+                Some("registry" | "registries") => {
+                    let [_, name, field @ ..] = path.as_slice() else {
+                        return None;
+                    };
+                    // Variable names can't hold the dashes registry names often do
+                    let name = config["registries"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|r| r["name"].as_str())
+                        .find(|n| n.to_lowercase().replace('-', "_") == *name)
+                        .map_or_else(|| name.clone(), str::to_string);
+                    [vec!["registries".to_string(), name], field.to_vec()].concat()
+                }
+                _ => path,
+            };
+            Some((var, path, value))
+        })
+        .collect()
+}
+
+/// The environment overrides as a layer over `config`. Values are read as
+/// JSON unless the setting they replace holds a string or isn't set
+fn env_layer(config: &Value) -> Value {
+    let mut layer = Value::Object(Map::new());
+    for (_, path, raw) in env_overrides(config) {
+        let (value, path) = match path.as_slice() {
+            [registries, name, field @ ..] if registries == "registries" && !field.is_empty() => {
+                let existing = config["registries"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find(|r| r["name"] == name.as_str())
+                    .and_then(|r| lookup_path(r, field));
+                let mut registry = json_object([("name".to_string(), Value::String(name.clone()))]);
+                set_path(&mut registry, field, env_value(existing, &raw));
+                merge(&mut layer, json_object([("registries".to_string(), Value::Array(vec![registry]))]), true);
+                continue;
+            }
+            path => (env_value(lookup_path(config, path), &raw), path),
+        };
+        set_path(&mut layer, path, value);
+    }
+    layer
+}
+
+fn env_value(existing: Option<&Value>, raw: &str) -> Value {
+    match existing {
+        Some(Value::String(_)) | None => Value::String(raw.to_string()),
+        Some(_) => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+    }
+}
+
+fn json_object(fields: impl IntoIterator<Item = (String, Value)>) -> Value {
+    Value::Object(fields.into_iter().collect())
+}
+
+fn set_path(value: &mut Value, path: &[String], new: Value) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut value = value;
+    for field in parents {
+        if !value.is_object() {
+            *value = Value::Object(Map::new());
+        }
+        value = value.as_object_mut().unwrap().entry(field.clone()).or_insert(Value::Null);
+    }
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    value.as_object_mut().unwrap().insert(last.clone(), new);
+}
+
+/// Carries what changed between `before` and `after` into the user's layer,
+/// registries by name
+fn apply_changes(user: &mut Value, before: &Value, after: &Value) {
+    if before == after {
+        return;
+    }
+    let (Value::Object(before), Value::Object(after)) = (before, after) else {
+        *user = after.clone();
+        return;
+    };
+    if !user.is_object() {
+        *user = Value::Object(Map::new());
+    }
+    let user = user.as_object_mut().unwrap();
+    for (key, value) in after {
+        match before.get(key) {
+            Some(old) if old == value => {}
+            Some(Value::Array(old)) if key == "registries" => {
+                let user_registries = user.entry(key.clone()).or_insert_with(|| Value::Array(Vec::new()));
+                apply_registry_changes(user_registries, old, value.as_array().map(Vec::as_slice).unwrap_or_default());
+            }
+            Some(old) => apply_changes(user.entry(key.clone()).or_insert(Value::Null), old, value),
+            None => {
+                user.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    for key in before.keys().filter(|key| !after.contains_key(*key)) {
+        user.remove(key);
+    }
+}
+
+fn apply_registry_changes(user: &mut Value, before: &[Value], after: &[Value]) {
+    if !user.is_array() {
+        *user = Value::Array(Vec::new());
+    }
+    let user = user.as_array_mut().unwrap();
+    for registry in after {
+        if before.contains(registry) {
+            continue;
+        }
+        match user.iter_mut().find(|r| r["name"] == registry["name"]) {
+            Some(existing) => *existing = registry.clone(),
+            None => user.push(registry.clone()),
+        }
+    }
+    for registry in before {
+        if !after.iter().any(|r| r["name"] == registry["name"]) {
+            user.retain(|r| r["name"] != registry["name"]);
+        }
+    }
+}
+
+fn lookup_mut<'a>(value: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    key.split('.').try_fold(value, |value, field| value.get_mut(field))
+}
+
+fn flatten(prefix: &str, value: &Value, entries: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(fields) => {
+            for (field, value) in fields {
+                let key = if prefix.is_empty() {
+                    field.clone()
+                } else {
+                    format!("{}.{}", prefix, field)
+                };
+                flatten(&key, value, entries);
+            }
+        }
+        value => entries.push((prefix.to_string(), value.clone())),
+    }
+}
+
+/// Parses an edited config, rejecting keys serde would silently drop
+fn parse_edited(content: &str, toml: bool) -> Result<()> {
+    let edited = parse_layer(content, toml).map_err(GripError::ConfigError)?;
+    check_layer(&edited)
+}
+
+/// Writes settings to a project's `.grip.toml`, once they check out
+pub fn write_project_config(path: &Path, settings: &Value) -> Result<()> {
+    check_layer(settings)?;
+    write_layer(path, settings)
+}
+
+/// Checks the settings of one layer, which only has to hold what it changes
+fn check_layer(edited: &Value) -> Result<()> {
+    let mut merged = serde_json::to_value(Config::default())?;
+    merge(&mut merged, edited.clone(), false);
+    let config: Config = serde_json::from_value(merged)?;
+
+    let mut known = Vec::new();
+    flatten("", &serde_json::to_value(&config)?, &mut known);
+    let mut entries = Vec::new();
+    flatten("", edited, &mut entries);
+    let unknown: Vec<String> = entries
+        .into_iter()
+        .filter(|(key, value)| !value.is_null() && !known.iter().any(|(k, _)| k == key))
+        .map(|(key, _)| key)
+        .collect();
+    if !unknown.is_empty() {
+        bail!(ConfigError, "Unknown config keys: {}", unknown.join(", "));
+    }
+    Ok(())
+}
+
+/// Opens the config file in `$VISUAL` or `$EDITOR` and only saves the result
+/// once it parses, offering to edit again when it doesn't. Works on a copy so
+/// an invalid config is never left behind, even when the current one is
+/// broken and grip can't otherwise start
+pub fn edit(path: &Path, non_interactive: bool) -> Result<()> {
+    if !path.exists() {
+        Config::load(path)?;
+    }
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+
+    let draft = tempfile::Builder::new()
+        .prefix("grip-config-")
+        .suffix(if is_toml(path) { ".toml" } else { ".json" })
+        .tempfile()?;
+    std::fs::copy(path, draft.path())?;
+
+    loop {
+        // The editor may carry its own arguments, as in `code --wait`
+        let mut words = editor.split_whitespace();
+        let program = words.next().unwrap_or("vi");
+        let status = std::process::Command::new(program)
+            .args(words)
+            .arg(draft.path())
+            .status()
+            .map_err(|e| GripError::Other(format!("Failed to run editor '{}': {}", editor, e)))?;
+        if !status.success() {
+            bail!("Editor '{}' exited with {}; config left unchanged", editor, status);
+        }
+
+        let content = std::fs::read_to_string(draft.path())?;
+        match parse_edited(&content, is_toml(path)) {
+            Ok(()) => {
+                std::fs::write(path, content)?;
+                ui::success!("Saved {}", path.display());
+                return Ok(());
+            }
+            Err(e) => {
+                ui::failure!("Invalid config: {}", e);
+                let retry = !non_interactive
+                    && dialoguer::Confirm::new()
+                        .with_prompt("Edit again?")
+                        .default(true)
+                        .interact()?;
+                if !retry {
+                    bail!(ConfigError, "Config left unchanged; the edited copy was invalid");
+                }
+            }
+        }
+    }
+}
+
+/// Where grip keeps its cache, registries and logs, and by default installed
+/// packages and their state. `GRIP_DATA_DIR` moves it, and `GRIP_HOME` moves
+/// it along with packages the config installs elsewhere
+pub fn data_dir() -> Result<PathBuf> {
+    for var in ["GRIP_DATA_DIR", "GRIP_HOME"] {
+        if let Some(dir) = std::env::var_os(var).filter(|dir| !dir.is_empty()) {
+            return Ok(PathBuf::from(dir));
+        }
+    }
+    Ok(dirs::data_local_dir()
+        .ok_or_else(|| GripError::ConfigError("Failed to get local data directory".to_string()))?
+        .join("grip"))
+}
+
+/// Where one run of grip reads its config and keeps its data
+#[derive(Debug, Clone)]
+pub struct Locations {
+    /// The user's config file
+    pub config_file: PathBuf,
+    /// See [`data_dir`]
+    pub data_dir: PathBuf,
+}
+
+impl Locations {
+    /// The config file and data directory given, as with `--config` and
+    /// `--data-dir`, and otherwise those from the environment or the
+    /// platform's defaults. Relative paths are made absolute, since links
+    /// into the data directory are followed from anywhere
+    pub fn resolve(config_file: Option<PathBuf>, data_dir: Option<PathBuf>) -> Result<Self> {
+        let config_file = match config_file {
+            Some(file) => file,
+            None => Config::path()?,
+        };
+        let data_dir = match data_dir {
+            Some(dir) => dir,
+            None => self::data_dir()?,
+        };
+        Ok(Self {
+            config_file: std::path::absolute(config_file)?,
+            data_dir: std::path::absolute(data_dir)?,
+        })
+    }
+}
@@ -0,0 +1,381 @@
+use crate::error::{GripError, Result};
+use crate::package::{Asset, Release};
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Ranges closer than this are fetched in one request, since a request costs
+/// more than a few blocks of data
+const MERGE_GAP: u64 = 64 * 1024;
+
+/// A companion asset that lets an upgrade reuse the previous version's
+/// download rather than fetching the whole artifact again
+pub enum Companion<'a> {
+    /// A zsync control file: block checksums of the new asset, so blocks the
+    /// old one already has are copied and only the rest are downloaded
+    Zsync(&'a Asset),
+    /// A zstd patch against the previous version's asset, as made by
+    /// `zstd --patch-from=<previous asset> <asset> -o <asset>.delta`
+    Delta(&'a Asset),
+}
+
+/// The companion published next to `asset`, preferring zsync, which works
+/// from any earlier version, over a patch made against one in particular
+pub fn companion<'a>(release: &'a Release, asset: &Asset) -> Option<Companion<'a>> {
+    let find = |suffix: &str| {
+        let name = format!("{}{}", asset.name, suffix);
+        release.assets.iter().find(|a| a.name == name)
+    };
+    find(".zsync")
+        .map(Companion::Zsync)
+        .or_else(|| find(".delta").map(Companion::Delta))
+}
+
+/// Rebuilds the new asset from `old` and a zstd patch made against it.
+/// Patches carry a checksum of their output, so one made against another
+/// version fails rather than producing a corrupt file
+pub fn apply_patch(old: &[u8], patch: &[u8], output: &Path) -> Result<()> {
+    use std::io::Write;
+    use zstd::zstd_safe::{DCtx, DParameter, InBuffer, OutBuffer};
+
+    let fail = |stage: &str, code: usize| {
+        GripError::DownloadError(format!(
+            "Failed to {} patch: {}",
+            stage,
+            zstd::zstd_safe::get_error_name(code)
+        ))
+    };
+    let mut context = DCtx::create();
+    // Patches of large files reach back further than the default window allows
+    context
+        .set_parameter(DParameter::WindowLogMax(31))
+        .map_err(|code| fail("configure", code))?;
+    context.ref_prefix(old).map_err(|code| fail("load", code))?;
+
+    let mut file = std::io::BufWriter::new(std::fs::File::create(output)?);
+    let mut input = InBuffer::around(patch);
+    let mut chunk = vec![0u8; 1 << 20];
+    loop {
+        let mut out = OutBuffer::around(&mut chunk[..]);
+        let remaining = context
+            .decompress_stream(&mut out, &mut input)
+            .map_err(|code| fail("apply", code))?;
+        let written = out.pos();
+        file.write_all(&chunk[..written])?;
+        if remaining == 0 && input.pos() == patch.len() {
+            break;
+        }
+        if written == 0 && input.pos() == patch.len() {
+            return Err(GripError::DownloadError("Patch is truncated".to_string()));
+        }
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// A parsed `.zsync` control file
+pub struct ControlFile {
+    block_size: usize,
+    length: u64,
+    /// Consecutive blocks that must match before one counts, which lets
+    /// the checksums be shorter
+    seq_matches: usize,
+    rsum_mask: u16,
+    checksum_bytes: usize,
+    /// Where the new asset is, relative to the control file
+    pub url: Option<String>,
+    sha1: String,
+    blocks: Vec<BlockSum>,
+}
+
+struct BlockSum {
+    rsum: (u16, u16),
+    checksum: Vec<u8>,
+}
+
+impl ControlFile {
+    pub fn parse(content: &[u8]) -> Result<Self> {
+        let invalid = |problem: &str| GripError::DownloadError(format!("Invalid zsync file: {}", problem));
+
+        let mut headers = HashMap::new();
+        let mut offset = 0;
+        loop {
+            let end = content[offset..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .ok_or_else(|| invalid("no end to its headers"))?;
+            let line = String::from_utf8_lossy(&content[offset..offset + end]).trim_end().to_string();
+            offset += end + 1;
+            if line.is_empty() {
+                break;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                headers.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+
+        let number = |key: &str| -> Result<u64> {
+            headers
+                .get(key)
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| invalid(&format!("missing {}", key)))
+        };
+        if !headers.contains_key("URL") && headers.contains_key("Z-URL") {
+            return Err(invalid("only compressed targets are listed, which grip can't rebuild"));
+        }
+        let block_size = number("Blocksize")? as usize;
+        let length = number("Length")?;
+        let hash_lengths: Vec<usize> = headers
+            .get("Hash-Lengths")
+            .map(|v| v.split(',').filter_map(|n| n.trim().parse().ok()).collect())
+            .unwrap_or_else(|| vec![1, 4, 16]);
+        let [seq_matches, rsum_bytes, checksum_bytes] = hash_lengths[..] else {
+            return Err(invalid("bad Hash-Lengths"));
+        };
+        if block_size == 0 || !(1..=2).contains(&seq_matches) || !(1..=4).contains(&rsum_bytes) || !(3..=16).contains(&checksum_bytes) {
+            return Err(invalid("bad block size or Hash-Lengths"));
+        }
+        let sha1 = headers
+            .get("SHA-1")
+            .ok_or_else(|| invalid("missing SHA-1"))?
+            .to_lowercase();
+
+        let entry = rsum_bytes + checksum_bytes;
+        // Length comes from the server, so a huge one mustn't overflow
+        let end = usize::try_from(length.div_ceil(block_size as u64))
+            .ok()
+            .and_then(|count| count.checked_mul(entry))
+            .and_then(|size| offset.checked_add(size))
+            .ok_or_else(|| invalid("bad Length"))?;
+        let sums = content
+            .get(offset..end)
+            .ok_or_else(|| invalid("truncated block checksums"))?;
+        let blocks = sums
+            .chunks(entry)
+            .map(|chunk| {
+                // The rsum is stored big-endian with its leading bytes dropped
+                let mut rsum = [0u8; 4];
+                rsum[4 - rsum_bytes..].copy_from_slice(&chunk[..rsum_bytes]);
+                BlockSum {
+                    rsum: (u16::from_be_bytes([rsum[0], rsum[1]]), u16::from_be_bytes([rsum[2], rsum[3]])),
+                    checksum: chunk[rsum_bytes..].to_vec(),
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            block_size,
+            length,
+            seq_matches,
+            rsum_mask: match rsum_bytes {
+                1 | 2 => 0,
+                3 => 0xff,
+                _ => 0xffff,
+            },
+            checksum_bytes,
+            url: headers.get("URL").cloned(),
+            sha1,
+            blocks,
+        })
+    }
+
+    /// The size of the new asset
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// Finds the new asset's blocks in `old`, returning where in `old` each
+    /// one is, if anywhere
+    pub fn locate(&self, old: &[u8]) -> Vec<Option<usize>> {
+        let block_size = self.block_size;
+        let mut found = vec![None; self.blocks.len()];
+        let mut by_rsum: HashMap<(u16, u16), Vec<usize>> = HashMap::new();
+        for (i, block) in self.blocks.iter().enumerate() {
+            by_rsum.entry(block.rsum).or_default().push(i);
+        }
+
+        // The new asset's last block is padded with zeros, so the old one is too
+        let mut padded = old.to_vec();
+        padded.resize(old.len() + block_size, 0);
+        if padded.len() < block_size {
+            return found;
+        }
+
+        let mut position = 0;
+        let (mut a, mut b) = rsum(&padded[..block_size]);
+        while position + block_size <= padded.len() {
+            let mut matched = false;
+            if let Some(candidates) = by_rsum.get(&(a & self.rsum_mask, b)) {
+                let checksum = md4(&padded[position..position + block_size]);
+                for &i in candidates {
+                    if found[i].is_none() && self.matches_from(&padded, position, i, &checksum) {
+                        found[i] = Some(position);
+                        matched = true;
+                    }
+                }
+            }
+
+            let next = if matched { position + block_size } else { position + 1 };
+            if next + block_size > padded.len() {
+                break;
+            }
+            if matched {
+                (a, b) = rsum(&padded[next..next + block_size]);
+            } else {
+                let (out, into) = (padded[position] as u16, padded[position + block_size] as u16);
+                a = a.wrapping_sub(out).wrapping_add(into);
+                b = b.wrapping_sub((block_size as u16).wrapping_mul(out)).wrapping_add(a);
+            }
+            position = next;
+        }
+        found
+    }
+
+    /// Whether block `i` is at `position`, with as many following blocks as
+    /// the control file asks to match too
+    fn matches_from(&self, data: &[u8], position: usize, i: usize, checksum: &[u8; 16]) -> bool {
+        if checksum[..self.checksum_bytes] != self.blocks[i].checksum[..] {
+            return false;
+        }
+        (1..self.seq_matches).all(|k| {
+            let (Some(block), Some(window)) = (
+                self.blocks.get(i + k),
+                data.get(position + k * self.block_size..position + (k + 1) * self.block_size),
+            ) else {
+                // There is nothing after the last block to compare
+                return true;
+            };
+            let (a, b) = rsum(window);
+            (a & self.rsum_mask, b) == block.rsum && md4(window)[..self.checksum_bytes] == block.checksum[..]
+        })
+    }
+
+    /// Byte ranges of the new asset, end exclusive, that `found` lacks,
+    /// merged where close together
+    pub fn missing_ranges(&self, found: &[Option<usize>]) -> Vec<(u64, u64)> {
+        let block_size = self.block_size as u64;
+        let mut ranges: Vec<(u64, u64)> = Vec::new();
+        for (i, _) in found.iter().enumerate().filter(|(_, f)| f.is_none()) {
+            let start = i as u64 * block_size;
+            let end = (start + block_size).min(self.length);
+            match ranges.last_mut() {
+                Some(last) if start - last.1 <= MERGE_GAP => last.1 = end,
+                _ => ranges.push((start, end)),
+            }
+        }
+        ranges
+    }
+
+    /// Writes the new asset from the blocks found in `old` and the downloaded
+    /// `ranges`, then checks it against the control file's SHA-1
+    pub fn assemble(&self, old: &[u8], found: &[Option<usize>], ranges: &[((u64, u64), Vec<u8>)], output: &Path) -> Result<()> {
+        let mut data = vec![0u8; self.blocks.len() * self.block_size];
+        for (i, position) in found.iter().enumerate() {
+            if let Some(position) = position {
+                let available = old.len().saturating_sub(*position).min(self.block_size);
+                data[i * self.block_size..i * self.block_size + available]
+                    .copy_from_slice(&old[*position..*position + available]);
+            }
+        }
+        for ((start, end), bytes) in ranges {
+            if bytes.len() as u64 != end - start {
+                return Err(GripError::DownloadError(format!(
+                    "Expected {} bytes of the asset, got {}",
+                    end - start,
+                    bytes.len()
+                )));
+            }
+            data[*start as usize..*end as usize].copy_from_slice(bytes);
+        }
+        data.truncate(self.length as usize);
+
+        let actual: String = Sha1::digest(&data).iter().map(|b| format!("{:02x}", b)).collect();
+        if actual != self.sha1 {
+            return Err(GripError::DownloadError(
+                "Rebuilt asset doesn't match its zsync file's SHA-1".to_string(),
+            ));
+        }
+        std::fs::write(output, data)?;
+        Ok(())
+    }
+}
+
+/// zsync's weak checksum of a block
+fn rsum(block: &[u8]) -> (u16, u16) {
+    let len = block.len() as u16;
+    block.iter().enumerate().fold((0u16, 0u16), |(a, b), (i, &c)| {
+        (
+            a.wrapping_add(c as u16),
+            b.wrapping_add(len.wrapping_sub(i as u16).wrapping_mul(c as u16)),
+        )
+    })
+}
+
+/// An MD4 round: its mixing function, constant, word order and shifts
+type Md4Round = (fn(u32, u32, u32) -> u32, u32, [usize; 16], [u32; 4]);
+
+const MD4_ROUNDS: [Md4Round; 3] = [
+    (
+        |x, y, z| (x & y) | (!x & z),
+        0,
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+        [3, 7, 11, 19],
+    ),
+    (
+        |x, y, z| (x & y) | (x & z) | (y & z),
+        0x5a827999,
+        [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15],
+        [3, 5, 9, 13],
+    ),
+    (
+        |x, y, z| x ^ y ^ z,
+        0x6ed9eba1,
+        [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15],
+        [3, 9, 11, 15],
+    ),
+];
+
+/// MD4 (RFC 1320), which zsync uses for its strong block checksums
+fn md4(data: &[u8]) -> [u8; 16] {
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    for chunk in message.chunks(64) {
+        let x: Vec<u32> = chunk
+            .chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+
+        for (f, constant, order, shifts) in MD4_ROUNDS {
+            for (step, &k) in order.iter().enumerate() {
+                let shift = shifts[step % 4];
+                let value = |w: u32, x1: u32, y: u32, z: u32| {
+                    w.wrapping_add(f(x1, y, z)).wrapping_add(x[k]).wrapping_add(constant).rotate_left(shift)
+                };
+                match step % 4 {
+                    0 => a = value(a, b, c, d),
+                    1 => d = value(d, a, b, c),
+                    2 => c = value(c, d, a, b),
+                    _ => b = value(b, c, d, a),
+                }
+            }
+        }
+
+        state[0] = state[0].wrapping_add(a);
+        state[1] = state[1].wrapping_add(b);
+        state[2] = state[2].wrapping_add(c);
+        state[3] = state[3].wrapping_add(d);
+    }
+
+    let mut digest = [0u8; 16];
+    for (i, word) in state.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
@@ -0,0 +1,77 @@
+use crate::error::{GripError, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// A registry package's dependency on another, written `name` or
+/// `name <requirement>`, e.g. `pcre2 >=10.40`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,
+    pub requirement: Option<String>,
+}
+
+impl Dependency {
+    pub fn parse(spec: &str) -> Self {
+        let spec = spec.trim();
+        match spec.split_once(char::is_whitespace) {
+            Some((name, requirement)) if !requirement.trim().is_empty() => Self {
+                name: name.to_string(),
+                requirement: Some(requirement.trim().to_string()),
+            },
+            _ => Self {
+                name: spec.to_string(),
+                requirement: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.requirement {
+            Some(requirement) => write!(f, "{} {}", self.name, requirement),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Orders every package reachable from `roots` so each comes after all of its
+/// dependencies, failing on a cycle
+pub fn install_order(graph: &BTreeMap<String, Vec<Dependency>>, roots: &[String]) -> Result<Vec<String>> {
+    fn visit(
+        name: &str,
+        graph: &BTreeMap<String, Vec<Dependency>>,
+        path: &mut Vec<String>,
+        done: &mut HashSet<String>,
+        order: &mut Vec<String>,
+    ) -> Result<()> {
+        if done.contains(name) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|n| n == name) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(name.to_string());
+            return Err(GripError::InstallError(format!(
+                "Dependency cycle: {}",
+                cycle.join(" -> ")
+            )));
+        }
+
+        path.push(name.to_string());
+        for dependency in graph.get(name).into_iter().flatten() {
+            visit(&dependency.name, graph, path, done, order)?;
+        }
+        path.pop();
+
+        done.insert(name.to_string());
+        order.push(name.to_string());
+        Ok(())
+    }
+
+    let mut done = HashSet::new();
+    let mut order = Vec::new();
+    for root in roots {
+        visit(root, graph, &mut Vec::new(), &mut done, &mut order)?;
+    }
+    Ok(order)
+}
@@ -0,0 +1,113 @@
+pub type Result<T, E = GripError> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GripError {
+    #[error("Package not found: {0}")]
+    PackageNotFound(String),
+    #[error("Registry not found: {0}")]
+    RegistryNotFound(String),
+    #[error("Version not found: {0}")]
+    VersionNotFound(String),
+    #[error("Asset not found: {0}")]
+    AssetNotFound(String),
+    #[error("Download error: {0}")]
+    DownloadError(String),
+    #[error("Installation failed: {0}")]
+    InstallError(String),
+    #[error("Checksum mismatch: {0}")]
+    ChecksumMismatch(String),
+    #[error("Signature verification failed: {0}")]
+    SignatureError(String),
+    #[error("Failed to extract archive {0}")]
+    ExtractionError(String),
+    #[error("GitHub API rate limit exceeded: {0}")]
+    RateLimited(String),
+    #[error("Registry error: {0}")]
+    RegistryError(String),
+    #[error("{0} is not available offline")]
+    Offline(String),
+    /// A config, state, manifest or lock file that can't be read or is invalid
+    #[error("{0}")]
+    ConfigError(String),
+    /// Arguments that don't make sense together
+    #[error("{0}")]
+    Usage(String),
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Request error: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Invalid JSON: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Prompt failed: {0}")]
+    PromptError(#[from] dialoguer::Error),
+    /// The SQLite state database failed, e.g. locked for too long or damaged
+    #[error("State database error: {0}")]
+    DatabaseError(#[from] rusqlite::Error),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl GripError {
+    /// The process exit code for this kind of failure. These are stable so
+    /// scripts can tell, say, a missing package from a network outage
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            GripError::Usage(_) => 2,
+            GripError::PackageNotFound(_) => 3,
+            GripError::VersionNotFound(_) => 4,
+            GripError::AssetNotFound(_) => 5,
+            GripError::RegistryNotFound(_) => 6,
+            GripError::RegistryError(_) => 7,
+            GripError::RequestError(_) | GripError::DownloadError(_) => 8,
+            GripError::RateLimited(_) => 9,
+            GripError::Offline(_) => 10,
+            GripError::ChecksumMismatch(_) => 11,
+            GripError::SignatureError(_) => 12,
+            GripError::ExtractionError(_) => 13,
+            GripError::ConfigError(_) | GripError::JsonError(_) => 14,
+            GripError::InstallError(_)
+            | GripError::IoError(_)
+            | GripError::PromptError(_)
+            | GripError::DatabaseError(_)
+            | GripError::Other(_) => 1,
+        }
+    }
+
+    /// A short, stable name for the kind of failure, used in JSON output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GripError::PackageNotFound(_) => "package_not_found",
+            GripError::RegistryNotFound(_) => "registry_not_found",
+            GripError::VersionNotFound(_) => "version_not_found",
+            GripError::AssetNotFound(_) => "asset_not_found",
+            GripError::DownloadError(_) | GripError::RequestError(_) => "network",
+            GripError::InstallError(_) => "install_failed",
+            GripError::ChecksumMismatch(_) => "checksum_mismatch",
+            GripError::SignatureError(_) => "signature_invalid",
+            GripError::ExtractionError(_) => "extraction_failed",
+            GripError::RateLimited(_) => "rate_limited",
+            GripError::RegistryError(_) => "registry_error",
+            GripError::Offline(_) => "offline",
+            GripError::ConfigError(_) | GripError::JsonError(_) => "invalid_config",
+            GripError::Usage(_) => "usage",
+            GripError::IoError(_) => "io",
+            GripError::PromptError(_) => "prompt",
+            GripError::DatabaseError(_) => "database",
+            GripError::Other(_) => "other",
+        }
+    }
+}
+
+/// Returns early with a [`GripError::Other`], or another variant when given
+/// one, formatting the message like `format!`
+#[macro_export]
+macro_rules! bail {
+    ($variant:ident, $($arg:tt)*) => {
+        return Err($crate::error::GripError::$variant(format!($($arg)*)))
+    };
+    ($($arg:tt)*) => {
+        return Err($crate::error::GripError::Other(format!($($arg)*)))
+    };
+}
+
+pub use crate::bail;
@@ -0,0 +1,156 @@
+use crate::error::Result;
+use crate::path::Shell;
+use std::path::{Path, PathBuf};
+
+/// Directories release archives keep shell completions in
+const COMPLETION_DIRS: &[&str] = &[
+    "completions", "completion", "complete", "autocomplete", "shell-completions", "shell_completions",
+];
+
+/// How deep in a version directory completions and man pages are looked for,
+/// so a vendored tree isn't mistaken for the package's own
+const MAX_DEPTH: usize = 4;
+
+/// Where grip links the completions and man pages of active versions. Man
+/// finds `share/man` next to the bin directory on PATH by itself
+pub fn share_dir(root: &Path) -> PathBuf {
+    root.join("share")
+}
+
+/// The directory of completions for `shell` that `grip shellenv` loads
+pub fn completions_dir(root: &Path, shell: Shell) -> PathBuf {
+    share_dir(root).join("completions").join(shell.name())
+}
+
+/// The shell completions and man pages in an installed version, each with
+/// where it goes under the share directory, e.g. `completions/zsh/_rg` or
+/// `man/man1/rg.1`
+pub fn find(version_dir: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut found: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for path in crate::utils::walk_files(version_dir)? {
+        let Ok(relative) = path.strip_prefix(version_dir) else {
+            continue;
+        };
+        let Some(name) = relative.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        if relative.components().count() > MAX_DEPTH || !path.is_file() {
+            continue;
+        }
+
+        let in_completions = relative
+            .parent()
+            .into_iter()
+            .flat_map(Path::components)
+            .any(|c| COMPLETION_DIRS.contains(&c.as_os_str().to_string_lossy().to_lowercase().as_str()));
+        let destination = if in_completions {
+            completion_destination(relative, &name)
+        } else {
+            man_section(&name)
+                .filter(|_| is_man_page(&path))
+                .map(|section| Path::new("man").join(format!("man{}", section)).join(&name))
+        };
+        // The shallowest copy wins where a name turns up twice
+        if let Some(destination) = destination {
+            if !found.iter().any(|(_, d)| *d == destination) {
+                found.push((path, destination));
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Where a completion script goes, named the way each shell loads it on
+/// demand: `_<command>` for zsh and `<command>.fish` for fish
+fn completion_destination(relative: &Path, name: &str) -> Option<PathBuf> {
+    let parent = relative
+        .parent()
+        .and_then(Path::file_name)
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let (shell, file) = if let Some(command) = name.strip_suffix(".fish") {
+        ("fish", format!("{}.fish", command))
+    } else if parent == "fish" {
+        ("fish", format!("{}.fish", name))
+    } else if let Some(command) = name.strip_suffix(".zsh") {
+        ("zsh", format!("_{}", command.trim_start_matches('_')))
+    } else if (name.starts_with('_') && !name.contains('.')) || parent == "zsh" {
+        ("zsh", format!("_{}", name.trim_start_matches('_')))
+    } else if let Some(command) = name.strip_suffix(".bash").or_else(|| name.strip_suffix(".bash-completion")) {
+        ("bash", command.to_string())
+    } else if parent == "bash" {
+        ("bash", name.to_string())
+    } else {
+        // PowerShell, Elvish and Nushell scripts have nowhere to be loaded from
+        return None;
+    };
+    Some(Path::new("completions").join(shell).join(file))
+}
+
+/// The section of a man page named like `rg.1` or `openssl.1ssl.gz`
+fn man_section(name: &str) -> Option<char> {
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    // Shared libraries are versioned the same way
+    if name.contains(".so.") {
+        return None;
+    }
+    let (stem, extension) = name.rsplit_once('.')?;
+    let section = extension.chars().next()?;
+    (!stem.is_empty() && ('1'..='9').contains(&section) && extension[1..].chars().all(|c| c.is_ascii_alphabetic()))
+        .then_some(section)
+}
+
+/// Whether a file starts like roff source or is gzipped, as man pages are
+fn is_man_page(path: &Path) -> bool {
+    use std::io::Read;
+    let mut start = [0u8; 2];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut start))
+        .is_ok_and(|()| start == [0x1f, 0x8b] || start[0] == b'.' || start[0] == b'\'')
+}
+
+/// Links the completions and man pages found in `version_dir` into the share
+/// directory, replacing what another version linked there. Returns the links
+/// made, relative to the share directory
+#[cfg(unix)]
+pub fn link(root: &Path, version_dir: &Path) -> Result<Vec<PathBuf>> {
+    let share_dir = share_dir(root);
+    let mut linked = Vec::new();
+    for (source, destination) in find(version_dir)? {
+        let link = share_dir.join(&destination);
+        if let Some(parent) = link.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp = link.with_file_name(format!(
+            ".{}.tmp",
+            destination.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        if temp.symlink_metadata().is_ok() {
+            std::fs::remove_file(&temp)?;
+        }
+        crate::utils::create_symlink(&source, &temp)?;
+        std::fs::rename(&temp, &link)?;
+        linked.push(destination);
+    }
+    Ok(linked)
+}
+
+/// Completions and man pages are only linked where shells and man read them
+#[cfg(not(unix))]
+pub fn link(_root: &Path, _version_dir: &Path) -> Result<Vec<PathBuf>> {
+    Ok(Vec::new())
+}
+
+/// Removes links `link` made to files under `owner_dir`, leaving any that
+/// another package has since taken over
+pub fn unlink(root: &Path, links: &[PathBuf], owner_dir: &Path) -> Result<()> {
+    let share_dir = share_dir(root);
+    for link in links {
+        let path = share_dir.join(link);
+        let ours = std::fs::read_link(&path).is_ok_and(|target| target.starts_with(owner_dir));
+        if ours {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
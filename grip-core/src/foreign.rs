@@ -0,0 +1,188 @@
+use crate::error::{GripError, Result};
+use crate::package::Package;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Package lists exported by other package managers that `grip import --from`
+/// understands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForeignFormat {
+    /// A Homebrew `Brewfile`, as written by `brew bundle dump`
+    Brewfile,
+    /// The JSON written by `scoop export`, or its older one-app-per-line text
+    Scoop,
+    /// The JSON written by `winget export`
+    Winget,
+}
+
+impl ForeignFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            ForeignFormat::Brewfile => "Brewfile",
+            ForeignFormat::Scoop => "scoop",
+            ForeignFormat::Winget => "winget",
+        }
+    }
+}
+
+/// One package from a foreign list, with the grip names it might go by, most
+/// likely first
+#[derive(Debug, Clone)]
+pub struct ForeignEntry {
+    /// The name as the other package manager knows it
+    pub name: String,
+    pub candidates: Vec<String>,
+}
+
+/// Reads the packages out of a foreign package list
+pub fn parse(format: ForeignFormat, content: &str) -> Result<Vec<ForeignEntry>> {
+    match format {
+        ForeignFormat::Brewfile => Ok(parse_brewfile(content)),
+        ForeignFormat::Scoop => parse_scoop(content),
+        ForeignFormat::Winget => parse_winget(content),
+    }
+}
+
+/// `brew "name"` and `cask "name"` lines; taps, App Store apps and editor
+/// extensions aren't packages grip could install
+fn parse_brewfile(content: &str) -> Vec<ForeignEntry> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let rest = line.strip_prefix("brew ").or_else(|| line.strip_prefix("cask "))?;
+            let quote = rest.trim_start().chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let name = rest.trim_start()[1..].split(quote).next()?;
+            // Tapped formulae are `owner/tap/formula`, versioned ones `formula@1.2`
+            let formula = name.rsplit('/').next()?;
+            let formula = formula.split('@').next()?;
+            Some(ForeignEntry {
+                name: name.to_string(),
+                candidates: vec![formula.to_lowercase()],
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct ScoopExport {
+    #[serde(default)]
+    apps: Vec<ScoopApp>,
+}
+
+#[derive(Deserialize)]
+struct ScoopApp {
+    #[serde(rename = "Name")]
+    name: String,
+}
+
+fn parse_scoop(content: &str) -> Result<Vec<ForeignEntry>> {
+    let names: Vec<String> = if content.trim_start().starts_with('{') {
+        let export: ScoopExport = serde_json::from_str(content)
+            .map_err(|e| GripError::ConfigError(format!("Invalid scoop export: {}", e)))?;
+        export.apps.into_iter().map(|app| app.name).collect()
+    } else {
+        // Older scoop writes `name (v:1.2.3) [bucket]` per line
+        content
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .map(str::to_string)
+            .collect()
+    };
+
+    Ok(names
+        .into_iter()
+        .map(|name| ForeignEntry {
+            candidates: vec![name.to_lowercase()],
+            name,
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct WingetExport {
+    #[serde(rename = "Sources", default)]
+    sources: Vec<WingetSource>,
+}
+
+#[derive(Deserialize)]
+struct WingetSource {
+    #[serde(rename = "Packages", default)]
+    packages: Vec<WingetPackage>,
+}
+
+#[derive(Deserialize)]
+struct WingetPackage {
+    #[serde(rename = "PackageIdentifier")]
+    id: String,
+}
+
+/// Identifiers look like `Publisher.Name` or `Publisher.Name.Variant`, which
+/// often lines up with a GitHub `owner/repo`
+fn parse_winget(content: &str) -> Result<Vec<ForeignEntry>> {
+    let export: WingetExport = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+        .map_err(|e| GripError::ConfigError(format!("Invalid winget export: {}", e)))?;
+
+    Ok(export
+        .sources
+        .into_iter()
+        .flat_map(|source| source.packages)
+        .map(|package| {
+            let id = package.id.to_lowercase();
+            let mut candidates = Vec::new();
+            if let Some((publisher, rest)) = id.split_once('.') {
+                let name = rest.split('.').next().unwrap_or(rest);
+                candidates.push(format!("{}/{}", publisher, name));
+                candidates.push(name.to_string());
+            }
+            candidates.push(id.replace('.', "-"));
+            ForeignEntry {
+                name: package.id,
+                candidates,
+            }
+        })
+        .collect())
+}
+
+/// Lowercased names and `owner/repo` paths of registry packages, for matching
+/// foreign entries against
+#[derive(Debug, Default)]
+pub struct PackageIndex {
+    names: HashMap<String, String>,
+    /// `owner/repo` and bare `repo`, which only count when no name matches
+    repositories: HashMap<String, String>,
+}
+
+impl PackageIndex {
+    /// Indexes packages in priority order; earlier packages win on conflicts
+    pub fn add(&mut self, package: &Package) {
+        let name = &package.info.name;
+        self.names.entry(name.to_lowercase()).or_insert_with(|| name.clone());
+
+        let repository = package.info.repository.to_lowercase();
+        let path = repository
+            .split_once(':')
+            .filter(|(_, rest)| !rest.starts_with("//"))
+            .map_or(repository.as_str(), |(_, rest)| rest)
+            .trim_end_matches('/')
+            .trim_end_matches(".git");
+        let segments: Vec<&str> = path.rsplit('/').take(2).collect();
+        if let [repo, owner] = segments.as_slice() {
+            self.repositories
+                .entry(format!("{}/{}", owner, repo))
+                .or_insert_with(|| name.clone());
+            self.repositories
+                .entry(repo.to_string())
+                .or_insert_with(|| name.clone());
+        }
+    }
+
+    /// The grip package an entry most likely corresponds to
+    pub fn find(&self, entry: &ForeignEntry) -> Option<&str> {
+        entry
+            .candidates
+            .iter()
+            .find_map(|candidate| self.names.get(candidate).or_else(|| self.repositories.get(candidate)))
+            .map(String::as_str)
+    }
+}
@@ -0,0 +1,60 @@
+use crate::error::{GripError, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// The extended attribute macOS puts on downloaded files, which makes
+/// Gatekeeper check them the first time they run
+pub const QUARANTINE_ATTRIBUTE: &str = "com.apple.quarantine";
+
+/// Removes the quarantine attribute from `file`, returning whether it had one
+pub fn remove_quarantine(file: &Path) -> Result<bool> {
+    let has_attribute = Command::new("xattr")
+        .args(["-p", QUARANTINE_ATTRIBUTE])
+        .arg(file)
+        .output()
+        .map_err(|e| GripError::Other(format!("Failed to run xattr: {}", e)))?
+        .status
+        .success();
+    if !has_attribute {
+        return Ok(false);
+    }
+
+    let output = Command::new("xattr")
+        .args(["-d", QUARANTINE_ATTRIBUTE])
+        .arg(file)
+        .output()
+        .map_err(|e| GripError::Other(format!("Failed to run xattr: {}", e)))?;
+    if !output.status.success() {
+        return Err(GripError::Other(format!(
+            "Failed to remove the quarantine attribute from {}: {}",
+            file.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(true)
+}
+
+/// Why Gatekeeper would refuse to run `executable` once quarantined: it is
+/// unsigned, or signed but not notarized. `None` when it would run, or when
+/// `codesign` and `spctl` aren't available to tell
+pub fn assess(executable: &Path) -> Option<&'static str> {
+    let signed = Command::new("codesign")
+        .args(["--verify", "--strict"])
+        .arg(executable)
+        .output()
+        .ok()?
+        .status
+        .success();
+    if !signed {
+        return Some("is not signed");
+    }
+
+    let notarized = Command::new("spctl")
+        .args(["--assess", "--type", "open", "--context", "context:primary-signature"])
+        .arg(executable)
+        .output()
+        .ok()?
+        .status
+        .success();
+    (!notarized).then_some("is signed but not notarized")
+}
@@ -0,0 +1,5616 @@
+use crate::attestation::{self, Attestation};
+use crate::bundle::Bundle;
+use crate::cache::AssetCache;
+use crate::config::{self, AssetPreferences, Config, Locations, Registry, RegistryAuth, RegistrySource, Scope};
+use crate::dependency::{self, Dependency};
+use crate::error::{bail, GripError, Result};
+use crate::foreign::{self, ForeignFormat, PackageIndex};
+use crate::gatekeeper;
+use crate::history::{Action, Transaction};
+use crate::hooks::{self, HookContext};
+use crate::installer::{self, InstallerKind, InstallerRecord};
+use crate::install_manifest::{self, InstallManifest};
+use crate::integrity::{Change, FileManifest};
+use crate::lock::{self, FileLock};
+use crate::package::asset_matcher::{self, AssetMatch, Libc, Platform};
+use crate::package::{Asset, Package, PackageInfo, Release};
+use crate::project::{self, LockedPackage, Lockfile, Manifest, ProjectDependency};
+use crate::registry::publish::{self, Published};
+use crate::registry::{self, provider, validate, RegistryManager};
+use crate::state::{InstalledPackage, PackageState};
+use crate::storage::{self, StateStore};
+use crate::store::Store;
+use crate::template::{self, Template};
+use crate::timings::{Phase, Span, Timings};
+use crate::sbom::{self, SbomFormat};
+use crate::path::{PruneReason, Shell};
+use crate::{changelog, checksum, delta, extras, license, osv, path, picker, plugin, resolver, search, shim, signature, ui, update_check, usage, utils, version_file};
+use colored::Colorize;
+use dialoguer::{Confirm, Input};
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::rename;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+
+/// When grip is allowed to ask the user to choose between alternatives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromptMode {
+    /// Always prompt, even when a choice could be made automatically
+    Always,
+    /// Choose automatically and prompt only when the choice is ambiguous
+    WhenAmbiguous,
+    /// Never prompt; fail when a choice can't be made automatically
+    Never,
+}
+
+/// Platforms `grip publish` reports release asset coverage for
+const PUBLISHED_PLATFORMS: &[Platform] = &[
+    Platform { os: "linux", arch: "x86_64", libc: Some(Libc::Gnu) },
+    Platform { os: "linux", arch: "aarch64", libc: Some(Libc::Gnu) },
+    Platform { os: "macos", arch: "x86_64", libc: None },
+    Platform { os: "macos", arch: "aarch64", libc: None },
+    Platform { os: "windows", arch: "x86_64", libc: None },
+];
+
+/// A difference between the project install and what grip.json and grip.lock
+/// ask for, found by `grip sync`
+#[derive(Serialize)]
+struct Drift {
+    name: String,
+    /// The active version, or `None` when the package isn't installed
+    installed: Option<String>,
+    /// The locked version or requirement, or `None` when nothing needs the package
+    wanted: Option<String>,
+}
+
+/// One result of `grip doctor`, with a suggested fix when something is wrong
+#[derive(Serialize)]
+struct Finding {
+    check: String,
+    ok: bool,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix: Option<String>,
+}
+
+impl Finding {
+    fn ok(check: &str, detail: impl Into<String>) -> Self {
+        Self {
+            check: check.to_string(),
+            ok: true,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn problem(check: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            check: check.to_string(),
+            ok: false,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// What `install` settled on before anything is downloaded
+struct PlannedInstall {
+    name: String,
+    package: Package,
+    release: Release,
+    asset: Asset,
+}
+
+/// What `upgrade` settled on for a package with a newer release
+struct PlannedUpgrade {
+    install: PlannedInstall,
+    current_version: String,
+    current_path: PathBuf,
+    /// The package's releases, for the notes between the two versions
+    releases: Vec<Release>,
+}
+
+/// A downloaded, verified and unpacked release waiting to be moved into place
+struct StagedInstall {
+    staging: tempfile::TempDir,
+    /// Executables found in the staging directory, the main one first
+    relative_executables: Vec<PathBuf>,
+    sha256: String,
+    attestation: Option<Attestation>,
+    /// A Windows installer to run once the asset, kept as downloaded, is in place
+    installer: Option<InstallerKind>,
+    license: Option<String>,
+}
+
+/// What an install put on disk
+#[derive(Serialize)]
+pub struct InstallOutcome {
+    /// The release tag that was installed
+    pub tag: String,
+    pub asset: Asset,
+    /// Digest of the downloaded asset
+    pub sha256: String,
+    /// The version directory the release was unpacked into
+    pub install_path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<Attestation>,
+    /// Set when nothing was installed because of `--dry-run`, in which case
+    /// `sha256` is empty
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    /// Set when the release was already the active version and was left as is
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub already_installed: bool,
+}
+
+/// The order `grip list` shows packages in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListSort {
+    Name,
+    /// Largest first
+    Size,
+    /// Most recently installed first
+    Installed,
+}
+
+/// A grip session: the loaded config and package state, and everything needed
+/// to install, upgrade and remove packages
+pub struct Grip {
+    pub config: Config,
+    pub registry_manager: RegistryManager,
+    cache: AssetCache,
+    store: Store,
+    /// Loaded on first use, since many commands only look up a few packages
+    /// in the state store
+    package_state: OnceLock<PackageState>,
+    state_store: Box<dyn StateStore>,
+    /// Where packages, their shims and the package state live for the scope
+    /// grip was opened with
+    pub root: PathBuf,
+    scope: Scope,
+    /// Skips post-install hooks and registry post-install steps
+    pub no_hooks: bool,
+    /// Installs the active version again when it is asked for, instead of
+    /// reporting it as already installed
+    pub reinstall: bool,
+    /// Set while `grip rollback` reinstalls a version, so the history records
+    /// a rollback rather than a downgrade
+    rolling_back: bool,
+    /// Resolves installs, upgrades and uninstalls and prints what they would
+    /// change, without downloading anything or touching packages or the state
+    pub dry_run: bool,
+    /// How long each step of the run took, for `--timings`
+    pub timings: Arc<Timings>,
+    /// The digests and source repositories grip.json pins its dependencies
+    /// to, while a project install runs
+    pins: BTreeMap<String, ProjectDependency>,
+    non_interactive: bool,
+}
+
+impl Grip {
+    pub async fn new(
+        non_interactive: bool,
+        quiet: bool,
+        offline: bool,
+        timeout: Option<u64>,
+        scope: Scope,
+        locations: &Locations,
+    ) -> Result<Self> {
+        let data_dir = locations.data_dir.clone();
+
+        std::fs::create_dir_all(&data_dir)?;
+
+        let config = Config::load(&locations.config_file)?;
+        let root = config.install_root(scope, &data_dir)?;
+        std::fs::create_dir_all(&root)?;
+        provider::set_github_api_url(config.github_api_url.as_deref());
+        let mut registry_manager = RegistryManager::new(data_dir.clone(), config.github_token());
+        registry_manager.quiet = quiet;
+        registry_manager.offline = offline;
+        registry_manager.require_signed = config.settings.require_signed_registries;
+        let mut network = config.network.clone();
+        if let Some(timeout) = timeout {
+            network.timeout_secs = timeout;
+        }
+        registry_manager.set_network(network)?;
+        registry_manager.cache_ttl = std::time::Duration::from_secs(config.cache_ttl);
+        registry_manager.set_registry_auth(&config.registries);
+        registry_manager.host_tokens = config.host_tokens();
+        let state_store = storage::open(config.settings.state_backend, &root)?;
+
+        let cache = AssetCache::new(&data_dir, config.cache.clone());
+        let store = Store::new(&data_dir);
+        let timings = registry_manager.timings.clone();
+
+        Ok(Self {
+            config,
+            registry_manager,
+            cache,
+            store,
+            package_state: OnceLock::new(),
+            state_store,
+            root,
+            scope,
+            no_hooks: false,
+            reinstall: false,
+            rolling_back: false,
+            dry_run: false,
+            timings,
+            pins: BTreeMap::new(),
+            non_interactive,
+        })
+    }
+
+    /// Revalidates registries and release lists rather than trusting copies
+    /// newer than `cache_ttl`
+    pub fn set_refresh(&mut self, refresh: bool) {
+        self.registry_manager.refresh = refresh;
+    }
+
+    /// Puts `dir` on the user's PATH. Project installs are left off it, since
+    /// they only make sense inside the project
+    async fn add_to_path(&self, dir: &Path) -> Result<()> {
+        match self.scope {
+            Scope::User if !self.config.settings.modify_path => {
+                let on_path = std::env::var_os("PATH")
+                    .is_some_and(|paths| std::env::split_paths(&paths).any(|entry| entry == dir));
+                if !on_path {
+                    ui::status!("Add {} to PATH to run it; `grip shellenv` prints how", dir.display());
+                }
+                Ok(())
+            }
+            Scope::User => path::add_to_path(dir).await,
+            Scope::Project => {
+                ui::status!(
+                    "Project installs aren't added to PATH; use `grip --local run` or add {} to PATH",
+                    dir.display()
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Links a shim in the bin directory: a symlink to `executable`, or with
+    /// `version_shims` a script that lets version files choose the version.
+    /// `usage_stats` needs the script too, since a symlink never reaches grip
+    /// to count the run. Project installs always get symlinks, since grip.lock
+    /// pins those
+    fn link_shim(&self, bin_dir: &Path, name: &str, package_name: &str, executable: &Path) -> Result<PathBuf> {
+        let settings = &self.config.settings;
+        if (settings.version_shims || settings.usage_stats) && self.scope == Scope::User {
+            let grip = std::env::current_exe()?;
+            shim::link_versioned(bin_dir, name, package_name, executable, &grip)
+        } else {
+            shim::link(bin_dir, name, executable)
+        }
+    }
+
+    /// Runs a Windows installer asset once the user confirms, returning the
+    /// executables it put in place and what it registered. Suggests a portable
+    /// asset from the same release first, since grip manages those fully
+    fn run_installer(
+        &self,
+        package_name: &str,
+        package: &Package,
+        release: &Release,
+        asset: &Asset,
+        file: &Path,
+        kind: InstallerKind,
+    ) -> Result<(Vec<PathBuf>, InstallerRecord)> {
+        let others = release.assets.iter().filter(|a| a.name != asset.name);
+        if let AssetMatch::Found(portable) = asset_matcher::best_match(others, &Platform::current()) {
+            if asset_matcher::is_archive(&portable.name) {
+                ui::warning!(
+                    "{} is an installer; {} looks like a portable build grip can manage itself (--asset {})",
+                    asset.name,
+                    portable.name,
+                    portable.name
+                );
+            }
+        }
+        if self.non_interactive {
+            bail!(
+                InstallError,
+                "{} is an installer, which only runs once confirmed; run grip interactively or pick another asset with --asset",
+                asset.name
+            );
+        }
+
+        let how = if kind.is_silent() { "silently" } else { "(it may ask you questions)" };
+        let confirmed = Confirm::new()
+            .with_prompt(format!("Run the {} installer {}?", asset.name, how))
+            .default(true)
+            .interact()?;
+        if !confirmed {
+            bail!(Usage, "Installation of {} cancelled", package_name);
+        }
+
+        ui::status!("Running {}", asset.name);
+        let record = installer::run(file, kind)?;
+        if record.uninstall.is_none() {
+            ui::warning!(
+                "Couldn't tell what {} installed; `grip uninstall {}` won't remove it",
+                asset.name,
+                package_name
+            );
+        }
+
+        let mut executables = Vec::new();
+        if let Some(location) = &record.location {
+            for name in package.info.executables(&Platform::current(), package_name)? {
+                if let Some(path) = utils::find_executable(location, &name)? {
+                    if !executables.contains(&path) {
+                        executables.push(path);
+                    }
+                }
+            }
+        }
+        Ok((executables, record))
+    }
+
+    /// Strips the macOS quarantine attribute from a new install's executables,
+    /// or from every binary in it when none was found, and warns about those
+    /// Gatekeeper would still refuse to run
+    fn clear_quarantine(&self, install_dir: &Path, executables: &[PathBuf]) -> Result<()> {
+        let files = if executables.is_empty() {
+            utils::walk_files(install_dir)?
+                .into_iter()
+                .filter(|file| utils::is_binary(file))
+                .collect()
+        } else {
+            executables.iter().filter(|e| e.is_file()).cloned().collect::<Vec<_>>()
+        };
+
+        for file in &files {
+            if !self.config.settings.remove_quarantine {
+                if let Some(problem) = gatekeeper::assess(file) {
+                    ui::warning!("{} {}; Gatekeeper may block it on first run", file.display(), problem);
+                }
+                continue;
+            }
+            match gatekeeper::remove_quarantine(file) {
+                Ok(removed) => tracing::debug!(file = %file.display(), removed, "cleared quarantine"),
+                Err(e) => ui::warning!("{}", e),
+            }
+            if let Some(problem) = gatekeeper::assess(file) {
+                ui::warning!(
+                    "{} {}; it runs because grip removed its quarantine attribute",
+                    file.display(),
+                    problem
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes a package directory `add_to_path` put on the user's PATH off it
+    fn remove_from_path(&self, dir: &Path) -> Result<()> {
+        if self.scope == Scope::User && path::remove_from_path(dir)? {
+            ui::success!("Removed {} from PATH", dir.display());
+        }
+        Ok(())
+    }
+
+    /// Every configured registry, or only the one named by `--registry`
+    fn registries(&self, only: Option<&str>) -> Result<Vec<config::Registry>> {
+        match only {
+            Some(name) => {
+                let registry = self
+                    .config
+                    .registries
+                    .iter()
+                    .find(|r| r.name == name)
+                    .ok_or_else(|| GripError::RegistryNotFound(name.to_string()))?;
+                Ok(vec![registry.clone()])
+            }
+            None => Ok(self.config.registries.clone()),
+        }
+    }
+
+    /// Locks a package against other grip processes changing its files, then
+    /// drops the loaded package state so decisions are made on the latest copy
+    fn lock_package(&mut self, package_name: &str) -> Result<FileLock> {
+        let data_dir = &self.root;
+        let lock = FileLock::acquire(&lock::package_lock_path(data_dir, package_name), package_name)?;
+        self.package_state = OnceLock::new();
+        Ok(lock)
+    }
+
+    /// Every installed package, read from the state store the first time it
+    /// is needed
+    pub fn package_state(&self) -> Result<&PackageState> {
+        if let Some(state) = self.package_state.get() {
+            return Ok(state);
+        }
+        let state = self.state_store.load()?;
+        Ok(self.package_state.get_or_init(|| state))
+    }
+
+    /// Changes the package state through [`PackageState::update`] and keeps
+    /// the result as the loaded state
+    fn update_state<T>(&mut self, f: impl FnOnce(&mut PackageState) -> Result<T>) -> Result<T> {
+        self.package_state = OnceLock::new();
+        let (state, value) = PackageState::update(self.state_store.as_ref(), f)?;
+        self.package_state = OnceLock::from(state);
+        Ok(value)
+    }
+
+    fn prompt_mode(&self, interactive: bool) -> Result<PromptMode> {
+        match (interactive, self.non_interactive) {
+            (true, true) => {
+                bail!(Usage, "--interactive can't be used in non-interactive mode")
+            }
+            (true, false) => Ok(PromptMode::Always),
+            (false, false) => Ok(PromptMode::WhenAmbiguous),
+            (false, true) => Ok(PromptMode::Never),
+        }
+    }
+
+    pub async fn install(
+        &mut self,
+        package_name: &str,
+        version: Option<String>,
+        asset: Option<String>,
+        interactive: bool,
+        pre: bool,
+        registry: Option<&str>,
+    ) -> Result<InstallOutcome> {
+        self.install_dependencies(&[package_name.to_string()], registry)
+            .await?;
+        let plan = self
+            .plan_install(package_name, version, asset, interactive, pre, registry)
+            .await?;
+        if let Some(outcome) = self.existing_install(&plan.name, &plan.release, &plan.asset)? {
+            return Ok(outcome);
+        }
+
+        let outcome = self
+            .install_release(&plan.name, &plan.package, &plan.release, &plan.asset)
+            .await?;
+
+        if !self.dry_run {
+            ui::success!("Installation complete!");
+        }
+        Ok(outcome)
+    }
+
+    /// Installs several packages: every release and asset is chosen first, so
+    /// prompts come before any download, then up to `jobs` download at once
+    pub async fn install_many(
+        &mut self,
+        specs: &[String],
+        jobs: usize,
+        interactive: bool,
+        pre: bool,
+        registry: Option<&str>,
+    ) -> Result<Vec<(String, InstallOutcome)>> {
+        let names: Vec<String> = specs.iter().map(|spec| project::parse_spec(spec).0).collect();
+        self.install_dependencies(&names, registry).await?;
+
+        let mut failed = Vec::new();
+        let mut plans = Vec::new();
+        for spec in specs {
+            let (name, version) = project::parse_spec(spec);
+            match self.plan_install(&name, version, None, interactive, pre, registry).await {
+                Ok(plan) if plans.iter().any(|p: &PlannedInstall| p.name == plan.name) => {}
+                Ok(plan) => plans.push(plan),
+                Err(e) => {
+                    ui::failure!("Failed to resolve {}: {}", name.cyan(), e);
+                    failed.push(name);
+                }
+            }
+        }
+
+        let mut existing = Vec::new();
+        let mut pending = Vec::new();
+        for plan in plans {
+            match self.existing_install(&plan.name, &plan.release, &plan.asset)? {
+                Some(outcome) => existing.push((plan.name.clone(), outcome)),
+                None => pending.push(plan),
+            }
+        }
+        let plans = pending;
+
+        if self.dry_run {
+            let mut planned = existing;
+            for plan in plans {
+                let outcome = self
+                    .describe_install(&plan.name, &plan.package, &plan.release, &plan.asset)
+                    .await?;
+                planned.push((plan.name, outcome));
+            }
+            if !failed.is_empty() {
+                bail!(InstallError, "Failed to resolve: {}", failed.join(", "));
+            }
+            return Ok(planned);
+        }
+
+        let this = &*self;
+        let staged: Vec<_> = futures_util::stream::iter(plans)
+            .map(|plan| async move {
+                let staged = this
+                    .stage_release(&plan.name, &plan.package, &plan.release, &plan.asset)
+                    .await;
+                (plan, staged)
+            })
+            .buffer_unordered(jobs.max(1))
+            .collect()
+            .await;
+
+        let mut installed = existing;
+        for (plan, staged) in staged {
+            let result = match staged {
+                Ok(staged) => {
+                    self.finish_install(&plan.name, &plan.package, &plan.release, &plan.asset, staged)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(outcome) => installed.push((plan.name, outcome)),
+                Err(e) => {
+                    ui::failure!("Failed to install {}: {}", plan.name.cyan(), e);
+                    failed.push(plan.name);
+                }
+            }
+        }
+
+        if !failed.is_empty() {
+            bail!(InstallError, "Failed to install: {}", failed.join(", "));
+        }
+
+        if !self.dry_run {
+            let count = installed.iter().filter(|(_, outcome)| !outcome.already_installed).count();
+            ui::success!("Installed {} packages", count);
+        }
+        Ok(installed)
+    }
+
+    /// Installs the dependencies of `roots`, and theirs, that are missing or
+    /// don't satisfy what depends on them, each after its own dependencies
+    async fn install_dependencies(&mut self, roots: &[String], registry: Option<&str>) -> Result<()> {
+        let registries = self.registries(registry)?;
+        let mut graph: BTreeMap<String, Vec<Dependency>> = BTreeMap::new();
+        let mut queue: Vec<String> = roots.to_vec();
+        while let Some(name) = queue.pop() {
+            if graph.contains_key(&name) {
+                continue;
+            }
+            let dependencies = if provider::is_repository_spec(&name) {
+                Vec::new()
+            } else {
+                match self.registry_manager.find_package(&registries, &name).await {
+                    Ok(package) => package.info.dependencies.iter().map(|d| Dependency::parse(d)).collect(),
+                    // A missing root is reported when it is installed
+                    Err(_) if roots.contains(&name) => Vec::new(),
+                    Err(e) => return Err(e),
+                }
+            };
+            queue.extend(dependencies.iter().map(|d| d.name.clone()));
+            graph.insert(name, dependencies);
+        }
+
+        let mut requirements: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for dependency in graph.values().flatten() {
+            let entry = requirements.entry(dependency.name.as_str()).or_default();
+            entry.extend(dependency.requirement.as_deref());
+        }
+
+        for name in dependency::install_order(&graph, roots)? {
+            if roots.contains(&name) {
+                continue;
+            }
+            // Requirements from several dependents must all hold at once
+            let requirement = requirements
+                .get(name.as_str())
+                .filter(|r| !r.is_empty())
+                .map(|r| r.join(", "));
+            tracing::debug!(dependency = %name, ?requirement, "resolving dependency");
+            if let Some(installed) = self.package_state()?.get_package(&name) {
+                let satisfied = requirement
+                    .as_deref()
+                    .is_none_or(|r| resolver::satisfies(&installed.version, r));
+                if satisfied {
+                    continue;
+                }
+                if installed.pinned {
+                    bail!(
+                        InstallError,
+                        "{} is pinned at {}, but {} is required",
+                        name,
+                        installed.version,
+                        requirement.unwrap_or_default()
+                    );
+                }
+            }
+
+            ui::status!("Installing dependency {}", name.cyan());
+            let plan = self
+                .plan_install(&name, requirement, None, false, false, registry)
+                .await?;
+            self.install_release(&plan.name, &plan.package, &plan.release, &plan.asset)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Looks up a package and settles on the release and asset to install,
+    /// prompting where needed
+    async fn plan_install(
+        &self,
+        package_name: &str,
+        version: Option<String>,
+        asset: Option<String>,
+        interactive: bool,
+        pre: bool,
+        registry: Option<&str>,
+    ) -> Result<PlannedInstall> {
+        ui::status!("Looking up package {}", package_name.cyan());
+        ui::emit(ui::Event::ResolveStarted { package: package_name });
+
+        let started = Instant::now();
+        let package = self
+            .registry_manager
+            .find_package(&self.registries(registry)?, package_name)
+            .await?;
+        self.timings
+            .record(Span::new(Phase::Resolve, started).package(installed_name(package_name)));
+
+        ui::status!("Found package in repository: {}", package.info.repository.cyan());
+        let package_name = installed_name(package_name);
+        self.check_pinned_source(package_name, &package.info.repository)?;
+
+        let repository = &package.info.repository;
+        let prompt_mode = self.prompt_mode(interactive)?;
+
+        // A version requirement may match any release, but the newest or the
+        // picker's first page are usually all that is needed
+        let release = match version {
+            Some(ref v) => {
+                let releases = self.registry_manager.get_releases(repository).await?;
+                if releases.is_empty() {
+                    bail!(VersionNotFound, "No releases found for package '{}'", package_name);
+                }
+                resolver::resolve(&releases, v, pre)?.clone()
+            }
+            None if prompt_mode == PromptMode::Never => {
+                let latest = self.registry_manager.get_latest_release(repository).await?;
+                ui::status!("Selected latest version {}", latest.tag_name.cyan());
+                latest
+            }
+            None => self.pick_release(package_name, repository).await?,
+        };
+
+        tracing::debug!(package = package_name, ?version, pre, tag = %release.tag_name, "resolved release");
+        let preferences = self.asset_preferences(package_name)?;
+        let asset = select_asset(&release, &package.info, asset.as_deref(), &preferences, prompt_mode)?.clone();
+        tracing::debug!(package = package_name, asset = %asset.name, "selected asset");
+        ui::emit(ui::Event::Resolved {
+            package: package_name,
+            version: &release.tag_name,
+            asset: &asset.name,
+        });
+
+        Ok(PlannedInstall {
+            name: package_name.to_string(),
+            release,
+            package,
+            asset,
+        })
+    }
+
+    /// Asks which release to install, fetching older pages of releases only
+    /// when asked to
+    async fn pick_release(&self, package_name: &str, repository: &str) -> Result<Release> {
+        let mut page = self.registry_manager.release_page(repository, None).await?;
+        let mut releases = Vec::new();
+        registry::append_releases(&mut releases, page.releases);
+        if releases.is_empty() {
+            bail!(VersionNotFound, "No releases found for package '{}'", package_name);
+        }
+
+        ui::status!("Available versions (type to search):");
+        let mut default = 0;
+        loop {
+            let mut choices: Vec<picker::Choice> = releases.iter().map(release_choice).collect();
+            if page.next.is_some() {
+                choices.push(picker::Choice::new("Older versions...", "fetch the next page"));
+            }
+            let selection = picker::pick(&format!("Select version of {}", package_name), &choices, default)?;
+            if selection < releases.len() {
+                return Ok(releases.swap_remove(selection));
+            }
+
+            let Some(next) = page.next else {
+                unreachable!("only offered with another page");
+            };
+            page = self.registry_manager.release_page(repository, Some(&next)).await?;
+            default = releases.len();
+            registry::append_releases(&mut releases, page.releases);
+        }
+    }
+
+    /// Installs a single artifact from a URL or local file, outside any registry
+    pub async fn install_direct(
+        &mut self,
+        package_name: &str,
+        version: &str,
+        url: Option<String>,
+        file: Option<PathBuf>,
+    ) -> Result<InstallOutcome> {
+        let (url, repository) = match (url, file) {
+            (Some(url), _) => (url.clone(), format!("url:{}", url)),
+            (None, Some(file)) => {
+                let path = std::fs::canonicalize(&file)?;
+                (format!("file://{}", path.display()), format!("file:{}", path.display()))
+            }
+            (None, None) => bail!(Usage, "Specify --url or --file"),
+        };
+
+        let file_name = url
+            .split(['?', '#'])
+            .next()
+            .and_then(|u| u.rsplit('/').next())
+            .filter(|name| !name.is_empty())
+            .unwrap_or(package_name)
+            .to_string();
+
+        let package = Package::from_repository(&repository, package_name);
+        let asset = Asset {
+            name: file_name,
+            browser_download_url: url,
+            size: None,
+        };
+        let release = Release {
+            tag_name: version.to_string(),
+            assets: vec![asset.clone()],
+            ..Default::default()
+        };
+        if let Some(outcome) = self.existing_install(package_name, &release, &asset)? {
+            return Ok(outcome);
+        }
+
+        let outcome = self
+            .install_release(package_name, &package, &release, &asset)
+            .await?;
+
+        if !self.dry_run {
+            ui::success!("Installation complete!");
+        }
+        Ok(outcome)
+    }
+
+    /// Downloads and unpacks `asset` into the package's version directory and
+    /// records it as the current version in the package state.
+    ///
+    /// Everything is staged in a temporary directory next to the version
+    /// directory and only moved into place once it is complete, so a failure
+    /// leaves the previous install and state untouched.
+    async fn install_release(
+        &mut self,
+        package_name: &str,
+        package: &Package,
+        release: &Release,
+        asset: &Asset,
+    ) -> Result<InstallOutcome> {
+        if self.dry_run {
+            return self.describe_install(package_name, package, release, asset).await;
+        }
+        let staged = self
+            .stage_release(package_name, package, release, asset)
+            .await?;
+        self.finish_install(package_name, package, release, asset, staged)
+            .await
+    }
+
+    /// The outcome of an earlier install of the same release and asset, when
+    /// it is still the active version and on disk, so it needn't be redone
+    fn existing_install(&self, package_name: &str, release: &Release, asset: &Asset) -> Result<Option<InstallOutcome>> {
+        if self.reinstall {
+            return Ok(None);
+        }
+        let Some(installed) = self.package_state()?.get_package(package_name) else {
+            return Ok(None);
+        };
+        if installed.version != release.tag_name
+            || installed.asset_name.as_deref() != Some(asset.name.as_str())
+            || !installed.install_path.is_dir()
+        {
+            return Ok(None);
+        }
+        // Installs from before digests were recorded can't be reported on
+        let Some(sha256) = installed.sha256.clone() else {
+            return Ok(None);
+        };
+        // A copy that isn't what grip.json pins is replaced, and the download
+        // checked against the pin
+        if self.pinned_sha256(package_name).is_some_and(|pinned| *pinned != sha256) {
+            return Ok(None);
+        }
+        ui::success!(
+            "{} {} is already installed; pass --reinstall to install it again",
+            package_name.cyan(),
+            release.tag_name
+        );
+        Ok(Some(InstallOutcome {
+            tag: release.tag_name.clone(),
+            asset: asset.clone(),
+            sha256,
+            install_path: installed.install_path.clone(),
+            attestation: installed.attestation,
+            dry_run: false,
+            already_installed: true,
+        }))
+    }
+
+    /// Prints what installing `asset` would download, write, link and run,
+    /// for `--dry-run`
+    async fn describe_install(
+        &self,
+        package_name: &str,
+        package: &Package,
+        release: &Release,
+        asset: &Asset,
+    ) -> Result<InstallOutcome> {
+        let target_dir = self.root.join("packages").join(package_name).join(&release.tag_name);
+        let license = self.package_license(package_name, package).await?;
+        ui::status!("Would install {} {}", package_name.cyan(), release.tag_name);
+        if let Some(license) = license {
+            ui::print_detail(&format!("licensed under {}", license));
+        }
+        match self.cache.get(&asset.browser_download_url) {
+            Some(_) => ui::print_detail(&format!("use the cached {}", asset.name)),
+            None => ui::print_detail(&format!("download {}", asset.browser_download_url)),
+        }
+        let how = if asset_matcher::is_archive(&asset.name) { "extract" } else { "copy" };
+        let replacing = if target_dir.exists() { ", replacing what is there" } else { "" };
+        ui::print_detail(&format!("{} it into {}{}", how, target_dir.display(), replacing));
+
+        let bin_dir = shim::bin_dir(&self.root);
+        let names = package.info.executables(&Platform::current(), package_name)?;
+        for name in names.iter().chain(&self.aliases_after_install(package_name, package)?) {
+            ui::print_detail(&format!("link {}", bin_dir.join(shim::shim_name(Path::new(name))).display()));
+        }
+        if self.scope == Scope::User {
+            ui::print_detail(&format!("put {} on PATH unless it already is", bin_dir.display()));
+        }
+        if !self.no_hooks {
+            let user_hooks = self.config.hooks.packages.get(package_name).into_iter().flatten();
+            let hooks = package.info.post_install.iter().chain(user_hooks).chain(&self.config.hooks.post_install);
+            for command in hooks {
+                ui::print_detail(&format!("run {}", command));
+            }
+        }
+
+        Ok(InstallOutcome {
+            tag: release.tag_name.clone(),
+            asset: asset.clone(),
+            sha256: String::new(),
+            install_path: target_dir,
+            attestation: None,
+            dry_run: true,
+            already_installed: false,
+        })
+    }
+
+    /// The package's license: the registry's, or else the one GitHub detected
+    /// in its repository. Fails when `settings.denied_licenses` rules it out
+    async fn package_license(&self, package_name: &str, package: &Package) -> Result<Option<String>> {
+        let license = match &package.info.license {
+            Some(license) => Some(license.clone()),
+            None => self.github_license(&package.info.repository).await,
+        };
+        if let Some(license) = &license {
+            if let Some(entry) = license::denied(license, &self.config.settings.denied_licenses) {
+                bail!(
+                    InstallError,
+                    "{} is licensed under {}, which denied_licenses rules out ({})",
+                    package_name,
+                    license,
+                    entry
+                );
+            }
+        }
+        Ok(license)
+    }
+
+    /// The license GitHub detected in a repository, for packages whose
+    /// registry entry doesn't give one
+    async fn github_license(&self, repository: &str) -> Option<String> {
+        if provider::is_direct_source(repository) {
+            return None;
+        }
+        let (host, repo) = provider::for_repository(repository).ok()?.attestation_repo()?;
+        if host != "github.com" {
+            return None;
+        }
+        let metadata = self.registry_manager.github_metadata(&repo).await.ok()?;
+        metadata["license"]["spdx_id"]
+            .as_str()
+            .filter(|id| !id.is_empty() && *id != "NOASSERTION")
+            .map(str::to_string)
+    }
+
+    /// Downloads, verifies and unpacks an asset into a staging directory next
+    /// to where it will be installed
+    async fn stage_release(
+        &self,
+        package_name: &str,
+        package: &Package,
+        release: &Release,
+        asset: &Asset,
+    ) -> Result<StagedInstall> {
+        let license = self.package_license(package_name, package).await?;
+        let package_dir = self
+            .root
+            .join("packages")
+            .join(package_name);
+
+        std::fs::create_dir_all(&package_dir)?;
+        // Dropping the staging directory on any early return removes it
+        let staging = tempfile::Builder::new()
+            .prefix(".staging-")
+            .tempdir_in(&package_dir)?;
+        // The cache checks its copy's digest, and downloads compute theirs as
+        // they arrive; only files rebuilt from a previous version are hashed
+        // afresh
+        let started = Instant::now();
+        let cached = self.cache.get(&asset.browser_download_url);
+        let (downloaded_file, digest, source) = match &cached {
+            Some((path, entry)) => {
+                ui::status!("Using cached {}", asset.name.cyan());
+                let staged = staging.path().join(&asset.name);
+                std::fs::copy(path, &staged)?;
+                (staged, Some(entry.sha256.clone()), "from the cache")
+            }
+            None => match self.download_from_previous(package_name, release, asset, staging.path()).await {
+                Some(staged) => (staged, None, "rebuilt from the previous version"),
+                None => {
+                    let (downloaded, sha256) = self
+                        .registry_manager
+                        .download_asset(&asset.browser_download_url, &asset.name, staging.path())
+                        .await?;
+                    (downloaded, Some(sha256), "")
+                }
+            },
+        };
+        let downloaded_size = std::fs::metadata(&downloaded_file).map_or(0, |m| m.len());
+        let mut span = Span::new(Phase::Download, started).package(package_name).bytes(downloaded_size);
+        if !source.is_empty() {
+            span = span.detail(source);
+        }
+        self.timings.record(span);
+
+        let started = Instant::now();
+        let sha256 = self
+            .verify_download(package_name, package, release, asset, &downloaded_file, digest)
+            .await?;
+        self.verify_signature(package, release, asset, &downloaded_file)
+            .await?;
+        let attestation = self
+            .verify_attestation(package, release, asset, &downloaded_file)
+            .await?;
+        self.timings.record(Span::new(Phase::Verify, started).package(package_name));
+        if cached.is_none() {
+            if let Err(e) = self.cache.put(&asset.browser_download_url, &downloaded_file, &sha256) {
+                ui::warning!("Failed to cache {}: {}", asset.name, e);
+            }
+        }
+
+        let executable_names = package.info.executables(&Platform::current(), package_name)?;
+
+        let installer = match cfg!(windows) {
+            true => installer::detect(&asset.name, &downloaded_file)?,
+            false => None,
+        };
+        let staged_executables = if installer.is_some() {
+            Vec::new()
+        } else if utils::detect_archive_format(&downloaded_file)?.is_some() {
+            ui::status!("Extracting {}", asset.name);
+            ui::emit(ui::Event::ExtractStarted { package: package_name, file: &asset.name });
+            let started = Instant::now();
+            let max_size = self.config.settings.max_extracted_size_mb.saturating_mul(1024 * 1024);
+            let format = utils::extract_archive(&downloaded_file, staging.path(), max_size).await?;
+            self.timings.record(
+                Span::new(Phase::Extract, started)
+                    .package(package_name)
+                    .bytes(utils::dir_size(staging.path()).saturating_sub(downloaded_size)),
+            );
+            std::fs::remove_file(&downloaded_file)?;
+            utils::flatten_single_dir(staging.path())?;
+            // A compressed bare executable, such as tool.gz, is the main
+            // executable once decompressed, whatever it was called
+            let files = utils::walk_files(staging.path())?;
+            if let ([decompressed], true) = (files.as_slice(), format.is_single_file()) {
+                let executable = staging.path().join(&executable_names[0]);
+                rename(decompressed, &executable)?;
+                vec![executable]
+            } else {
+                // Distribution packages also carry docs, libraries and helpers;
+                // what belongs on PATH is in usr/bin (bin once flattened)
+                let bin_dir = ["usr/bin", "bin"]
+                    .iter()
+                    .map(|dir| staging.path().join(dir))
+                    .find(|dir| format.is_system_package() && dir.is_dir());
+                let mut executables: Vec<PathBuf> = Vec::new();
+                for (i, name) in executable_names.iter().enumerate() {
+                    let in_bin_dir = match &bin_dir {
+                        Some(bin_dir) => utils::find_executable(bin_dir, name)?,
+                        None => None,
+                    };
+                    let executable = match in_bin_dir {
+                        Some(path) => Some(path),
+                        None => utils::find_executable(staging.path(), name)?,
+                    };
+                    tracing::debug!(executable_name = name, found = ?executable, "looked for the executable");
+                    match executable {
+                        Some(path) if !executables.contains(&path) => executables.push(path),
+                        // The main executable's absence is reported once it's installed
+                        _ if i == 0 => {}
+                        _ => ui::warning!("{} has no executable named {}", asset.name, name),
+                    }
+                }
+                executables
+            }
+        } else {
+            // A bare download, AppImages included, is the main executable itself
+            let new_pathbuf = utils::change_file_name(&downloaded_file, executable_names[0].clone());
+            rename(downloaded_file, &new_pathbuf)?;
+            vec![new_pathbuf]
+        };
+
+        let mut relative_executables = Vec::new();
+        for executable in &staged_executables {
+            utils::make_executable(executable)?;
+            if let Ok(relative) = executable.strip_prefix(staging.path()) {
+                relative_executables.push(relative.to_path_buf());
+            }
+        }
+
+        Ok(StagedInstall {
+            staging,
+            relative_executables,
+            sha256,
+            attestation,
+            installer,
+            license,
+        })
+    }
+
+    /// Moves a staged install into place, records it and links its executable
+    async fn finish_install(
+        &mut self,
+        package_name: &str,
+        package: &Package,
+        release: &Release,
+        asset: &Asset,
+        staged: StagedInstall,
+    ) -> Result<InstallOutcome> {
+        let StagedInstall {
+            staging,
+            relative_executables,
+            sha256,
+            attestation,
+            installer,
+            license,
+        } = staged;
+        let started = Instant::now();
+        let _lock = self.lock_package(package_name)?;
+        let package_dir = self
+            .root
+            .join("packages")
+            .join(package_name);
+        let target_dir = package_dir.join(&release.tag_name);
+        let previous_version = self.package_state()?.get_package(package_name).map(|p| p.version.clone());
+        let previous_extras = self
+            .package_state()?
+            .get_package(package_name)
+            .map(|p| (p.extras.clone(), p.install_path.clone()));
+
+        // Reinstalling the same version moves the old copy aside until the new
+        // one is recorded, so it can be restored if anything goes wrong
+        let previous = if target_dir.exists() {
+            let backup = package_dir.join(format!(".previous-{}", release.tag_name));
+            if backup.exists() {
+                std::fs::remove_dir_all(&backup)?;
+            }
+            rename(&target_dir, &backup)?;
+            Some(backup)
+        } else {
+            None
+        };
+
+        let staged = staging.keep();
+        if let Err(e) = rename(&staged, &target_dir) {
+            let _ = std::fs::remove_dir_all(&staged);
+            if let Some(backup) = &previous {
+                rename(backup, &target_dir)?;
+            }
+            return Err(e.into());
+        }
+        ui::success!("Installed to {:?}", target_dir);
+
+        let (executables, installer_record) = match installer {
+            Some(kind) => {
+                let installer_file = target_dir.join(&asset.name);
+                match self.run_installer(package_name, package, release, asset, &installer_file, kind) {
+                    Ok((executables, record)) => (executables, Some(record)),
+                    Err(e) => {
+                        std::fs::remove_dir_all(&target_dir)?;
+                        if let Some(backup) = &previous {
+                            rename(backup, &target_dir)?;
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+            None => {
+                let executables = relative_executables
+                    .iter()
+                    .map(|relative| target_dir.join(relative))
+                    .collect();
+                (executables, None)
+            }
+        };
+
+        // Shims of the previously active version the new one no longer has
+        let stale_shims: Vec<String> = self
+            .package_state()?
+            .get_package(package_name)
+            .map(|installed| {
+                installed
+                    .executables
+                    .iter()
+                    .map(|old| shim::shim_name(old))
+                    .filter(|name| !executables.iter().any(|e| shim::shim_name(e) == *name))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let installed_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let size = self.package_size(package_name);
+        let aliases = self.aliases_after_install(package_name, package)?;
+        let recorded = self.update_state(|state| {
+            state.add_package(
+                package_name.to_string(),
+                release.tag_name.clone(),
+                target_dir.clone(),
+                executables.clone(),
+                Some(asset.name.clone()),
+                Some(package.info.repository.clone()),
+            );
+            state.set_registry(package_name, package.registry.clone());
+            state.set_attestation(package_name, attestation);
+            state.set_dependencies(package_name, package.info.dependencies.clone());
+            state.set_installer(package_name, installer_record.clone());
+            state.set_installed_at(package_name, installed_at, size);
+            state.set_download(package_name, asset.browser_download_url.clone(), sha256.clone());
+            state.set_aliases(package_name, aliases.clone());
+            state.set_license(package_name, license.clone());
+            Ok(())
+        });
+
+        if let Err(e) = recorded {
+            std::fs::remove_dir_all(&target_dir)?;
+            if let Some(backup) = &previous {
+                rename(backup, &target_dir)?;
+            }
+            return Err(e);
+        }
+
+        if let Some(backup) = previous {
+            std::fs::remove_dir_all(backup)?;
+        }
+
+        let action = match &previous_version {
+            _ if self.rolling_back => Action::Rollback,
+            None => Action::Install,
+            Some(version) if *version == release.tag_name => Action::Reinstall,
+            Some(version) if is_newer(&release.tag_name, version) => Action::Upgrade,
+            Some(_) => Action::Downgrade,
+        };
+        self.record_history(Transaction::new(
+            action,
+            package_name,
+            previous_version,
+            Some(release.tag_name.clone()),
+        ));
+
+        if cfg!(target_os = "macos") {
+            self.clear_quarantine(&target_dir, &executables)?;
+        }
+
+        let bin_dir = shim::bin_dir(&self.root);
+        for name in &stale_shims {
+            shim::unlink(&bin_dir, name)?;
+        }
+        let mut install_manifest = InstallManifest::default();
+        let linkable: Vec<&PathBuf> = executables.iter().filter(|e| e.is_file()).collect();
+        if linkable.is_empty() {
+            ui::warning!("No executable found for {}, adding its directory to PATH instead", package_name);
+            self.add_to_path(&target_dir).await?;
+            install_manifest.path.push(target_dir.clone());
+        } else {
+            for executable in &linkable {
+                let name = shim::shim_name(executable);
+                let shim = self.link_shim(&bin_dir, &name, package_name, executable)?;
+                install_manifest.shims.push(name);
+                ui::success!("Linked {}", shim.display());
+            }
+            if let Some(main) = executables.first().filter(|main| main.is_file()) {
+                for alias in &aliases {
+                    let name = shim::shim_name(Path::new(alias));
+                    let shim = self.link_shim(&bin_dir, &name, package_name, main)?;
+                    install_manifest.shims.push(name);
+                    ui::success!("Linked {}", shim.display());
+                }
+            }
+            self.add_to_path(&bin_dir).await?;
+        }
+        if installer_record.is_none() {
+            match self.link_extras(package_name, &target_dir, previous_extras) {
+                Ok(()) => {
+                    install_manifest.extras = self
+                        .package_state()?
+                        .get_package(package_name)
+                        .map(|p| p.extras.clone())
+                        .unwrap_or_default();
+                }
+                Err(e) => ui::warning!("Failed to link {}'s completions and man pages: {}", package_name, e),
+            }
+        }
+
+        self.timings.record(Span::new(Phase::Link, started).package(package_name));
+
+        let context = HookContext {
+            package: package_name,
+            version: &release.tag_name,
+            install_dir: &target_dir,
+            executable: executables.first().map(PathBuf::as_path),
+        };
+        let started = Instant::now();
+        self.run_hooks(package, &context)?;
+        self.timings.record(Span::new(Phase::Hooks, started).package(package_name));
+        // Last, since shared files are read-only and hooks may write to theirs
+        if self.config.settings.dedupe_files && installer_record.is_none() {
+            match self.store.dedupe(&target_dir) {
+                Ok(0) => {}
+                Ok(saved) => ui::status!("Shared {} with other installs", utils::format_size(saved)),
+                Err(e) => ui::warning!("Failed to share {}'s files with other installs: {}", package_name, e),
+            }
+        }
+        // Recorded after hooks, which may add files of their own
+        let manifest = FileManifest::record(&target_dir)
+            .and_then(|manifest| manifest.save(&FileManifest::path(&package_dir, &release.tag_name)));
+        let recorded = install_manifest
+            .record_entries(&target_dir)
+            .and_then(|()| install_manifest.save(&self.root, package_name, &release.tag_name));
+        if let Err(e) = manifest.and(recorded) {
+            ui::warning!("Failed to record {}'s installed files: {}", package_name, e);
+        }
+        if let Some(caveats) = &package.info.caveats {
+            ui::status!("Caveats for {}:", package_name.cyan());
+            for line in caveats.lines() {
+                ui::print_detail(line);
+            }
+        }
+        ui::emit(ui::Event::Completed {
+            package: package_name,
+            version: &release.tag_name,
+            path: &target_dir.to_string_lossy(),
+        });
+
+        Ok(InstallOutcome {
+            tag: release.tag_name.clone(),
+            asset: asset.clone(),
+            sha256,
+            install_path: target_dir,
+            attestation,
+            dry_run: false,
+            already_installed: false,
+        })
+    }
+
+    /// Links the shell completions and man pages shipped in `version_dir` into
+    /// the share directory, after removing those of the previously active
+    /// version, and records them
+    fn link_extras(
+        &mut self,
+        package_name: &str,
+        version_dir: &Path,
+        previous: Option<(Vec<PathBuf>, PathBuf)>,
+    ) -> Result<()> {
+        if let Some((links, previous_dir)) = previous {
+            extras::unlink(&self.root, &links, &previous_dir)?;
+        }
+        let linked = extras::link(&self.root, version_dir)?;
+        let completions = linked.iter().filter(|l| l.starts_with("completions")).count();
+        if !linked.is_empty() {
+            ui::success!(
+                "Linked {} shell completion(s) and {} man page(s)",
+                completions,
+                linked.len() - completions
+            );
+        }
+        self.update_state(|state| {
+            state.set_extras(package_name, linked);
+            Ok(())
+        })
+    }
+
+    /// Runs the registry's post-install steps once confirmed, then the user's
+    /// own hooks. A failing hook is reported but doesn't undo the install
+    fn run_hooks(&self, package: &Package, context: &HookContext) -> Result<()> {
+        if self.no_hooks {
+            return Ok(());
+        }
+
+        let steps = &package.info.post_install;
+        let run_steps = if steps.is_empty() {
+            false
+        } else if self.config.hooks.trust_registry {
+            true
+        } else {
+            ui::status!("{} has post-install steps:", context.package.cyan());
+            for step in steps {
+                ui::print_detail(&format!("$ {}", step));
+            }
+            if self.non_interactive {
+                ui::warning!(
+                    "Skipped them; confirm interactively or set hooks.trust_registry to run them"
+                );
+                false
+            } else {
+                Confirm::new()
+                    .with_prompt("Run them?")
+                    .default(false)
+                    .interact()?
+            }
+        };
+
+        let user_hooks = self
+            .config
+            .hooks
+            .packages
+            .get(context.package)
+            .into_iter()
+            .flatten();
+        let commands = steps
+            .iter()
+            .filter(|_| run_steps)
+            .chain(user_hooks)
+            .chain(&self.config.hooks.post_install);
+        for command in commands {
+            ui::status!("Running {}", command);
+            if let Err(e) = hooks::run(command, context) {
+                ui::failure!("Post-install hook failed: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Rebuilds `asset` from the cached download of the installed version when
+    /// the release publishes a zsync file or patch for it. Returns None, to
+    /// download the asset in full, when there is nothing to start from or
+    /// rebuilding fails
+    async fn download_from_previous(
+        &self,
+        package_name: &str,
+        release: &Release,
+        asset: &Asset,
+        target_dir: &Path,
+    ) -> Option<PathBuf> {
+        if self.registry_manager.offline {
+            return None;
+        }
+        let companion = delta::companion(release, asset)?;
+        let previous_url = self.package_state().ok()?.get_package(package_name)?.url.as_ref()?;
+        if *previous_url == asset.browser_download_url {
+            return None;
+        }
+        let (previous, _) = self.cache.get(previous_url)?;
+
+        let output = target_dir.join(&asset.name);
+        let result = match companion {
+            delta::Companion::Zsync(control) => self.zsync(&previous, control, asset, &output).await,
+            delta::Companion::Delta(patch) => self.patch(&previous, patch, asset, &output).await,
+        };
+        match result {
+            Ok(()) => Some(output),
+            Err(e) => {
+                ui::warning!("Couldn't update {} from the previous version ({}); downloading it in full", asset.name, e);
+                let _ = std::fs::remove_file(&output);
+                None
+            }
+        }
+    }
+
+    /// Copies the blocks of `asset` that `previous` already has and fetches
+    /// the rest with range requests
+    async fn zsync(&self, previous: &Path, control: &Asset, asset: &Asset, output: &Path) -> Result<()> {
+        let control_file = delta::ControlFile::parse(
+            &self.registry_manager.fetch_bytes(&control.browser_download_url).await?,
+        )?;
+        // The URL in a zsync file is relative to the zsync file itself
+        let url = match &control_file.url {
+            Some(url) => reqwest::Url::parse(&control.browser_download_url)
+                .and_then(|base| base.join(url))
+                .map(String::from)
+                .unwrap_or_else(|_| asset.browser_download_url.clone()),
+            None => asset.browser_download_url.clone(),
+        };
+
+        let old = std::fs::read(previous)?;
+        let found = control_file.locate(&old);
+        if found.iter().all(Option::is_none) {
+            bail!(DownloadError, "it shares nothing with the previous version");
+        }
+        let missing = control_file.missing_ranges(&found);
+        let to_fetch: u64 = missing.iter().map(|(start, end)| end - start).sum();
+        ui::status!(
+            "Updating {} from the previous version ({} of {} to download)",
+            asset.name.cyan(),
+            utils::format_size(to_fetch),
+            utils::format_size(control_file.length())
+        );
+
+        let mut ranges = Vec::new();
+        for (start, end) in missing {
+            let bytes = self.registry_manager.fetch_range(&url, start, end).await?;
+            ranges.push(((start, end), bytes));
+        }
+        control_file.assemble(&old, &found, &ranges, output)
+    }
+
+    /// Applies a zstd patch of the asset against `previous`
+    async fn patch(&self, previous: &Path, patch: &Asset, asset: &Asset, output: &Path) -> Result<()> {
+        ui::status!("Updating {} from the previous version with {}", asset.name.cyan(), patch.name);
+        let temp_dir = tempfile::tempdir()?;
+        let (patch_file, _) = self
+            .registry_manager
+            .download_asset(&patch.browser_download_url, &patch.name, temp_dir.path())
+            .await?;
+        delta::apply_patch(&std::fs::read(previous)?, &std::fs::read(patch_file)?, output)
+    }
+
+    /// Checks a downloaded asset against the digest pinned in the registry
+    /// metadata or, failing that, one published alongside it in the release,
+    /// and returns the asset's actual digest. `digest` is the file's digest
+    /// when it was computed on the way in
+    async fn verify_download(
+        &self,
+        package_name: &str,
+        package: &Package,
+        release: &Release,
+        asset: &Asset,
+        downloaded_file: &Path,
+        digest: Option<String>,
+    ) -> Result<String> {
+        let actual = match digest {
+            Some(digest) => digest,
+            None => checksum::sha256_file(downloaded_file)?,
+        };
+
+        let pinned = self.pinned_sha256(package_name);
+        if let Some(pinned) = pinned {
+            if actual != *pinned {
+                return Err(GripError::ChecksumMismatch(format!(
+                    "{} has sha256 {} but grip.json pins {}",
+                    asset.name, actual, pinned
+                )));
+            }
+            ui::success!("Verified sha256 against grip.json");
+        }
+
+        let expected = match package.info.checksums.get(&asset.name) {
+            Some(digest) => Some((digest.to_lowercase(), "registry metadata".to_string())),
+            None => match checksum::find_checksum_asset(release, &asset.name) {
+                Some(checksum_asset) => {
+                    let content = self
+                        .registry_manager
+                        .fetch_text(&checksum_asset.browser_download_url)
+                        .await?;
+                    let digest = checksum::parse_checksum(&content, &asset.name).ok_or_else(|| {
+                        GripError::ChecksumMismatch(format!(
+                            "{} has no entry for {}",
+                            checksum_asset.name, asset.name
+                        ))
+                    })?;
+                    Some((digest, checksum_asset.name.clone()))
+                }
+                None => None,
+            },
+        };
+
+        let Some((expected, source)) = expected else {
+            if pinned.is_none() {
+                ui::warning!("No checksum published for {}, skipping verification", asset.name);
+            }
+            return Ok(actual);
+        };
+
+        if actual != expected {
+            return Err(GripError::ChecksumMismatch(format!(
+                "{} has sha256 {} but {} expects {}",
+                asset.name, actual, source, expected
+            )));
+        }
+
+        ui::success!("Verified sha256 against {}", source);
+        Ok(actual)
+    }
+
+    fn pinned_sha256(&self, package_name: &str) -> Option<&String> {
+        self.pins.get(package_name)?.sha256.as_ref()
+    }
+
+    /// Fails when grip.json pins `package_name` to another repository than
+    /// the one it resolved to
+    fn check_pinned_source(&self, package_name: &str, repository: &str) -> Result<()> {
+        let Some(pinned) = self
+            .pins
+            .get(package_name)
+            .and_then(|dependency| dependency.source_repository.as_deref())
+        else {
+            return Ok(());
+        };
+        if !provider::same_repository(pinned, repository) {
+            bail!(
+                InstallError,
+                "grip.json pins {} to {}, but it resolves to {}",
+                package_name,
+                pinned,
+                repository
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks a downloaded asset's detached signature against the key the
+    /// registry declares for the package. Unsigned or unverifiable assets are
+    /// only refused when `require_signatures` is set
+    async fn verify_signature(
+        &self,
+        package: &Package,
+        release: &Release,
+        asset: &Asset,
+        downloaded_file: &Path,
+    ) -> Result<()> {
+        let keys = &package.info.public_keys;
+        let required = self.config.settings.require_signatures;
+        let found = signature::find_signature_asset(release, &asset.name, keys);
+
+        let (signature_asset, kind, key) = match found {
+            Some((signature_asset, kind)) => match kind.key(keys) {
+                Some(key) => (signature_asset, kind, key),
+                None if required => {
+                    return Err(GripError::SignatureError(format!(
+                        "{} is signed with {} but the registry declares no {} key for {}",
+                        asset.name,
+                        signature_asset.name,
+                        kind.name(),
+                        package.info.name
+                    )));
+                }
+                None => {
+                    ui::warning!(
+                        "No trusted {} key for {}, skipping signature verification",
+                        kind.name(),
+                        package.info.name
+                    );
+                    return Ok(());
+                }
+            },
+            None if required => {
+                return Err(GripError::SignatureError(format!(
+                    "{} has no signature and require_signatures is on",
+                    asset.name
+                )));
+            }
+            None if !keys.is_empty() => {
+                ui::warning!("No signature published for {}, skipping verification", asset.name);
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+
+        let signature_bytes = self
+            .registry_manager
+            .fetch_bytes(&signature_asset.browser_download_url)
+            .await?;
+        signature::verify(kind, downloaded_file, &signature_bytes, key)?;
+
+        ui::success!("Verified {} signature {}", kind.name(), signature_asset.name);
+        Ok(())
+    }
+
+    /// Checks the Sigstore attestation of a downloaded asset when its package
+    /// or the config opts in: a bundle published with the release is checked
+    /// with cosign, otherwise GitHub's attestations for the repository with gh
+    async fn verify_attestation(
+        &self,
+        package: &Package,
+        release: &Release,
+        asset: &Asset,
+        downloaded_file: &Path,
+    ) -> Result<Option<Attestation>> {
+        let policy = match &package.info.attestations {
+            Some(policy) => policy.clone(),
+            None if self.config.settings.verify_attestations => Default::default(),
+            None => return Ok(None),
+        };
+        let github = provider::for_repository(&package.info.repository)
+            .ok()
+            .and_then(|p| p.attestation_repo());
+
+        if let Some(bundle_asset) = attestation::find_bundle_asset(release, &asset.name) {
+            let identity = policy
+                .identity
+                .or_else(|| github.as_ref().map(|(host, repo)| attestation::github_identity(host, repo)))
+                .ok_or_else(|| {
+                    GripError::SignatureError(format!(
+                        "{} has no attestations.identity to check {} against",
+                        package.info.name, bundle_asset.name
+                    ))
+                })?;
+            let issuer = policy
+                .issuer
+                .unwrap_or_else(|| attestation::GITHUB_ACTIONS_ISSUER.to_string());
+
+            let bundle = self
+                .registry_manager
+                .fetch_bytes(&bundle_asset.browser_download_url)
+                .await?;
+            attestation::verify_cosign(downloaded_file, &bundle, &identity, &issuer)?;
+            ui::success!("Verified Sigstore bundle {}", bundle_asset.name);
+            return Ok(Some(Attestation::Cosign));
+        }
+
+        let Some((host, repo)) = github else {
+            return Err(GripError::SignatureError(format!(
+                "{} has no Sigstore bundle and {} is not on GitHub",
+                asset.name, package.info.repository
+            )));
+        };
+        if self.registry_manager.offline {
+            return Err(GripError::Offline(format!("GitHub attestation of {}", asset.name)));
+        }
+
+        let token = if host == "github.com" {
+            self.config.github_token()
+        } else {
+            self.registry_manager.host_tokens.get(&host).cloned()
+        };
+        attestation::verify_github(downloaded_file, &host, &repo, token.as_deref())?;
+        ui::success!("Verified GitHub attestation from {}", repo);
+        Ok(Some(Attestation::GitHub))
+    }
+
+    /// Removes a package, refusing while other installed packages depend on
+    /// it unless `force` is set
+    pub async fn uninstall(&mut self, package_name: &str, force: bool) -> Result<()> {
+        if self.dry_run {
+            return self.describe_uninstall(package_name, force);
+        }
+        let _lock = self.lock_package(package_name)?;
+        let package = self.update_state(|state| {
+            let dependents = state.dependents(package_name);
+            if !dependents.is_empty() && !force {
+                let names: Vec<&str> = dependents.iter().map(|n| n.as_str()).collect();
+                bail!(
+                    InstallError,
+                    "{} is needed by {}; pass --force to uninstall it anyway",
+                    package_name,
+                    names.join(", ")
+                );
+            }
+            state.remove_package(package_name).ok_or_else(|| {
+                GripError::InstallError(format!("Package '{}' is not installed", package_name))
+            })
+        })?;
+
+        let package_dir = self
+            .root
+            .join("packages")
+            .join(package_name);
+        // What the state records is undone too, for versions installed
+        // before grip kept manifests
+        let mut shims: Vec<String> = package
+            .executables
+            .iter()
+            .map(|executable| shim::shim_name(executable))
+            .chain(package.aliases.iter().map(|alias| shim::shim_name(Path::new(alias))))
+            .collect();
+        let mut links = package.extras.clone();
+        let mut path_dirs = Vec::new();
+        let mut manifests = Vec::new();
+        for version in &package.installed_versions {
+            match InstallManifest::load(&self.root, package_name, version)? {
+                Some(manifest) => {
+                    shims.extend(manifest.shims.iter().cloned());
+                    links.extend(manifest.extras.iter().cloned());
+                    path_dirs.extend(manifest.path.iter().cloned());
+                    manifests.push((version, Some(manifest)));
+                }
+                // Packages without executables had their directory put on PATH instead
+                None => {
+                    path_dirs.push(package_dir.join(version));
+                    manifests.push((version, None));
+                }
+            }
+        }
+
+        // Only links still pointing at this package are removed, since
+        // another package may have taken a name over
+        let bin_dir = shim::bin_dir(&self.root);
+        for name in &shims {
+            let owned = shim::points_into(&bin_dir, name, &package_dir)
+                || package.executables.iter().any(|e| shim::is_linked(&bin_dir, name, e));
+            if owned {
+                shim::unlink(&bin_dir, name)?;
+            }
+        }
+        if let Some(record) = &package.installer {
+            ui::status!("Running the uninstaller {} registered", package_name.cyan());
+            if let Err(e) = installer::uninstall(record) {
+                ui::warning!(
+                    "Failed to uninstall what {}'s installer put in place: {}; remove {} from Apps & Features",
+                    package_name,
+                    e,
+                    record.display_name.as_deref().unwrap_or(package_name)
+                );
+            }
+        }
+
+        links.sort();
+        links.dedup();
+        extras::unlink(&self.root, &links, &package_dir)?;
+        for dir in &path_dirs {
+            self.remove_from_path(dir)?;
+        }
+
+        let mut left = 0;
+        let mut kept = Vec::new();
+        for (version, manifest) in &manifests {
+            let version_dir = package_dir.join(version);
+            match manifest {
+                Some(manifest) => left += manifest.remove_entries(&version_dir)?,
+                None if version_dir.exists() => std::fs::remove_dir_all(&version_dir)?,
+                None => {}
+            }
+            if version_dir.exists() {
+                kept.push(version_dir);
+            }
+        }
+        // The rest is grip's own: file lists and backups of interrupted reinstalls
+        for entry in std::fs::read_dir(&package_dir).into_iter().flatten() {
+            let path = entry?.path();
+            if !kept.contains(&path) {
+                remove_tree(&path)?;
+            }
+        }
+        if kept.is_empty() && package_dir.exists() {
+            std::fs::remove_dir(&package_dir)?;
+        }
+        let manifests_dir = install_manifest::dir(&self.root, package_name);
+        if manifests_dir.exists() {
+            std::fs::remove_dir_all(manifests_dir)?;
+        }
+
+        self.record_history(Transaction::new(Action::Uninstall, package_name, Some(package.version.clone()), None));
+        ui::success!("Uninstalled {}", package_name.cyan());
+        if left > 0 {
+            ui::status!(
+                "Kept {} file(s) {} created in {} after it was installed; `grip clean` removes them",
+                left,
+                package_name,
+                package_dir.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Deletes the inactive versions of `package_name`, or of every installed
+    /// package, keeping the `keep` newest of them alongside the active one
+    pub fn prune(&mut self, package_name: Option<&str>, keep: usize) -> Result<()> {
+        let names = self.installed_names(package_name)?;
+        let mut pruned = Vec::new();
+        for name in &names {
+            let _lock = self.lock_package(name)?;
+            let Some(package) = self.package_state()?.get_package(name) else {
+                continue;
+            };
+            let mut inactive: Vec<String> = package
+                .installed_versions
+                .iter()
+                .filter(|v| **v != package.version)
+                .cloned()
+                .collect();
+            inactive.sort_by(|a, b| {
+                if is_newer(a, b) {
+                    std::cmp::Ordering::Less
+                } else if is_newer(b, a) {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            });
+            let removed: Vec<String> = inactive.into_iter().skip(keep).collect();
+            if removed.is_empty() {
+                continue;
+            }
+
+            let package_dir = self.root.join("packages").join(name);
+            for version in &removed {
+                let version_dir = package_dir.join(version);
+                let size = utils::dir_size(&version_dir);
+                self.remove_from_path(&version_dir)?;
+                if version_dir.exists() {
+                    std::fs::remove_dir_all(&version_dir)?;
+                }
+                let manifest = FileManifest::path(&package_dir, version);
+                if manifest.exists() {
+                    std::fs::remove_file(manifest)?;
+                }
+                let install_manifest = InstallManifest::path(&self.root, name, version);
+                if install_manifest.exists() {
+                    std::fs::remove_file(install_manifest)?;
+                }
+                pruned.push((name.clone(), version.clone(), size));
+            }
+            let size = self.package_size(name);
+            self.update_state(|state| {
+                for version in &removed {
+                    state.remove_version(name, version);
+                }
+                state.set_size(name, size);
+                Ok(())
+            })?;
+        }
+
+        let freed: u64 = pruned.iter().map(|(_, _, size)| size).sum();
+        if ui::json_output() {
+            let versions: Vec<_> = pruned
+                .iter()
+                .map(|(name, version, size)| serde_json::json!({ "name": name, "version": version, "size": size }))
+                .collect();
+            return ui::print_json(&serde_json::json!({ "removed": versions, "freed": freed }));
+        }
+        if pruned.is_empty() {
+            ui::status!("No old versions to remove");
+            return Ok(());
+        }
+        for (name, version, size) in &pruned {
+            ui::success!("Removed {} {} ({})", name.cyan(), version, utils::format_size(*size));
+        }
+        ui::success!("Removed {} old version(s), freeing {}", pruned.len(), utils::format_size(freed));
+        Ok(())
+    }
+
+    /// Prints what uninstalling `package_name` would remove, for `--dry-run`
+    fn describe_uninstall(&self, package_name: &str, force: bool) -> Result<()> {
+        let package = self.package_state()?.get_package(package_name).ok_or_else(|| {
+            GripError::InstallError(format!("Package '{}' is not installed", package_name))
+        })?;
+        let dependents = self.package_state()?.dependents(package_name);
+        if !dependents.is_empty() && !force {
+            let names: Vec<&str> = dependents.iter().map(|n| n.as_str()).collect();
+            bail!(
+                InstallError,
+                "{} is needed by {}; pass --force to uninstall it anyway",
+                package_name,
+                names.join(", ")
+            );
+        }
+
+        ui::status!("Would uninstall {} {}", package_name.cyan(), package.version);
+        let bin_dir = shim::bin_dir(&self.root);
+        let aliases = package.aliases.iter().map(Path::new);
+        for executable in package.executables.iter().map(PathBuf::as_path).chain(aliases) {
+            ui::print_detail(&format!("unlink {}", bin_dir.join(shim::shim_name(executable)).display()));
+        }
+        if let Some(command) = package.installer.as_ref().and_then(|record| record.uninstall.as_ref()) {
+            ui::print_detail(&format!("run {}", command));
+        }
+        if package.executables.is_empty() && self.scope == Scope::User {
+            ui::print_detail(&format!("take {} off PATH", package.install_path.display()));
+        }
+        ui::print_detail(&format!("remove {}", self.root.join("packages").join(package_name).display()));
+        Ok(())
+    }
+
+    /// The aliases a package has once installed: those it already had, or on
+    /// a first install the registry's defaults that no other link is using
+    fn aliases_after_install(&self, package_name: &str, package: &Package) -> Result<Vec<String>> {
+        let state = self.package_state()?;
+        if let Some(installed) = state.get_package(package_name) {
+            return Ok(installed.aliases.clone());
+        }
+        let bin_dir = shim::bin_dir(&self.root);
+        let mut aliases = Vec::new();
+        for alias in &package.info.aliases {
+            let shim_name = shim::shim_name(Path::new(alias));
+            if let Some(owner) = state.shim_owner(&shim_name) {
+                ui::warning!("Not linking {} as {}, which {} already uses", package_name, alias, owner);
+            } else if bin_dir.join(&shim_name).symlink_metadata().is_ok() {
+                ui::warning!("Not linking {} as {}, which is already in {}", package_name, alias, bin_dir.display());
+            } else if !aliases.contains(alias) {
+                aliases.push(alias.clone());
+            }
+        }
+        Ok(aliases)
+    }
+
+    /// Links the main executable of `package_name` under `alias` as well
+    pub fn alias(&mut self, alias: &str, package_name: &str) -> Result<()> {
+        if alias.is_empty() || alias.starts_with('.') || alias.contains(['/', '\\']) {
+            bail!(Usage, "'{}' isn't a valid command name", alias);
+        }
+        let _lock = self.lock_package(package_name)?;
+        let installed = self.package_state()?.get_package(package_name).ok_or_else(|| {
+            GripError::InstallError(format!("Package '{}' is not installed", package_name))
+        })?;
+        let Some(main) = installed.executable().map(Path::to_path_buf) else {
+            bail!(InstallError, "{} has no executable to alias", package_name);
+        };
+
+        let bin_dir = shim::bin_dir(&self.root);
+        let shim_name = shim::shim_name(Path::new(alias));
+        match self.package_state()?.shim_owner(&shim_name) {
+            // Linking an existing alias again repairs it
+            Some(owner) if owner == package_name && installed.aliases.iter().any(|a| a == alias) => {}
+            Some(owner) => bail!(InstallError, "{} is already linked to {}", alias, owner),
+            None if bin_dir.join(&shim_name).symlink_metadata().is_ok() => bail!(
+                InstallError,
+                "{} already exists and wasn't created by grip",
+                bin_dir.join(&shim_name).display()
+            ),
+            None => {}
+        }
+
+        let shim = self.link_shim(&bin_dir, &shim_name, package_name, &main)?;
+        InstallManifest::add_links(&self.root, package_name, &installed.version, &[shim_name], &[])?;
+        self.update_state(|state| {
+            let mut aliases = state.get_package(package_name).map(|p| p.aliases.clone()).unwrap_or_default();
+            if !aliases.iter().any(|a| a == alias) {
+                aliases.push(alias.to_string());
+            }
+            state.set_aliases(package_name, aliases);
+            Ok(())
+        })?;
+        ui::success!("Linked {} to {}", shim.display(), package_name.cyan());
+        Ok(())
+    }
+
+    /// Removes an alias set up by `grip alias` or the registry
+    pub fn unalias(&mut self, alias: &str) -> Result<()> {
+        let owner = self
+            .package_state()?
+            .list_packages()
+            .into_iter()
+            .find(|(_, package)| package.aliases.iter().any(|a| a == alias))
+            .map(|(name, _)| name.clone())
+            .ok_or_else(|| GripError::Usage(format!("No installed package has the alias {}", alias)))?;
+        let _lock = self.lock_package(&owner)?;
+
+        shim::unlink(&shim::bin_dir(&self.root), &shim::shim_name(Path::new(alias)))?;
+        self.update_state(|state| {
+            let mut aliases = state.get_package(&owner).map(|p| p.aliases.clone()).unwrap_or_default();
+            aliases.retain(|a| a != alias);
+            state.set_aliases(&owner, aliases);
+            Ok(())
+        })?;
+        ui::success!("Removed the alias {} of {}", alias, owner.cyan());
+        Ok(())
+    }
+
+    /// `package_name` if it is installed, or every installed package by name
+    fn installed_names(&self, package_name: Option<&str>) -> Result<Vec<String>> {
+        match package_name {
+            Some(name) => {
+                if self.package_state()?.get_package(name).is_none() {
+                    bail!(PackageNotFound, "{} is not installed", name);
+                }
+                Ok(vec![name.to_string()])
+            }
+            None => {
+                let mut names: Vec<String> =
+                    self.package_state()?.list_packages().into_iter().map(|(name, _)| name.clone()).collect();
+                names.sort();
+                Ok(names)
+            }
+        }
+    }
+
+    /// Re-hashes the active version's files of `package_name`, or of every
+    /// installed package, against the manifest recorded when it was installed.
+    /// With `repair`, packages whose files changed are installed again, from
+    /// the cached asset when there is one. Returns whether everything matched
+    pub async fn verify(&mut self, package_name: Option<&str>, repair: bool) -> Result<bool> {
+        let packages = match package_name {
+            Some(name) => match self.state_store.package(name)? {
+                Some(package) => vec![(name.to_string(), package)],
+                None => bail!(PackageNotFound, "{} is not installed", name),
+            },
+            None => self.state_store.packages(None)?,
+        };
+        let mut results = Vec::new();
+        for (name, package) in packages {
+            let package_dir = self.root.join("packages").join(&name);
+            let checked = match FileManifest::load(&FileManifest::path(&package_dir, &package.version))? {
+                Some(manifest) => Some((manifest.files.len(), manifest.verify(&package.install_path)?)),
+                None => None,
+            };
+            results.push((name, package.version.clone(), checked));
+        }
+
+        if ui::json_output() {
+            let json: Vec<_> = results
+                .iter()
+                .map(|(name, version, checked)| {
+                    serde_json::json!({
+                        "name": name,
+                        "version": version,
+                        "verified": checked.is_some(),
+                        "changes": checked.as_ref().map(|(_, changes)| changes),
+                    })
+                })
+                .collect();
+            ui::print_json(&json)?;
+        } else {
+            for (name, version, checked) in &results {
+                match checked {
+                    None => ui::warning!(
+                        "{} {}: no record of its files; reinstall it to verify it later",
+                        name.cyan(),
+                        version
+                    ),
+                    Some((count, changes)) if changes.is_empty() => {
+                        ui::success!("{} {}: {} file(s) intact", name.cyan(), version, count)
+                    }
+                    Some((_, changes)) => {
+                        ui::failure!("{} {}: {} file(s) changed", name.cyan(), version, changes.len());
+                        for change in changes {
+                            let label = match change.change {
+                                Change::Modified => "modified".yellow(),
+                                Change::Missing => "missing".red(),
+                                Change::Added => "added".blue(),
+                            };
+                            println!("    {:<8} {}", label, change.path);
+                        }
+                    }
+                }
+            }
+        }
+
+        let changed: Vec<&String> = results
+            .iter()
+            .filter(|(_, _, checked)| checked.as_ref().is_some_and(|(_, changes)| !changes.is_empty()))
+            .map(|(name, _, _)| name)
+            .collect();
+        if changed.is_empty() {
+            return Ok(true);
+        }
+        if !repair {
+            if !ui::json_output() {
+                println!("\n{} Run `grip verify --repair` to reinstall them", "→".blue());
+            }
+            return Ok(false);
+        }
+        for name in changed {
+            ui::status!("Repairing {}", name.cyan());
+            self.reinstall(name).await?;
+        }
+        Ok(true)
+    }
+
+    /// Installs the active version of an installed package again, with the
+    /// asset and from the source it was installed from
+    async fn reinstall(&mut self, package_name: &str) -> Result<()> {
+        let installed = self.package_state()?.get_package(package_name).cloned().ok_or_else(|| {
+            GripError::InstallError(format!("Package '{}' is not installed", package_name))
+        })?;
+        let reinstall = std::mem::replace(&mut self.reinstall, true);
+        let result = self
+            .install_from_source(package_name, &installed, &installed.version, installed.asset_name.clone())
+            .await;
+        self.reinstall = reinstall;
+        result
+    }
+
+    /// Installs `version` of an installed package through the same route it
+    /// was first installed by
+    async fn install_from_source(
+        &mut self,
+        package_name: &str,
+        installed: &InstalledPackage,
+        version: &str,
+        asset: Option<String>,
+    ) -> Result<()> {
+        match installed.repository.as_deref() {
+            Some(source) if provider::is_direct_source(source) => {
+                let (url, file) = match source.split_once(':') {
+                    Some(("url", url)) => (Some(url.to_string()), None),
+                    Some((_, path)) => (None, Some(PathBuf::from(path))),
+                    None => (None, None),
+                };
+                self.install_direct(package_name, version, url, file).await?;
+            }
+            repository => {
+                // Packages installed straight from a repository have no registry entry
+                let lookup = match repository {
+                    Some(repository) if provider::is_repository_spec(repository) => repository.to_string(),
+                    _ => package_name.to_string(),
+                };
+                self.install(&lookup, Some(version.to_string()), asset, false, true, None)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends to the history, warning rather than failing the change it records
+    fn record_history(&self, transaction: Transaction) {
+        if let Err(e) = self.state_store.record(transaction) {
+            ui::warning!("Failed to update the history: {}", e);
+        }
+    }
+
+    /// Prints a software bill of materials for every installed package
+    pub fn sbom(&self, format: SbomFormat) -> Result<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        ui::print_json(&sbom::generate(&self.package_state()?.list_packages(), format, now))
+    }
+
+    /// Prints the log of installs, upgrades and uninstalls, of every package
+    /// or only `package_name`
+    pub fn history(&self, package_name: Option<&str>) -> Result<()> {
+        let transactions = self.state_store.history(package_name)?;
+        if ui::json_output() {
+            return ui::print_json(&transactions);
+        }
+        if transactions.is_empty() {
+            ui::status!("No history yet");
+            return Ok(());
+        }
+
+        let mut table = ui::Table::new(&["DATE", "ACTION", "PACKAGE", "VERSIONS"]);
+        for transaction in &transactions {
+            let versions = match (&transaction.from, &transaction.to) {
+                (Some(from), Some(to)) if from != to => format!("{} → {}", from, to),
+                (_, Some(to)) => to.clone(),
+                (Some(from), None) => from.clone(),
+                (None, None) => String::new(),
+            };
+            table.add_row(vec![
+                utils::format_time(transaction.timestamp),
+                transaction.action.name().to_string(),
+                transaction.package.cyan().to_string(),
+                versions,
+            ]);
+        }
+        table.print();
+        Ok(())
+    }
+
+    /// Prints how often and when each installed package was run, most run
+    /// first, from the runs counted with `usage_stats` on. `reset` forgets them
+    pub fn stats(&self, reset: bool) -> Result<()> {
+        if reset {
+            usage::reset(&self.root)?;
+            ui::success!("Forgot the counted runs");
+            return Ok(());
+        }
+        let enabled = self.config.settings.usage_stats;
+        let record = usage::load(&self.root)?;
+        let mut packages: Vec<(&String, &InstalledPackage, usage::PackageUsage)> = self
+            .package_state()?
+            .list_packages()
+            .into_iter()
+            .map(|(name, package)| {
+                let counted = record.as_ref().and_then(|r| r.packages.get(name)).cloned();
+                (name, package, counted.unwrap_or_default())
+            })
+            .collect();
+        packages.sort_by(|a, b| {
+            b.2.runs
+                .cmp(&a.2.runs)
+                .then(b.2.last_run.cmp(&a.2.last_run))
+                .then(a.0.cmp(b.0))
+        });
+
+        if ui::json_output() {
+            let packages: Vec<_> = packages
+                .iter()
+                .map(|(name, package, counted)| {
+                    serde_json::json!({
+                        "name": name,
+                        "version": package.version,
+                        "runs": counted.runs,
+                        "last_run": Some(counted.last_run).filter(|t| *t > 0),
+                        "installed_at": package.installed_at,
+                    })
+                })
+                .collect();
+            return ui::print_json(&serde_json::json!({
+                "enabled": enabled,
+                "since": record.as_ref().map(|r| r.since),
+                "packages": packages,
+            }));
+        }
+        if !enabled {
+            ui::status!(
+                "Usage statistics are off; `grip config set settings.usage_stats true` and then `grip reshim` turn them on"
+            );
+        }
+        let Some(record) = record else {
+            if enabled {
+                ui::status!("No runs counted yet");
+            }
+            return Ok(());
+        };
+        if packages.is_empty() {
+            ui::status!("No packages installed");
+            return Ok(());
+        }
+
+        let now = update_check::now();
+        let mut table = ui::Table::new(&["PACKAGE", "RUNS", "LAST RUN", "INSTALLED"]).align_right(1);
+        for (name, package, counted) in &packages {
+            let last_run = match counted.last_run {
+                0 => "never".to_string(),
+                at => utils::format_age(now.saturating_sub(at)),
+            };
+            table.add_row(vec![
+                name.cyan().to_string(),
+                counted.runs.to_string(),
+                last_run,
+                package.installed_at.map(utils::format_date).unwrap_or_else(|| "-".to_string()),
+            ]);
+        }
+        table.print();
+        ui::status!("Runs counted since {}", utils::format_date(record.since));
+        Ok(())
+    }
+
+    /// Suggests uninstalling packages not run for `period`, such as `90d`,
+    /// going by the runs counted with `usage_stats` on. Packages installed, or
+    /// counted, for less than that haven't had the chance to be run and aren't
+    /// suggested, and neither are those other packages depend on
+    pub fn suggest_unused(&self, period: &str) -> Result<()> {
+        let Some(within) = utils::parse_duration(period) else {
+            bail!(Usage, "Invalid duration '{}'; use a number with s, m, h, d or w, e.g. 90d", period);
+        };
+        if !self.config.settings.usage_stats {
+            bail!(
+                Usage,
+                "--unused goes by counted runs, which are off; `grip config set settings.usage_stats true` and then `grip reshim` turn them on"
+            );
+        }
+        let now = update_check::now();
+        let record = usage::load(&self.root)?;
+        let since = record.as_ref().map_or(now, |r| r.since);
+        let cutoff = now.saturating_sub(within.as_secs());
+
+        let mut unused = Vec::new();
+        for (name, package) in self.package_state()?.list_packages() {
+            let last_run = record
+                .as_ref()
+                .and_then(|r| r.packages.get(name))
+                .map_or(0, |counted| counted.last_run);
+            let last_seen = last_run.max(since).max(package.installed_at.unwrap_or(0));
+            if last_seen <= cutoff && self.package_state()?.dependents(name).is_empty() {
+                unused.push((name, package, last_run));
+            }
+        }
+
+        if ui::json_output() {
+            let packages: Vec<_> = unused
+                .iter()
+                .map(|(name, package, last_run)| {
+                    serde_json::json!({
+                        "name": name,
+                        "version": package.version,
+                        "last_run": Some(*last_run).filter(|t| *t > 0),
+                        "size": package.size,
+                    })
+                })
+                .collect();
+            return ui::print_json(&serde_json::json!({ "since": since, "unused": packages }));
+        }
+        if unused.is_empty() {
+            if since > cutoff {
+                ui::status!(
+                    "Runs have only been counted since {}, so nothing has gone unused for {} yet",
+                    utils::format_date(since),
+                    period
+                );
+            } else {
+                ui::status!("Every package was run in the last {}", period);
+            }
+            return Ok(());
+        }
+
+        let mut table = ui::Table::new(&["PACKAGE", "VERSION", "LAST RUN", "SIZE"]).align_right(3);
+        for (name, package, last_run) in &unused {
+            let last_run = match last_run {
+                0 => "never".to_string(),
+                at => utils::format_age(now.saturating_sub(*at)),
+            };
+            table.add_row(vec![
+                name.cyan().to_string(),
+                package.version.clone(),
+                last_run,
+                package.size.map(utils::format_size).unwrap_or_else(|| "-".to_string()),
+            ]);
+        }
+        table.print();
+        let freed: u64 = unused.iter().filter_map(|(_, package, _)| package.size).sum();
+        ui::status!(
+            "{} package(s) not run in the last {} take {}; `grip uninstall <package>` removes one, e.g. `grip uninstall {}`",
+            unused.len(),
+            period,
+            utils::format_size(freed),
+            unused[0].0
+        );
+        Ok(())
+    }
+
+    /// Reverts the most recent upgrade to `package_name`'s active version,
+    /// switching back to the version it replaced. That version's directory is
+    /// used if it is still there; otherwise the version is installed again,
+    /// from the download cache when possible
+    pub async fn rollback(&mut self, package_name: &str) -> Result<()> {
+        let installed = self.package_state()?.get_package(package_name).cloned().ok_or_else(|| {
+            GripError::InstallError(format!("Package '{}' is not installed", package_name))
+        })?;
+        let current = installed.version.clone();
+        let previous = self
+            .state_store
+            .history(Some(package_name))?
+            .into_iter()
+            .rev()
+            .find(|t| t.action == Action::Upgrade && t.to.as_ref() == Some(&current))
+            .and_then(|t| t.from)
+            .ok_or_else(|| {
+                GripError::Other(format!("No upgrade of {} to {} to roll back", package_name, current))
+            })?;
+
+        let version_dir = self.root.join("packages").join(package_name).join(&previous);
+        if installed.installed_versions.contains(&previous) && version_dir.is_dir() {
+            self.use_version(package_name, &previous).await?;
+            self.record_history(Transaction::new(Action::Rollback, package_name, Some(current), Some(previous.clone())));
+        } else {
+            if installed.repository.as_deref().is_some_and(provider::is_direct_source) {
+                bail!(
+                    InstallError,
+                    "{} {} was removed and {} was installed from a URL or file; reinstall the old build with --url or --file",
+                    package_name,
+                    previous,
+                    package_name
+                );
+            }
+            ui::status!("{} {} was removed; installing it again", package_name.cyan(), previous);
+            self.rolling_back = true;
+            let result = self.install_from_source(package_name, &installed, &previous, None).await;
+            self.rolling_back = false;
+            result?;
+        }
+
+        ui::success!("Rolled {} back to {}", package_name.cyan(), previous);
+        ui::status!("Run `grip pin {}` to keep `upgrade --all` from upgrading it again", package_name);
+        Ok(())
+    }
+
+    /// Switches which installed version of a package its shim points at
+    pub async fn use_version(&mut self, package_name: &str, version: &str) -> Result<()> {
+        let _lock = self.lock_package(package_name)?;
+        let installed = self.package_state()?.get_package(package_name).ok_or_else(|| {
+            GripError::InstallError(format!("Package '{}' is not installed", package_name))
+        })?;
+
+        if installed.version == version {
+            ui::success!("{} {} is already active", package_name.cyan(), version);
+            return Ok(());
+        }
+
+        let version_dir = self
+            .root
+            .join("packages")
+            .join(package_name)
+            .join(version);
+        if !installed.installed_versions.iter().any(|v| v == version) || !version_dir.is_dir() {
+            bail!(
+                InstallError,
+                "{} {} is not installed; run `grip install {} --version {}` first",
+                package_name,
+                version,
+                package_name,
+                version
+            );
+        }
+
+        let old_executables = installed.executables.clone();
+        let previous_extras = (installed.extras.clone(), installed.install_path.clone());
+        let ran_installer = installed.installer.is_some();
+        let executables = locate_executables(installed, &version_dir, package_name)?;
+
+        let bin_dir = shim::bin_dir(&self.root);
+        for old in &old_executables {
+            shim::unlink(&bin_dir, &shim::shim_name(old))?;
+        }
+        let mut shims = Vec::new();
+        for executable in &executables {
+            shims.push(shim::shim_name(executable));
+            self.link_shim(&bin_dir, &shim::shim_name(executable), package_name, executable)?;
+        }
+        if let Some(main) = executables.first() {
+            for alias in &installed.aliases {
+                shims.push(shim::shim_name(Path::new(alias)));
+                self.link_shim(&bin_dir, &shim::shim_name(Path::new(alias)), package_name, main)?;
+            }
+        }
+
+        self.update_state(|state| {
+            state.set_active(package_name, version, version_dir.clone(), executables)
+        })?;
+        if !ran_installer {
+            self.link_extras(package_name, &version_dir, Some(previous_extras))?;
+        }
+        let extras = self
+            .package_state()?
+            .get_package(package_name)
+            .map(|p| p.extras.clone())
+            .unwrap_or_default();
+        InstallManifest::add_links(&self.root, package_name, version, &shims, &extras)?;
+
+        ui::success!("Now using {} {}", package_name.cyan(), version);
+        Ok(())
+    }
+
+    /// Runs a package's executable, installing the package first if it or the
+    /// requested version is missing
+    pub async fn run(&mut self, spec: &str, args: &[String]) -> Result<i32> {
+        let (requested, mut version) = project::parse_spec(spec);
+        let package_name = installed_name(&requested).to_string();
+
+        // A version file pins the version unless one was asked for
+        let cwd = std::env::current_dir()?;
+        let pin = version_file::find(&cwd, &package_name).filter(|_| version.is_none());
+        let installed_version = self.package_state()?.get_package(&package_name).and_then(|p| {
+            match (&version, &pin) {
+                (Some(v), _) => p.installed_versions.iter().find(|i| version_file::matches(i, v)).cloned(),
+                (None, Some(pin)) => pinned_tag(p, pin),
+                (None, None) => Some(p.version.clone()),
+            }
+        });
+        if installed_version.is_none() {
+            if let Some(pin) = &pin {
+                tracing::debug!(package = %package_name, file = %pin.file.display(), "pinned by version file");
+                version = pin.versions.first().cloned();
+            }
+        }
+
+        let tag = match installed_version {
+            Some(tag) => tag,
+            None => {
+                ui::status!("{} is not installed, installing it first", requested.cyan());
+                self.install(&requested, version, None, false, false, None).await?.tag
+            }
+        };
+
+        let installed = self
+            .package_state()?
+            .get_package(&package_name)
+            .ok_or_else(|| GripError::InstallError(format!("Package '{}' is not installed", package_name)))?;
+        let executable = if installed.version == tag {
+            installed.executable().map(Path::to_path_buf)
+        } else {
+            let version_dir = self
+                .root
+                .join("packages")
+                .join(&package_name)
+                .join(&tag);
+            locate_executables(installed, &version_dir, &package_name)?
+                .into_iter()
+                .next()
+        };
+        let executable = executable.ok_or_else(|| {
+            GripError::InstallError(format!("No executable found for {} {}", package_name, tag))
+        })?;
+        self.record_run(&package_name);
+        shim::exec(&executable, None, args)
+    }
+
+    /// Runs what a `version_shims` shim stands for: the executable named
+    /// `file_name` from the version a version file pins for the current
+    /// directory, or else from the active version. `arg0` is the name the
+    /// shim was run as, which the executable is run as too
+    pub fn run_shim(&self, package_name: &str, file_name: &str, arg0: Option<&str>, args: &[String]) -> Result<i32> {
+        let installed = self
+            .package_state()?
+            .get_package(package_name)
+            .ok_or_else(|| GripError::InstallError(format!("Package '{}' is not installed", package_name)))?;
+
+        let executables = match version_file::find(&std::env::current_dir()?, package_name) {
+            Some(pin) => {
+                let Some(tag) = pinned_tag(installed, &pin) else {
+                    bail!(
+                        InstallError,
+                        "{} pins {} to {}, which isn't installed; run `grip install {} --version {}`",
+                        pin.file.display(),
+                        package_name,
+                        pin.versions.join(" or "),
+                        package_name,
+                        pin.versions[0]
+                    );
+                };
+                let version_dir = self.root.join("packages").join(package_name).join(&tag);
+                locate_executables(installed, &version_dir, package_name)?
+            }
+            None => installed.executables.clone(),
+        };
+        let executable = executables
+            .iter()
+            .find(|e| e.file_name().is_some_and(|name| name == file_name))
+            .or(executables.first())
+            .ok_or_else(|| GripError::InstallError(format!("No executable found for {}", package_name)))?;
+        let program = shim::multi_call_program(&shim::bin_dir(&self.root), executable, arg0);
+        self.record_run(package_name);
+        shim::exec(&program, arg0, args)
+    }
+
+    /// Counts a run of `package_name` with `usage_stats` on. Failing to is
+    /// only logged, since the program should start either way
+    fn record_run(&self, package_name: &str) {
+        if !self.config.settings.usage_stats || self.scope != Scope::User {
+            return;
+        }
+        if let Err(e) = usage::record_run(&self.root, package_name) {
+            tracing::debug!(package = %package_name, error = %e, "failed to count the run");
+        }
+    }
+
+    /// Links every installed package's shims again, e.g. after turning
+    /// `version_shims` or `usage_stats` on or off
+    pub fn reshim(&mut self) -> Result<()> {
+        let bin_dir = shim::bin_dir(&self.root);
+        let packages: Vec<(String, InstalledPackage)> = self
+            .package_state()?
+            .list_packages()
+            .into_iter()
+            .map(|(name, package)| (name.clone(), package.clone()))
+            .collect();
+        for (name, package) in &packages {
+            let _lock = self.lock_package(name)?;
+            for executable in package.executables.iter().filter(|e| e.is_file()) {
+                self.link_shim(&bin_dir, &shim::shim_name(executable), name, executable)?;
+            }
+            if let Some(main) = package.executable().filter(|main| main.is_file()) {
+                for alias in &package.aliases {
+                    self.link_shim(&bin_dir, &shim::shim_name(Path::new(alias)), name, main)?;
+                }
+            }
+        }
+        ui::success!("Linked the shims of {} packages", packages.len());
+        Ok(())
+    }
+
+    /// Runs `grip-<name>` for a subcommand grip doesn't have, passing it where
+    /// grip keeps its data through the environment. Returns its exit code
+    pub fn run_plugin(&self, name: &str, args: &[String]) -> Result<i32> {
+        let bin_dir = shim::bin_dir(&self.root);
+        let Some(plugin) = plugin::find(name, &bin_dir) else {
+            bail!(
+                Usage,
+                "'{}' isn't a grip command, and no grip-{} executable was found on PATH",
+                name,
+                name
+            );
+        };
+        tracing::debug!(plugin = %plugin.display(), "running plugin");
+
+        let mut command = std::process::Command::new(&plugin);
+        command
+            .args(args)
+            .env("GRIP_VERSION", env!("CARGO_PKG_VERSION"))
+            .env("GRIP_DATA_DIR", &self.root)
+            .env("GRIP_BIN_DIR", &bin_dir)
+            .env("GRIP_STATE", self.state_store.export()?)
+            .env("GRIP_CONFIG", self.config.file())
+            .env("GRIP_OUTPUT", if ui::json_output() { "json" } else { "text" });
+        if let Ok(grip) = std::env::current_exe() {
+            command.env("GRIP", grip);
+        }
+        let status = command
+            .status()
+            .map_err(|e| GripError::Other(format!("Failed to run {}: {}", plugin.display(), e)))?;
+        Ok(status.code().unwrap_or(1))
+    }
+
+    /// Checks installed packages, shims, PATH and registries, printing a fix for
+    /// each problem. Returns whether everything is healthy
+    pub async fn doctor(&self) -> Result<bool> {
+        let mut findings = self.local_findings()?;
+        for registry in &self.config.registries {
+            findings.push(match self.registry_manager.check_registry(registry).await {
+                Ok(()) => Finding::ok(
+                    "registry",
+                    format!("{} ({}) is reachable", registry.name, registry.url),
+                ),
+                Err(e) => Finding::problem(
+                    "registry",
+                    format!("{} ({}) is unreachable: {}", registry.name, registry.url, e),
+                    if registry.name == "default" {
+                        "check your network connection".to_string()
+                    } else {
+                        format!(
+                            "check the URL and credentials, or remove it with `grip registry remove {}`",
+                            registry.name
+                        )
+                    },
+                ),
+            });
+        }
+
+        let healthy = findings.iter().all(|f| f.ok);
+        if ui::json_output() {
+            ui::print_json(&findings)?;
+            return Ok(healthy);
+        }
+
+        for finding in &findings {
+            if finding.ok {
+                ui::success!("{}", finding.detail);
+            } else {
+                ui::failure!("{}", finding.detail);
+                if let Some(fix) = &finding.fix {
+                    println!("    {} {}", "fix:".yellow(), fix);
+                }
+            }
+        }
+
+        let problems = findings.iter().filter(|f| !f.ok).count();
+        if problems == 0 {
+            ui::success!("No problems found");
+        } else {
+            ui::warning!("{} problem(s) found", problems);
+        }
+        Ok(healthy)
+    }
+
+    /// A one-screen overview to check before starting work: installed and
+    /// outdated packages, the cache, how fresh the registries are, the project
+    /// in the current directory and the problems `doctor` finds without the
+    /// network. Outdated packages are counted from cached release lists and
+    /// the last update check unless `--refresh` asks for the latest
+    pub async fn status(&mut self) -> Result<()> {
+        // A copy, as the registry settings change while it is in use
+        let state = self.package_state()?.clone();
+        let mut installed = state.list_packages();
+        installed.sort_by_key(|(name, _)| name.as_str());
+        let offline = self.registry_manager.offline;
+        self.registry_manager.offline = offline || !self.registry_manager.refresh;
+        let latest = self.latest_releases(&installed).await;
+        self.registry_manager.offline = offline;
+
+        let checked = update_check::load(&self.root);
+        let (mut outdated, mut unknown) = (Vec::new(), 0);
+        for ((name, package), release) in installed.iter().zip(&latest) {
+            let latest = release
+                .as_ref()
+                .map(|release| &release.tag_name)
+                .or_else(|| checked.latest.get(*name));
+            match latest {
+                Some(latest) if is_newer(latest, &package.version) => outdated.push(name.as_str()),
+                Some(_) => {}
+                None => unknown += 1,
+            }
+        }
+
+        let cached = self.cache.list()?;
+        let cache_size: u64 = cached.iter().map(|entry| entry.size).sum();
+        let registries: Vec<(Registry, Option<std::time::Duration>)> = registry::by_priority(&self.config.registries)
+            .into_iter()
+            .map(|registry| {
+                let age = self.registry_manager.registry_age(&registry);
+                (registry, age)
+            })
+            .collect();
+        let project = match Manifest::find()? {
+            Some(path) => Some((Manifest::load(&path)?, path)),
+            None => None,
+        };
+        let problems: Vec<Finding> = self.local_findings()?.into_iter().filter(|f| !f.ok).collect();
+
+        if ui::json_output() {
+            return ui::print_json(&serde_json::json!({
+                "installed": installed.len(),
+                "outdated": outdated,
+                "unknown": unknown,
+                "cache": { "assets": cached.len(), "bytes": cache_size },
+                "registries": registries.iter().map(|(registry, age)| serde_json::json!({
+                    "name": registry.name,
+                    "url": registry.url,
+                    "age_seconds": age.map(|age| age.as_secs()),
+                    "stale": age.is_some_and(|age| age >= self.registry_manager.cache_ttl),
+                })).collect::<Vec<_>>(),
+                "project": project.as_ref().map(|(manifest, path)| serde_json::json!({
+                    "name": manifest.name,
+                    "path": path,
+                    "dependencies": manifest.dependencies.len(),
+                    "locked": path.with_file_name(project::LOCK_FILE).exists(),
+                })),
+                "problems": problems,
+            }));
+        }
+
+        let mut packages = format!("{} installed", installed.len());
+        match outdated.len() {
+            0 => {}
+            1 => packages.push_str(&format!(", {} outdated ({})", "1".yellow(), outdated[0])),
+            n if n <= 3 => packages.push_str(&format!(", {} outdated ({})", n.to_string().yellow(), outdated.join(", "))),
+            n => packages.push_str(&format!(", {} outdated", n.to_string().yellow())),
+        }
+        if unknown > 0 {
+            packages.push_str(&format!(", {} not checked yet", unknown));
+        }
+        println!("  {:<12} {}", "packages:", packages);
+        println!(
+            "  {:<12} {} asset(s), {}",
+            "cache:",
+            cached.len(),
+            utils::format_size(cache_size)
+        );
+        for (i, (registry, age)) in registries.iter().enumerate() {
+            let freshness = match age {
+                None if matches!(registry.source(), RegistrySource::Local(_)) => "local directory".normal(),
+                None => "not fetched yet".yellow(),
+                Some(age) if *age >= self.registry_manager.cache_ttl => {
+                    format!("updated {}", utils::format_age(age.as_secs())).yellow()
+                }
+                Some(age) => format!("updated {}", utils::format_age(age.as_secs())).normal(),
+            };
+            let label = if i == 0 { "registries:" } else { "" };
+            println!("  {:<12} {} ({})", label, registry.name.cyan(), freshness);
+        }
+        match &project {
+            Some((manifest, path)) => {
+                let locked = if path.with_file_name(project::LOCK_FILE).exists() {
+                    "locked"
+                } else {
+                    "not locked"
+                };
+                println!(
+                    "  {:<12} {} ({}), {} dependencies, {}",
+                    "project:",
+                    manifest.name.cyan(),
+                    path.display(),
+                    manifest.dependencies.len(),
+                    locked
+                );
+            }
+            None => println!("  {:<12} none", "project:"),
+        }
+        if problems.is_empty() {
+            println!("  {:<12} {}", "problems:", "none".green());
+        } else {
+            for (i, problem) in problems.iter().enumerate() {
+                let label = if i == 0 { "problems:" } else { "" };
+                println!("  {:<12} {}", label, problem.detail.red());
+            }
+        }
+
+        if !outdated.is_empty() {
+            println!("\n{} Run `grip upgrade --all` to update", "→".blue());
+        }
+        if !problems.is_empty() {
+            println!("{} Run `grip doctor` for fixes", "→".blue());
+        }
+        Ok(())
+    }
+
+    /// The checks of `doctor` that need no network: the package state,
+    /// installed files, shims and PATH
+    fn local_findings(&self) -> Result<Vec<Finding>> {
+        let data_dir = &self.root;
+        let bin_dir = shim::bin_dir(data_dir);
+        let state = self.package_state()?;
+        let mut findings = vec![Finding::ok(
+            "state",
+            format!(
+                "{} parses ({} package(s))",
+                self.state_store.path().display(),
+                state.packages.len()
+            ),
+        )];
+
+        let mut packages = state.list_packages();
+        packages.sort_by_key(|(name, _)| name.as_str());
+        for (name, package) in &packages {
+            let reinstall = format!("grip install {} --version {}", name, package.version);
+
+            if !package.install_path.is_dir() {
+                findings.push(Finding::problem(
+                    "package",
+                    format!("{} {}: {} is missing", name, package.version, package.install_path.display()),
+                    format!("reinstall with `{}` or remove it with `grip uninstall {}`", reinstall, name),
+                ));
+                continue;
+            }
+            for version in &package.installed_versions {
+                let dir = data_dir.join("packages").join(name).join(version);
+                if !dir.is_dir() {
+                    findings.push(Finding::problem(
+                        "package",
+                        format!("{} {}: {} is missing", name, version, dir.display()),
+                        format!("reinstall with `grip install {} --version {}`", name, version),
+                    ));
+                }
+            }
+
+            if package.executables.is_empty() {
+                findings.push(Finding::ok(
+                    "package",
+                    format!("{} {} (no executable, directory added to PATH)", name, package.version),
+                ));
+                continue;
+            }
+            let problems_before = findings.len();
+            for executable in &package.executables {
+                if !executable.is_file() {
+                    findings.push(Finding::problem(
+                        "executable",
+                        format!("{}: {} is missing", name, executable.display()),
+                        format!("reinstall with `{}`", reinstall),
+                    ));
+                } else if !utils::is_binary(executable) {
+                    findings.push(Finding::problem(
+                        "executable",
+                        format!("{}: {} is not executable", name, executable.display()),
+                        format!("run `chmod +x {}` or reinstall with `{}`", executable.display(), reinstall),
+                    ));
+                } else if !shim::is_linked(&bin_dir, &shim::shim_name(executable), executable) {
+                    findings.push(Finding::problem(
+                        "shim",
+                        format!(
+                            "{}: {} does not point at {}",
+                            name,
+                            bin_dir.join(shim::shim_name(executable)).display(),
+                            executable.display()
+                        ),
+                        format!("relink it with `grip use {} {}`", name, package.version),
+                    ));
+                }
+            }
+            if let Some(main) = package.executable().filter(|main| main.is_file()) {
+                for alias in &package.aliases {
+                    let shim_name = shim::shim_name(Path::new(alias));
+                    if !shim::is_linked(&bin_dir, &shim_name, main) {
+                        findings.push(Finding::problem(
+                            "shim",
+                            format!(
+                                "{}: alias {} does not point at {}",
+                                name,
+                                bin_dir.join(&shim_name).display(),
+                                main.display()
+                            ),
+                            format!("relink it with `grip alias {} {}`", alias, name),
+                        ));
+                    }
+                }
+            }
+            if findings.len() > problems_before {
+                continue;
+            }
+            findings.push(Finding::ok("package", format!("{} {}", name, package.version)));
+        }
+
+        let on_path = std::env::var_os("PATH")
+            .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir == bin_dir));
+        findings.push(if on_path || self.scope == Scope::Project {
+            Finding::ok("path", format!("{} is on PATH", bin_dir.display()))
+        } else {
+            Finding::problem(
+                "path",
+                format!("{} is not on PATH in this shell", bin_dir.display()),
+                "open a new terminal, or add `eval \"$(grip shellenv)\"` to your shell's rc file",
+            )
+        });
+        Ok(findings)
+    }
+
+    /// Prints the cached assets and their total size
+    pub fn cache_list(&self) -> Result<()> {
+        let entries = self.cache.list()?;
+        if ui::json_output() {
+            return ui::print_json(&entries);
+        }
+        if entries.is_empty() {
+            ui::status!("Cache is empty");
+            return Ok(());
+        }
+
+        let mut table = ui::Table::new(&["ASSET", "SIZE", "SHA256"]).align_right(1);
+        for entry in &entries {
+            table.add_row(vec![
+                entry.file_name.clone(),
+                utils::format_size(entry.size),
+                entry.sha256[..12.min(entry.sha256.len())].to_string(),
+            ]);
+        }
+        table.print();
+        let total = entries.iter().map(|e| e.size).sum();
+        ui::status!("{} cached asset(s), {}", entries.len(), utils::format_size(total));
+        Ok(())
+    }
+
+    /// Removes every cached asset
+    pub fn cache_clean(&self) -> Result<()> {
+        let (count, freed) = self.cache.clean()?;
+        ui::success!("Removed {} cached asset(s), freeing {}", count, utils::format_size(freed));
+        Ok(())
+    }
+
+    /// Deletes stored files no installed version links to any more, as after
+    /// uninstalls and prunes
+    pub fn gc(&self) -> Result<()> {
+        let (count, freed) = self.store.gc()?;
+        if ui::json_output() {
+            return ui::print_json(&serde_json::json!({ "removed": count, "freed": freed }));
+        }
+        if count == 0 {
+            ui::status!("No unused files to remove");
+            return Ok(());
+        }
+        ui::success!("Removed {} unused file(s), freeing {}", count, utils::format_size(freed));
+        Ok(())
+    }
+
+    /// Removes what nothing refers to any more: package directories and
+    /// versions the package state doesn't record, as interrupted installs
+    /// leave behind, and stored files no version uses. With `all`, also the
+    /// download cache, cached registry responses and clones of registries
+    /// that are no longer configured
+    pub fn clean(&mut self, all: bool) -> Result<()> {
+        let mut removed: Vec<(PathBuf, u64)> = Vec::new();
+
+        let packages_dir = self.root.join("packages");
+        let names: Vec<String> = std::fs::read_dir(&packages_dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.file_name().to_string_lossy().into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        for name in names {
+            // Held so a directory an install is staging into isn't removed under it
+            let _lock = self.lock_package(&name)?;
+            let package_dir = packages_dir.join(&name);
+            let Some(package) = self.package_state()?.get_package(&name) else {
+                removed.push(remove_tree(&package_dir)?);
+                continue;
+            };
+            let known: HashSet<PathBuf> = package
+                .installed_versions
+                .iter()
+                .flat_map(|version| [package_dir.join(version), FileManifest::path(&package_dir, version)])
+                .collect();
+            for entry in std::fs::read_dir(&package_dir)? {
+                let path = entry?.path();
+                if !known.contains(&path) {
+                    removed.push(remove_tree(&path)?);
+                }
+            }
+        }
+
+        // Manifests of versions no longer installed
+        for entry in std::fs::read_dir(self.root.join(install_manifest::DIR)).into_iter().flatten() {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let _lock = self.lock_package(&name)?;
+            let known: HashSet<PathBuf> = self
+                .package_state()?
+                .get_package(&name)
+                .map(|package| {
+                    package
+                        .installed_versions
+                        .iter()
+                        .map(|version| InstallManifest::path(&self.root, &name, version))
+                        .collect()
+                })
+                .unwrap_or_default();
+            if known.is_empty() {
+                removed.push(remove_tree(&entry.path())?);
+                continue;
+            }
+            for manifest in std::fs::read_dir(entry.path())? {
+                let path = manifest?.path();
+                if !known.contains(&path) {
+                    removed.push(remove_tree(&path)?);
+                }
+            }
+        }
+
+        let (stored_files, stored_bytes) = self.store.gc()?;
+
+        let (mut cached_assets, mut cached_bytes) = (0, 0);
+        if all {
+            (cached_assets, cached_bytes) = self.cache.clean()?;
+            let data_dir = &self.registry_manager.data_dir;
+            let responses = data_dir.join("responses");
+            if responses.exists() {
+                removed.push(remove_tree(&responses)?);
+            }
+            let cloned: Vec<&str> = self
+                .config
+                .registries
+                .iter()
+                .filter(|registry| matches!(registry.source(), RegistrySource::Git(_)))
+                .map(|registry| registry.name.as_str())
+                .collect();
+            for entry in std::fs::read_dir(data_dir.join("registries")).into_iter().flatten() {
+                let entry = entry?;
+                if !cloned.contains(&entry.file_name().to_string_lossy().as_ref()) {
+                    removed.push(remove_tree(&entry.path())?);
+                }
+            }
+        }
+
+        let freed = removed.iter().map(|(_, size)| size).sum::<u64>() + stored_bytes + cached_bytes;
+        if ui::json_output() {
+            let removed: Vec<_> = removed
+                .iter()
+                .map(|(path, size)| serde_json::json!({ "path": path, "size": size }))
+                .collect();
+            return ui::print_json(&serde_json::json!({
+                "removed": removed,
+                "stored_files": stored_files,
+                "cached_assets": cached_assets,
+                "freed": freed,
+            }));
+        }
+        for (path, size) in &removed {
+            ui::success!("Removed {} ({})", path.display(), utils::format_size(*size));
+        }
+        if stored_files > 0 {
+            ui::success!("Removed {} unused stored file(s) ({})", stored_files, utils::format_size(stored_bytes));
+        }
+        if cached_assets > 0 {
+            ui::success!("Removed {} cached asset(s) ({})", cached_assets, utils::format_size(cached_bytes));
+        }
+        if freed == 0 && removed.is_empty() {
+            ui::status!("Nothing to clean");
+        } else {
+            ui::status!("Freed {}", utils::format_size(freed));
+        }
+        Ok(())
+    }
+
+    /// Uninstalls every package, takes the PATH entries and startup file lines
+    /// grip added out again, and deletes the install root along with the
+    /// download cache, store and registry clones. The config and logs are
+    /// kept. Asks first unless `force` is set
+    pub async fn purge(&mut self, force: bool) -> Result<()> {
+        let data_dir = self.registry_manager.data_dir.clone();
+        let mut dirs = vec![self.root.clone()];
+        if self.scope == Scope::User && data_dir != self.root {
+            dirs.push(data_dir.clone());
+        }
+        if !force {
+            if self.non_interactive {
+                bail!(Usage, "grip purge removes every installed package; pass --force to run it without asking");
+            }
+            let listed: Vec<String> = dirs.iter().map(|dir| dir.display().to_string()).collect();
+            let confirmed = Confirm::new()
+                .with_prompt(format!(
+                    "Uninstall all {} package(s) and delete {}?",
+                    self.package_state()?.list_packages().len(),
+                    listed.join(" and ")
+                ))
+                .default(false)
+                .interact()?;
+            if !confirmed {
+                ui::status!("Nothing was removed");
+                return Ok(());
+            }
+        }
+
+        let mut names: Vec<String> = self.package_state()?.list_packages().into_iter().map(|(name, _)| name.clone()).collect();
+        names.sort();
+        for name in &names {
+            self.uninstall(name, true).await?;
+        }
+
+        let (path_entries, startup_files) = match self.scope {
+            Scope::User => (path::prune(&self.root, &[])?, path::remove_setup()?),
+            Scope::Project => (Vec::new(), Vec::new()),
+        };
+        // The data directory's logs stay, and with them what purge did
+        let mut removed = Vec::new();
+        for dir in &dirs {
+            if !dir.exists() {
+                continue;
+            }
+            for entry in std::fs::read_dir(dir)? {
+                let entry = entry?;
+                if *dir == data_dir && entry.file_name() == "logs" {
+                    continue;
+                }
+                removed.push(remove_tree(&entry.path())?.0);
+            }
+            let _ = std::fs::remove_dir(dir);
+        }
+
+        if ui::json_output() {
+            return ui::print_json(&serde_json::json!({
+                "uninstalled": names,
+                "path_entries": path_entries,
+                "startup_files": startup_files,
+                "removed": removed,
+            }));
+        }
+        for entry in &path_entries {
+            ui::success!("Removed {} from {}", entry.dir.display(), entry.location);
+        }
+        for file in &startup_files {
+            ui::success!("Removed the `grip shellenv` line from {}", file.display());
+        }
+        for dir in &dirs {
+            if *dir == data_dir {
+                ui::success!("Deleted {}, apart from its logs", dir.display());
+            } else {
+                ui::success!("Deleted {}", dir.display());
+            }
+        }
+        if !path_entries.is_empty() || !startup_files.is_empty() {
+            ui::warning!("Open a new terminal for the changes to take effect");
+        }
+        Ok(())
+    }
+
+    /// Prints the active version's executable, failing if it is missing
+    pub fn which(&self, package_name: &str) -> Result<()> {
+        let installed = self.state_store.package(package_name)?.ok_or_else(|| {
+            GripError::InstallError(format!("Package '{}' is not installed", package_name))
+        })?;
+        let executable = installed.executable().ok_or_else(|| {
+            GripError::InstallError(format!(
+                "{} {} has no recorded executable",
+                package_name, installed.version
+            ))
+        })?;
+        if !executable.is_file() {
+            return Err(GripError::InstallError(format!(
+                "Executable for {} {} is missing: {}",
+                package_name,
+                installed.version,
+                executable.display()
+            )));
+        }
+
+        if ui::json_output() {
+            return ui::print_json(&serde_json::json!({
+                "name": package_name,
+                "version": installed.version,
+                "executable": executable,
+                "executables": installed.executables,
+            }));
+        }
+        println!("{}", executable.display());
+        Ok(())
+    }
+
+    /// The directories grip puts on PATH: the shim directory, and the install
+    /// directory of each package without a linkable executable
+    fn path_dirs(&self) -> Result<Vec<PathBuf>> {
+        let mut dirs = vec![shim::bin_dir(&self.root)];
+        let mut packages = self.package_state()?.list_packages();
+        packages.sort_by(|a, b| a.0.cmp(b.0));
+        dirs.extend(
+            packages
+                .into_iter()
+                .filter(|(_, installed)| installed.executables.is_empty())
+                .map(|(_, installed)| installed.install_path.clone()),
+        );
+        Ok(dirs)
+    }
+
+    /// Takes PATH entries grip added for packages that are gone off PATH
+    /// again, along with repeated entries
+    pub fn path_prune(&self) -> Result<()> {
+        if self.scope == Scope::Project {
+            bail!(Usage, "Project installs aren't added to PATH, so there is nothing to prune");
+        }
+        let pruned = path::prune(&self.root, &self.path_dirs()?)?;
+
+        if ui::json_output() {
+            return ui::print_json(&pruned);
+        }
+        if pruned.is_empty() {
+            ui::success!("PATH has no stale or repeated grip entries");
+            return Ok(());
+        }
+        for entry in &pruned {
+            let reason = match entry.reason {
+                PruneReason::Stale => "no longer installed",
+                PruneReason::Duplicate => "duplicate",
+            };
+            ui::success!("Removed {} from {} ({})", entry.dir.display(), entry.location, reason);
+        }
+        ui::warning!("Open a new terminal for the changes to take effect");
+        Ok(())
+    }
+
+    /// Prints the statements that put grip's directories on PATH and load the
+    /// completions of installed packages, for `eval "$(grip shellenv)"` in a
+    /// shell's startup file
+    pub fn shellenv(&self, shell: Option<Shell>) -> Result<()> {
+        let shell = shell.or_else(Shell::detect).ok_or_else(|| {
+            GripError::Usage("Couldn't tell which shell you use; pass --shell".to_string())
+        })?;
+        let dirs = self.path_dirs()?;
+        let completions = extras::completions_dir(&self.root, shell);
+        let man = extras::share_dir(&self.root).join("man");
+
+        if ui::json_output() {
+            return ui::print_json(&serde_json::json!({
+                "shell": shell.name(),
+                "paths": dirs,
+                "completions": completions,
+                "man": man,
+                "setup": shell.setup_line(&dirs),
+            }));
+        }
+        print!("{}", shell.env_script(&dirs));
+        print!("{}", shell.share_script(&completions, &man));
+        Ok(())
+    }
+
+    pub fn pin(&mut self, package_name: &str, pinned: bool) -> Result<()> {
+        self.update_state(|state| {
+            state.set_pinned(package_name, pinned)
+        })?;
+
+        if pinned {
+            ui::success!("Pinned {}; `upgrade --all` will skip it", package_name.cyan());
+        } else {
+            ui::success!("Unpinned {}", package_name.cyan());
+        }
+        Ok(())
+    }
+
+    pub async fn upgrade(&mut self, package_name: Option<String>, all: bool) -> Result<()> {
+        let names: Vec<String> = match package_name {
+            Some(name) => vec![name],
+            None if all => self
+                .package_state()?
+                .list_packages()
+                .into_iter()
+                .map(|(name, _)| name.clone())
+                .collect(),
+            None => bail!(Usage, "Specify a package to upgrade or pass --all"),
+        };
+
+        if names.is_empty() {
+            ui::status!("No packages installed");
+            return Ok(());
+        }
+
+        let mut failed = Vec::new();
+        for name in &names {
+            let pinned = self
+                .package_state()?
+                .get_package(name)
+                .is_some_and(|p| p.pinned);
+            if pinned {
+                if !all {
+                    bail!(
+                        InstallError,
+                        "{} is pinned; run `grip unpin {}` to allow upgrades",
+                        name,
+                        name
+                    );
+                }
+                ui::status!("Skipping pinned package {}", name.cyan());
+                continue;
+            }
+
+            let direct = self
+                .package_state()?
+                .get_package(name)
+                .and_then(|p| p.repository.as_deref())
+                .is_some_and(provider::is_direct_source);
+            if direct && all {
+                ui::status!("Skipping {}, installed from a URL or file", name.cyan());
+                continue;
+            }
+
+            if let Err(e) = self.upgrade_package(name).await {
+                if !all {
+                    return Err(e);
+                }
+                ui::failure!("Failed to upgrade {}: {}", name.cyan(), e);
+                failed.push(name.as_str());
+            }
+        }
+
+        if !failed.is_empty() {
+            bail!(InstallError, "Failed to upgrade: {}", failed.join(", "));
+        }
+        Ok(())
+    }
+
+    async fn upgrade_package(&mut self, package_name: &str) -> Result<()> {
+        let Some(plan) = self.plan_upgrade(package_name).await? else {
+            return Ok(());
+        };
+        let PlannedUpgrade {
+            install,
+            current_version,
+            current_path,
+            releases,
+        } = plan;
+        let release = &install.release;
+
+        // Show what changed, breaking changes included, before committing to it
+        if !self.non_interactive && !self.dry_run && !ui::json_output() {
+            let notes = changelog::between(&releases, Some(&current_version), Some(&release.tag_name));
+            if !notes.is_empty() {
+                println!();
+                print!("{}", changelog::render_releases(&notes));
+            }
+            let confirmed = Confirm::new()
+                .with_prompt(format!(
+                    "Upgrade {} from {} to {}?",
+                    package_name, current_version, release.tag_name
+                ))
+                .default(true)
+                .interact()?;
+            if !confirmed {
+                ui::status!("Skipped upgrading {}", package_name.cyan());
+                return Ok(());
+            }
+        }
+
+        ui::status!("Upgrading {} from {} to {}", package_name.cyan(), current_version, release.tag_name);
+
+        let target_dir = self
+            .install_release(package_name, &install.package, release, &install.asset)
+            .await?
+            .install_path;
+
+        if self.dry_run {
+            if current_path != target_dir {
+                ui::print_detail(&format!("keep {} for `grip rollback`", current_path.display()));
+            }
+            return Ok(());
+        }
+
+        // The old version's directory stays for `grip rollback` until pruned,
+        // but only the new one belongs on PATH
+        if current_path != target_dir {
+            self.remove_from_path(&current_path)?;
+        }
+
+        ui::success!("Upgraded {} to {}", package_name.cyan(), release.tag_name);
+        Ok(())
+    }
+
+    /// Finds the latest release of an installed package and the asset to
+    /// upgrade to, or `None` when it is up to date
+    async fn plan_upgrade(&self, package_name: &str) -> Result<Option<PlannedUpgrade>> {
+        let (current_version, current_asset, current_path, current_executables, lookup) = {
+            let installed = self.package_state()?.get_package(package_name).ok_or_else(|| {
+                GripError::InstallError(format!("Package '{}' is not installed", package_name))
+            })?;
+            if installed.repository.as_deref().is_some_and(provider::is_direct_source) {
+                bail!(
+                    InstallError,
+                    "{} was installed from a URL or file; reinstall it with --url or --file to update",
+                    package_name
+                );
+            }
+            (
+                installed.version.clone(),
+                installed.asset_name.clone(),
+                installed.install_path.clone(),
+                installed
+                    .executables
+                    .iter()
+                    .filter_map(|e| e.file_name().map(|n| n.to_string_lossy().into_owned()))
+                    .collect::<Vec<_>>(),
+                lookup_name(package_name, installed, &self.config.registries),
+            )
+        };
+
+        ui::status!("Checking {} for updates", package_name.cyan());
+
+        let mut package = self
+            .registry_manager
+            .find_package(&self.config.registries, &lookup)
+            .await?;
+        // Without a registry entry, keep the executables the package was installed with
+        if package.info.executables.is_empty() {
+            package.info.executables = current_executables;
+        }
+
+        let releases = self.registry_manager.get_releases(&package.info.repository).await?;
+        let release = releases
+            .iter()
+            .find(|r| !r.draft && !r.prerelease)
+            .cloned()
+            .ok_or_else(|| {
+                GripError::VersionNotFound(format!("latest release of {}", package.info.repository))
+            })?;
+
+        if !is_newer(&release.tag_name, &current_version) {
+            ui::success!("{} is already up to date ({})", package_name.cyan(), current_version);
+            return Ok(None);
+        }
+
+        // Prefer the asset matching the previously installed one, with the
+        // version number swapped for the new release's, unless the registry
+        // says which asset to use
+        let old_version = current_version.trim_start_matches('v');
+        let matching_asset = current_asset
+            .filter(|_| package.info.asset_pattern(&Platform::current()).is_none())
+            .map(|name| name.replace(old_version, release.version()))
+            .and_then(|name| release.assets.iter().find(|a| a.name == name));
+
+        let asset = match matching_asset {
+            Some(asset) => asset.clone(),
+            None => {
+                let preferences = self.asset_preferences(package_name)?;
+                select_asset(&release, &package.info, None, &preferences, self.prompt_mode(false)?)?.clone()
+            }
+        };
+
+        Ok(Some(PlannedUpgrade {
+            install: PlannedInstall {
+                name: package_name.to_string(),
+                package,
+                release,
+                asset,
+            },
+            current_version,
+            current_path,
+            releases,
+        }))
+    }
+
+    /// Lists every package with a newer release, asks which to upgrade, then
+    /// downloads up to `jobs` of them at once and sums up how each went
+    pub async fn upgrade_interactive(&mut self, jobs: usize) -> Result<()> {
+        if self.non_interactive || ui::json_output() {
+            bail!(Usage, "--interactive asks which packages to upgrade, so it can't be used with -y or --output json");
+        }
+        let mut names: Vec<String> = self
+            .package_state()?
+            .list_packages()
+            .into_iter()
+            .filter(|(_, package)| {
+                !package.pinned && !package.repository.as_deref().is_some_and(provider::is_direct_source)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+
+        let this = &*self;
+        let planned: Vec<_> = futures_util::stream::iter(&names)
+            .map(|name| async move { (name, this.plan_upgrade(name).await) })
+            .buffered(jobs.max(1))
+            .collect()
+            .await;
+        let mut plans = Vec::new();
+        for (name, plan) in planned {
+            match plan {
+                Ok(Some(plan)) => plans.push(plan),
+                Ok(None) => {}
+                Err(e) => ui::warning!("Couldn't check {} for updates: {}", name.cyan(), e),
+            }
+        }
+        if plans.is_empty() {
+            ui::success!("Every package is up to date");
+            return Ok(());
+        }
+
+        let width = plans.iter().map(|plan| plan.install.name.len()).max().unwrap_or(0);
+        let items: Vec<String> = plans
+            .iter()
+            .map(|plan| {
+                format!(
+                    "{:width$}  {} → {}",
+                    plan.install.name,
+                    plan.current_version,
+                    plan.install.release.tag_name,
+                    width = width
+                )
+            })
+            .collect();
+        let selected = dialoguer::MultiSelect::new()
+            .with_prompt("Upgrade which packages? (space toggles, enter confirms)")
+            .items(&items)
+            .defaults(&vec![true; items.len()])
+            .interact()?;
+        let plans: Vec<PlannedUpgrade> = plans
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| selected.contains(i))
+            .map(|(_, plan)| plan)
+            .collect();
+        if plans.is_empty() {
+            ui::status!("Nothing selected to upgrade");
+            return Ok(());
+        }
+
+        if self.dry_run {
+            for plan in &plans {
+                let install = &plan.install;
+                self.describe_install(&install.name, &install.package, &install.release, &install.asset)
+                    .await?;
+            }
+            return Ok(());
+        }
+
+        let this = &*self;
+        let staged: Vec<_> = futures_util::stream::iter(plans)
+            .map(|plan| async move {
+                let install = &plan.install;
+                let staged = this
+                    .stage_release(&install.name, &install.package, &install.release, &install.asset)
+                    .await;
+                (plan, staged)
+            })
+            .buffer_unordered(jobs.max(1))
+            .collect()
+            .await;
+
+        let mut results = Vec::new();
+        for (plan, staged) in staged {
+            let install = &plan.install;
+            let result = match staged {
+                Ok(staged) => {
+                    self.finish_install(&install.name, &install.package, &install.release, &install.asset, staged)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            let result = result.and_then(|outcome| {
+                if plan.current_path != outcome.install_path {
+                    self.remove_from_path(&plan.current_path)?;
+                }
+                Ok(())
+            });
+            if let Err(e) = &result {
+                ui::failure!("Failed to upgrade {}: {}", install.name.cyan(), e);
+            }
+            results.push((plan, result));
+        }
+        results.sort_by(|(a, _), (b, _)| a.install.name.cmp(&b.install.name));
+
+        println!();
+        let mut table = ui::Table::new(&["PACKAGE", "FROM", "TO", "RESULT"]);
+        let mut failed = Vec::new();
+        for (plan, result) in &results {
+            let outcome = match result {
+                Ok(()) => "upgraded".green().to_string(),
+                Err(_) => {
+                    failed.push(plan.install.name.as_str());
+                    "failed".red().to_string()
+                }
+            };
+            table.add_row(vec![
+                plan.install.name.cyan().to_string(),
+                plan.current_version.clone(),
+                plan.install.release.tag_name.clone(),
+                outcome,
+            ]);
+        }
+        table.print();
+
+        if !failed.is_empty() {
+            bail!(InstallError, "Failed to upgrade: {}", failed.join(", "));
+        }
+        Ok(())
+    }
+
+    /// Prints a package's release notes, newest first: those after `since`,
+    /// or after the installed version when not given, or else all of them
+    pub async fn changelog(&self, package_name: &str, since: Option<&str>) -> Result<()> {
+        let installed = self.package_state()?.get_package(package_name);
+        if installed
+            .and_then(|p| p.repository.as_deref())
+            .is_some_and(provider::is_direct_source)
+        {
+            bail!(
+                Usage,
+                "{} was installed from a URL or file and has no release notes",
+                package_name
+            );
+        }
+        let lookup = match installed {
+            Some(installed) => lookup_name(package_name, installed, &self.config.registries),
+            None => package_name.to_string(),
+        };
+        let package = self
+            .registry_manager
+            .find_package(&self.config.registries, &lookup)
+            .await?;
+        let releases = self.registry_manager.get_releases(&package.info.repository).await?;
+
+        let since = since.or(installed.map(|p| p.version.as_str()));
+        if let Some(since) = since {
+            let known = releases.iter().any(|r| r.tag_name == since);
+            if !known && resolver::parse_tag(since).is_none() {
+                bail!(VersionNotFound, "{} of {}", since, package_name);
+            }
+        }
+        let notes = changelog::between(&releases, since, None);
+
+        if ui::json_output() {
+            let entries: Vec<serde_json::Value> = notes
+                .iter()
+                .map(|r| {
+                    serde_json::json!({
+                        "tag": r.tag_name,
+                        "published_at": r.published_at,
+                        "body": r.body,
+                    })
+                })
+                .collect();
+            return ui::print_json(&entries);
+        }
+
+        if notes.is_empty() {
+            match since {
+                Some(since) => ui::status!("No releases of {} after {}", package_name.cyan(), since),
+                None => ui::status!("{} has no releases", package_name.cyan()),
+            }
+            return Ok(());
+        }
+        print!("{}", changelog::render_releases(&notes));
+        Ok(())
+    }
+
+    /// Adds a registry to the config; `file://` paths are made absolute. A
+    /// public key, given bare or as a minisign.pub file, is checked before it
+    /// is saved
+    pub fn registry_add(
+        &mut self,
+        name: &str,
+        url: &str,
+        priority: i32,
+        auth: Option<RegistryAuth>,
+        public_key: Option<&str>,
+    ) -> Result<()> {
+        if self.config.registries.iter().any(|r| r.name == name) {
+            bail!(Usage, "Registry '{}' already exists", name);
+        }
+
+        let mut registry = config::Registry::new(name, url, priority, auth)?;
+        if let Some(key) = public_key {
+            let key = if Path::new(key).is_file() {
+                std::fs::read_to_string(key)?
+            } else {
+                key.to_string()
+            };
+            signature::minisign_public_key(&key)?;
+            registry.public_key = Some(key.trim().to_string());
+        }
+        let url = registry.url.clone();
+        self.config.registries.push(registry);
+
+        self.config.save()?;
+        ui::success!("Added registry {} ({})", name.cyan(), url);
+        Ok(())
+    }
+
+    /// Removes a registry from the config along with its local clone
+    pub fn registry_remove(&mut self, name: &str) -> Result<()> {
+        if name == "default" {
+            bail!(Usage, "Cannot remove default registry");
+        }
+
+        let original_len = self.config.registries.len();
+        self.config.registries.retain(|r| r.name != name);
+
+        if self.config.registries.len() == original_len {
+            return Err(GripError::RegistryNotFound(name.to_string()));
+        }
+
+        self.config.save()?;
+
+        // Clones live in the data directory whatever the install root is
+        let registry_path = self.registry_manager.data_dir.join("registries").join(name);
+        if registry_path.exists() {
+            std::fs::remove_dir_all(registry_path)?;
+        }
+
+        ui::success!("Removed registry {}", name.cyan());
+        Ok(())
+    }
+
+    /// Changes the priority packages are looked up in a registry with, and
+    /// shows the lookup order that results
+    pub fn registry_set_priority(&mut self, name: &str, priority: i32) -> Result<()> {
+        let registry = self
+            .config
+            .registries
+            .iter_mut()
+            .find(|r| r.name == name)
+            .ok_or_else(|| GripError::RegistryNotFound(name.to_string()))?;
+        let previous = std::mem::replace(&mut registry.priority, priority);
+        self.config.save()?;
+
+        ui::success!("Changed the priority of {} from {} to {}", name.cyan(), previous, priority);
+        let order: Vec<String> = registry::by_priority(&self.config.registries)
+            .into_iter()
+            .map(|r| format!("{} ({})", r.name, r.priority))
+            .collect();
+        ui::print_detail(&format!("packages are looked up in {}", order.join(", ")));
+        Ok(())
+    }
+
+    /// Renames a registry, along with its local clone and the record of which
+    /// installed packages came from it
+    pub fn registry_rename(&mut self, old: &str, new: &str) -> Result<()> {
+        if new.is_empty() || new.contains(['/', ':']) || new.chars().any(char::is_whitespace) {
+            bail!(Usage, "Registry names can't be empty or contain '/', ':' or spaces");
+        }
+        if self.config.registries.iter().any(|r| r.name == new) {
+            bail!(Usage, "Registry '{}' already exists", new);
+        }
+        let registry = self
+            .config
+            .registries
+            .iter_mut()
+            .find(|r| r.name == old)
+            .ok_or_else(|| GripError::RegistryNotFound(old.to_string()))?;
+        registry.name = new.to_string();
+        let is_git = matches!(registry.source(), RegistrySource::Git(_));
+        self.config.save()?;
+
+        // Clones are kept by registry name; a missing one is cloned again when needed
+        let registries_dir = self.registry_manager.data_dir.join("registries");
+        if is_git && registries_dir.join(old).is_dir() && !registries_dir.join(new).exists() {
+            std::fs::rename(registries_dir.join(old), registries_dir.join(new))?;
+        }
+
+        self.update_state(|state| {
+            let names: Vec<String> = state
+                .list_packages()
+                .into_iter()
+                .filter(|(_, package)| package.registry.as_deref() == Some(old))
+                .map(|(name, _)| name.clone())
+                .collect();
+            for name in names {
+                state.set_registry(&name, Some(new.to_string()));
+            }
+            Ok(())
+        })?;
+
+        ui::success!("Renamed registry {} to {}", old.cyan(), new.cyan());
+        Ok(())
+    }
+
+    /// Refreshes the local copy of one registry, or all of them
+    pub async fn registry_update(&self, name: Option<&str>) -> Result<()> {
+        for registry in self.registries(name)? {
+            self.registry_manager.update_registry(&registry).await?;
+        }
+        ui::success!("Registries up to date");
+        Ok(())
+    }
+
+    /// Prints the configured registries
+    pub fn registry_list(&self) -> Result<()> {
+        if ui::json_output() {
+            // Only the kind of auth is shown so secrets stay out of logs
+            let registries: Vec<_> = registry::by_priority(&self.config.registries)
+                .iter()
+                .map(|r| {
+                    serde_json::json!({
+                        "name": r.name,
+                        "url": r.url,
+                        "priority": r.priority,
+                        "auth": r.auth.as_ref().map(RegistryAuth::kind),
+                        "signed": r.public_key.is_some(),
+                    })
+                })
+                .collect();
+            return ui::print_json(&registries);
+        }
+        ui::status!("Configured registries, in lookup order:");
+        for registry in &registry::by_priority(&self.config.registries) {
+            let auth = registry
+                .auth
+                .as_ref()
+                .map(|a| format!(", auth: {}", a.kind()))
+                .unwrap_or_default();
+            let signed = if registry.public_key.is_some() { ", signed" } else { "" };
+            println!(
+                "  {} {} (priority: {}, url: {}{}{})",
+                "→".blue(),
+                registry.name.cyan(),
+                registry.priority,
+                registry.url,
+                auth,
+                signed
+            );
+        }
+        Ok(())
+    }
+
+    /// Scaffolds a new registry in `dir`, named after the directory unless
+    /// `name` is given
+    pub fn registry_create(&self, dir: &Path, name: Option<&str>) -> Result<()> {
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => std::path::absolute(dir)?
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .ok_or_else(|| GripError::Usage("Pass --name for the new registry".to_string()))?,
+        };
+        validate::create(dir, &name)?;
+        ui::success!("Created registry {} in {}", name.cyan(), dir.display());
+        ui::print_detail(&format!(
+            "Add packages to {}, then check them with `grip registry validate {}`",
+            dir.join("packages").display(),
+            dir.display()
+        ));
+        Ok(())
+    }
+
+    /// Checks a registry directory before it's published; fails when any
+    /// definition has errors, while warnings alone pass
+    pub fn registry_validate(&self, dir: &Path) -> Result<()> {
+        let problems = validate::validate(dir)?;
+        let errors = problems
+            .iter()
+            .filter(|p| p.severity == validate::Severity::Error)
+            .count();
+
+        if ui::json_output() {
+            ui::print_json(&problems)?;
+        } else {
+            for problem in &problems {
+                let file = problem.file.strip_prefix(dir).unwrap_or(&problem.file);
+                match problem.severity {
+                    validate::Severity::Error => ui::failure!("{}: {}", file.display(), problem.message),
+                    validate::Severity::Warning => ui::warning!("{}: {}", file.display(), problem.message),
+                }
+            }
+        }
+
+        if errors > 0 {
+            bail!(RegistryError, "{} has {} error(s)", dir.display(), errors);
+        }
+        ui::success!("{} is valid", dir.display());
+        Ok(())
+    }
+
+    /// Writes the index of a registry directory, for its maintainers to sign
+    /// after every change
+    pub fn registry_index(&self, dir: &Path) -> Result<()> {
+        let (path, files) = registry::index::Index::write(dir)?;
+        ui::success!("Wrote {} listing {} file(s)", path.display(), files);
+        ui::print_detail(&format!(
+            "Sign it with `minisign -Sm {}`, and commit it with {}",
+            path.display(),
+            registry::index::SIGNATURE_FILE
+        ));
+        Ok(())
+    }
+
+    /// Submits a package definition to a registry, read from `file` or drafted
+    /// from a GitHub repository's releases, by pull request or, with `push`,
+    /// by pushing to it directly
+    pub async fn publish(&self, file: Option<&Path>, from: Option<&str>, registry: &str, push: bool) -> Result<()> {
+        let registry = self.registries(Some(registry))?.remove(0);
+        let definition: serde_json::Value = match (file, from) {
+            (Some(file), _) => serde_json::from_str(&utils::read_input(file)?).map_err(|e| {
+                GripError::ConfigError(format!("Failed to parse {}: {}", file.display(), e))
+            })?,
+            (None, Some(url)) => self.draft_definition(url).await?,
+            (None, None) => bail!(
+                Usage,
+                "Pass a package definition file, or --from with a GitHub repository to draft one"
+            ),
+        };
+
+        let problems = validate::check_definition(&definition)?;
+        if !problems.is_empty() {
+            bail!(RegistryError, "Invalid package definition:\n  {}", problems.join("\n  "));
+        }
+        let name = definition["name"].as_str().unwrap_or_default().to_string();
+        let content = format!("{}\n", serde_json::to_string_pretty(&definition)?);
+
+        if !self.non_interactive && !ui::json_output() {
+            println!("{}", content);
+            let confirmed = Confirm::new()
+                .with_prompt(format!("Publish {} to {}?", name, registry.name))
+                .default(true)
+                .interact()?;
+            if !confirmed {
+                bail!(Usage, "Publishing cancelled");
+            }
+        }
+
+        let published = self.registry_manager.publish(&registry, &name, &content, push).await?;
+        if ui::json_output() {
+            let (kind, location) = match &published {
+                Published::Written(path) => ("written", path.display().to_string()),
+                Published::Pushed => ("pushed", registry.url.clone()),
+                Published::PullRequest(url) => ("pull_request", url.clone()),
+            };
+            return ui::print_json(&serde_json::json!({
+                "package": name,
+                "registry": registry.name,
+                "published": kind,
+                "location": location,
+            }));
+        }
+        match published {
+            Published::Written(path) => ui::success!("Wrote {} to {}", name.cyan(), path.display()),
+            Published::Pushed => ui::success!("Pushed {} to {}", name.cyan(), registry.name.cyan()),
+            Published::PullRequest(url) => ui::success!("Opened a pull request for {}: {}", name.cyan(), url),
+        }
+        Ok(())
+    }
+
+    /// Drafts a definition from a GitHub repository's description and topics,
+    /// checking that its latest release has assets grip can install. The
+    /// guesses can be corrected when interactive
+    async fn draft_definition(&self, url: &str) -> Result<serde_json::Value> {
+        let repository = publish::parse_github_url(url)
+            .ok_or_else(|| GripError::Usage(format!("'{}' is not a GitHub repository", url)))?;
+        ui::status!("Inspecting {}...", repository.cyan());
+
+        let release = self.registry_manager.get_latest_release(&repository).await?;
+        let covered: Vec<String> = PUBLISHED_PLATFORMS
+            .iter()
+            .filter(|platform| !matches!(asset_matcher::best_match(&release.assets, platform), AssetMatch::NoMatch))
+            .map(|platform| format!("{}-{}", platform.os, platform.arch))
+            .collect();
+        if covered.is_empty() {
+            bail!(
+                AssetNotFound,
+                "{} {} has no release assets grip can install",
+                repository,
+                release.tag_name
+            );
+        }
+        ui::status!("Latest release {} has assets for {}", release.tag_name, covered.join(", "));
+
+        let metadata = match self.registry_manager.github_metadata(&repository).await {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                ui::warning!("Couldn't read the description of {}: {}", repository, e);
+                serde_json::Value::Null
+            }
+        };
+        let mut name = repository.rsplit('/').next().unwrap_or_default().to_lowercase();
+        let mut description = metadata["description"].as_str().unwrap_or_default().to_string();
+        let mut tags: Vec<String> = metadata["topics"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|topic| topic.as_str().map(str::to_string))
+            .collect();
+
+        if !self.non_interactive {
+            name = Input::new().with_prompt("Package name").default(name).interact_text()?;
+            description = Input::new()
+                .with_prompt("Description")
+                .default(description)
+                .allow_empty(true)
+                .interact_text()?;
+            let answer: String = Input::new()
+                .with_prompt("Tags (comma-separated)")
+                .default(tags.join(", "))
+                .allow_empty(true)
+                .interact_text()?;
+            tags = answer
+                .split(',')
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect();
+        }
+
+        let mut definition = serde_json::json!({
+            "name": name,
+            "repository": repository,
+        });
+        if !description.is_empty() {
+            definition["description"] = description.into();
+        }
+        if let Some(homepage) = metadata["homepage"].as_str().filter(|h| !h.is_empty()) {
+            definition["homepage"] = homepage.into();
+        }
+        if let Some(license) = metadata["license"]["spdx_id"].as_str().filter(|id| *id != "NOASSERTION") {
+            definition["license"] = license.into();
+        }
+        if !tags.is_empty() {
+            definition["tags"] = tags.into();
+        }
+        Ok(definition)
+    }
+
+    /// Prints one setting; strings are printed bare so scripts can use them
+    pub fn config_get(&self, key: &str) -> Result<()> {
+        let value = self.config.get(key)?;
+        if ui::json_output() {
+            return ui::print_json(&value);
+        }
+        match value {
+            serde_json::Value::String(s) => println!("{}", s),
+            value => println!("{}", value),
+        }
+        Ok(())
+    }
+
+    pub fn config_set(&mut self, key: &str, value: &str) -> Result<()> {
+        self.config.set(key, value)?;
+        self.config.save()?;
+        match self.config.get(key) {
+            Ok(value) => ui::success!("Set {} to {}", key.cyan(), value),
+            Err(_) => ui::success!("Unset {}", key.cyan()),
+        }
+        Ok(())
+    }
+
+    pub fn config_list(&self) -> Result<()> {
+        let entries = self.config.entries()?;
+        if ui::json_output() {
+            let map: serde_json::Map<_, _> = entries.into_iter().collect();
+            return ui::print_json(&map);
+        }
+        let width = entries.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+        for (key, value) in entries {
+            println!("{:<width$}  {}", key.cyan(), value);
+        }
+        Ok(())
+    }
+
+    pub fn config_path(&self) -> Result<()> {
+        let sources = self.config.sources()?;
+        if ui::json_output() {
+            return ui::print_json(&sources);
+        }
+        for source in sources {
+            let location = if source.loaded {
+                source.location.normal()
+            } else {
+                format!("{} (not found)", source.location).dimmed()
+            };
+            println!("{:<11}  {}", source.layer.cyan(), location);
+        }
+        Ok(())
+    }
+
+    /// Creates grip.json in the current directory, from a template if given,
+    /// asking for the project's name and version unless non-interactive
+    pub async fn init(&self, template: Option<&str>, force: bool) -> Result<()> {
+        let manifest_path = Path::new(project::MANIFEST_FILE);
+        let config_path = Path::new(config::PROJECT_CONFIG);
+        if manifest_path.exists() && !force {
+            bail!(Usage, "{} already exists; pass --force to replace it", project::MANIFEST_FILE);
+        }
+
+        let template = match template {
+            Some(template) => self.load_template(template).await?,
+            None => Template::default(),
+        };
+        if !template.config.is_empty() && config_path.exists() && !force {
+            bail!(Usage, "{} already exists; pass --force to replace it", config::PROJECT_CONFIG);
+        }
+        if let Some(description) = &template.description {
+            ui::status!("{}", description);
+        }
+
+        let mut name = std::env::current_dir()?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "grip-project".to_string());
+        let mut version = template.version.clone().unwrap_or_else(|| "0.1.0".to_string());
+        if !self.non_interactive && !ui::json_output() {
+            name = Input::new().with_prompt("Project name").default(name).interact_text()?;
+            version = Input::new().with_prompt("Version").default(version).interact_text()?;
+        }
+
+        let mut manifest = Manifest::new(&name);
+        manifest.version = version;
+        manifest.dependencies = template
+            .dependencies
+            .into_iter()
+            .map(|(name, version)| (name, ProjectDependency::new(&version)))
+            .collect();
+        let has_config = !template.config.is_empty();
+        if has_config {
+            config::write_project_config(config_path, &serde_json::Value::Object(template.config))?;
+        }
+        manifest.save(manifest_path)?;
+
+        if has_config {
+            ui::success!("Created {} and {}", project::MANIFEST_FILE, config::PROJECT_CONFIG);
+        } else {
+            ui::success!("Created {}", project::MANIFEST_FILE);
+        }
+        if !manifest.dependencies.is_empty() {
+            ui::status!("Run {} to install the project's dependencies", "grip install".cyan());
+        }
+        Ok(())
+    }
+
+    /// Finds a template by name in the registries, or fetches the repository
+    /// it points at
+    async fn load_template(&self, template: &str) -> Result<Template> {
+        if !template::is_location(template) {
+            let path = self
+                .registry_manager
+                .find_template(&self.config.registries, template)
+                .await?
+                .ok_or_else(|| GripError::Other(format!("No template named '{}' in the registries", template)))?;
+            return Template::load(&path);
+        }
+
+        ui::status!("Fetching template {}...", template.cyan());
+        let source = Registry {
+            name: "template".to_string(),
+            url: template.to_string(),
+            priority: 0,
+            auth: None,
+            public_key: None,
+        };
+        let dir = tempfile::tempdir()?;
+        let repository = self
+            .registry_manager
+            .fetch_repository(&source, &dir.path().join("template"))
+            .await?;
+        let path = repository.join(template::TEMPLATE_FILE);
+        if !path.is_file() {
+            bail!(ConfigError, "{} has no {}", template, template::TEMPLATE_FILE);
+        }
+        Template::load(&path)
+    }
+
+    /// How to choose between a package's assets: the config's preferences,
+    /// with the nearest grip.json's overrides for the package on top
+    fn asset_preferences(&self, package_name: &str) -> Result<AssetPreferences> {
+        let preferences = self.config.assets.clone();
+        let Some(manifest_path) = Manifest::find()? else {
+            return Ok(preferences);
+        };
+        Ok(match Manifest::load(&manifest_path)?.assets.get(package_name) {
+            Some(overrides) => preferences.merged(overrides),
+            None => preferences,
+        })
+    }
+
+    fn find_manifest() -> Result<PathBuf> {
+        Manifest::find()?.ok_or_else(|| {
+            GripError::ConfigError(
+                "No grip.json found in this directory or its parents; run `grip init` first".to_string(),
+            )
+        })
+    }
+
+    /// Finds the project's grip.json and returns it with its workspace members
+    /// and the dependencies to install for them all
+    fn project_dependencies() -> Result<(PathBuf, Vec<project::Member>, BTreeMap<String, ProjectDependency>)> {
+        let manifest_path = Self::find_manifest()?;
+        let manifest = Manifest::load(&manifest_path)?;
+        let root_dir = manifest_path.parent().unwrap_or(Path::new("."));
+        let members = manifest.members(root_dir)?;
+        let dependencies = if manifest.workspaces.is_empty() {
+            manifest.dependencies.clone()
+        } else {
+            ui::status!("Workspace with {} members", members.len());
+            project::workspace_dependencies(&manifest, &members, root_dir)?
+        };
+        Ok((manifest_path, members, dependencies))
+    }
+
+    /// Holds the installs that follow to what the dependencies pin
+    fn pin_dependencies(&mut self, dependencies: &BTreeMap<String, ProjectDependency>) {
+        self.pins = dependencies
+            .iter()
+            .filter(|(_, dependency)| dependency.is_pinned())
+            .map(|(spec, dependency)| (installed_name(spec).to_string(), dependency.clone()))
+            .collect();
+    }
+
+    /// Installs every dependency listed in the project's grip.json and records
+    /// what was resolved in grip.lock, or with `frozen` installs exactly what
+    /// grip.lock records. At the root of a workspace that covers every member's
+    /// dependencies too, each installed once
+    pub async fn install_project(&mut self, frozen: bool) -> Result<()> {
+        let (manifest_path, members, dependencies) = Self::project_dependencies()?;
+        let lock_path = manifest_path.with_file_name(project::LOCK_FILE);
+        let root_dir = manifest_path.parent().unwrap_or(Path::new("."));
+        self.pin_dependencies(&dependencies);
+
+        if frozen {
+            let lockfile = Lockfile::load(&lock_path)?.ok_or_else(|| {
+                GripError::ConfigError(format!(
+                    "--frozen requires {}, run `grip install` first",
+                    lock_path.display()
+                ))
+            })?;
+            if !lockfile.matches(&dependencies) {
+                bail!(
+                    ConfigError,
+                    "{} is out of date with {}; run `grip install` to update it",
+                    lock_path.display(),
+                    manifest_path.display()
+                );
+            }
+
+            for (name, locked) in &lockfile.packages {
+                self.check_locked_pins(name, locked)?;
+                if self.is_installed(name, &locked.tag)? {
+                    ui::success!("{} {} is already installed", name.cyan(), locked.tag);
+                    continue;
+                }
+                self.install_locked(name, locked).await?;
+            }
+            self.link_members(root_dir, &members)?;
+
+            if !self.dry_run {
+                ui::success!("All dependencies installed from {}", lock_path.display());
+            }
+            return Ok(());
+        }
+
+        if dependencies.is_empty() {
+            ui::status!("No dependencies in {}", manifest_path.display());
+            self.link_members(root_dir, &members)?;
+            return Ok(());
+        }
+
+        ui::status!("Installing {} dependencies from {}", dependencies.len(), manifest_path.display());
+
+        let mut lockfile = Lockfile::load(&lock_path)?.unwrap_or_default();
+        lockfile
+            .packages
+            .retain(|name, _| dependencies.contains_key(name));
+
+        for (name, dependency) in &dependencies {
+            let requirement = &dependency.version;
+            let up_to_date = match lockfile.packages.get(name) {
+                Some(locked) => {
+                    locked.requirement == *requirement
+                        && dependency.sha256.as_ref().is_none_or(|pinned| *pinned == locked.sha256)
+                        && self.is_installed(name, &locked.tag)?
+                }
+                None => false,
+            };
+            if up_to_date {
+                ui::success!("{} is already installed", name.cyan());
+                continue;
+            }
+
+            let version = project::requested_version(requirement);
+            let outcome = self.install(name, version, None, false, false, None).await?;
+            let package = self
+                .registry_manager
+                .find_package(&self.config.registries, name)
+                .await?;
+
+            lockfile.packages.insert(
+                name.clone(),
+                LockedPackage {
+                    requirement: requirement.clone(),
+                    repository: package.info.repository,
+                    tag: outcome.tag,
+                    asset: outcome.asset.name,
+                    url: outcome.asset.browser_download_url,
+                    sha256: outcome.sha256,
+                    attestation: outcome.attestation,
+                },
+            );
+        }
+
+        self.link_members(root_dir, &members)?;
+        if self.dry_run {
+            ui::print_detail(&format!("write {}", lock_path.display()));
+            return Ok(());
+        }
+        lockfile.save(&lock_path)?;
+
+        ui::success!("All dependencies installed");
+        Ok(())
+    }
+
+    /// Links each workspace member's `.grip` to the project install at the
+    /// root, so `grip --local run` works from inside any member. Only applies
+    /// to project installs; user installs are already shared
+    fn link_members(&self, root_dir: &Path, members: &[project::Member]) -> Result<()> {
+        if self.scope != Scope::Project {
+            return Ok(());
+        }
+        // Relative when the install is the root's own, so the repository can move
+        let relative = self.root == root_dir.join(config::PROJECT_DIR);
+        for member in members {
+            let link = member.dir.join(config::PROJECT_DIR);
+            let target = match member.dir.strip_prefix(root_dir) {
+                Ok(sub) if relative => sub
+                    .components()
+                    .map(|_| Path::new(".."))
+                    .collect::<PathBuf>()
+                    .join(config::PROJECT_DIR),
+                _ => self.root.clone(),
+            };
+            if std::fs::read_link(&link).is_ok_and(|existing| existing == target) {
+                continue;
+            }
+            if link.symlink_metadata().is_ok() {
+                ui::warning!("{} already exists; leaving it in place of a link to {}", link.display(), self.root.display());
+                continue;
+            }
+            if self.dry_run {
+                ui::print_detail(&format!("link {} -> {}", link.display(), target.display()));
+                continue;
+            }
+            utils::create_symlink(&target, &link)?;
+            ui::print_detail(&format!("Linked {} to {}", link.display(), self.root.display()));
+        }
+        Ok(())
+    }
+
+    /// Compares the project install with grip.json and grip.lock: dependencies
+    /// that are missing or at another version, and packages no dependency
+    /// needs. With `check` only reports the differences and returns whether
+    /// there are any; otherwise removes the unneeded packages and installs
+    /// the rest as `grip install` would
+    pub async fn sync(&mut self, check: bool) -> Result<bool> {
+        let (manifest_path, members, dependencies) = Self::project_dependencies()?;
+        let lock_path = manifest_path.with_file_name(project::LOCK_FILE);
+        let root_dir = manifest_path.parent().unwrap_or(Path::new("."));
+        self.pin_dependencies(&dependencies);
+        let lockfile = Lockfile::load(&lock_path)?;
+        let lock_current = lockfile.as_ref().is_some_and(|lockfile| lockfile.matches(&dependencies));
+        let drift = self.project_drift(&dependencies, lockfile.as_ref())?;
+
+        if check {
+            let in_sync = drift.is_empty() && lock_current;
+            if ui::json_output() {
+                ui::print_json(&serde_json::json!({
+                    "in_sync": in_sync,
+                    "lockfile_current": lock_current,
+                    "drift": drift,
+                }))?;
+                return Ok(!in_sync);
+            }
+            if !lock_current {
+                ui::warning!("{} is out of date with {}", lock_path.display(), manifest_path.display());
+            }
+            if !drift.is_empty() {
+                let mut table = ui::Table::new(&["PACKAGE", "INSTALLED", "WANTED"]);
+                for drift in &drift {
+                    table.add_row(vec![
+                        drift.name.cyan().to_string(),
+                        drift.installed.as_deref().map_or("missing".red(), |v| v.normal()).to_string(),
+                        drift.wanted.as_deref().map_or("not needed".yellow(), |v| v.normal()).to_string(),
+                    ]);
+                }
+                table.print();
+            }
+            if in_sync {
+                ui::success!("{} matches {}", self.root.display(), lock_path.display());
+            } else {
+                println!("\n{} Run `grip sync` to fix", "→".blue());
+            }
+            return Ok(!in_sync);
+        }
+
+        for drift in drift.iter().filter(|drift| drift.wanted.is_none()) {
+            ui::status!("Removing {}, which grip.json doesn't need", drift.name.cyan());
+            self.uninstall(&drift.name, true).await?;
+        }
+
+        if lock_current {
+            let lockfile = lockfile.unwrap_or_default();
+            for (name, locked) in &lockfile.packages {
+                if !self.is_installed(name, &locked.tag)? {
+                    self.install_locked(name, locked).await?;
+                }
+            }
+            self.link_members(root_dir, &members)?;
+        } else {
+            self.install_project(false).await?;
+        }
+
+        // Locked versions that were installed but aren't active are switched to
+        if let Some(lockfile) = Lockfile::load(&lock_path)? {
+            for (name, locked) in &lockfile.packages {
+                let name = installed_name(name);
+                let active = self.package_state()?.get_package(name).map(|p| p.version.clone());
+                if active.is_some_and(|active| active != locked.tag) {
+                    self.use_version(name, &locked.tag).await?;
+                }
+            }
+        }
+        ui::success!("{} matches {}", self.root.display(), lock_path.display());
+        Ok(false)
+    }
+
+    /// How the installed packages differ from the dependencies: those not
+    /// installed, those whose active version isn't the locked one or doesn't
+    /// satisfy the requirement, and those neither a dependency nor needed by one
+    fn project_drift(&self, dependencies: &BTreeMap<String, ProjectDependency>, lockfile: Option<&Lockfile>) -> Result<Vec<Drift>> {
+        let state = self.package_state()?;
+        let mut drift = Vec::new();
+        let mut needed: Vec<String> = Vec::new();
+        for (spec, dependency) in dependencies {
+            let requirement = &dependency.version;
+            let name = installed_name(spec);
+            needed.push(name.to_string());
+            let locked = lockfile
+                .and_then(|lockfile| lockfile.packages.get(spec))
+                .filter(|locked| locked.requirement == *requirement);
+            let wanted = locked.map_or(requirement.as_str(), |locked| locked.tag.as_str());
+            let installed = state.get_package(name).map(|p| p.version.as_str());
+            let differs = match installed {
+                None => true,
+                Some(version) if locked.is_some() => version != wanted,
+                Some(version) => project::requested_version(requirement)
+                    .is_some_and(|requirement| !resolver::satisfies(version, &requirement)),
+            };
+            if differs {
+                drift.push(Drift {
+                    name: name.to_string(),
+                    installed: installed.map(str::to_string),
+                    wanted: Some(wanted.to_string()),
+                });
+            }
+        }
+
+        // Dependencies of dependencies are needed too
+        let mut i = 0;
+        while i < needed.len() {
+            if let Some(package) = state.get_package(&needed[i]) {
+                for dependency in &package.dependencies {
+                    let name = Dependency::parse(dependency).name;
+                    if !needed.contains(&name) {
+                        needed.push(name);
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        let mut installed = state.list_packages();
+        installed.sort_by_key(|(name, _)| name.as_str());
+        for (name, package) in installed {
+            if !needed.contains(name) {
+                drift.push(Drift {
+                    name: name.clone(),
+                    installed: Some(package.version.clone()),
+                    wanted: None,
+                });
+            }
+        }
+        Ok(drift)
+    }
+
+    /// Writes the installed packages to stdout as a bundle for `grip import`
+    pub fn export(&self) -> Result<()> {
+        ui::print_json(&Bundle::from_state(self.package_state()?))
+    }
+
+    /// Installs every package in a bundle at its recorded version, skipping
+    /// those already installed, and re-applies pins
+    pub async fn import(&mut self, path: &Path, jobs: usize) -> Result<()> {
+        let bundle = Bundle::load(path)?;
+        ui::status!("Importing {} packages from {}", bundle.packages.len(), path.display());
+
+        let mut resolved = Vec::new();
+        let mut direct = Vec::new();
+        let mut failed = Vec::new();
+        for package in &bundle.packages {
+            if self.is_installed(&package.name, &package.version)? {
+                ui::success!("{} {} is already installed", package.name.cyan(), package.version);
+            } else if let Some(url) = package.source.strip_prefix("url:") {
+                direct.push((package, url.to_string()));
+            } else if package.source.starts_with("file:") {
+                ui::failure!(
+                    "Skipping {}: it was installed from a local file ({})",
+                    package.name.cyan(),
+                    package.source
+                );
+                failed.push(package.name.clone());
+            } else {
+                resolved.push(package);
+            }
+        }
+
+        if !resolved.is_empty() {
+            let specs: Vec<String> = resolved
+                .iter()
+                .map(|p| format!("{}@{}", p.source, p.version))
+                .collect();
+            // Failures are reported as they happen; what's missing afterwards failed
+            let _ = self.install_many(&specs, jobs, false, false, None).await;
+            for package in &resolved {
+                if !self.is_installed(&package.name, &package.version)? {
+                    failed.push(package.name.clone());
+                }
+            }
+        }
+        for (package, url) in direct {
+            if let Err(e) = self
+                .install_direct(&package.name, &package.version, Some(url), None)
+                .await
+            {
+                ui::failure!("Failed to install {}: {}", package.name.cyan(), e);
+                failed.push(package.name.clone());
+            }
+        }
+
+        for package in bundle.packages.iter().filter(|p| p.pinned) {
+            if self.package_state()?.get_package(&package.name).is_some() {
+                self.pin(&package.name, true)?;
+            }
+        }
+
+        if !failed.is_empty() {
+            bail!(InstallError, "Failed to import: {}", failed.join(", "));
+        }
+        ui::success!("Imported {}", path.display());
+        Ok(())
+    }
+
+    /// Installs the latest release of every package in another package
+    /// manager's export that maps to a registry package, then reports the
+    /// entries that don't
+    pub async fn import_foreign(&mut self, format: ForeignFormat, path: &Path, jobs: usize) -> Result<()> {
+        let entries = foreign::parse(format, &utils::read_input(path)?)?;
+        ui::status!("Matching {} {} entries against the registries", entries.len(), format.name());
+
+        let registries = registry::by_priority(&self.config.registries);
+        let mut index = PackageIndex::default();
+        for registry in &registries {
+            for package in self.registry_manager.list_packages(registry).await? {
+                index.add(&package);
+            }
+        }
+
+        let mut names: Vec<String> = Vec::new();
+        let mut unmapped = Vec::new();
+        for entry in &entries {
+            match index.find(entry) {
+                Some(name) if self.package_state()?.get_package(name).is_some() => {
+                    ui::success!("{} is already installed (as {})", entry.name, name.cyan());
+                }
+                Some(name) => {
+                    if !names.iter().any(|n| n == name) {
+                        names.push(name.to_string());
+                    }
+                }
+                None => unmapped.push(entry.name.as_str()),
+            }
+        }
+
+        let result = if names.is_empty() {
+            Ok(Vec::new())
+        } else {
+            self.install_many(&names, jobs, false, false, None).await
+        };
+
+        if !unmapped.is_empty() {
+            ui::warning!(
+                "{} of {} entries have no grip package: {}",
+                unmapped.len(),
+                entries.len(),
+                unmapped.join(", ")
+            );
+        }
+        result.map(|_| ())
+    }
+
+    fn is_installed(&self, name: &str, version: &str) -> Result<bool> {
+        Ok(self
+            .package_state()?
+            .get_package(installed_name(name))
+            .is_some_and(|installed| installed.installed_versions.iter().any(|v| v == version)))
+    }
+
+    /// Installs the exact asset recorded in a lockfile entry, refusing it if
+    /// its digest no longer matches
+    async fn install_locked(&mut self, package_name: &str, locked: &LockedPackage) -> Result<()> {
+        ui::status!("Installing {} {} from lockfile", package_name.cyan(), locked.tag);
+        self.check_locked_pins(package_name, locked)?;
+
+        let mut package = self
+            .registry_manager
+            .find_package(&self.config.registries, package_name)
+            .await?;
+        package
+            .info
+            .checksums
+            .insert(locked.asset.clone(), locked.sha256.clone());
+
+        let asset = Asset {
+            name: locked.asset.clone(),
+            browser_download_url: locked.url.clone(),
+            size: None,
+        };
+        let release = Release {
+            tag_name: locked.tag.clone(),
+            assets: vec![asset.clone()],
+            ..Default::default()
+        };
+
+        self.install_release(installed_name(package_name), &package, &release, &asset)
+            .await?;
+        Ok(())
+    }
+
+    /// Fails when grip.lock records another artifact than grip.json pins
+    fn check_locked_pins(&self, package_name: &str, locked: &LockedPackage) -> Result<()> {
+        let name = installed_name(package_name);
+        self.check_pinned_source(name, &locked.repository)?;
+        if let Some(pinned) = self.pinned_sha256(name).filter(|pinned| **pinned != locked.sha256) {
+            bail!(
+                ChecksumMismatch,
+                "{} records sha256 {} for {} but grip.json pins {}",
+                project::LOCK_FILE,
+                locked.sha256,
+                package_name,
+                pinned
+            );
+        }
+        Ok(())
+    }
+
+    /// Records a dependency in the project's grip.json, pinning the latest
+    /// release when no version is given
+    pub async fn add(&mut self, spec: &str) -> Result<()> {
+        let manifest_path = Self::find_manifest()?;
+        let mut manifest = Manifest::load(&manifest_path)?;
+        let (name, version) = project::parse_spec(spec);
+
+        let package = self
+            .registry_manager
+            .find_package(&self.config.registries, &name)
+            .await?;
+
+        let version = match version {
+            Some(v) => v,
+            None => {
+                self.registry_manager
+                    .get_latest_release(&package.info.repository)
+                    .await?
+                    .tag_name
+            }
+        };
+
+        let previous = manifest.dependencies.insert(name.clone(), ProjectDependency::new(&version));
+        if previous.is_some_and(|previous| previous.is_pinned() && previous.version != version) {
+            ui::warning!("Dropped the sha256 and source_repository pinned for {}'s previous version", name);
+        }
+        manifest.save(&manifest_path)?;
+
+        ui::success!("Added {} {} to {}", name.cyan(), version, manifest_path.display());
+        Ok(())
+    }
+
+    /// Searches package names, tags and descriptions across all registries
+    pub async fn search(&self, query: &str, limit: usize) -> Result<()> {
+        let registries = registry::by_priority(&self.config.registries);
+
+        // Registries are visited highest priority first, so the first hit for
+        // a name is the one `install` would use
+        let mut seen = HashSet::new();
+        let mut results = Vec::new();
+        for registry in &registries {
+            for package in self.registry_manager.list_packages(registry).await? {
+                if let Some(score) = search::score_package(query, &package.info) {
+                    if seen.insert(package.info.name.clone()) {
+                        results.push((score, registry.name.clone(), package));
+                    }
+                }
+            }
+        }
+
+        if results.is_empty() && !ui::json_output() {
+            ui::warning!("No packages matching '{}'", query);
+            return Ok(());
+        }
+
+        results.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.2.info.name.cmp(&b.2.info.name)));
+        let total = results.len();
+        results.truncate(limit);
+
+        let latest_versions = futures_util::future::join_all(results.iter().map(|(_, _, package)| {
+            self.registry_manager
+                .get_latest_release(&package.info.repository)
+        }))
+        .await;
+
+        if ui::json_output() {
+            let json: Vec<_> = results
+                .iter()
+                .zip(&latest_versions)
+                .map(|((_, registry, package), latest)| {
+                    serde_json::json!({
+                        "name": package.info.name,
+                        "registry": registry,
+                        "repository": package.info.repository,
+                        "description": package.info.description,
+                        "latest_version": latest.as_ref().ok().map(|r| &r.tag_name),
+                    })
+                })
+                .collect();
+            return ui::print_json(&json);
+        }
+
+        let mut table = ui::Table::new(&["NAME", "REGISTRY", "VERSION", "DESCRIPTION"]);
+        for ((_, registry, package), latest) in results.iter().zip(latest_versions) {
+            table.add_row(vec![
+                package.info.name.cyan().to_string(),
+                registry.clone(),
+                latest.map(|r| r.tag_name).unwrap_or_else(|_| "-".to_string()),
+                package.info.description.clone().unwrap_or_default(),
+            ]);
+        }
+        table.print();
+
+        if total > results.len() {
+            ui::status!("{} more results, use --limit to show them", total - results.len());
+        }
+        Ok(())
+    }
+
+    /// Shows registry metadata, available versions, the latest release notes
+    /// and local install details for a package
+    pub async fn info(&self, package_name: &str, versions_shown: usize) -> Result<()> {
+        let installed = self.package_state()?.get_package(installed_name(package_name));
+        // An installed package is described from the registry it came from
+        let lookup = match installed {
+            Some(installed) if package_name == installed_name(package_name) => {
+                lookup_name(package_name, installed, &self.config.registries)
+            }
+            _ => package_name.to_string(),
+        };
+        let package = self
+            .registry_manager
+            .find_package(&self.config.registries, &lookup)
+            .await?;
+        let info = &package.info;
+        let license = info.license.clone().or_else(|| installed?.license.clone());
+
+        if ui::json_output() {
+            let releases = self.registry_manager.get_releases(&info.repository).await?;
+            let latest = releases.iter().find(|r| !r.draft && !r.prerelease);
+            return ui::print_json(&serde_json::json!({
+                "name": info.name,
+                "description": info.description,
+                "registry": package.registry,
+                "repository": info.repository,
+                "homepage": info.homepage,
+                "license": license,
+                "executable_name": info.executable_name,
+                "executables": info.executables(&Platform::current(), installed_name(package_name))?,
+                "platforms": info.platforms,
+                "tags": info.tags,
+                "definition": (!package.path.as_os_str().is_empty()).then_some(&package.path),
+                "installed": installed.map(|p| serde_json::json!({
+                    "version": p.version,
+                    "registry": p.registry,
+                    "install_path": p.install_path,
+                    "executables": p.executables,
+                    "installed_versions": p.installed_versions,
+                    "attestation": p.attestation,
+                })),
+                "versions": releases.iter().take(versions_shown).map(|r| serde_json::json!({
+                    "tag": r.tag_name,
+                    "published_at": r.published_at,
+                    "prerelease": r.prerelease,
+                })).collect::<Vec<_>>(),
+                "latest": latest.map(|r| serde_json::json!({
+                    "tag": r.tag_name,
+                    "release_notes": r.body,
+                })),
+            }));
+        }
+
+        println!("{} {}", info.name.cyan().bold(), info.description.as_deref().unwrap_or(""));
+        if let Some(registry) = &package.registry {
+            println!("  {:<12} {}", "registry:", registry);
+        }
+        println!("  {:<12} {}", "repository:", info.repository);
+        if let Some(homepage) = &info.homepage {
+            println!("  {:<12} {}", "homepage:", homepage);
+        }
+        if let Some(license) = &license {
+            println!("  {:<12} {}", "license:", license);
+        }
+        if info.executable(&Platform::current()).is_some() || !info.executables.is_empty() {
+            let executables = info.executables(&Platform::current(), installed_name(package_name))?;
+            let label = if executables.len() > 1 { "executables:" } else { "executable:" };
+            println!("  {:<12} {}", label, executables.join(", "));
+        }
+        if let Some(pattern) = info.asset_pattern(&Platform::current()) {
+            println!("  {:<12} {}", "asset:", pattern);
+        }
+        if !info.tags.is_empty() {
+            println!("  {:<12} {}", "tags:", info.tags.join(", "));
+        }
+        if !package.path.as_os_str().is_empty() {
+            println!("  {:<12} {}", "definition:", package.path.display());
+        }
+
+        match installed {
+            Some(installed) => {
+                match &installed.registry {
+                    Some(registry) if package.registry.as_ref() != Some(registry) => println!(
+                        "  {:<12} {} {}",
+                        "installed:",
+                        installed.version.green(),
+                        format!("(from registry {})", registry).yellow()
+                    ),
+                    _ => println!("  {:<12} {}", "installed:", installed.version.green()),
+                }
+                println!("  {:<12} {}", "path:", installed.install_path.display());
+                if installed.executables.len() > 1 {
+                    let linked: Vec<String> = installed
+                        .executables
+                        .iter()
+                        .map(|e| shim::shim_name(e))
+                        .collect();
+                    println!("  {:<12} {}", "linked:", linked.join(", "));
+                }
+                if let Some(attestation) = installed.attestation {
+                    println!("  {:<12} {}", "attested:", attestation);
+                }
+                if installed.installed_versions.len() > 1 {
+                    println!(
+                        "  {:<12} {}",
+                        "versions:",
+                        installed.installed_versions.join(", ")
+                    );
+                }
+            }
+            None => println!("  {:<12} {}", "installed:", "no".yellow()),
+        }
+
+        let releases = self.registry_manager.get_releases(&info.repository).await?;
+        if releases.is_empty() {
+            println!("\n{} No releases published", "!".yellow());
+            return Ok(());
+        }
+
+        println!("\n{} Available versions:", "→".blue());
+        for release in releases.iter().take(versions_shown) {
+            let mut line = format!("  {}", release.tag_name);
+            if let Some(date) = &release.published_at {
+                line.push_str(&format!(" ({})", date.split('T').next().unwrap_or(date)));
+            }
+            if release.prerelease {
+                line.push_str(" [pre-release]");
+            }
+            println!("{}", line);
+        }
+        if releases.len() > versions_shown {
+            println!("  ... and {} more", releases.len() - versions_shown);
+        }
+
+        if let Some(latest) = releases.iter().find(|r| !r.draft && !r.prerelease) {
+            println!("\n{} Release notes for {}:", "→".blue(), latest.tag_name.cyan());
+            match latest.body.as_deref().map(str::trim) {
+                Some(body) if !body.is_empty() => {
+                    for line in body.lines() {
+                        println!("  {}", line);
+                    }
+                }
+                _ => println!("  (no release notes)"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The latest release of each of `installed`, or `None` where it couldn't
+    /// be looked up
+    async fn latest_releases(&self, installed: &[(&String, &InstalledPackage)]) -> Vec<Option<Release>> {
+        // Packages installed before repositories were recorded need a registry lookup
+        let mut repositories = Vec::new();
+        for (name, package) in installed {
+            let repository = match &package.repository {
+                Some(repository) => Some(repository.clone()),
+                None => self
+                    .registry_manager
+                    .find_package(&self.config.registries, name)
+                    .await
+                    .ok()
+                    .map(|p| p.info.repository),
+            };
+            repositories.push(repository);
+        }
+
+        futures_util::future::join_all(repositories.iter().map(|repository| async move {
+            match repository {
+                Some(repository) => self.registry_manager.get_latest_release(repository).await.ok(),
+                None => None,
+            }
+        }))
+        .await
+    }
+
+    /// Looks up the latest release of every installed package and records
+    /// them for the update notice. `grip update-check` runs this in the
+    /// background
+    pub async fn check_for_updates(&self) -> Result<()> {
+        let installed = self.package_state()?.list_packages();
+        let latest = self.latest_releases(&installed).await;
+        let mut record = update_check::load(&self.root);
+        record.latest = installed
+            .iter()
+            .zip(latest)
+            .filter_map(|((name, _), release)| Some((name.to_string(), release?.tag_name)))
+            .collect();
+        update_check::save(&self.root, &record)
+    }
+
+    /// With `update_check.interval` set, says how many packages the last
+    /// background check found newer releases of, and starts another check
+    /// once that one is older than the interval. Never waits on the network
+    pub fn update_notice(&self) {
+        let Ok(Some(interval)) = self.config.update_check.interval() else {
+            return;
+        };
+        if self.registry_manager.offline {
+            return;
+        }
+        let mut record = update_check::load(&self.root);
+        if update_check::is_due(&record, interval) {
+            record.checked_at = update_check::now();
+            // Recorded first, so commands run meanwhile don't start checks of their own
+            let started = update_check::save(&self.root, &record)
+                .and_then(|()| update_check::spawn(&self.registry_manager.data_dir, self.config.file()));
+            if let Err(e) = started {
+                tracing::debug!("couldn't start the update check: {}", e);
+            }
+        }
+
+        // Compared with what is installed now, so packages upgraded since don't count
+        let Ok(state) = self.package_state() else {
+            return;
+        };
+        let outdated = record
+            .latest
+            .iter()
+            .filter(|(name, latest)| {
+                state
+                    .get_package(name)
+                    .is_some_and(|package| !package.pinned && is_newer(latest, &package.version))
+            })
+            .count();
+        // On stderr, so it never ends up in output another program reads
+        match outdated {
+            0 => {}
+            1 => eprintln!("{} 1 package has an update, run `grip upgrade --all`", ui::arrow()),
+            n => eprintln!("{} {} packages have updates, run `grip upgrade --all`", ui::arrow(), n),
+        }
+    }
+
+    /// Compares installed versions with the latest releases and returns whether
+    /// any package is out of date
+    pub async fn outdated(&self) -> Result<bool> {
+        let mut installed: Vec<(&String, &InstalledPackage)> = self.package_state()?.list_packages();
+        installed.sort_by_key(|(name, _)| name.as_str());
+
+        if installed.is_empty() {
+            ui::status!("No packages installed");
+            return Ok(false);
+        }
+
+        let latest = self.latest_releases(&installed).await;
+
+        if ui::json_output() {
+            let json: Vec<_> = installed
+                .iter()
+                .zip(&latest)
+                .map(|((name, package), latest)| {
+                    serde_json::json!({
+                        "name": name,
+                        "installed": package.version,
+                        "latest": latest.as_ref().map(|r| &r.tag_name),
+                        "outdated": latest.as_ref().is_some_and(|r| is_newer(&r.tag_name, &package.version)),
+                    })
+                })
+                .collect();
+            ui::print_json(&json)?;
+            return Ok(json.iter().any(|p| p["outdated"] == true));
+        }
+
+        let mut table = ui::Table::new(&["PACKAGE", "INSTALLED", "LATEST"]);
+        let mut any_outdated = false;
+        for ((name, package), latest) in installed.iter().zip(latest) {
+            let status = match latest {
+                Some(release) if is_newer(&release.tag_name, &package.version) => {
+                    any_outdated = true;
+                    release.tag_name.yellow()
+                }
+                Some(release) => release.tag_name.green(),
+                None => "unknown".red(),
+            };
+            table.add_row(vec![name.cyan().to_string(), package.version.clone(), status.to_string()]);
+        }
+        table.print();
+
+        if any_outdated {
+            println!("\n{} Run `grip upgrade --all` to update", "→".blue());
+        }
+        Ok(any_outdated)
+    }
+
+    /// Checks every installed package's active version against the OSV
+    /// vulnerability database. Returns whether any known vulnerability was found
+    pub async fn audit(&self) -> Result<bool> {
+        let mut installed: Vec<(&String, &InstalledPackage)> = self.package_state()?.list_packages();
+        installed.sort_by_key(|(name, _)| name.as_str());
+
+        if installed.is_empty() {
+            ui::status!("No packages installed");
+            return Ok(false);
+        }
+
+        // Only registry entries say which OSV ecosystem a package belongs to;
+        // one only counts if the package was installed from its repository
+        let mut queries = Vec::new();
+        for (name, package) in &installed {
+            let repository = package.repository.as_deref();
+            let osv = if repository.is_some_and(provider::is_direct_source) {
+                None
+            } else {
+                self.registry_manager
+                    .find_package(&self.config.registries, name)
+                    .await
+                    .ok()
+                    .filter(|p| repository.is_none_or(|r| r == p.info.repository))
+                    .and_then(|p| p.info.osv)
+            };
+            queries.push(osv::Query::new(osv.as_ref(), repository.and_then(provider::web_url), &package.version));
+        }
+
+        let api_url = self.config.osv_url.as_deref().unwrap_or(osv::DEFAULT_API_URL);
+        let known: Vec<osv::Query> = queries.iter().flatten().cloned().collect();
+        let mut ids = osv::query_batch(&self.registry_manager, api_url, &known).await?.into_iter();
+
+        let mut results = Vec::new();
+        for ((name, package), query) in installed.iter().zip(&queries) {
+            let Some(query) = query else {
+                results.push((*name, package.version.as_str(), None));
+                continue;
+            };
+            let found = futures_util::future::try_join_all(ids.next().unwrap_or_default().iter().map(|id| {
+                osv::vulnerability(&self.registry_manager, api_url, id, &query.package)
+            }))
+            .await?;
+            results.push((*name, package.version.as_str(), Some(found)));
+        }
+        let any_found = results.iter().any(|(_, _, found)| found.as_ref().is_some_and(|f| !f.is_empty()));
+
+        if ui::json_output() {
+            let json: Vec<_> = results
+                .iter()
+                .map(|(name, version, found)| {
+                    serde_json::json!({
+                        "name": name,
+                        "version": version,
+                        "audited": found.is_some(),
+                        "vulnerabilities": found.as_deref().unwrap_or_default(),
+                    })
+                })
+                .collect();
+            ui::print_json(&json)?;
+            return Ok(any_found);
+        }
+
+        let mut unaudited = Vec::new();
+        for (name, version, found) in &results {
+            match found {
+                None => unaudited.push(name.as_str()),
+                Some(found) if found.is_empty() => {}
+                Some(found) => {
+                    println!(
+                        "{} {} has {} known vulnerabilit{}:",
+                        name.cyan(),
+                        version,
+                        found.len(),
+                        if found.len() == 1 { "y" } else { "ies" }
+                    );
+                    for vulnerability in found {
+                        let severity = vulnerability.severity.as_deref().unwrap_or("unknown severity");
+                        let fixed = if vulnerability.fixed.is_empty() {
+                            "no fix listed".to_string()
+                        } else {
+                            format!("fixed in {}", vulnerability.fixed.join(", "))
+                        };
+                        ui::print_detail(&format!("{}  {}  {}", vulnerability.id.red(), severity, fixed));
+                        if let Some(summary) = &vulnerability.summary {
+                            ui::print_detail(&format!("  {}", summary));
+                        }
+                    }
+                }
+            }
+        }
+
+        if !unaudited.is_empty() {
+            ui::warning!(
+                "Not audited, as they weren't installed from a known repository: {}",
+                unaudited.join(", ")
+            );
+        }
+        if any_found {
+            println!("\n{} Run `grip upgrade` on the affected packages once a fixed release is out", "→".blue());
+        } else {
+            ui::success!("No known vulnerabilities in {} package(s)", results.len() - unaudited.len());
+        }
+        Ok(any_found)
+    }
+
+    /// Prints the license of every installed package, marking those that
+    /// `settings.denied_licenses` rules out
+    pub async fn licenses(&self) -> Result<()> {
+        let mut installed: Vec<(&String, &InstalledPackage)> = self.package_state()?.list_packages();
+        installed.sort_by_key(|(name, _)| name.as_str());
+
+        // Packages installed before licenses were recorded fall back to the registry
+        let mut licenses = Vec::new();
+        for (name, package) in &installed {
+            let license = match &package.license {
+                Some(license) => Some(license.clone()),
+                None => self
+                    .registry_manager
+                    .find_package(&self.config.registries, name)
+                    .await
+                    .ok()
+                    .filter(|p| package.repository.as_deref().is_none_or(|r| r == p.info.repository))
+                    .and_then(|p| p.info.license),
+            };
+            let denied = license
+                .as_deref()
+                .and_then(|l| license::denied(l, &self.config.settings.denied_licenses));
+            licenses.push((license, denied.is_some()));
+        }
+
+        if ui::json_output() {
+            let json: Vec<_> = installed
+                .iter()
+                .zip(&licenses)
+                .map(|((name, package), (license, denied))| {
+                    serde_json::json!({
+                        "name": name,
+                        "version": package.version,
+                        "license": license,
+                        "denied": denied,
+                    })
+                })
+                .collect();
+            return ui::print_json(&json);
+        }
+
+        if installed.is_empty() {
+            ui::status!("No packages installed");
+            return Ok(());
+        }
+
+        let mut table = ui::Table::new(&["PACKAGE", "VERSION", "LICENSE"]);
+        for ((name, package), (license, denied)) in installed.iter().zip(&licenses) {
+            let license = match license {
+                Some(license) if *denied => format!("{} {}", license, "[denied]".red()),
+                Some(license) => license.clone(),
+                None => "unknown".yellow().to_string(),
+            };
+            table.add_row(vec![name.cyan().to_string(), package.version.clone(), license]);
+        }
+        table.print();
+
+        let unknown = licenses.iter().filter(|(license, _)| license.is_none()).count();
+        let denied = licenses.iter().filter(|(_, denied)| *denied).count();
+        if denied > 0 {
+            ui::warning!("{} package(s) use a license denied_licenses rules out", denied);
+        }
+        if unknown > 0 {
+            ui::status!("{} package(s) have no known license", unknown);
+        }
+        Ok(())
+    }
+
+    /// Lists installed packages whose name contains `filter`, with the space
+    /// they take, when they were installed and whether the main executable is
+    /// still there
+    pub async fn list_packages(&self, filter: Option<&str>, sort: ListSort) -> Result<()> {
+        let mut packages: Vec<(String, InstalledPackage, u64)> = self
+            .state_store
+            .packages(filter)?
+            .into_iter()
+            .map(|(name, package)| {
+                // Packages installed before sizes were recorded are measured now
+                let size = package.size.unwrap_or_else(|| self.package_size(&name));
+                (name, package, size)
+            })
+            .collect();
+        packages.sort_by(|(a, a_package, a_size), (b, b_package, b_size)| match sort {
+            ListSort::Name => a.cmp(b),
+            ListSort::Size => b_size.cmp(a_size).then(a.cmp(b)),
+            ListSort::Installed => b_package.installed_at.cmp(&a_package.installed_at).then(a.cmp(b)),
+        });
+        let missing = |package: &InstalledPackage| package.executable().is_some_and(|e| !e.exists());
+
+        if ui::json_output() {
+            let packages: Vec<_> = packages
+                .into_iter()
+                .map(|(name, package, size)| {
+                    let mut json = serde_json::to_value(&package)?;
+                    json["name"] = serde_json::json!(name);
+                    json["size"] = serde_json::json!(size);
+                    json["executable_missing"] = serde_json::json!(missing(&package));
+                    Ok(json)
+                })
+                .collect::<Result<_>>()?;
+            return ui::print_json(&packages);
+        }
+        if packages.is_empty() {
+            match filter {
+                Some(filter) => ui::status!("No installed packages match '{}'", filter),
+                None => ui::status!("No packages installed"),
+            }
+            return Ok(());
+        }
+
+        let mut table =
+            ui::Table::new(&["PACKAGE", "VERSION", "REGISTRY", "SIZE", "INSTALLED", "OTHER VERSIONS"]).align_right(3);
+        for (name, package, size) in &packages {
+            let others: Vec<&str> = package
+                .installed_versions
+                .iter()
+                .filter(|v| **v != package.version)
+                .map(String::as_str)
+                .collect();
+            let mut flags = String::new();
+            if package.pinned {
+                flags.push_str(&format!(" {}", "[pinned]".yellow()));
+            }
+            if missing(package) {
+                flags.push_str(&format!(" {}", "[executable missing]".red()));
+            }
+            if !package.aliases.is_empty() {
+                flags.push_str(&format!(" (aliases: {})", package.aliases.join(", ")));
+            }
+            table.add_row(vec![
+                name.cyan().to_string(),
+                package.version.clone(),
+                package.registry.clone().unwrap_or_else(|| "-".to_string()),
+                utils::format_size(*size),
+                package.installed_at.map(utils::format_date).unwrap_or_else(|| "-".to_string()),
+                format!("{}{}", if others.is_empty() { "-".to_string() } else { others.join(", ") }, flags),
+            ]);
+        }
+        table.print();
+        let total = packages.iter().map(|(_, _, size)| size).sum();
+        ui::status!("{} package(s), {}", packages.len(), utils::format_size(total));
+        Ok(())
+    }
+
+    /// Disk space a package's installed versions take. Backups of a version
+    /// being reinstalled start with a dot and aren't counted
+    fn package_size(&self, package_name: &str) -> u64 {
+        std::fs::read_dir(self.root.join("packages").join(package_name))
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+                    .map(|e| utils::dir_size(&e.path()))
+                    .sum()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Prints installed packages with their dependencies beneath them, starting
+    /// from `package` or from every package nothing else depends on
+    pub fn tree(&self, package: Option<&str>) -> Result<()> {
+        let state = self.package_state()?;
+        let roots: Vec<String> = match package {
+            Some(name) => {
+                if state.get_package(name).is_none() {
+                    bail!(PackageNotFound, "{} is not installed", name);
+                }
+                vec![name.to_string()]
+            }
+            None => {
+                let mut roots: Vec<String> = state
+                    .list_packages()
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .filter(|name| state.dependents(name).is_empty())
+                    .cloned()
+                    .collect();
+                roots.sort();
+                roots
+            }
+        };
+
+        if ui::json_output() {
+            let trees: Vec<_> = roots
+                .iter()
+                .map(|root| tree_json(state, &Dependency::parse(root), &mut Vec::new()))
+                .collect();
+            return ui::print_json(&trees);
+        }
+        for root in &roots {
+            print_tree(state, &Dependency::parse(root), "", None, &mut Vec::new());
+        }
+        Ok(())
+    }
+}
+
+fn tree_json(state: &PackageState, dependency: &Dependency, path: &mut Vec<String>) -> serde_json::Value {
+    let installed = state.get_package(&dependency.name);
+    let cycle = path.contains(&dependency.name);
+    path.push(dependency.name.clone());
+    let children: Vec<_> = installed
+        .filter(|_| !cycle)
+        .map(|p| p.dependencies.iter().map(|d| tree_json(state, &Dependency::parse(d), path)).collect())
+        .unwrap_or_default();
+    path.pop();
+    serde_json::json!({
+        "name": dependency.name,
+        "requirement": dependency.requirement,
+        "version": installed.map(|p| &p.version),
+        "dependencies": children,
+    })
+}
+
+/// `last` is `None` for a root, otherwise whether it is its parent's last child
+fn print_tree(state: &PackageState, dependency: &Dependency, prefix: &str, last: Option<bool>, path: &mut Vec<String>) {
+    let (branch, child_prefix) = match last {
+        None => ("", String::new()),
+        Some(true) => ("└── ", format!("{}    ", prefix)),
+        Some(false) => ("├── ", format!("{}│   ", prefix)),
+    };
+    let installed = state.get_package(&dependency.name);
+    let status = match (installed, &dependency.requirement) {
+        (None, _) => "(not installed)".red().to_string(),
+        (Some(p), Some(r)) if !resolver::satisfies(&p.version, r) => {
+            format!("{} {}", p.version, format!("(needs {})", r).yellow())
+        }
+        (Some(p), _) => p.version.clone(),
+    };
+    let cycle = path.contains(&dependency.name);
+    println!(
+        "{}{}{} {}{}",
+        prefix,
+        branch,
+        dependency.name.cyan(),
+        status,
+        if cycle { " (cycle)".red().to_string() } else { String::new() }
+    );
+    let Some(installed) = installed.filter(|_| !cycle) else {
+        return;
+    };
+
+    path.push(dependency.name.clone());
+    let count = installed.dependencies.len();
+    for (i, child) in installed.dependencies.iter().enumerate() {
+        print_tree(state, &Dependency::parse(child), &child_prefix, Some(i + 1 == count), path);
+    }
+    path.pop();
+}
+
+/// The first of a pin's versions that is installed
+fn pinned_tag(installed: &InstalledPackage, pin: &version_file::Pin) -> Option<String> {
+    pin.versions.iter().find_map(|version| {
+        installed
+            .installed_versions
+            .iter()
+            .find(|tag| version_file::matches(tag, version))
+            .cloned()
+    })
+}
+
+/// Finds a version's executables. Versions are laid out alike, so the active
+/// version's relative paths are tried before searching the version's directory
+fn locate_executables(
+    installed: &InstalledPackage,
+    version_dir: &Path,
+    package_name: &str,
+) -> Result<Vec<PathBuf>> {
+    if installed.executables.is_empty() {
+        return Ok(utils::find_executable(version_dir, package_name)?.into_iter().collect());
+    }
+
+    let mut executables = Vec::new();
+    for current in &installed.executables {
+        let same_place = current
+            .strip_prefix(&installed.install_path)
+            .ok()
+            .map(|relative| version_dir.join(relative))
+            .filter(|p| p.is_file());
+        let found = match same_place {
+            Some(path) => Some(path),
+            None => {
+                let name = current
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| package_name.to_string());
+                utils::find_executable(version_dir, &name)?
+            }
+        };
+        if let Some(path) = found.filter(|path| !executables.contains(path)) {
+            executables.push(path);
+        }
+    }
+    Ok(executables)
+}
+
+/// The name a package is recorded under; repository specs like `gh:owner/repo`
+/// install as `repo`, and qualified names like `corp/tool` as `tool`
+pub fn installed_name(spec: &str) -> &str {
+    provider::repository_name(spec)
+        .or_else(|| registry::split_qualified(spec).map(|(_, name)| name))
+        .unwrap_or(spec)
+}
+
+/// What to look a package up by: the registry it came from while that is
+/// still configured, and packages installed straight from a repository have
+/// no registry entry
+fn lookup_name(package_name: &str, installed: &InstalledPackage, registries: &[Registry]) -> String {
+    match (&installed.registry, &installed.repository) {
+        (Some(registry), _) if registries.iter().any(|r| r.name == *registry) => {
+            format!("{}/{}", registry, package_name)
+        }
+        (_, Some(repository)) if provider::is_repository_spec(repository) => repository.clone(),
+        _ => package_name.to_string(),
+    }
+}
+
+/// Deletes a file or directory, returning it with the space it took
+fn remove_tree(path: &Path) -> Result<(PathBuf, u64)> {
+    let size = utils::dir_size(path);
+    if std::fs::symlink_metadata(path)?.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+    Ok((path.to_path_buf(), size))
+}
+
+/// Returns whether release tag `latest` is newer than the installed `current`,
+/// falling back to a plain comparison for tags that aren't semantic versions
+fn is_newer(latest: &str, current: &str) -> bool {
+    match (resolver::parse_tag(latest), resolver::parse_tag(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => latest != current,
+    }
+}
+
+/// Picks the named asset from a release, else the one the registry's asset
+/// pattern for this platform names, else the best match for the current
+/// platform, prompting as allowed by `prompt_mode`
+fn select_asset<'a>(
+    release: &'a Release,
+    package: &PackageInfo,
+    name: Option<&str>,
+    preferences: &AssetPreferences,
+    prompt_mode: PromptMode,
+) -> Result<&'a Asset> {
+    if let Some(a) = name {
+        return release
+            .assets
+            .iter()
+            .find(|asset| asset.name == a)
+            .ok_or_else(|| GripError::AssetNotFound(a.to_string()));
+    }
+
+    if release.assets.is_empty() {
+        bail!(AssetNotFound, "No assets found in release");
+    }
+
+    let platform = Platform::preferred(preferences)?;
+    let mut assets: Vec<&Asset> = release.assets.iter().collect();
+    if let Some(pattern) = package.asset_pattern(&platform) {
+        let matching: Vec<&Asset> = release
+            .assets
+            .iter()
+            .filter(|asset| asset_matcher::matches_pattern(&asset.name, pattern))
+            .collect();
+        tracing::debug!(pattern, matching = matching.len(), "matched the registry's asset pattern");
+        match matching.as_slice() {
+            [asset] => {
+                ui::status!("Selected asset {}", asset.name.cyan());
+                return Ok(asset);
+            }
+            [] => ui::warning!(
+                "No asset of {} matches the registry's pattern {}; guessing instead",
+                release.tag_name,
+                pattern
+            ),
+            // Narrow the choice to the assets the pattern allows
+            _ => assets = matching,
+        }
+    }
+
+    let candidates: Vec<&Asset> = if prompt_mode == PromptMode::Always {
+        assets
+    } else {
+        let matched = asset_matcher::best_preferred_match(assets.iter().copied(), &platform, preferences);
+        tracing::debug!(?platform, ?matched, "matched assets against this platform");
+        match matched {
+            AssetMatch::Found(asset) => {
+                ui::status!("Selected asset {}", asset.name.cyan());
+                return Ok(asset);
+            }
+            AssetMatch::Ambiguous(candidates) if prompt_mode == PromptMode::Never => {
+                let names: Vec<&str> = candidates.iter().map(|a| a.name.as_str()).collect();
+                bail!(
+                    Usage,
+                    "Multiple assets match this platform ({}); pass --asset to choose one",
+                    names.join(", ")
+                );
+            }
+            AssetMatch::Ambiguous(candidates) => candidates,
+            AssetMatch::NoMatch if prompt_mode == PromptMode::Never => {
+                bail!(
+                    AssetNotFound,
+                    "No asset matches this platform ({}-{}); pass --asset to choose one",
+                    platform.os,
+                    platform.arch
+                );
+            }
+            AssetMatch::NoMatch => {
+                ui::warning!("No asset matches this platform ({}-{})", platform.os, platform.arch);
+                assets
+            }
+        }
+    };
+
+    let choices: Vec<picker::Choice> = candidates
+        .iter()
+        .map(|asset| {
+            let size = asset.size.map(utils::format_size);
+            let hint = asset_matcher::platform_hint(&asset.name);
+            let detail: Vec<String> = size.into_iter().chain((!hint.is_empty()).then_some(hint)).collect();
+            picker::Choice::new(&asset.name, detail.join(", "))
+        })
+        .collect();
+
+    ui::status!("Available assets (type to search):");
+    let selection = picker::pick("Select asset", &choices, 0)?;
+
+    Ok(candidates[selection])
+}
+
+/// A release as the version picker shows it: its tag, with when it was
+/// published and whether it is a pre-release
+fn release_choice(release: &Release) -> picker::Choice {
+    let mut detail = release
+        .published_at
+        .as_deref()
+        .and_then(|date| date.get(..10))
+        .unwrap_or_default()
+        .to_string();
+    if release.prerelease {
+        if !detail.is_empty() {
+            detail.push_str(", ");
+        }
+        detail.push_str("pre-release");
+    }
+    picker::Choice::new(&release.tag_name, detail)
+}
+
@@ -0,0 +1,94 @@
+use crate::error::{GripError, Result};
+use crate::lock::{self, FileLock};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// How many transactions are kept; older ones are dropped as new ones come in
+pub(crate) const MAX_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Install,
+    /// The active version installed again
+    Reinstall,
+    Upgrade,
+    Downgrade,
+    Uninstall,
+    /// An upgrade reverted by `grip rollback`
+    Rollback,
+}
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Install => "install",
+            Action::Reinstall => "reinstall",
+            Action::Upgrade => "upgrade",
+            Action::Downgrade => "downgrade",
+            Action::Uninstall => "uninstall",
+            Action::Rollback => "rollback",
+        }
+    }
+}
+
+/// One change to a package's active version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transaction {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub action: Action,
+    pub package: String,
+    /// The version that was active before, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// The version active afterwards, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+}
+
+impl Transaction {
+    pub fn new(action: Action, package: &str, from: Option<String>, to: Option<String>) -> Self {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            timestamp,
+            action,
+            package: package.to_string(),
+            from,
+            to,
+        }
+    }
+}
+
+/// The log of installs, upgrades and uninstalls in `history.json`, oldest first
+pub fn load(data_dir: &Path) -> Result<Vec<Transaction>> {
+    let path = data_dir.join("history.json");
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    serde_json::from_str(&content)
+        .map_err(|e| GripError::ConfigError(format!("Failed to parse {}: {}", path.display(), e)))
+}
+
+/// Appends a transaction to the log
+pub fn record(data_dir: &Path, transaction: Transaction) -> Result<()> {
+    let _lock = FileLock::acquire(&lock::history_lock_path(data_dir), "the history")?;
+    let mut transactions = load(data_dir)?;
+    transactions.push(transaction);
+    if transactions.len() > MAX_ENTRIES {
+        transactions.drain(..transactions.len() - MAX_ENTRIES);
+    }
+    save(data_dir, &transactions)
+}
+
+/// Replaces the log in `history.json`
+pub(crate) fn save(data_dir: &Path, transactions: &[Transaction]) -> Result<()> {
+    let temp_file = data_dir.join("history.json.tmp");
+    std::fs::write(&temp_file, serde_json::to_string_pretty(transactions)?)?;
+    std::fs::rename(temp_file, data_dir.join("history.json"))?;
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use crate::error::{GripError, Result};
+use crate::ui;
+use std::path::Path;
+use std::process::Command;
+
+/// What a post-install hook is told about the install it follows, through
+/// `GRIP_PACKAGE`, `GRIP_VERSION`, `GRIP_INSTALL_DIR` and `GRIP_EXECUTABLE`
+pub struct HookContext<'a> {
+    pub package: &'a str,
+    pub version: &'a str,
+    pub install_dir: &'a Path,
+    pub executable: Option<&'a Path>,
+}
+
+/// Runs a hook command through the platform shell from the install directory
+pub fn run(command: &str, context: &HookContext) -> Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .current_dir(context.install_dir)
+        .env("GRIP_PACKAGE", context.package)
+        .env("GRIP_VERSION", context.version)
+        .env("GRIP_INSTALL_DIR", context.install_dir);
+    if let Some(executable) = context.executable {
+        shell.env("GRIP_EXECUTABLE", executable);
+    }
+    if ui::status_to_stderr() {
+        shell.stdout(std::io::stderr());
+    }
+
+    tracing::debug!(command, package = context.package, "running hook");
+    let status = shell
+        .status()
+        .map_err(|e| GripError::Other(format!("Failed to run `{}`: {}", command, e)))?;
+    if !status.success() {
+        return Err(GripError::Other(format!("`{}` exited with {}", command, status)));
+    }
+    Ok(())
+}
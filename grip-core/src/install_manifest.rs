@@ -0,0 +1,138 @@
+use crate::error::{GripError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Everything installing a version of a package created, so uninstalling it
+/// removes exactly that. Links are recorded when made and only removed while
+/// they still point into the package, since another package may have taken
+/// their name over since
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstallManifest {
+    /// Files and directories in the version directory, relative to it
+    #[serde(default)]
+    pub entries: Vec<String>,
+    /// Names linked in the bin directory while the version was active:
+    /// executables and aliases
+    #[serde(default)]
+    pub shims: Vec<String>,
+    /// Completions and man pages linked into the share directory, relative to it
+    #[serde(default)]
+    pub extras: Vec<PathBuf>,
+    /// Directories put on PATH for the version, in shell startup files or the
+    /// user PATH on Windows
+    #[serde(default)]
+    pub path: Vec<PathBuf>,
+}
+
+/// The directory under the install root manifests are kept in, one
+/// directory per package
+pub const DIR: &str = "manifests";
+
+/// Where the manifests of a package's versions are kept, apart from the
+/// package directory so the manifests aren't part of what they describe
+pub fn dir(root: &Path, package: &str) -> PathBuf {
+    root.join(DIR).join(package)
+}
+
+impl InstallManifest {
+    pub fn path(root: &Path, package: &str, version: &str) -> PathBuf {
+        dir(root, package).join(format!("{}.json", version))
+    }
+
+    /// Lists every file and directory under `version_dir`. Sorted, so each
+    /// directory comes before its contents
+    pub fn record_entries(&mut self, version_dir: &Path) -> Result<()> {
+        self.entries.clear();
+        self.add_dir(version_dir, version_dir)?;
+        self.entries.sort();
+        Ok(())
+    }
+
+    fn add_dir(&mut self, version_dir: &Path, dir: &Path) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let relative = path
+                .strip_prefix(version_dir)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            self.entries.push(relative);
+            if entry.file_type()?.is_dir() {
+                self.add_dir(version_dir, &path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the manifest of `version`, or `None` for versions installed
+    /// before grip recorded them
+    pub fn load(root: &Path, package: &str, version: &str) -> Result<Option<Self>> {
+        let path = Self::path(root, package, version);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| GripError::ConfigError(format!("Failed to parse {}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, root: &Path, package: &str, version: &str) -> Result<()> {
+        let path = Self::path(root, package, version);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Adds links made for an already installed version, e.g. by `grip use`
+    /// or `grip alias`. Versions without a manifest are left without one, so
+    /// uninstalling them still removes their whole directory
+    pub fn add_links(root: &Path, package: &str, version: &str, shims: &[String], extras: &[PathBuf]) -> Result<()> {
+        let Some(mut manifest) = Self::load(root, package, version)? else {
+            return Ok(());
+        };
+        for shim in shims {
+            if !manifest.shims.contains(shim) {
+                manifest.shims.push(shim.clone());
+            }
+        }
+        for extra in extras {
+            if !manifest.extras.contains(extra) {
+                manifest.extras.push(extra.clone());
+            }
+        }
+        manifest.save(root, package, version)
+    }
+
+    /// Removes the recorded entries of `version_dir`, contents before their
+    /// directories, and returns how many files are left that the package
+    /// created after it was installed. Directories that still hold such files
+    /// stay
+    pub fn remove_entries(&self, version_dir: &Path) -> Result<usize> {
+        for entry in self.entries.iter().rev() {
+            let path = version_dir.join(entry);
+            let Ok(metadata) = path.symlink_metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                // Fails while the directory holds files grip didn't install
+                let _ = std::fs::remove_dir(&path);
+            } else {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        if !version_dir.exists() {
+            return Ok(0);
+        }
+        let left = crate::utils::walk_files(version_dir)?.len();
+        if left == 0 {
+            std::fs::remove_dir_all(version_dir)?;
+        }
+        Ok(left)
+    }
+}
@@ -0,0 +1,220 @@
+use crate::error::{GripError, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// How much of a setup `.exe` is searched for an installer framework's marker
+const MARKER_SCAN_LIMIT: u64 = 4 * 1024 * 1024;
+
+/// A Windows installer asset, which has to run rather than be unpacked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallerKind {
+    /// A Windows Installer package, run with `msiexec`
+    Msi,
+    /// An Inno Setup installer
+    Inno,
+    /// A Nullsoft (NSIS) installer
+    Nsis,
+    /// A setup `.exe` of an unknown framework, which can't be run silently
+    Setup,
+}
+
+impl InstallerKind {
+    /// The arguments that install without prompting, for frameworks that
+    /// have them
+    fn silent_args(&self) -> &'static [&'static str] {
+        match self {
+            InstallerKind::Msi => &["/qn", "/norestart", "MSIINSTALLPERUSER=1", "ALLUSERS=2"],
+            InstallerKind::Inno => &["/VERYSILENT", "/SUPPRESSMSGBOXES", "/NORESTART"],
+            InstallerKind::Nsis => &["/S"],
+            InstallerKind::Setup => &[],
+        }
+    }
+
+    pub fn is_silent(&self) -> bool {
+        !self.silent_args().is_empty()
+    }
+}
+
+/// What an installer registered, so `grip uninstall` can undo it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstallerRecord {
+    pub kind: InstallerKind,
+    /// The name the installer registered under Apps & Features
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// Where the installer put its files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<PathBuf>,
+    /// Command that removes what the installer put in place
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uninstall: Option<String>,
+}
+
+/// Recognizes installers: `.msi` packages by name, and `.exe` files either
+/// named like a setup program or carrying an installer framework's marker
+pub fn detect(asset_name: &str, file: &Path) -> Result<Option<InstallerKind>> {
+    let name = asset_name.to_lowercase();
+    if name.ends_with(".msi") {
+        return Ok(Some(InstallerKind::Msi));
+    }
+    if !name.ends_with(".exe") {
+        return Ok(None);
+    }
+
+    let mut head = Vec::new();
+    std::fs::File::open(file)?
+        .take(MARKER_SCAN_LIMIT)
+        .read_to_end(&mut head)?;
+    let contains = |marker: &[u8]| head.windows(marker.len()).any(|window| window == marker);
+    if contains(b"Inno Setup") {
+        return Ok(Some(InstallerKind::Inno));
+    }
+    if contains(b"Nullsoft") {
+        return Ok(Some(InstallerKind::Nsis));
+    }
+    Ok(is_setup_name(&name).then_some(InstallerKind::Setup))
+}
+
+/// Whether an asset's name marks it as a setup program rather than a
+/// portable executable
+pub fn is_setup_name(asset_name: &str) -> bool {
+    let name = asset_name.to_lowercase();
+    name.ends_with(".exe") && ["setup", "installer", "install"].iter().any(|word| name.contains(word))
+}
+
+/// Runs an installer, silently when its framework allows, and works out what
+/// it registered by comparing the Uninstall registry keys before and after
+#[cfg(windows)]
+pub fn run(file: &Path, kind: InstallerKind) -> Result<InstallerRecord> {
+    let before = windows::uninstall_entries();
+
+    let mut command = match kind {
+        InstallerKind::Msi => {
+            let mut command = std::process::Command::new("msiexec");
+            command.arg("/i").arg(file);
+            command
+        }
+        _ => std::process::Command::new(file),
+    };
+    let status = command
+        .args(kind.silent_args())
+        .status()
+        .map_err(|e| GripError::InstallError(format!("Failed to run {}: {}", file.display(), e)))?;
+    // 3010 is msiexec's "succeeded, restart required"
+    if !status.success() && status.code() != Some(3010) {
+        return Err(GripError::InstallError(format!("{} exited with {}", file.display(), status)));
+    }
+
+    let entry = windows::uninstall_entries()
+        .into_iter()
+        .find(|entry| !before.iter().any(|known| known.key == entry.key));
+    Ok(match entry {
+        Some(entry) => InstallerRecord {
+            kind,
+            display_name: entry.display_name,
+            location: entry.location,
+            uninstall: entry.uninstall,
+        },
+        None => InstallerRecord {
+            kind,
+            display_name: None,
+            location: None,
+            uninstall: None,
+        },
+    })
+}
+
+#[cfg(not(windows))]
+pub fn run(file: &Path, _kind: InstallerKind) -> Result<InstallerRecord> {
+    Err(GripError::InstallError(format!(
+        "{} is a Windows installer and can only run on Windows",
+        file.display()
+    )))
+}
+
+/// Runs the uninstall command an installer registered
+pub fn uninstall(record: &InstallerRecord) -> Result<()> {
+    let Some(command) = &record.uninstall else {
+        return Err(GripError::InstallError(
+            "the installer didn't register a way to uninstall it".to_string(),
+        ));
+    };
+    let status = if cfg!(windows) {
+        std::process::Command::new("cmd").args(["/C", command]).status()
+    } else {
+        std::process::Command::new("sh").args(["-c", command]).status()
+    }
+    .map_err(|e| GripError::InstallError(format!("Failed to run `{}`: {}", command, e)))?;
+    if !status.success() && status.code() != Some(3010) {
+        return Err(GripError::InstallError(format!("`{}` exited with {}", command, status)));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::path::PathBuf;
+    use winreg::enums::*;
+    use winreg::RegKey;
+
+    const UNINSTALL_KEYS: &[&str] = &[
+        r"Software\Microsoft\Windows\CurrentVersion\Uninstall",
+        r"Software\WOW6432Node\Microsoft\Windows\CurrentVersion\Uninstall",
+    ];
+
+    pub struct UninstallEntry {
+        /// The hive and subkey, which identify the entry
+        pub key: String,
+        pub display_name: Option<String>,
+        pub location: Option<PathBuf>,
+        pub uninstall: Option<String>,
+    }
+
+    /// Every program registered under Apps & Features, for the user and the machine
+    pub fn uninstall_entries() -> Vec<UninstallEntry> {
+        let mut entries = Vec::new();
+        for (hive_name, hive) in [("HKCU", HKEY_CURRENT_USER), ("HKLM", HKEY_LOCAL_MACHINE)] {
+            let hive = RegKey::predef(hive);
+            for path in UNINSTALL_KEYS {
+                let Ok(uninstall) = hive.open_subkey_with_flags(path, KEY_READ) else {
+                    continue;
+                };
+                for name in uninstall.enum_keys().filter_map(|name| name.ok()) {
+                    let Ok(key) = uninstall.open_subkey_with_flags(&name, KEY_READ) else {
+                        continue;
+                    };
+                    let value = |name: &str| {
+                        key.get_value::<String, _>(name)
+                            .ok()
+                            .filter(|value| !value.trim().is_empty())
+                    };
+                    // Installers without an install location often point
+                    // their icon at the main executable
+                    let location = value("InstallLocation").map(PathBuf::from).or_else(|| {
+                        let icon = value("DisplayIcon")?;
+                        let icon = icon.split(',').next()?.trim_matches('"');
+                        PathBuf::from(icon).parent().map(PathBuf::from)
+                    });
+                    entries.push(UninstallEntry {
+                        key: format!(r"{}\{}\{}", hive_name, path, name),
+                        display_name: value("DisplayName"),
+                        location,
+                        uninstall: value("QuietUninstallString").or_else(|| {
+                            // msiexec's /I in UninstallString opens the repair UI
+                            value("UninstallString").map(|command| {
+                                if name.starts_with('{') && command.to_lowercase().contains("msiexec") {
+                                    format!("msiexec /x {} /qn /norestart", name)
+                                } else {
+                                    command
+                                }
+                            })
+                        }),
+                    });
+                }
+            }
+        }
+        entries
+    }
+}
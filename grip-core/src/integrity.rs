@@ -0,0 +1,111 @@
+use crate::checksum;
+use crate::error::{GripError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The files an install put in its version directory, by path relative to
+/// it, with their sha256 digests. Symlinks are recorded by their target
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileManifest {
+    pub files: BTreeMap<String, String>,
+}
+
+/// How a file differs from what was installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Modified,
+    Missing,
+    Added,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    pub path: String,
+    pub change: Change,
+}
+
+impl FileManifest {
+    /// Where the file list of `version` is kept: beside the version
+    /// directories, so it isn't part of what it describes
+    pub fn path(package_dir: &Path, version: &str) -> PathBuf {
+        package_dir.join(format!(".files-{}.json", version))
+    }
+
+    /// Hashes every file under `dir`
+    pub fn record(dir: &Path) -> Result<Self> {
+        let mut manifest = Self::default();
+        manifest.add_dir(dir, dir)?;
+        Ok(manifest)
+    }
+
+    fn add_dir(&mut self, root: &Path, dir: &Path) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let file_type = std::fs::symlink_metadata(&path)?.file_type();
+            if file_type.is_dir() {
+                self.add_dir(root, &path)?;
+                continue;
+            }
+            let relative = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let digest = if file_type.is_symlink() {
+                format!("symlink:{}", std::fs::read_link(&path)?.display())
+            } else {
+                checksum::sha256_file(&path)?
+            };
+            self.files.insert(relative, digest);
+        }
+        Ok(())
+    }
+
+    /// Reads a manifest, or `None` for versions installed before grip
+    /// recorded them
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| GripError::ConfigError(format!("Failed to parse {}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Re-hashes the files under `dir` and lists every one that no longer
+    /// matches the manifest
+    pub fn verify(&self, dir: &Path) -> Result<Vec<FileChange>> {
+        let current = if dir.is_dir() { Self::record(dir)? } else { Self::default() };
+        let mut changes: Vec<FileChange> = self
+            .files
+            .iter()
+            .filter_map(|(path, digest)| {
+                let change = match current.files.get(path) {
+                    None => Change::Missing,
+                    Some(found) if found != digest => Change::Modified,
+                    Some(_) => return None,
+                };
+                Some(FileChange { path: path.clone(), change })
+            })
+            .collect();
+        changes.extend(
+            current
+                .files
+                .keys()
+                .filter(|path| !self.files.contains_key(*path))
+                .map(|path| FileChange { path: path.clone(), change: Change::Added }),
+        );
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(changes)
+    }
+}
@@ -0,0 +1,70 @@
+//! The engine behind the `grip` command line tool: registry resolution,
+//! release providers, downloads, verification, extraction and the record of
+//! installed packages.
+//!
+//! [`Grip`] ties these together the way the CLI uses them:
+//!
+//! ```no_run
+//! # async fn example() -> grip_core::Result<()> {
+//! let locations = grip_core::config::Locations::resolve(None, None)?;
+//! let mut grip = grip_core::Grip::new(true, true, false, None, grip_core::config::Scope::User, &locations).await?;
+//! let outcome = grip.install("ripgrep", Some("^14".to_string()), None, false, false, None).await?;
+//! println!("installed {} to {}", outcome.tag, outcome.install_path.display());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The modules can also be used on their own, e.g. [`registry::RegistryManager`]
+//! to look packages up or [`utils::extract_archive`] to unpack an asset.
+
+pub mod activation;
+pub mod attestation;
+pub mod bundle;
+pub mod cache;
+pub mod changelog;
+pub mod checksum;
+pub mod config;
+pub mod delta;
+pub mod dependency;
+pub mod error;
+pub mod extras;
+pub mod foreign;
+pub mod gatekeeper;
+pub mod history;
+pub mod hooks;
+pub mod install_manifest;
+pub mod installer;
+pub mod integrity;
+pub mod license;
+mod grip;
+pub mod lock;
+pub mod migrations;
+pub mod osv;
+pub mod package;
+pub mod path;
+pub mod picker;
+pub mod plugin;
+pub mod project;
+pub mod registry;
+pub mod resolver;
+pub mod sbom;
+pub mod search;
+pub mod setup;
+pub mod shim;
+pub mod signature;
+pub mod state;
+pub mod storage;
+pub mod store;
+pub mod system_package;
+pub mod template;
+pub mod timings;
+pub mod ui;
+pub mod update_check;
+pub mod usage;
+pub mod utils;
+pub mod version_file;
+
+pub use config::Config;
+pub use error::{GripError, Result};
+pub use grip::{installed_name, Grip, InstallOutcome, ListSort};
+pub use state::{InstalledPackage, PackageState};
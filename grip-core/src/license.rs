@@ -0,0 +1,52 @@
+/// The entry of `deny_list` that rules out a license expression, if any. An
+/// expression is ruled out when every alternative joined by `OR` names a
+/// denied license, so `MIT OR AGPL-3.0-only` is still allowed. Entries match
+/// SPDX ids case-insensitively, and a trailing `*` matches any id starting
+/// with the rest, as `AGPL-*` does `AGPL-3.0-only` and `AGPL-3.0-or-later`
+pub fn denied<'a>(expression: &str, deny_list: &'a [String]) -> Option<&'a str> {
+    let expression = expression.replace(['(', ')'], " ");
+    let mut first = None;
+    for alternative in split_keyword(&expression, "OR") {
+        let entry = licenses(&alternative).find_map(|id| deny_list.iter().find(|entry| matches(entry, id)))?;
+        first.get_or_insert(entry.as_str());
+    }
+    first
+}
+
+/// The license ids in a conjunction, leaving out `AND` and `WITH` exceptions
+fn licenses(conjunction: &str) -> impl Iterator<Item = &str> {
+    let mut tokens = conjunction.split_whitespace().peekable();
+    std::iter::from_fn(move || loop {
+        let token = tokens.next()?;
+        if token.eq_ignore_ascii_case("AND") {
+            continue;
+        }
+        if token.eq_ignore_ascii_case("WITH") {
+            tokens.next();
+            continue;
+        }
+        return Some(token);
+    })
+}
+
+fn split_keyword(expression: &str, keyword: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    for token in expression.split_whitespace() {
+        if token.eq_ignore_ascii_case(keyword) {
+            parts.push(String::new());
+        } else if let Some(part) = parts.last_mut() {
+            part.push(' ');
+            part.push_str(token);
+        }
+    }
+    parts.retain(|part| !part.trim().is_empty());
+    parts
+}
+
+fn matches(entry: &str, id: &str) -> bool {
+    let (entry, id) = (entry.to_lowercase(), id.to_lowercase());
+    match entry.strip_suffix('*') {
+        Some(prefix) => id.starts_with(prefix),
+        None => entry == id,
+    }
+}
@@ -0,0 +1,75 @@
+use crate::error::Result;
+use crate::ui;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+
+/// An exclusive advisory lock held on a file until dropped, so concurrent grip
+/// processes take turns instead of overwriting each other's changes
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Blocks until the lock at `path` is free, saying what is being waited for
+    /// when another process holds it
+    pub fn acquire(path: &Path, what: &str) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                ui::status!("Waiting for another grip process to release {}", what);
+                file.lock()?;
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        Ok(Self { _file: file })
+    }
+
+    /// Takes the lock at `path` if it is free, or returns `None` at once when
+    /// another process holds it
+    pub fn try_acquire(path: &Path) -> Result<Option<Self>> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { _file: file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+}
+
+/// The lock guarding `package_state.json`
+pub fn state_lock_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("package_state.lock")
+}
+
+/// The lock held while a package's directory is changed. Locks live outside
+/// the package directory so uninstalling can remove it while holding one
+pub fn package_lock_path(data_dir: &Path, package_name: &str) -> PathBuf {
+    data_dir.join("locks").join(format!("{}.lock", package_name))
+}
+
+/// The lock guarding `history.json`
+pub fn history_lock_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("history.lock")
+}
+
+/// The lock guarding `usage.json`
+pub fn usage_lock_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("usage.lock")
+}
@@ -0,0 +1,75 @@
+use crate::error::Result;
+use serde_json::Value;
+
+/// The `package_state.json` schema this build reads and writes
+pub const STATE_SCHEMA_VERSION: u32 = 2;
+
+/// Upgrades a state document by one schema version, in place
+type Migration = fn(&mut Value) -> Result<()>;
+
+// MIGRATIONS[n] upgrades version n to n + 1
+const MIGRATIONS: &[Migration] = &[unversioned_to_v1, v1_to_v2];
+
+/// The schema version of a state document; files written before versioning are 0
+pub fn state_version(state: &Value) -> u32 {
+    state
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .map_or(0, |v| v as u32)
+}
+
+/// Runs every migration between the document's version and the current one
+pub fn migrate_state(state: &mut Value) -> Result<()> {
+    for migration in MIGRATIONS.iter().skip(state_version(state) as usize) {
+        migration(state)?;
+    }
+    Ok(())
+}
+
+/// Unversioned files could record an active version missing from
+/// `installed_versions`; versioned files always list it
+fn unversioned_to_v1(state: &mut Value) -> Result<()> {
+    let packages = state
+        .get_mut("packages")
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|packages| packages.values_mut());
+
+    for package in packages {
+        let Some(version) = package.get("version").cloned() else {
+            continue;
+        };
+        match package.get_mut("installed_versions").and_then(Value::as_array_mut) {
+            Some(versions) if !versions.contains(&version) => versions.push(version),
+            Some(_) => {}
+            None => package["installed_versions"] = Value::Array(vec![version]),
+        }
+    }
+
+    state["schema_version"] = Value::from(1);
+    Ok(())
+}
+
+/// Packages could link one `executable_path`; they now list `executables`
+fn v1_to_v2(state: &mut Value) -> Result<()> {
+    let packages = state
+        .get_mut("packages")
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|packages| packages.values_mut());
+
+    for package in packages {
+        let Some(package) = package.as_object_mut() else {
+            continue;
+        };
+        let executables: Vec<Value> = package
+            .remove("executable_path")
+            .filter(|path| !path.is_null())
+            .into_iter()
+            .collect();
+        package.insert("executables".to_string(), Value::Array(executables));
+    }
+
+    state["schema_version"] = Value::from(2);
+    Ok(())
+}
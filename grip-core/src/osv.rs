@@ -0,0 +1,163 @@
+use crate::error::Result;
+use crate::package::OsvPackage;
+use crate::registry::RegistryManager;
+use serde::{Deserialize, Serialize};
+
+/// The public OSV API, used unless `osv_url` points at a mirror
+pub const DEFAULT_API_URL: &str = "https://api.osv.dev";
+
+/// The most queries OSV accepts in one batch
+const BATCH_SIZE: usize = 1000;
+
+/// Asks which vulnerabilities affect one version of a package
+#[derive(Debug, Clone, Serialize)]
+pub struct Query {
+    pub package: OsvPackage,
+    pub version: String,
+}
+
+impl Query {
+    /// A query for an installed version. Registry packages that name their
+    /// OSV ecosystem are looked up there, by the version without a `v`
+    /// prefix; others as a git repository, by release tag
+    pub fn new(osv: Option<&OsvPackage>, web_url: Option<String>, version: &str) -> Option<Self> {
+        match (osv, web_url) {
+            (Some(osv), _) => Some(Self {
+                package: osv.clone(),
+                version: version.strip_prefix('v').unwrap_or(version).to_string(),
+            }),
+            (None, Some(web_url)) => Some(Self {
+                package: OsvPackage {
+                    ecosystem: "GIT".to_string(),
+                    name: web_url,
+                },
+                version: version.to_string(),
+            }),
+            (None, None) => None,
+        }
+    }
+}
+
+/// A known vulnerability, as `grip audit` reports it
+#[derive(Debug, Clone, Serialize)]
+pub struct Vulnerability {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// The advisory's rating, e.g. `HIGH`, or its CVSS vector when it has no rating
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<String>,
+    /// Versions the advisory says fix it
+    pub fixed: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct BatchResponse {
+    #[serde(default)]
+    results: Vec<BatchResult>,
+}
+
+#[derive(Deserialize)]
+struct BatchResult {
+    #[serde(default)]
+    vulns: Vec<VulnerabilityId>,
+}
+
+#[derive(Deserialize)]
+struct VulnerabilityId {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct Advisory {
+    id: String,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    severity: Vec<SeverityScore>,
+    #[serde(default)]
+    database_specific: Option<serde_json::Value>,
+    #[serde(default)]
+    affected: Vec<Affected>,
+}
+
+#[derive(Deserialize)]
+struct SeverityScore {
+    score: String,
+}
+
+#[derive(Deserialize)]
+struct Affected {
+    #[serde(default)]
+    package: Option<OsvPackage>,
+    #[serde(default)]
+    ranges: Vec<Range>,
+}
+
+#[derive(Deserialize)]
+struct Range {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    events: Vec<serde_json::Value>,
+}
+
+/// The ids of the vulnerabilities affecting each query's version, in order
+pub async fn query_batch(registry: &RegistryManager, api_url: &str, queries: &[Query]) -> Result<Vec<Vec<String>>> {
+    let url = format!("{}/v1/querybatch", api_url.trim_end_matches('/'));
+    let mut ids = Vec::with_capacity(queries.len());
+    for chunk in queries.chunks(BATCH_SIZE) {
+        let body = registry.post_json(&url, &serde_json::json!({ "queries": chunk })).await?;
+        let response: BatchResponse = serde_json::from_str(&body)?;
+        let mut results = response.results.into_iter();
+        for _ in chunk {
+            let vulns = results.next().map(|r| r.vulns).unwrap_or_default();
+            ids.push(vulns.into_iter().map(|v| v.id).collect());
+        }
+    }
+    Ok(ids)
+}
+
+/// Fetches an advisory, keeping the fixed versions listed for `package`
+pub async fn vulnerability(
+    registry: &RegistryManager,
+    api_url: &str,
+    id: &str,
+    package: &OsvPackage,
+) -> Result<Vulnerability> {
+    let url = format!("{}/v1/vulns/{}", api_url.trim_end_matches('/'), id);
+    let advisory: Advisory = serde_json::from_str(&registry.fetch_text(&url).await?)?;
+
+    let severity = advisory
+        .database_specific
+        .as_ref()
+        .and_then(|d| d.get("severity"))
+        .and_then(|s| s.as_str())
+        .map(str::to_string)
+        .or_else(|| advisory.severity.first().map(|s| s.score.clone()));
+
+    // Git ranges are fixed by commits, which mean little next to a release tag
+    let mut fixed = Vec::new();
+    for affected in &advisory.affected {
+        let matches = affected.package.as_ref().is_none_or(|p| {
+            p.ecosystem.eq_ignore_ascii_case(&package.ecosystem) && p.name.eq_ignore_ascii_case(&package.name)
+        });
+        if !matches {
+            continue;
+        }
+        for range in affected.ranges.iter().filter(|r| r.kind != "GIT") {
+            for version in range.events.iter().filter_map(|e| e.get("fixed")?.as_str()) {
+                if !fixed.iter().any(|f| f == version) {
+                    fixed.push(version.to_string());
+                }
+            }
+        }
+    }
+
+    Ok(Vulnerability {
+        id: advisory.id,
+        summary: advisory.summary,
+        severity,
+        fixed,
+    })
+}
@@ -0,0 +1,227 @@
+pub mod asset_matcher;
+
+use crate::error::{bail, Result};
+use asset_matcher::Platform;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PackageInfo {
+    pub name: String,
+    pub repository: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub homepage: Option<String>,
+    /// SPDX license expression, e.g. `MIT OR Apache-2.0`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    pub executable_name: Option<String>,
+    /// Every executable to link, for packages that ship several; the main one
+    /// is `executable_name` when set, or else the first listed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub executables: Vec<String>,
+    /// Extra names the main executable is linked as, e.g. `k` for kubectl
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Expected sha256 digests, keyed by asset name
+    #[serde(default)]
+    pub checksums: HashMap<String, String>,
+    /// Keys that release assets are signed with
+    #[serde(default)]
+    pub public_keys: PublicKeys,
+    /// Opts the package into Sigstore attestation checks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestations: Option<AttestationPolicy>,
+    /// Commands to run after installing, e.g. to set up shell completions.
+    /// Users are asked before they run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_install: Vec<String>,
+    /// Notes shown after installing, such as environment variables to set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caveats: Option<String>,
+    /// Other registry packages this one needs, as `name` or `name <requirement>`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+    /// Asset patterns and executable names for particular platforms, keyed by
+    /// `os` or `os-arch` such as `windows` or `linux-x86_64`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub platforms: BTreeMap<String, PlatformSettings>,
+    /// Where the package's vulnerabilities are filed in the OSV database, for
+    /// `grip audit`; otherwise its repository is looked up as a git project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub osv: Option<OsvPackage>,
+}
+
+/// A package as the OSV database names it, e.g. ecosystem `Go` and name
+/// `github.com/junegunn/fzf`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OsvPackage {
+    pub ecosystem: String,
+    pub name: String,
+}
+
+/// What to install on one platform, overriding asset matching and
+/// `executable_name`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlatformSettings {
+    /// Asset name pattern, where `*` matches any run of characters and `?` any
+    /// one, e.g. `tool-*-linux-musl.tar.gz`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executable: Option<String>,
+}
+
+impl PackageInfo {
+    /// The `platforms` entries that apply to `platform`, `os-arch` before `os`
+    fn platform_settings(&self, platform: &Platform) -> impl Iterator<Item = &PlatformSettings> {
+        [format!("{}-{}", platform.os, platform.arch), platform.os.to_string()]
+            .into_iter()
+            .filter_map(move |key| self.platforms.get(&key))
+    }
+
+    /// The asset pattern declared for `platform`, if any
+    pub fn asset_pattern(&self, platform: &Platform) -> Option<&str> {
+        self.platform_settings(platform)
+            .find_map(|settings| settings.asset.as_deref())
+    }
+
+    /// The executable declared for `platform`, falling back to `executable_name`
+    pub fn executable(&self, platform: &Platform) -> Option<&str> {
+        self.platform_settings(platform)
+            .find_map(|settings| settings.executable.as_deref())
+            .or(self.executable_name.as_deref())
+    }
+
+    /// The names of every executable to link on `platform`, the main one
+    /// first. Executables are named after the package unless the registry
+    /// says otherwise. Names are joined onto install directories, so one that
+    /// isn't a plain file name, such as `../../.bashrc`, is refused
+    pub fn executables(&self, platform: &Platform, package_name: &str) -> Result<Vec<String>> {
+        let mut names = self.executables.clone();
+        if let Some(main) = self.executable(platform) {
+            names.retain(|name| name != main);
+            names.insert(0, main.to_string());
+        }
+        if names.is_empty() {
+            names.push(package_name.to_string());
+        }
+        if let Some(name) = names.iter().find(|name| !is_file_name(name)) {
+            bail!(RegistryError, "{} has an invalid executable name '{}'", self.name, name);
+        }
+        Ok(names)
+    }
+}
+
+/// Whether `name` names a file without leading anywhere else: no separators,
+/// no `.` or `..`, and no root or drive
+fn is_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    !name.contains(['/', '\\'])
+        && matches!((components.next(), components.next()), (Some(Component::Normal(_)), None))
+}
+
+/// Who must have signed a package's Sigstore attestations. Both default to
+/// the GitHub Actions workflows of the package's repository
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AttestationPolicy {
+    /// Regex the signing certificate's identity must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+    /// OIDC issuer of the signing certificate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+}
+
+/// Trusted public keys for verifying detached signatures of release assets
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PublicKeys {
+    /// A minisign public key, bare or as the contents of a `minisign.pub` file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minisign: Option<String>,
+    /// An ASCII-armored GPG public key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpg: Option<String>,
+}
+
+impl PublicKeys {
+    pub fn is_empty(&self) -> bool {
+        self.minisign.is_none() && self.gpg.is_none()
+    }
+}
+
+pub struct Package {
+    pub info: PackageInfo,
+    pub path: PathBuf,
+    /// The registry the definition was found in
+    pub registry: Option<String>,
+}
+
+impl Package {
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let content = std::fs::read_to_string(&path)?;
+        let info: PackageInfo = serde_json::from_str(&content)?;
+        Ok(Self { info, path, registry: None })
+    }
+
+    /// A definition for a repository installed without a registry entry
+    pub fn from_repository(spec: &str, name: &str) -> Self {
+        Self {
+            info: PackageInfo {
+                name: name.to_string(),
+                repository: spec.to_string(),
+                description: None,
+                homepage: None,
+                license: None,
+                executable_name: None,
+                executables: Vec::new(),
+                aliases: Vec::new(),
+                tags: Vec::new(),
+                checksums: HashMap::new(),
+                public_keys: PublicKeys::default(),
+                attestations: None,
+                post_install: Vec::new(),
+                caveats: None,
+                dependencies: Vec::new(),
+                platforms: BTreeMap::new(),
+                osv: None,
+            },
+            path: PathBuf::new(),
+            registry: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub prerelease: bool,
+    /// Release notes, in markdown
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub published_at: Option<String>,
+    pub assets: Vec<Asset>,
+}
+
+impl Release {
+    /// Returns the tag name with any leading `v` stripped
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+    /// In bytes, where the forge reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
@@ -0,0 +1,338 @@
+use crate::config::AssetPreferences;
+use crate::error::{GripError, Result};
+use crate::installer;
+use crate::package::Asset;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Libc {
+    Gnu,
+    Musl,
+}
+
+/// The platform release assets are matched against
+#[derive(Debug, Clone, Copy)]
+pub struct Platform {
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub libc: Option<Libc>,
+}
+
+/// Result of matching a release's assets against a platform
+#[derive(Debug)]
+pub enum AssetMatch<'a> {
+    /// A single asset scored higher than all others
+    Found(&'a Asset),
+    /// Several assets share the best score
+    Ambiguous(Vec<&'a Asset>),
+    /// No asset is compatible with the platform
+    NoMatch,
+}
+
+const OS_KEYWORDS: &[(&str, &[&str])] = &[
+    ("linux", &["linux"]),
+    ("macos", &["darwin", "macos", "macosx", "osx", "apple", "mac"]),
+    ("windows", &["windows", "win64", "win32", "win", "msvc", "mingw"]),
+    ("freebsd", &["freebsd"]),
+    ("netbsd", &["netbsd"]),
+    ("openbsd", &["openbsd"]),
+    ("android", &["android"]),
+];
+
+// Checked in order, so 64-bit x86 names win over the bare `x86` keyword
+const ARCH_KEYWORDS: &[(&str, &[&str])] = &[
+    ("x86_64", &["x86_64", "x86-64", "amd64", "x64"]),
+    ("aarch64", &["aarch64", "arm64"]),
+    ("i686", &["i686", "i586", "i386", "386", "x86", "x32"]),
+    ("arm", &["armv7", "armv7l", "armv6", "armhf", "armel", "arm"]),
+    ("powerpc64", &["ppc64le", "ppc64", "powerpc64le"]),
+    ("s390x", &["s390x"]),
+    ("riscv64", &["riscv64", "riscv64gc"]),
+    ("mips", &["mips", "mipsel", "mips64"]),
+];
+
+const UNIVERSAL_KEYWORDS: &[&str] = &["universal", "universal2"];
+
+// Checksums, signatures and other release metadata that are never installable
+const AUXILIARY_SUFFIXES: &[&str] = &[
+    ".sha256", ".sha256sum", ".sha512", ".sha512sum", ".sha1", ".md5", ".asc", ".sig",
+    ".minisig", ".pem", ".crt", ".cert", ".sbom", ".spdx", ".txt", ".json", ".jsonl", ".md",
+    ".yml", ".yaml", ".b3", ".zsync", ".delta",
+];
+
+const AUXILIARY_NAMES: &[&str] = &["checksums", "sha256sums", "sha512sums", "source code"];
+
+const ARCHIVE_SUFFIXES: &[&str] = &[
+    ".zip", ".tar.gz", ".tgz", ".tar.xz", ".txz", ".tar.bz2", ".tbz2", ".tar.zst", ".tzst", ".tar", ".7z",
+];
+
+// Distribution packages, whose files grip can unpack but which usually come
+// alongside a plain archive of the same build
+const SYSTEM_PACKAGE_SUFFIXES: &[&str] = &[".deb", ".rpm"];
+
+// Formats that need a system installer rather than being unpacked in place
+const INSTALLER_SUFFIXES: &[&str] = &[".msi", ".dmg", ".pkg", ".apk"];
+
+impl Platform {
+    /// Detects the platform grip is running on
+    pub fn current() -> Self {
+        let os = utils::get_platform();
+        let libc = if os == "linux" { Some(detect_libc()) } else { None };
+
+        Self {
+            os,
+            arch: utils::get_arch(),
+            libc,
+        }
+    }
+
+    /// The platform to match assets against under `preferences`: this one,
+    /// or another architecture they name
+    pub fn preferred(preferences: &AssetPreferences) -> Result<Self> {
+        let mut platform = Self::current();
+        if let Some(arch) = &preferences.arch {
+            let arch = arch.to_lowercase();
+            platform.arch = ARCH_KEYWORDS
+                .iter()
+                .find(|(id, keywords)| *id == arch || keywords.contains(&arch.as_str()))
+                .map(|(id, _)| *id)
+                .ok_or_else(|| GripError::ConfigError(format!("Unknown architecture '{}' in asset preferences", arch)))?;
+        }
+        Ok(platform)
+    }
+}
+
+fn detect_libc() -> Libc {
+    if cfg!(target_env = "musl") {
+        return Libc::Musl;
+    }
+
+    // Alpine and other musl distributions ship the musl dynamic loader in /lib
+    let has_musl_loader = std::fs::read_dir("/lib")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .any(|entry| entry.file_name().to_string_lossy().starts_with("ld-musl-"))
+        })
+        .unwrap_or(false);
+
+    if has_musl_loader {
+        Libc::Musl
+    } else {
+        Libc::Gnu
+    }
+}
+
+/// Returns whether `keyword` appears in `name` delimited by non-alphanumeric
+/// characters, so `win` does not match `darwin`
+fn contains_keyword(name: &str, keyword: &str) -> bool {
+    name.match_indices(keyword).any(|(start, _)| {
+        let before = name[..start].chars().next_back();
+        let after = name[start + keyword.len()..].chars().next();
+        !before.is_some_and(|c| c.is_ascii_alphanumeric())
+            && !after.is_some_and(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+fn detect<'k>(name: &str, table: &[(&'k str, &[&str])]) -> Option<&'k str> {
+    table
+        .iter()
+        .find(|(_, keywords)| keywords.iter().any(|k| contains_keyword(name, k)))
+        .map(|(id, _)| *id)
+}
+
+fn detect_os(name: &str) -> Option<&'static str> {
+    detect(name, OS_KEYWORDS).or_else(|| {
+        if name.ends_with(".exe") || name.ends_with(".msi") {
+            Some("windows")
+        } else if name.ends_with(".dmg") || name.ends_with(".pkg") {
+            Some("macos")
+        } else if name.ends_with(".deb") || name.ends_with(".rpm") || name.ends_with(".appimage") {
+            Some("linux")
+        } else {
+            None
+        }
+    })
+}
+
+/// Whether the asset is an archive grip unpacks, as opposed to a bare
+/// executable or an installer
+pub fn is_archive(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ARCHIVE_SUFFIXES.iter().any(|s| name.ends_with(s))
+}
+
+/// Returns whether the asset is a checksum, signature or other metadata file
+pub fn is_auxiliary(name: &str) -> bool {
+    let name = name.to_lowercase();
+    AUXILIARY_SUFFIXES.iter().any(|s| name.ends_with(s))
+        || AUXILIARY_NAMES.iter().any(|n| name.starts_with(n))
+}
+
+/// What an asset's name says about where it runs and what it is, as in
+/// "linux x86_64 musl" or "windows x86_64 installer", for showing in pickers
+pub fn platform_hint(asset_name: &str) -> String {
+    let name = asset_name.to_lowercase();
+    let mut hint = Vec::new();
+    hint.extend(detect_os(&name));
+    match detect(&name, ARCH_KEYWORDS) {
+        Some(arch) => hint.push(arch),
+        None if UNIVERSAL_KEYWORDS.iter().any(|k| contains_keyword(&name, k)) => hint.push("universal"),
+        None => {}
+    }
+    if contains_keyword(&name, "musl") {
+        hint.push("musl");
+    } else if contains_keyword(&name, "gnu") || contains_keyword(&name, "glibc") {
+        hint.push("gnu");
+    }
+    if SYSTEM_PACKAGE_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+        hint.push("system package");
+    } else if INSTALLER_SUFFIXES.iter().any(|s| name.ends_with(s)) || installer::is_setup_name(&name) {
+        hint.push("installer");
+    }
+    hint.join(" ")
+}
+
+/// Scores an asset name for the platform, or returns `None` if it cannot run there
+pub fn score(asset_name: &str, platform: &Platform) -> Option<i32> {
+    if is_auxiliary(asset_name) {
+        return None;
+    }
+
+    let name = asset_name.to_lowercase();
+    let mut score = 0;
+
+    match detect_os(&name) {
+        Some(os) if os == platform.os => score += 10,
+        Some(_) => return None,
+        None => {}
+    }
+
+    match detect(&name, ARCH_KEYWORDS) {
+        Some(arch) if arch == platform.arch => score += 5,
+        // Apple Silicon runs x86_64 binaries through Rosetta
+        Some("x86_64") if platform.os == "macos" && platform.arch == "aarch64" => score += 1,
+        Some(_) => return None,
+        None if UNIVERSAL_KEYWORDS.iter().any(|k| contains_keyword(&name, k)) => score += 4,
+        None => {}
+    }
+
+    if let Some(libc) = platform.libc {
+        let is_musl = contains_keyword(&name, "musl");
+        let is_gnu = contains_keyword(&name, "gnu") || contains_keyword(&name, "glibc");
+        match libc {
+            // Static musl builds run fine on glibc systems
+            Libc::Gnu if is_gnu => score += 3,
+            Libc::Gnu if is_musl => score += 2,
+            Libc::Musl if is_musl => score += 3,
+            Libc::Musl if is_gnu => return None,
+            _ => {}
+        }
+    }
+
+    if ARCHIVE_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+        score += 1;
+    } else if SYSTEM_PACKAGE_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+        score -= 1;
+    } else if INSTALLER_SUFFIXES.iter().any(|s| name.ends_with(s)) || installer::is_setup_name(&name) {
+        score -= 3;
+    }
+
+    Some(score)
+}
+
+/// Scores an asset as `score` does, then adjusts for the user's preferences.
+/// Avoided assets still score, so they are chosen when nothing else fits
+pub fn preferred_score(asset_name: &str, platform: &Platform, preferences: &AssetPreferences) -> Option<i32> {
+    let mut score = score(asset_name, platform)?;
+    let name = asset_name.to_lowercase();
+    let is_musl = contains_keyword(&name, "musl");
+
+    if preferences.rosetta == Some(false)
+        && platform.os == "macos"
+        && platform.arch == "aarch64"
+        && detect(&name, ARCH_KEYWORDS) == Some("x86_64")
+    {
+        return None;
+    }
+    match preferences.libc {
+        Some(Libc::Musl) if is_musl => score += 2,
+        Some(Libc::Gnu) if contains_keyword(&name, "gnu") || contains_keyword(&name, "glibc") => score += 2,
+        _ => {}
+    }
+    // musl builds are static, whether or not they say so
+    if preferences.prefer_static == Some(true) && (contains_keyword(&name, "static") || is_musl) {
+        score += 2;
+    }
+    if preferences.avoid.iter().any(|glob| matches_pattern(&name, &glob.to_lowercase())) {
+        score -= 100;
+    }
+    Some(score)
+}
+
+/// Whether an asset name matches a registry's asset pattern, where `*` matches
+/// any run of characters and `?` any single character
+pub fn matches_pattern(name: &str, pattern: &str) -> bool {
+    let name: Vec<char> = name.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    let (mut n, mut p) = (0, 0);
+    // Where the last `*` was and how much of the name it had taken
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                n += 1;
+                p += 1;
+            }
+            _ => match backtrack {
+                Some((star, taken)) => {
+                    backtrack = Some((star, taken + 1));
+                    p = star + 1;
+                    n = taken + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Picks the asset best suited to the platform
+pub fn best_match<'a>(assets: impl IntoIterator<Item = &'a Asset>, platform: &Platform) -> AssetMatch<'a> {
+    best_preferred_match(assets, platform, &AssetPreferences::default())
+}
+
+/// Picks the asset best suited to the platform and the user's preferences
+pub fn best_preferred_match<'a>(
+    assets: impl IntoIterator<Item = &'a Asset>,
+    platform: &Platform,
+    preferences: &AssetPreferences,
+) -> AssetMatch<'a> {
+    let scored: Vec<(&Asset, i32)> = assets
+        .into_iter()
+        .filter_map(|asset| preferred_score(&asset.name, platform, preferences).map(|s| (asset, s)))
+        .collect();
+
+    let Some(best) = scored.iter().map(|(_, s)| *s).max() else {
+        return AssetMatch::NoMatch;
+    };
+
+    let mut candidates: Vec<&Asset> = scored
+        .into_iter()
+        .filter(|(_, s)| *s == best)
+        .map(|(asset, _)| asset)
+        .collect();
+
+    if candidates.len() == 1 {
+        AssetMatch::Found(candidates.remove(0))
+    } else {
+        AssetMatch::Ambiguous(candidates)
+    }
+}
@@ -0,0 +1,613 @@
+use crate::error::Result;
+use crate::ui;
+use serde::Serialize;
+#[cfg(unix)]
+use crate::error::GripError;
+use std::path::{Path, PathBuf};
+
+/// Why `prune` took an entry off PATH
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PruneReason {
+    /// The directory no longer belongs to an installed package
+    Stale,
+    /// The directory was already on PATH from the same place
+    Duplicate,
+}
+
+/// A grip-managed PATH entry removed by `prune`
+#[derive(Debug, Serialize)]
+pub struct PrunedEntry {
+    /// The startup file, or the registry value, the entry was in
+    pub location: String,
+    pub dir: PathBuf,
+    pub reason: PruneReason,
+}
+
+/// Adds `path` to the user's PATH in the registry, which PowerShell,
+/// Nushell and cmd.exe all start from, so no profile needs changing
+#[cfg(windows)]
+pub async fn add_to_path(path: &Path) -> Result<()> {
+    ui::status!("Adding packages directory to PATH...");
+
+    let environment = windows::environment()?;
+    let current_path = windows::read_path(&environment)?;
+
+    let new_dir = path.to_string_lossy().into_owned();
+    if current_path.split(';').any(|entry| windows::same_entry(entry, &new_dir)) {
+        ui::success!("Directory already in PATH");
+        return Ok(());
+    }
+
+    let new_path = if current_path.is_empty() || current_path.ends_with(';') {
+        format!("{}{}", current_path, new_dir)
+    } else {
+        format!("{};{}", current_path, new_dir)
+    };
+    windows::write_path(&environment, &new_path)?;
+
+    ui::success!("Added to PATH: {}", new_dir);
+    ui::warning!("You may need to restart your terminal for changes to take effect");
+    Ok(())
+}
+
+/// Takes `path` off the user's PATH again, returning whether it was there
+#[cfg(windows)]
+pub fn remove_from_path(path: &Path) -> Result<bool> {
+    let environment = windows::environment()?;
+    let current_path = windows::read_path(&environment)?;
+
+    let dir = path.to_string_lossy();
+    let entries: Vec<&str> = current_path.split(';').collect();
+    let kept: Vec<&str> = entries
+        .iter()
+        .copied()
+        .filter(|entry| !windows::same_entry(entry, &dir))
+        .collect();
+    if kept.len() == entries.len() {
+        return Ok(false);
+    }
+
+    windows::write_path(&environment, &kept.join(";"))?;
+    Ok(true)
+}
+
+/// Takes entries under `root` off the user's PATH unless they are in `keep`,
+/// and drops repeats of the ones that are
+#[cfg(windows)]
+pub fn prune(root: &Path, keep: &[PathBuf]) -> Result<Vec<PrunedEntry>> {
+    let environment = windows::environment()?;
+    let current_path = windows::read_path(&environment)?;
+
+    let root = windows::normalize(&root.to_string_lossy());
+    let keep: Vec<String> = keep.iter().map(|dir| windows::normalize(&dir.to_string_lossy())).collect();
+
+    let mut pruned = Vec::new();
+    let mut seen = Vec::new();
+    let mut kept = Vec::new();
+    for entry in current_path.split(';') {
+        let normalized = windows::normalize(entry);
+        if entry.trim().is_empty() || !normalized.starts_with(&root) {
+            kept.push(entry);
+            continue;
+        }
+        let reason = if !keep.contains(&normalized) {
+            PruneReason::Stale
+        } else if seen.contains(&normalized) {
+            PruneReason::Duplicate
+        } else {
+            seen.push(normalized);
+            kept.push(entry);
+            continue;
+        };
+        pruned.push(PrunedEntry {
+            location: r"HKEY_CURRENT_USER\Environment\Path".to_string(),
+            dir: PathBuf::from(entry),
+            reason,
+        });
+    }
+
+    if !pruned.is_empty() {
+        windows::write_path(&environment, &kept.join(";"))?;
+    }
+    Ok(pruned)
+}
+
+/// The per-user `Environment` registry key, which cmd.exe, PowerShell and GUI
+/// apps all read PATH from
+#[cfg(windows)]
+mod windows {
+    use crate::error::{GripError, Result};
+    use winreg::enums::*;
+    use winreg::types::FromRegValue;
+    use winreg::{RegKey, RegValue};
+
+    pub fn environment() -> Result<RegKey> {
+        RegKey::predef(HKEY_CURRENT_USER)
+            .create_subkey("Environment")
+            .map(|(key, _)| key)
+            .map_err(|e| GripError::Other(format!("Failed to open Environment registry key: {}", e)))
+    }
+
+    /// The user's PATH, unexpanded. A user without one has an empty PATH
+    pub fn read_path(environment: &RegKey) -> Result<String> {
+        match environment.get_raw_value("Path") {
+            Ok(value) => String::from_reg_value(&value)
+                .map_err(|e| GripError::Other(format!("Failed to read PATH: {}", e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(GripError::Other(format!("Failed to get current PATH: {}", e))),
+        }
+    }
+
+    /// Writes PATH back as `REG_EXPAND_SZ`, so entries like
+    /// `%USERPROFILE%\bin` keep expanding, and tells running programs
+    pub fn write_path(environment: &RegKey, path: &str) -> Result<()> {
+        let bytes = path
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let value = RegValue {
+            bytes,
+            vtype: REG_EXPAND_SZ,
+        };
+        environment
+            .set_raw_value("Path", &value)
+            .map_err(|e| GripError::Other(format!("Failed to update PATH: {}", e)))?;
+        broadcast_change();
+        Ok(())
+    }
+
+    /// Whether a PATH entry names `dir`. Windows paths are case-insensitive,
+    /// and entries may carry a trailing separator or `%VARIABLES%`
+    pub fn same_entry(entry: &str, dir: &str) -> bool {
+        !entry.trim().is_empty() && normalize(entry) == normalize(dir)
+    }
+
+    /// A PATH entry in a form that compares equal to other spellings of it
+    pub fn normalize(path: &str) -> String {
+        expand_variables(path.trim())
+            .trim_end_matches(['\\', '/'])
+            .replace('/', "\\")
+            .to_lowercase()
+    }
+
+    /// Expands `%NAME%` references the way the shell does, leaving unknown
+    /// ones as they are
+    fn expand_variables(path: &str) -> String {
+        let mut expanded = String::new();
+        let mut rest = path;
+        while let Some(start) = rest.find('%') {
+            let Some(len) = rest[start + 1..].find('%') else {
+                break;
+            };
+            let name = &rest[start + 1..start + 1 + len];
+            expanded.push_str(&rest[..start]);
+            match std::env::var(name) {
+                Ok(value) if !name.is_empty() => expanded.push_str(&value),
+                _ => expanded.push_str(&rest[start..start + len + 2]),
+            }
+            rest = &rest[start + len + 2..];
+        }
+        expanded.push_str(rest);
+        expanded
+    }
+
+    /// Tells Explorer and other running programs the environment changed, so
+    /// new terminals started from them see the new PATH
+    fn broadcast_change() {
+        use winapi::shared::minwindef::LPARAM;
+        use winapi::um::winuser::{SendMessageTimeoutW, HWND_BROADCAST, SMTO_ABORTIFHUNG, WM_SETTINGCHANGE};
+
+        let wide_env: Vec<u16> = "Environment\0".encode_utf16().collect();
+        unsafe {
+            SendMessageTimeoutW(
+                HWND_BROADCAST,
+                WM_SETTINGCHANGE,
+                0,
+                wide_env.as_ptr() as LPARAM,
+                SMTO_ABORTIFHUNG,
+                5000,
+                std::ptr::null_mut(),
+            );
+        }
+    }
+}
+
+/// Shell startup files grip may have added PATH lines to, each with the shell
+/// whose syntax it takes. `~/.profile` is for shells grip doesn't know, and
+/// takes bash's
+#[cfg(unix)]
+fn shell_rc_files() -> Result<Vec<(PathBuf, Shell)>> {
+    let home = PathBuf::from(home_dir()?);
+    let mut files = vec![
+        (home.join(".bashrc"), Shell::Bash),
+        (home.join(".zshrc"), Shell::Zsh),
+        (home.join(".profile"), Shell::Bash),
+    ];
+    for shell in [Shell::Fish, Shell::PowerShell, Shell::Nu] {
+        if let Some(rc_file) = shell.rc_file() {
+            files.push((rc_file, shell));
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(unix)]
+fn home_dir() -> Result<String> {
+    std::env::var("HOME").map_err(|_| GripError::ConfigError("Failed to get HOME directory".to_string()))
+}
+
+/// Adds a line putting `path` on PATH to the user's shell startup file, in
+/// that shell's syntax, unless it is already there or the file sets PATH up
+/// through `grip shellenv`. Shells grip doesn't know get `~/.profile`
+#[cfg(unix)]
+pub async fn add_to_path(path: &Path) -> Result<()> {
+    use std::io::Write;
+
+    let (shell_rc, shell) = match Shell::detect().and_then(|shell| Some((shell.rc_file()?, shell))) {
+        Some(found) => found,
+        // Without SHELL, bash is the likeliest
+        None if std::env::var_os("SHELL").is_none() => (PathBuf::from(home_dir()?).join(".bashrc"), Shell::Bash),
+        None => (PathBuf::from(home_dir()?).join(".profile"), Shell::Bash),
+    };
+    let path_line = shell.path_line(path);
+
+    let rc_content = std::fs::read_to_string(&shell_rc)
+        .unwrap_or_else(|_| String::new());
+
+    if Shell::is_set_up(&rc_content) {
+        ui::success!("Directory is on PATH through `grip shellenv` in {}", shell_rc.display());
+    } else if !rc_content.lines().any(|line| line.trim() == path_line) {
+        if let Some(parent) = shell_rc.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&shell_rc)?
+            .write_all(format!("\n{}", path_line).as_bytes())?;
+
+        ui::success!("Added to PATH in {}", shell_rc.display());
+        ui::warning!("Open a new terminal, or load {} again, for the change to take effect", shell_rc.display());
+    } else {
+        ui::success!("Directory already in PATH");
+    }
+
+    Ok(())
+}
+
+/// Takes `path` off the user's PATH again, returning whether it was there.
+/// Every shell's startup file is checked, since `SHELL` may have changed
+/// since it was added
+#[cfg(unix)]
+pub fn remove_from_path(path: &Path) -> Result<bool> {
+    let mut removed = false;
+    for (shell_rc, shell) in shell_rc_files()? {
+        let Ok(rc_content) = std::fs::read_to_string(&shell_rc) else {
+            continue;
+        };
+        let path_line = format!("\n{}", shell.path_line(path));
+        if rc_content.contains(&path_line) {
+            std::fs::write(&shell_rc, rc_content.replace(&path_line, ""))?;
+            removed = true;
+        }
+    }
+    Ok(removed)
+}
+
+/// Takes the PATH lines grip added to shell startup files for directories
+/// under `root` out unless they are in `keep`, and drops repeats of the ones
+/// that are. Each file keeps its own line, since different shells read
+/// different files
+#[cfg(unix)]
+pub fn prune(root: &Path, keep: &[PathBuf]) -> Result<Vec<PrunedEntry>> {
+    let mut pruned = Vec::new();
+    for (shell_rc, shell) in shell_rc_files()? {
+        let Ok(rc_content) = std::fs::read_to_string(&shell_rc) else {
+            continue;
+        };
+
+        let mut seen = Vec::new();
+        let mut kept: Vec<&str> = Vec::new();
+        let mut changed = false;
+        for line in rc_content.split_inclusive('\n') {
+            let dir = shell.path_line_dir(line).filter(|dir| dir.starts_with(root));
+            let Some(dir) = dir else {
+                kept.push(line);
+                continue;
+            };
+            let reason = if !keep.contains(&dir) {
+                PruneReason::Stale
+            } else if seen.contains(&dir) {
+                PruneReason::Duplicate
+            } else {
+                seen.push(dir);
+                kept.push(line);
+                continue;
+            };
+            // grip writes a blank line before each of its lines
+            if kept.last().is_some_and(|previous| previous.trim().is_empty()) {
+                kept.pop();
+            }
+            changed = true;
+            pruned.push(PrunedEntry {
+                location: shell_rc.display().to_string(),
+                dir,
+                reason,
+            });
+        }
+
+        if changed {
+            std::fs::write(&shell_rc, kept.concat())?;
+        }
+    }
+    Ok(pruned)
+}
+
+/// Written above the setup line grip adds to a startup file
+pub const SETUP_COMMENT: &str = "# Put packages installed by grip on PATH (grip shellenv)";
+
+/// Appends `line`, below a [`SETUP_COMMENT`], to a startup file
+pub fn add_setup(rc_file: &Path, line: &str) -> Result<()> {
+    use std::io::Write;
+
+    let rc_content = std::fs::read_to_string(rc_file).unwrap_or_default();
+    if let Some(parent) = rc_file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let separator = match rc_content.as_str() {
+        "" => "",
+        content if content.ends_with('\n') => "\n",
+        _ => "\n\n",
+    };
+    std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(rc_file)?
+        .write_all(format!("{}{}\n{}\n", separator, SETUP_COMMENT, line).as_bytes())?;
+    Ok(())
+}
+
+/// Takes the setup lines grip added to startup files, each below a
+/// [`SETUP_COMMENT`], out again. Returns the files changed
+pub fn remove_setup() -> Result<Vec<PathBuf>> {
+    let mut changed = Vec::new();
+    for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell, Shell::Nu] {
+        let Some(rc_file) = shell.rc_file() else {
+            continue;
+        };
+        let Ok(rc_content) = std::fs::read_to_string(&rc_file) else {
+            continue;
+        };
+        if changed.contains(&rc_file) || !rc_content.contains(SETUP_COMMENT) {
+            continue;
+        }
+
+        let mut lines = rc_content.split_inclusive('\n').peekable();
+        let mut kept: Vec<&str> = Vec::new();
+        while let Some(line) = lines.next() {
+            if line.trim() != SETUP_COMMENT {
+                kept.push(line);
+                continue;
+            }
+            // The blank line written before the comment goes with it
+            if kept.last().is_some_and(|line| line.trim().is_empty()) {
+                kept.pop();
+            }
+            // Nushell's setup is a `$env.PATH` line per directory
+            while lines
+                .next_if(|line| Shell::is_set_up(line) || line.trim_start().starts_with("$env.PATH = ($env.PATH"))
+                .is_some()
+            {}
+        }
+        std::fs::write(&rc_file, kept.concat())?;
+        changed.push(rc_file);
+    }
+    Ok(changed)
+}
+
+/// A shell `grip shellenv` can print PATH setup for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+    Nu,
+}
+
+impl Shell {
+    /// The user's shell: Nushell and PowerShell from the variables they set,
+    /// otherwise `SHELL`. Windows defaults to PowerShell
+    pub fn detect() -> Option<Self> {
+        if std::env::var_os("NU_VERSION").is_some() {
+            return Some(Shell::Nu);
+        }
+        let from_shell = std::env::var("SHELL").ok().and_then(|shell| {
+            let name = Path::new(&shell).file_name()?.to_string_lossy().into_owned();
+            match name.trim_end_matches(".exe") {
+                "bash" => Some(Shell::Bash),
+                "zsh" => Some(Shell::Zsh),
+                "fish" => Some(Shell::Fish),
+                "pwsh" | "powershell" => Some(Shell::PowerShell),
+                "nu" => Some(Shell::Nu),
+                _ => None,
+            }
+        });
+        from_shell.or_else(|| {
+            (cfg!(windows) || std::env::var_os("PSModulePath").is_some()).then_some(Shell::PowerShell)
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::PowerShell => "powershell",
+            Shell::Nu => "nu",
+        }
+    }
+
+    /// Statements that put `dirs` at the front of PATH, skipping any already
+    /// on it so evaluating them in nested shells doesn't pile up entries
+    pub fn env_script(&self, dirs: &[PathBuf]) -> String {
+        let mut script = String::new();
+        for dir in dirs.iter().rev() {
+            let dir = dir.to_string_lossy();
+            let line = match self {
+                Shell::Bash | Shell::Zsh => {
+                    let quoted = format!("'{}'", dir.replace('\'', r"'\''"));
+                    format!(
+                        "case \":$PATH:\" in *:{0}:*) ;; *) export PATH={0}\"${{PATH:+:$PATH}}\" ;; esac",
+                        quoted
+                    )
+                }
+                Shell::Fish => format!("fish_add_path --global --path '{}'", fish_escape(&dir)),
+                Shell::PowerShell => {
+                    let quoted = format!("'{}'", dir.replace('\'', "''"));
+                    format!(
+                        "if (($env:PATH -split [IO.Path]::PathSeparator) -notcontains {0}) {{ $env:PATH = {0} + [IO.Path]::PathSeparator + $env:PATH }}",
+                        quoted
+                    )
+                }
+                Shell::Nu => format!(
+                    "$env.PATH = ($env.PATH | split row (char esep) | prepend \"{}\" | uniq)",
+                    nu_escape(&dir)
+                ),
+            };
+            script.push_str(&line);
+            script.push('\n');
+        }
+        script
+    }
+
+    /// Statements that load the completions in `completions` and, when
+    /// MANPATH is set, add `man` to it. Without MANPATH, man finds the pages
+    /// from the bin directory on PATH
+    pub fn share_script(&self, completions: &Path, man: &Path) -> String {
+        let sh_quote = |path: &Path| format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"));
+        match self {
+            Shell::Bash => format!(
+                "for f in {0}/*; do if [ -r \"$f\" ]; then . \"$f\"; fi; done\n\
+                 if [ -n \"${{MANPATH:-}}\" ]; then case \":$MANPATH:\" in *:{1}:*) ;; *) export MANPATH={1}:\"$MANPATH\" ;; esac; fi\n",
+                sh_quote(completions),
+                sh_quote(man)
+            ),
+            // fpath only counts before compinit; after it, each function is registered directly
+            Shell::Zsh => format!(
+                "fpath=({0} $fpath)\n\
+                 if (( $+functions[compdef] )); then for f in {0}/_*(N); do autoload -Uz ${{f:t}}; compdef ${{f:t}} ${{${{f:t}}#_}}; done; fi\n\
+                 if [ -n \"${{MANPATH:-}}\" ]; then case \":$MANPATH:\" in *:{1}:*) ;; *) export MANPATH={1}:\"$MANPATH\" ;; esac; fi\n",
+                sh_quote(completions),
+                sh_quote(man)
+            ),
+            Shell::Fish => format!(
+                "contains '{0}' $fish_complete_path; or set --global --prepend fish_complete_path '{0}'\n\
+                 set -q MANPATH; and not contains '{1}' $MANPATH; and set --global --export --prepend MANPATH '{1}'\n",
+                fish_escape(&completions.to_string_lossy()),
+                fish_escape(&man.to_string_lossy())
+            ),
+            // Neither has a place to load completion scripts from
+            Shell::PowerShell | Shell::Nu => String::new(),
+        }
+    }
+
+    /// The line for the shell's startup file that sets PATH up through
+    /// `grip shellenv`. Nushell can't evaluate generated code, so its line
+    /// holds `dirs` directly
+    pub fn setup_line(&self, dirs: &[PathBuf]) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("eval \"$(grip shellenv --shell {})\"", self.name()),
+            Shell::Fish => "grip shellenv --shell fish | source".to_string(),
+            Shell::PowerShell => "grip shellenv --shell powershell | Out-String | Invoke-Expression".to_string(),
+            Shell::Nu => self.env_script(dirs).trim_end().to_string(),
+        }
+    }
+
+    /// The startup file interactive shells read
+    pub fn rc_file(&self) -> Option<PathBuf> {
+        let home = dirs::home_dir()?;
+        let config = if cfg!(windows) {
+            dirs::config_dir()
+        } else {
+            Some(home.join(".config"))
+        };
+        match self {
+            Shell::Bash => Some(home.join(".bashrc")),
+            Shell::Zsh => Some(home.join(".zshrc")),
+            Shell::Fish => Some(config?.join("fish").join("config.fish")),
+            Shell::PowerShell if cfg!(windows) => Some(
+                dirs::document_dir()?
+                    .join("PowerShell")
+                    .join("Microsoft.PowerShell_profile.ps1"),
+            ),
+            Shell::PowerShell => Some(config?.join("powershell").join("Microsoft.PowerShell_profile.ps1")),
+            Shell::Nu => Some(config?.join("nushell").join("env.nu")),
+        }
+    }
+
+    /// The line grip adds to the shell's startup file to put `dir` on PATH,
+    /// where `grip shellenv` isn't set up
+    #[cfg(unix)]
+    fn path_line(&self, dir: &Path) -> String {
+        let dir = dir.to_string_lossy();
+        match self {
+            Shell::Bash | Shell::Zsh => format!("export PATH=\"{}:$PATH\"", dir),
+            Shell::Fish => format!("fish_add_path --global --path '{}'", fish_escape(&dir)),
+            Shell::PowerShell => format!(
+                "$env:PATH = '{}' + [IO.Path]::PathSeparator + $env:PATH",
+                dir.replace('\'', "''")
+            ),
+            Shell::Nu => format!(
+                "$env.PATH = ($env.PATH | split row (char esep) | prepend \"{}\")",
+                nu_escape(&dir)
+            ),
+        }
+    }
+
+    /// The directory a line [`Shell::path_line`] wrote puts on PATH
+    #[cfg(unix)]
+    fn path_line_dir(&self, line: &str) -> Option<PathBuf> {
+        let line = line.trim();
+        let dir = match self {
+            Shell::Bash | Shell::Zsh => line.strip_prefix("export PATH=\"")?.strip_suffix(":$PATH\"")?.to_string(),
+            Shell::Fish => unescape(line.strip_prefix("fish_add_path --global --path '")?.strip_suffix('\'')?),
+            Shell::PowerShell => line
+                .strip_prefix("$env:PATH = '")?
+                .strip_suffix("' + [IO.Path]::PathSeparator + $env:PATH")?
+                .replace("''", "'"),
+            Shell::Nu => unescape(
+                line.strip_prefix("$env.PATH = ($env.PATH | split row (char esep) | prepend \"")?
+                    .strip_suffix("\")")?,
+            ),
+        };
+        Some(PathBuf::from(dir))
+    }
+
+    /// Whether a startup file already sets PATH up through `grip shellenv`;
+    /// [`SETUP_COMMENT`] marks lines grip added itself
+    pub fn is_set_up(rc_content: &str) -> bool {
+        rc_content.contains("grip shellenv")
+    }
+}
+
+pub(crate) fn fish_escape(path: &str) -> String {
+    path.replace('\\', r"\\").replace('\'', r"\'")
+}
+
+fn nu_escape(path: &str) -> String {
+    path.replace('\\', r"\\").replace('"', "\\\"")
+}
+
+/// Undoes backslash escaping, as fish and Nushell strings use
+#[cfg(unix)]
+fn unescape(escaped: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        unescaped.extend(if c == '\\' { chars.next() } else { Some(c) });
+    }
+    unescaped
+}
@@ -0,0 +1,115 @@
+use crate::error::Result;
+use crate::search;
+use dialoguer::console::{style, Key, Term};
+
+/// The most choices shown at once; the rest are reached by scrolling or by
+/// narrowing the search
+const MAX_VISIBLE: usize = 15;
+
+/// An entry in a picker: what is searched and chosen, and dimmed details
+/// shown beside it
+pub struct Choice {
+    pub label: String,
+    pub detail: String,
+}
+
+impl Choice {
+    pub fn new(label: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Asks for one of `choices`, narrowing them as the user types, and returns
+/// its index. Typed characters match in order but not necessarily together,
+/// so "1.8" finds v1.28.0 and "lnxmusl" finds an x86_64-linux-musl asset
+pub fn pick(prompt: &str, choices: &[Choice], default: usize) -> Result<usize> {
+    let term = Term::stderr();
+    let rows = (term.size().0 as usize).saturating_sub(2).clamp(3, MAX_VISIBLE);
+    let width = term.size().1 as usize;
+
+    let mut query = String::new();
+    let mut selected = default;
+    let mut first_row = default.saturating_sub(rows - 1);
+    let mut drawn = 0;
+
+    term.hide_cursor()?;
+    let result = loop {
+        let matches = filter(choices, &query);
+        selected = selected.min(matches.len().saturating_sub(1));
+        first_row = first_row.min(selected).max((selected + 1).saturating_sub(rows));
+
+        term.clear_last_lines(drawn)?;
+        term.write_line(&format!("{} {}: {}", style("?").yellow(), prompt, query))?;
+        drawn = 1;
+        if matches.is_empty() {
+            term.write_line(&style("  No matches").dim().to_string())?;
+            drawn += 1;
+        }
+        for (row, (index, positions)) in matches.iter().enumerate().skip(first_row).take(rows) {
+            let choice = &choices[*index];
+            let marker = if row == selected { style(">").cyan().to_string() } else { " ".to_string() };
+            let mut line = format!("{} {}", marker, highlight(&choice.label, positions));
+            if !choice.detail.is_empty() {
+                line.push_str(&format!("  {}", style(&choice.detail).dim()));
+            }
+            term.write_line(&dialoguer::console::truncate_str(&line, width, "…"))?;
+            drawn += 1;
+        }
+        term.flush()?;
+
+        match term.read_key()? {
+            Key::ArrowUp if selected > 0 => selected -= 1,
+            Key::ArrowDown if selected + 1 < matches.len() => selected += 1,
+            Key::PageUp => selected = selected.saturating_sub(rows),
+            Key::PageDown => selected = (selected + rows).min(matches.len().saturating_sub(1)),
+            Key::Home => selected = 0,
+            Key::End => selected = matches.len().saturating_sub(1),
+            Key::Enter if !matches.is_empty() => break matches[selected].0,
+            Key::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            Key::Char(c) if !c.is_control() => {
+                query.push(c);
+                selected = 0;
+            }
+            _ => {}
+        }
+    };
+
+    term.clear_last_lines(drawn)?;
+    term.show_cursor()?;
+    term.write_line(&format!("{}: {}", prompt, choices[result].label))?;
+    Ok(result)
+}
+
+/// The choices matching `query`, best first, each with the positions of the
+/// label's matched characters. Choices matching equally keep their order
+fn filter(choices: &[Choice], query: &str) -> Vec<(usize, Vec<usize>)> {
+    let mut matches: Vec<(usize, u32, Vec<usize>)> = choices
+        .iter()
+        .enumerate()
+        .filter_map(|(index, choice)| {
+            let text = format!("{} {}", choice.label, choice.detail);
+            let (score, positions) = search::fuzzy_match(query, &text)?;
+            let label_len = choice.label.chars().count();
+            Some((index, score, positions.into_iter().filter(|&p| p < label_len).collect()))
+        })
+        .collect();
+    matches.sort_by_key(|m| std::cmp::Reverse(m.1));
+    matches.into_iter().map(|(index, _, positions)| (index, positions)).collect()
+}
+
+fn highlight(label: &str, positions: &[usize]) -> String {
+    label
+        .chars()
+        .enumerate()
+        .map(|(i, c)| match positions.contains(&i) {
+            true => style(c).cyan().bold().to_string(),
+            false => c.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,24 @@
+use std::path::{Path, PathBuf};
+
+/// The executable that provides `grip <name>`: `grip-<name>` in grip's own
+/// bin directory, so plugins installed with grip work before PATH is set up,
+/// or else the first on PATH
+pub fn find(name: &str, bin_dir: &Path) -> Option<PathBuf> {
+    let file_name = format!("grip-{}{}", name, std::env::consts::EXE_SUFFIX);
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    std::iter::once(bin_dir.to_path_buf())
+        .chain(std::env::split_paths(&path))
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
@@ -0,0 +1,378 @@
+use crate::attestation::Attestation;
+use crate::config::AssetPreferences;
+use crate::error::{bail, GripError, Result};
+use crate::package::asset_matcher;
+use crate::registry::provider;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_FILE: &str = "grip.json";
+pub const LOCK_FILE: &str = "grip.lock";
+
+/// A project's `grip.json`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    /// Package names mapped to the version to install
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, ProjectDependency>,
+    /// Globs of the directories holding a workspace's member projects,
+    /// relative to this file, e.g. `services/*`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub workspaces: Vec<String>,
+    /// Asset preferences for particular packages, overriding the config's
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub assets: BTreeMap<String, AssetPreferences>,
+    /// Fields grip doesn't know about, kept so saving doesn't drop them
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Manifest {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: "0.1.0".to_string(),
+            dependencies: BTreeMap::new(),
+            workspaces: Vec::new(),
+            assets: BTreeMap::new(),
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| GripError::ConfigError(format!("Failed to parse {}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Finds the nearest `grip.json` in the current directory or its ancestors
+    pub fn find() -> Result<Option<PathBuf>> {
+        let cwd = std::env::current_dir()?;
+        Ok(cwd
+            .ancestors()
+            .map(|dir| dir.join(MANIFEST_FILE))
+            .find(|path| path.is_file()))
+    }
+
+    /// The member projects of a workspace rooted at `root`: the directories
+    /// matching `workspaces` that have a grip.json, sorted by path
+    pub fn members(&self, root: &Path) -> Result<Vec<Member>> {
+        let mut dirs = Vec::new();
+        for pattern in &self.workspaces {
+            let matched = expand_glob(root, pattern)?;
+            if matched.is_empty() {
+                tracing::debug!(pattern, "workspace glob matched nothing");
+            }
+            dirs.extend(matched);
+        }
+        dirs.sort();
+        dirs.dedup();
+
+        let mut members = Vec::new();
+        for dir in dirs {
+            let manifest_path = dir.join(MANIFEST_FILE);
+            if dir == root || !manifest_path.is_file() {
+                continue;
+            }
+            members.push(Member {
+                manifest: Manifest::load(&manifest_path)?,
+                dir,
+            });
+        }
+        Ok(members)
+    }
+}
+
+/// A dependency in grip.json: a version requirement, written as a string, or
+/// an object that also pins what may be installed for it, e.g.
+/// `{"version": "14.1.0", "sha256": "...", "source_repository": "owner/repo"}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectDependency {
+    pub version: String,
+    /// The digest the installed asset must have
+    pub sha256: Option<String>,
+    /// The repository the registry must resolve the package to
+    pub source_repository: Option<String>,
+}
+
+impl ProjectDependency {
+    pub fn new(version: &str) -> Self {
+        Self {
+            version: version.to_string(),
+            sha256: None,
+            source_repository: None,
+        }
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.sha256.is_some() || self.source_repository.is_some()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PinnedDependency {
+    #[serde(default = "any_version")]
+    version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_repository: Option<String>,
+}
+
+fn any_version() -> String {
+    "*".to_string()
+}
+
+impl Serialize for ProjectDependency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        if !self.is_pinned() {
+            return serializer.serialize_str(&self.version);
+        }
+        PinnedDependency {
+            version: self.version.clone(),
+            sha256: self.sha256.clone(),
+            source_repository: self.source_repository.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ProjectDependency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = ProjectDependency;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a version, or an object with version, sha256 and source_repository")
+            }
+
+            fn visit_str<E: de::Error>(self, version: &str) -> std::result::Result<Self::Value, E> {
+                Ok(ProjectDependency::new(version))
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> std::result::Result<Self::Value, A::Error> {
+                let pinned = PinnedDependency::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                // A pin that can never match would fail every install, so
+                // it's refused when grip.json is read
+                let sha256 = match pinned.sha256 {
+                    Some(digest) if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) => {
+                        return Err(de::Error::custom(format!(
+                            "sha256 '{}' isn't 64 hexadecimal digits",
+                            digest
+                        )));
+                    }
+                    digest => digest.map(|d| d.to_lowercase()),
+                };
+                Ok(ProjectDependency {
+                    version: pinned.version,
+                    sha256,
+                    source_repository: pinned.source_repository,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// A project in a workspace
+#[derive(Debug)]
+pub struct Member {
+    pub dir: PathBuf,
+    pub manifest: Manifest,
+}
+
+/// Expands a workspace glob into the directories it matches. `*` and `?`
+/// match within one path segment, and hidden directories are skipped
+fn expand_glob(root: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let mut dirs = vec![root.to_path_buf()];
+    for segment in pattern.split(['/', '\\']).filter(|s| !s.is_empty() && *s != ".") {
+        let mut matched = Vec::new();
+        for dir in &dirs {
+            if !segment.contains(['*', '?']) {
+                let path = dir.join(segment);
+                if path.is_dir() {
+                    matched.push(path);
+                }
+                continue;
+            }
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                if !name.starts_with('.') && path.is_dir() && asset_matcher::matches_pattern(&name, segment) {
+                    matched.push(path);
+                }
+            }
+        }
+        dirs = matched;
+    }
+    Ok(dirs)
+}
+
+/// Every dependency of a workspace's root and members. A package asked for
+/// with different requirements is an error, except that `*` and `latest` give
+/// way to a specific one. Pins add up, but two different pins of the same
+/// field are an error too
+pub fn workspace_dependencies(
+    root: &Manifest,
+    members: &[Member],
+    root_dir: &Path,
+) -> Result<BTreeMap<String, ProjectDependency>> {
+    // Each field with the project that set it, for errors
+    #[derive(Default)]
+    struct Merged {
+        version: (String, String),
+        sha256: Option<(String, String)>,
+        source_repository: Option<(String, String)>,
+    }
+
+    fn merge(
+        name: &str,
+        field: &str,
+        merged: &mut Option<(String, String)>,
+        value: Option<&String>,
+        project: &str,
+        same: impl Fn(&str, &str) -> bool,
+    ) -> Result<()> {
+        match (merged.as_ref(), value) {
+            (Some((existing, by)), Some(value)) if !same(existing, value) => bail!(
+                ConfigError,
+                "{} is pinned to {} {} by {} and {} by {}; workspace members must agree",
+                name,
+                field,
+                existing,
+                by,
+                value,
+                project
+            ),
+            (None, Some(value)) => *merged = Some((value.clone(), project.to_string())),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    let mut dependencies: BTreeMap<String, Merged> = BTreeMap::new();
+    let projects = std::iter::once((Path::new("."), root))
+        .chain(members.iter().map(|m| (m.dir.strip_prefix(root_dir).unwrap_or(&m.dir), &m.manifest)));
+    for (dir, manifest) in projects {
+        for (name, dependency) in &manifest.dependencies {
+            let project = dir.display().to_string();
+            let requirement = &dependency.version;
+            let merged = dependencies.entry(name.clone()).or_default();
+            match &merged.version {
+                (existing, _) if requested_version(existing).is_none() => {
+                    merged.version = (requirement.clone(), project.clone());
+                }
+                (existing, _) if existing == requirement || requested_version(requirement).is_none() => {}
+                (existing, by) => bail!(
+                    ConfigError,
+                    "{} is required as {} by {} and as {} by {}; workspace members must agree",
+                    name,
+                    existing,
+                    by,
+                    requirement,
+                    project
+                ),
+            }
+            merge(name, "sha256", &mut merged.sha256, dependency.sha256.as_ref(), &project, |a, b| a == b)?;
+            merge(
+                name,
+                "source_repository",
+                &mut merged.source_repository,
+                dependency.source_repository.as_ref(),
+                &project,
+                provider::same_repository,
+            )?;
+        }
+    }
+    Ok(dependencies
+        .into_iter()
+        .map(|(name, merged)| {
+            let dependency = ProjectDependency {
+                version: merged.version.0,
+                sha256: merged.sha256.map(|(sha256, _)| sha256),
+                source_repository: merged.source_repository.map(|(repository, _)| repository),
+            };
+            (name, dependency)
+        })
+        .collect())
+}
+
+/// A project's `grip.lock`, recording exactly what each dependency resolved to
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub packages: BTreeMap<String, LockedPackage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedPackage {
+    /// The requirement from grip.json this entry was resolved from
+    pub requirement: String,
+    pub repository: String,
+    pub tag: String,
+    pub asset: String,
+    pub url: String,
+    pub sha256: String,
+    /// How the asset's provenance was verified when it was locked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<Attestation>,
+}
+
+impl Lockfile {
+    /// Loads a lockfile, returning `None` if it doesn't exist
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| GripError::ConfigError(format!("Failed to parse {}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Returns whether the lockfile covers exactly these dependencies with the
+    /// same requirements
+    pub fn matches(&self, dependencies: &BTreeMap<String, ProjectDependency>) -> bool {
+        self.packages.len() == dependencies.len()
+            && dependencies.iter().all(|(name, dependency)| {
+                self.packages
+                    .get(name)
+                    .is_some_and(|locked| locked.requirement == dependency.version)
+            })
+    }
+}
+
+/// Returns the exact version a dependency requirement asks for, or `None` when
+/// any version will do
+pub fn requested_version(requirement: &str) -> Option<String> {
+    match requirement.trim() {
+        "" | "*" | "latest" => None,
+        v => Some(v.to_string()),
+    }
+}
+
+/// Splits a `name@version` spec into its parts
+pub fn parse_spec(spec: &str) -> (String, Option<String>) {
+    match spec.split_once('@') {
+        Some((name, version)) if !version.is_empty() => (name.to_string(), Some(version.to_string())),
+        Some((name, _)) => (name.to_string(), None),
+        None => (spec.to_string(), None),
+    }
+}
@@ -0,0 +1,1134 @@
+pub mod index;
+pub mod provider;
+pub mod publish;
+pub mod validate;
+
+use crate::error::{Result, GripError};
+use crate::package::{Package, Release};
+use crate::checksum;
+use crate::template;
+use crate::timings::{Phase, Span, Timings};
+use crate::config::{NetworkSettings, Registry, RegistryAuth, RegistrySource};
+use crate::ui;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tokio::io::AsyncWriteExt;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often `--progress json` reports on a running download
+const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct RegistryManager {
+    pub data_dir: PathBuf,
+    client: reqwest::Client,
+    github_token: Option<String>,
+    /// Hide download progress bars
+    pub quiet: bool,
+    /// How long a registry clone is used before it is pulled again
+    pub cache_ttl: Duration,
+    /// Serve registries and API responses from disk only, never the network
+    pub offline: bool,
+    /// Revalidate registry clones and cached release lists however recent
+    pub refresh: bool,
+    /// Refuse registries without a public key to check their index against
+    pub require_signed: bool,
+    network: NetworkSettings,
+    /// Draws concurrent downloads as one stacked display
+    progress: indicatif::MultiProgress,
+    /// Registry credentials keyed by the host they belong to
+    registry_auth: HashMap<String, RegistryAuth>,
+    /// API tokens for release providers, keyed by host
+    pub host_tokens: HashMap<String, String>,
+    /// Resolved `Authorization` values, so token commands run once per host
+    auth_headers: Mutex<HashMap<String, String>>,
+    /// Where API requests' time is recorded for `--timings`
+    pub timings: Arc<Timings>,
+}
+
+/// An API response kept under `cache/api`, revalidated with its ETag
+#[derive(Clone, Serialize, Deserialize)]
+struct CachedResponse {
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    /// The next page of the listing, from the `Link` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next: Option<String>,
+    body: String,
+}
+
+/// One page of a repository's releases
+pub struct ReleasePage {
+    pub releases: Vec<Release>,
+    /// Where the following page is, if there is one
+    pub next: Option<String>,
+}
+
+/// Pages of releases `get_releases` follows before giving up on the oldest;
+/// providers list up to 100 per page
+const MAX_RELEASE_PAGES: usize = 10;
+
+// Hosts that may receive the GitHub token; release downloads redirect from
+// github.com to a CDN, and reqwest drops the header on cross-host redirects
+const GITHUB_HOSTS: &[&str] = &["github.com", "api.github.com"];
+
+/// Sent with every request; GitHub's API refuses requests without one
+pub const USER_AGENT: &str = concat!("grip/", env!("CARGO_PKG_VERSION"));
+
+// Touched after every successful pull; kept inside .git so it is never tracked
+const UPDATED_MARKER: &str = ".git/grip-updated";
+
+impl RegistryManager {
+    pub fn new(data_dir: PathBuf, github_token: Option<String>) -> Self {
+        Self {
+            data_dir,
+            client: reqwest::Client::builder()
+                .user_agent(USER_AGENT)
+                .build()
+                .unwrap_or_default(),
+            github_token,
+            quiet: false,
+            cache_ttl: Duration::ZERO,
+            offline: false,
+            refresh: false,
+            require_signed: false,
+            network: NetworkSettings::default(),
+            progress: indicatif::MultiProgress::new(),
+            registry_auth: HashMap::new(),
+            host_tokens: HashMap::new(),
+            auth_headers: Mutex::new(HashMap::new()),
+            timings: Arc::default(),
+        }
+    }
+
+    /// Applies retry, timeout, proxy, CA and connection pool settings,
+    /// rebuilding the HTTP client. The one client serves every registry fetch,
+    /// API call and download, so connections, and HTTP/2 where the server
+    /// offers it, are reused across them
+    pub fn set_network(&mut self, network: NetworkSettings) -> Result<()> {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(Duration::from_secs(network.connect_timeout_secs))
+            .pool_max_idle_per_host(network.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(network.pool_idle_timeout_secs))
+            .tcp_keepalive(Duration::from_secs(60));
+
+        if let Some(proxy) = &network.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| GripError::ConfigError(format!("Invalid proxy '{}': {}", proxy, e)))?
+                .no_proxy(reqwest::NoProxy::from_env());
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(ca_bundle) = &network.ca_bundle {
+            let pem = std::fs::read(ca_bundle).map_err(|e| {
+                GripError::ConfigError(format!("Failed to read CA bundle {}: {}", ca_bundle.display(), e))
+            })?;
+            for certificate in reqwest::Certificate::from_pem_bundle(&pem)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        self.client = builder.build()?;
+        self.network = network;
+        Ok(())
+    }
+
+    /// Sends a GET, retrying connection failures, timeouts and server errors
+    /// with exponential backoff. Downloads pass `whole_request_timeout: false`
+    /// so large files aren't cut off; their stalls are caught per chunk instead
+    async fn send(&self, url: &str, whole_request_timeout: bool) -> Result<reqwest::Response> {
+        if whole_request_timeout {
+            self.send_with_retries(url, "GET", || Ok(self.get(url)?.timeout(self.network.timeout())))
+                .await
+        } else {
+            // Downloads are timed by their callers, body and all
+            self.retry(url, "GET", || self.get(url)).await
+        }
+    }
+
+    /// Sends the request `build` makes, retrying server errors and dropped
+    /// connections, and records how long it took for `--timings`
+    async fn send_with_retries(
+        &self,
+        url: &str,
+        method: &str,
+        build: impl Fn() -> Result<reqwest::RequestBuilder>,
+    ) -> Result<reqwest::Response> {
+        let started = std::time::Instant::now();
+        let response = self.retry(url, method, build).await;
+        self.timings.record(Span::new(Phase::Api, started).detail(format!("{} {}", method, url)));
+        response
+    }
+
+    async fn retry(
+        &self,
+        url: &str,
+        method: &str,
+        build: impl Fn() -> Result<reqwest::RequestBuilder>,
+    ) -> Result<reqwest::Response> {
+        let mut attempt = 0;
+        loop {
+            let request = build()?;
+
+            tracing::debug!(url, attempt, "{}", method);
+            let result = request.send().await;
+            match &result {
+                Ok(response) => tracing::debug!(url, status = %response.status(), "response"),
+                Err(e) => tracing::debug!(url, error = %e, "request failed"),
+            }
+            let failure = match &result {
+                Ok(response)
+                    if response.status().is_server_error()
+                        || response.status() == reqwest::StatusCode::REQUEST_TIMEOUT =>
+                {
+                    Some(response.status().to_string())
+                }
+                Ok(_) => None,
+                Err(e) if e.is_timeout() || e.is_connect() || e.is_request() => Some(e.to_string()),
+                Err(_) => None,
+            };
+
+            match failure {
+                Some(reason) if attempt < self.network.retries => {
+                    attempt += 1;
+                    self.wait_before_retry(url, &reason, attempt).await;
+                }
+                _ => return Ok(result?),
+            }
+        }
+    }
+
+    async fn wait_before_retry(&self, what: &str, reason: &str, attempt: u32) {
+        let delay = self.network.backoff(attempt);
+        ui::warning!(
+            "{} failed ({}); retrying in {:.1}s ({}/{})",
+            what,
+            reason,
+            delay.as_secs_f32(),
+            attempt,
+            self.network.retries
+        );
+        tokio::time::sleep(delay).await;
+    }
+
+    /// Runs git for a registry, retrying failures. Transfers slower than 1 KB/s
+    /// for the configured timeout are aborted rather than left hanging
+    async fn git(&self, registry: &Registry, args: &[&str], dir: Option<&Path>) -> Result<bool> {
+        let mut attempt = 0;
+        loop {
+            let mut command = Command::new("git");
+            command
+                .args(self.git_config_args(registry)?)
+                .args(args)
+                .env("GIT_TERMINAL_PROMPT", "0")
+                .env("GIT_HTTP_LOW_SPEED_LIMIT", "1000")
+                .env("GIT_HTTP_LOW_SPEED_TIME", self.network.timeout_secs.to_string());
+            if let Some(dir) = dir {
+                command.current_dir(dir);
+            }
+
+            tracing::debug!(registry = %registry.name, ?args, ?dir, "running git");
+            if command.status().await?.success() {
+                return Ok(true);
+            }
+            if attempt >= self.network.retries {
+                return Ok(false);
+            }
+            attempt += 1;
+            self.wait_before_retry(&format!("git {}", args[0]), "exit status", attempt)
+                .await;
+        }
+    }
+
+    /// Registers the credentials of every registry that has them
+    pub fn set_registry_auth(&mut self, registries: &[Registry]) {
+        self.registry_auth = registries
+            .iter()
+            .filter_map(|r| Some((r.host()?, r.auth.clone()?)))
+            .collect();
+    }
+
+    /// The `Authorization` header for a host with registry credentials
+    fn auth_header(&self, host: &str) -> Result<Option<String>> {
+        let Some(auth) = self.registry_auth.get(host) else {
+            return Ok(None);
+        };
+
+        let mut cache = self.auth_headers.lock().unwrap();
+        if let Some(value) = cache.get(host) {
+            return Ok(Some(value.clone()));
+        }
+        let value = auth.header_value()?;
+        cache.insert(host.to_string(), value.clone());
+        Ok(Some(value))
+    }
+
+    /// Starts a GET request, authenticating it for registry and provider hosts
+    fn get(&self, url: &str) -> Result<reqwest::RequestBuilder> {
+        let request = self.client.get(url);
+
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string));
+        let Some(host) = host else {
+            return Ok(request);
+        };
+
+        if let Some(value) = self.auth_header(&host)? {
+            return Ok(request.header("Authorization", value));
+        }
+        if let Some(token) = self.host_tokens.get(&host) {
+            return Ok(request.bearer_auth(token));
+        }
+
+        Ok(match &self.github_token {
+            Some(token) if GITHUB_HOSTS.contains(&host.as_str()) => request.bearer_auth(token),
+            _ => request,
+        })
+    }
+
+    /// `git -c` arguments that send a registry's credentials without storing
+    /// them, and route git through the configured proxy and CA bundle
+    fn git_config_args(&self, registry: &Registry) -> Result<Vec<String>> {
+        let header = match registry.host() {
+            Some(host) => self.auth_header(&host)?,
+            None => None,
+        };
+        Ok(self.git_args_with_auth(header))
+    }
+
+    /// `git -c` arguments sending `Authorization: <header>` when given, with
+    /// the configured proxy and CA bundle
+    fn git_args_with_auth(&self, header: Option<String>) -> Vec<String> {
+        let mut settings = Vec::new();
+        if let Some(value) = header {
+            settings.push(format!("http.extraHeader=Authorization: {}", value));
+        }
+        if let Some(proxy) = &self.network.proxy {
+            settings.push(format!("http.proxy={}", proxy));
+        }
+        if let Some(ca_bundle) = &self.network.ca_bundle {
+            settings.push(format!("http.sslCAInfo={}", ca_bundle.display()));
+        }
+
+        settings
+            .into_iter()
+            .flat_map(|setting| ["-c".to_string(), setting])
+            .collect()
+    }
+
+    /// Turns an exhausted GitHub rate limit into an error naming the reset time
+    fn check_rate_limit(&self, response: &reqwest::Response) -> Result<()> {
+        let status = response.status();
+        if status != reqwest::StatusCode::FORBIDDEN && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Ok(());
+        }
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+        };
+        if header("x-ratelimit-remaining") != Some(0) && header("retry-after").is_none() {
+            return Ok(());
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let wait = header("retry-after")
+            .or_else(|| header("x-ratelimit-reset").map(|reset| reset.saturating_sub(now)))
+            .unwrap_or(0);
+
+        let hint = if self.github_token.is_some() {
+            String::new()
+        } else {
+            "; set GITHUB_TOKEN or github_token in the config to raise the limit".to_string()
+        };
+
+        Err(GripError::RateLimited(format!(
+            "resets in {} minute(s){}",
+            wait.div_ceil(60),
+            hint
+        )))
+    }
+
+    /// Returns a registry's local clone, pulling it only once the cache is stale
+    pub async fn ensure_registry(&self, registry: &Registry) -> Result<PathBuf> {
+        self.check_signing_required(registry)?;
+        let registry_path = self.registry_path(registry);
+
+        if !registry_path.exists() || matches!(registry.source(), RegistrySource::Local(_)) {
+            return self.update_registry(registry).await;
+        }
+        if self.offline || self.is_fresh(&registry_path.join(UPDATED_MARKER)) {
+            return Ok(registry_path);
+        }
+
+        match self.update_registry(registry).await {
+            Ok(path) => Ok(path),
+            Err(e) => {
+                ui::warning!("{}; using cached copy of {}", e, registry.name.cyan());
+                Ok(registry_path)
+            }
+        }
+    }
+
+    /// Clones or pulls a registry regardless of how recently it was refreshed.
+    /// A registry with a public key is checked against its signed index, and
+    /// a clone or pull that fails the check is undone
+    pub async fn update_registry(&self, registry: &Registry) -> Result<PathBuf> {
+        self.check_signing_required(registry)?;
+        let url = match registry.source() {
+            RegistrySource::Local(path) => {
+                if !path.is_dir() {
+                    return Err(GripError::RegistryError(format!(
+                        "Registry {} points at missing directory {}",
+                        registry.name,
+                        path.display()
+                    )));
+                }
+                self.verify_index(registry, &path)?;
+                return Ok(path);
+            }
+            RegistrySource::Git(url) => url,
+        };
+        if self.offline {
+            return Err(GripError::Offline(format!("Registry {}", registry.name)));
+        }
+        let registry_path = self.registry_path(registry);
+
+        if !registry_path.exists() {
+            ui::status!("Cloning registry {}...", registry.name.cyan());
+            let path = registry_path.to_string_lossy();
+            let cloned = self
+                .git(registry, &["clone", "--quiet", "--depth", "1", &url, &path], None)
+                .await?;
+
+            if !cloned {
+                return Err(GripError::RegistryError(format!(
+                    "Failed to clone registry {}", 
+                    registry.name
+                )));
+            }
+            if let Err(e) = self.verify_index(registry, &registry_path) {
+                std::fs::remove_dir_all(&registry_path)?;
+                return Err(e);
+            }
+        } else {
+            ui::status!("Updating registry {}...", registry.name.cyan());
+            let previous = head(&registry_path).await;
+            let pulled = self
+                .git(registry, &["pull", "--quiet", "--ff-only"], Some(&registry_path))
+                .await?;
+
+            if !pulled {
+                return Err(GripError::RegistryError(format!(
+                    "Failed to update registry {}", 
+                    registry.name
+                )));
+            }
+            if let Err(e) = self.verify_index(registry, &registry_path) {
+                match previous {
+                    Some(previous) => {
+                        self.git(registry, &["reset", "--quiet", "--hard", &previous], Some(&registry_path))
+                            .await?;
+                    }
+                    None => std::fs::remove_dir_all(&registry_path)?,
+                }
+                return Err(e);
+            }
+        }
+
+        std::fs::write(registry_path.join(UPDATED_MARKER), "")?;
+        Ok(registry_path)
+    }
+
+    /// Refuses a registry without a public key when
+    /// `settings.require_signed_registries` is on
+    fn check_signing_required(&self, registry: &Registry) -> Result<()> {
+        if self.require_signed && registry.public_key.is_none() {
+            return Err(GripError::SignatureError(format!(
+                "registry {} has no public key, and settings.require_signed_registries refuses unsigned registries",
+                registry.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks a registry with a public key against its signed index
+    fn verify_index(&self, registry: &Registry, registry_path: &Path) -> Result<()> {
+        let Some(key) = &registry.public_key else {
+            return Ok(());
+        };
+        index::verify(registry_path, key).map_err(|e| match e {
+            GripError::SignatureError(message) => {
+                GripError::SignatureError(format!("refusing registry {}: {}", registry.name, message))
+            }
+            e => e,
+        })?;
+        tracing::debug!(registry = %registry.name, "registry index signature verified");
+        Ok(())
+    }
+
+    /// Fetches a repository that isn't a registry, such as a project template,
+    /// the way registries are: directories are read in place and anything
+    /// else is shallow-cloned into `dest`
+    pub async fn fetch_repository(&self, source: &Registry, dest: &Path) -> Result<PathBuf> {
+        let url = match source.source() {
+            RegistrySource::Local(path) if path.is_dir() => return Ok(path),
+            RegistrySource::Local(path) => {
+                return Err(GripError::RegistryError(format!("{} does not exist", path.display())))
+            }
+            RegistrySource::Git(url) => url,
+        };
+        if self.offline {
+            return Err(GripError::Offline(source.url.clone()));
+        }
+
+        let path = dest.to_string_lossy();
+        if !self.git(source, &["clone", "--quiet", "--depth", "1", &url, &path], None).await? {
+            return Err(GripError::RegistryError(format!("Failed to clone {}", source.url)));
+        }
+        Ok(dest.to_path_buf())
+    }
+
+    /// Checks that a registry's source can be reached, without updating it
+    pub async fn check_registry(&self, registry: &Registry) -> Result<()> {
+        let url = match registry.source() {
+            RegistrySource::Local(path) if path.is_dir() => return Ok(()),
+            RegistrySource::Local(path) => {
+                return Err(GripError::RegistryError(format!("{} does not exist", path.display())))
+            }
+            RegistrySource::Git(url) => url,
+        };
+        if self.offline {
+            return Err(GripError::Offline(format!("Registry {}", registry.name)));
+        }
+
+        let output = Command::new("git")
+            .args(self.git_config_args(registry)?)
+            .args(["ls-remote", "--quiet", &url, "HEAD"])
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(GripError::RegistryError(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Where a registry's definitions live on disk
+    fn registry_path(&self, registry: &Registry) -> PathBuf {
+        match registry.source() {
+            RegistrySource::Local(path) => path,
+            RegistrySource::Git(_) => self.data_dir.join("registries").join(&registry.name),
+        }
+    }
+
+    /// How long ago a registry's clone was last pulled, or `None` for one that
+    /// was never cloned, or a local directory, which is read in place
+    pub fn registry_age(&self, registry: &Registry) -> Option<Duration> {
+        if matches!(registry.source(), RegistrySource::Local(_)) {
+            return None;
+        }
+        std::fs::metadata(self.registry_path(registry).join(UPDATED_MARKER))
+            .and_then(|m| m.modified())
+            .ok()
+            .map(|modified| modified.elapsed().unwrap_or_default())
+    }
+
+    /// Whether a clone or cached response was refreshed within the cache TTL,
+    /// going by the modification time of `marker`
+    fn is_fresh(&self, marker: &Path) -> bool {
+        if self.refresh {
+            return false;
+        }
+        std::fs::metadata(marker)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age < self.cache_ttl)
+    }
+
+    /// Looks a package up in every registry at once and returns the definition
+    /// from the highest-priority registry that has it, warning when others
+    /// define it too. A qualified `registry/name` is only looked up in that
+    /// registry
+    pub async fn find_package(&self, registries: &[Registry], package_name: &str) -> Result<Package> {
+        if let Some(name) = provider::repository_name(package_name) {
+            return Ok(Package::from_repository(package_name, name));
+        }
+        let (registries, package_name) = match split_qualified(package_name) {
+            Some((registry, name)) => {
+                let registry = registries
+                    .iter()
+                    .find(|r| r.name == registry)
+                    .ok_or_else(|| GripError::RegistryNotFound(registry.to_string()))?;
+                (std::slice::from_ref(registry), name)
+            }
+            None => (registries, package_name),
+        };
+
+        let sorted_registries = by_priority(registries);
+
+        let lookups = futures_util::future::join_all(sorted_registries.iter().map(|registry| async move {
+            let registry_path = self.ensure_registry(registry).await?;
+            let package_file = packages_dir(&registry_path).join(format!("{}.json", package_name));
+            Ok::<_, GripError>(package_file.exists().then_some(package_file))
+        }))
+        .await;
+
+        // A registry that failed before the first hit might have shadowed it
+        let mut hits = Vec::new();
+        for (registry, lookup) in sorted_registries.iter().zip(lookups) {
+            match lookup {
+                Ok(Some(package_file)) => hits.push((registry, package_file)),
+                Ok(None) => {}
+                Err(e) if hits.is_empty() => return Err(e),
+                Err(e) => ui::warning!("Skipping registry {}: {}", registry.name.cyan(), e),
+            }
+        }
+
+        tracing::debug!(
+            package = package_name,
+            hits = ?hits.iter().map(|(r, _)| r.name.as_str()).collect::<Vec<_>>(),
+            "registry lookup"
+        );
+        let Some((registry, package_file)) = hits.first() else {
+            return Err(GripError::PackageNotFound(package_name.to_string()));
+        };
+        if hits.len() > 1 {
+            let names: Vec<&str> = hits.iter().map(|(r, _)| r.name.as_str()).collect();
+            ui::warning!(
+                "{} found in multiple registries ({}); using {}, name it as <registry>/{} or pass --registry to choose",
+                package_name,
+                names.join(", "),
+                registry.name,
+                package_name
+            );
+        }
+        let mut package = Package::load(package_file.clone())?;
+        package.registry = Some(registry.name.clone());
+        Ok(package)
+    }
+
+    /// The first definition of a project template in the registries, highest
+    /// priority first
+    pub async fn find_template(&self, registries: &[Registry], name: &str) -> Result<Option<PathBuf>> {
+        for registry in &by_priority(registries) {
+            let registry_path = match self.ensure_registry(registry).await {
+                Ok(path) => path,
+                Err(e) => {
+                    ui::warning!("Skipping registry {}: {}", registry.name.cyan(), e);
+                    continue;
+                }
+            };
+            let template = registry_path.join(template::TEMPLATES_DIR).join(format!("{}.json", name));
+            if template.is_file() {
+                return Ok(Some(template));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Loads every package definition in a registry
+    pub async fn list_packages(&self, registry: &Registry) -> Result<Vec<Package>> {
+        let registry_path = self.ensure_registry(registry).await?;
+        let packages_path = packages_dir(&registry_path);
+
+        if !packages_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut packages = Vec::new();
+        for entry in std::fs::read_dir(packages_path)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                match Package::load(path.clone()) {
+                    Ok(mut package) => {
+                        package.registry = Some(registry.name.clone());
+                        packages.push(package);
+                    }
+                    Err(e) => ui::warning!("Skipping invalid package {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        Ok(packages)
+    }
+
+    /// Lists a repository's releases, newest first, following the provider's
+    /// pages up to `MAX_RELEASE_PAGES` of them
+    pub async fn get_releases(&self, repo: &str) -> Result<Vec<Release>> {
+        let mut page = self.release_page(repo, None).await?;
+        let mut releases = Vec::new();
+        for fetched in 1.. {
+            append_releases(&mut releases, page.releases);
+            let Some(next) = page.next else {
+                break;
+            };
+            if fetched == MAX_RELEASE_PAGES {
+                tracing::debug!(repo, pages = fetched, "stopped following release pages");
+                break;
+            }
+            page = self.release_page(repo, Some(&next)).await?;
+        }
+        Ok(releases)
+    }
+
+    /// Fetches one page of a repository's releases: the first when `url` is
+    /// `None`, otherwise the one a previous page's `next` points at. Each page
+    /// is cached with its ETag and used as is within the cache TTL; after that
+    /// it is revalidated, which costs nothing against GitHub's rate limit when
+    /// unchanged
+    pub async fn release_page(&self, repo: &str, url: Option<&str>) -> Result<ReleasePage> {
+        let provider = provider::for_repository(repo)?;
+        let url = url.map_or_else(|| provider.releases_url(), str::to_string);
+        let cache_path = self.api_cache_path(&url);
+        let cached = Self::read_api_cache(&cache_path);
+        let cached_page = |cached: CachedResponse| -> Result<ReleasePage> {
+            Ok(ReleasePage {
+                releases: provider.parse_releases(&cached.body)?,
+                next: cached.next,
+            })
+        };
+
+        if self.offline {
+            tracing::debug!(url, "offline, using the cached release list");
+            let cached = cached.ok_or_else(|| GripError::Offline(format!("Release list of {}", repo)))?;
+            return cached_page(cached);
+        }
+        if let Some(cached) = cached.as_ref().filter(|_| self.is_fresh(&cache_path)) {
+            tracing::debug!(url, "using the cached release list");
+            return cached_page(cached.clone());
+        }
+
+        let etag = cached.as_ref().and_then(|c| c.etag.clone());
+        let response = self
+            .send_with_retries(&url, "GET", || {
+                let request = self.get(&url)?.timeout(self.network.timeout());
+                Ok(match &etag {
+                    Some(etag) => request.header(reqwest::header::IF_NONE_MATCH, etag),
+                    None => request,
+                })
+            })
+            .await?;
+
+        if let Some(cached) = cached {
+            if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+                tracing::debug!(url, "release list unchanged");
+                Self::write_api_cache(&cache_path, &cached);
+                return cached_page(cached);
+            }
+            if let Err(e) = self.check_rate_limit(&response) {
+                ui::warning!("{}; using the cached release list of {}", e, repo);
+                return cached_page(cached);
+            }
+        }
+        self.check_rate_limit(&response)?;
+
+        if !response.status().is_success() {
+            if response.status() == 404 {
+                return Err(GripError::RegistryError(format!(
+                    "Repository '{}' not found on {}", 
+                    repo,
+                    provider.name()
+                )));
+            }
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(GripError::RegistryError(format!(
+                "{} API error ({}): {}", 
+                provider.name(),
+                status, error_text
+            )));
+        }
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(reqwest::header::ETAG);
+        let next = header(reqwest::header::LINK).and_then(|link| next_link(&link));
+        let body = response.text().await?;
+        let releases = provider.parse_releases(&body).map_err(|e| {
+            GripError::RegistryError(format!(
+                "Failed to parse {} releases: {}", 
+                provider.name(),
+                e
+            ))
+        })?;
+        tracing::debug!(repo, count = releases.len(), "fetched releases");
+        Self::write_api_cache(&cache_path, &CachedResponse { url, etag, next: next.clone(), body });
+        Ok(ReleasePage { releases, next })
+    }
+
+    /// Where an API response is cached along with its ETag
+    fn api_cache_path(&self, url: &str) -> PathBuf {
+        self.data_dir
+            .join("cache")
+            .join("api")
+            .join(format!("{}.json", checksum::sha256_hex(url.as_bytes())))
+    }
+
+    fn read_api_cache(path: &Path) -> Option<CachedResponse> {
+        serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+    }
+
+    /// Best effort, like `write_cached_response`; rewriting an unchanged
+    /// response marks it fresh again
+    fn write_api_cache(path: &Path, response: &CachedResponse) {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(content) = serde_json::to_string(response) {
+            let _ = std::fs::write(path, content);
+        }
+    }
+
+    /// Where the last successful response from `url` is kept for offline use
+    fn cached_response_path(&self, url: &str) -> PathBuf {
+        self.data_dir
+            .join("responses")
+            .join(checksum::sha256_hex(url.as_bytes()))
+    }
+
+    /// Best effort: a response that can't be stored is only missed offline
+    fn write_cached_response(&self, url: &str, body: impl AsRef<[u8]>) {
+        let path = self.cached_response_path(url);
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(path, body);
+    }
+
+    /// Returns the newest published, non-prerelease release of a repository
+    pub async fn get_latest_release(&self, repo: &str) -> Result<Release> {
+        let mut page = self.release_page(repo, None).await?;
+        for fetched in 1..=MAX_RELEASE_PAGES {
+            if let Some(latest) = page.releases.into_iter().find(|r| !r.draft && !r.prerelease) {
+                return Ok(latest);
+            }
+            match page.next {
+                Some(next) if fetched < MAX_RELEASE_PAGES => page = self.release_page(repo, Some(&next)).await?,
+                _ => break,
+            }
+        }
+        Err(GripError::VersionNotFound(format!("latest release of {}", repo)))
+    }
+
+    /// Fetches a small text file such as a published checksum list
+    pub async fn fetch_text(&self, url: &str) -> Result<String> {
+        let body = self.fetch_bytes(url).await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// Fetches a small file that may be binary, such as a detached signature
+    pub async fn fetch_bytes(&self, url: &str) -> Result<Vec<u8>> {
+        if self.offline {
+            return std::fs::read(self.cached_response_path(url))
+                .map_err(|_| GripError::Offline(url.to_string()));
+        }
+
+        let response = self.send(url, true).await?;
+
+        if !response.status().is_success() {
+            return Err(GripError::DownloadError(format!(
+                "Failed to fetch {} ({})",
+                url,
+                response.status()
+            )));
+        }
+
+        let body = response.bytes().await?;
+        self.write_cached_response(url, &body);
+        Ok(body.to_vec())
+    }
+
+    /// Fetches bytes `start..end` of a file, failing if the server ignores the
+    /// range rather than sending the whole file
+    pub async fn fetch_range(&self, url: &str, start: u64, end: u64) -> Result<Vec<u8>> {
+        if self.offline {
+            return Err(GripError::Offline(url.to_string()));
+        }
+
+        let response = self
+            .send_with_retries(url, "GET", || {
+                Ok(self
+                    .get(url)?
+                    .header(reqwest::header::RANGE, format!("bytes={}-{}", start, end - 1))
+                    .timeout(self.network.timeout()))
+            })
+            .await?;
+        self.check_rate_limit(&response)?;
+
+        if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            return Err(GripError::DownloadError(format!(
+                "Failed to fetch part of {} ({})",
+                url,
+                response.status()
+            )));
+        }
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Posts a JSON body to an API and returns the response body, as for
+    /// OSV's vulnerability queries
+    pub async fn post_json<T: serde::Serialize>(&self, url: &str, body: &T) -> Result<String> {
+        if self.offline {
+            return Err(GripError::Offline(url.to_string()));
+        }
+
+        let response = self
+            .send_with_retries(url, "POST", || {
+                Ok(self
+                    .client
+                    .post(url)
+                    .timeout(self.network.timeout())
+                    .json(body))
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GripError::DownloadError(format!(
+                "Failed to query {} ({})",
+                url,
+                response.status()
+            )));
+        }
+        Ok(response.text().await?)
+    }
+
+    /// A progress bar for a download, hidden when quiet, when progress is
+    /// reported as events, or when stderr isn't a terminal to redraw it on
+    fn progress_bar(&self, filename: &str, content_length: Option<u64>) -> indicatif::ProgressBar {
+        if self.quiet || ui::progress_events() || !std::io::stderr().is_terminal() {
+            return indicatif::ProgressBar::hidden();
+        }
+
+        let pb = match content_length {
+            Some(total_size) => {
+                let pb = indicatif::ProgressBar::new(total_size);
+                pb.set_style(
+                    indicatif::ProgressStyle::default_bar()
+                        .template("{spinner:.green} {prefix} [{bar:40.cyan/blue}] {bytes}/{total_bytes} {bytes_per_sec} ({eta})")
+                        .unwrap()
+                        .progress_chars("#>-")
+                );
+                pb
+            }
+            // Without a Content-Length there is nothing to measure against
+            None => {
+                let pb = indicatif::ProgressBar::new_spinner();
+                pb.set_style(
+                    indicatif::ProgressStyle::default_spinner()
+                        .template("{spinner:.green} {prefix} {bytes} {bytes_per_sec} ({elapsed})")
+                        .unwrap()
+                );
+                pb
+            }
+        };
+        let pb = self.progress.add(pb);
+        pb.set_prefix(filename.to_string());
+        pb
+    }
+
+    /// Streams a response body to `path`, failing if the server stalls for
+    /// longer than the timeout, and returns its sha256 digest, computed as the
+    /// chunks arrive
+    async fn write_body(
+        &self,
+        response: reqwest::Response,
+        path: &Path,
+        filename: &str,
+        pb: &indicatif::ProgressBar,
+    ) -> Result<String> {
+        let total_bytes = response.content_length();
+        let mut file = tokio::fs::File::create(path).await?;
+        let mut stream = response.bytes_stream();
+        let mut downloaded = 0u64;
+        let mut hasher = checksum::StreamingSha256::default();
+        let mut reported = std::time::Instant::now();
+
+        loop {
+            let next = tokio::time::timeout(self.network.timeout(), stream.next())
+                .await
+                .map_err(|_| {
+                    GripError::DownloadError(format!(
+                        "no data received for {}s",
+                        self.network.timeout_secs
+                    ))
+                })?;
+            let Some(chunk_result) = next else { break };
+            let chunk = chunk_result?;
+            hasher.update(&chunk);
+            file.write_all(&chunk).await?;
+            downloaded += chunk.len() as u64;
+            pb.set_position(downloaded);
+            if reported.elapsed() >= PROGRESS_EVENT_INTERVAL {
+                reported = std::time::Instant::now();
+                ui::emit(ui::Event::DownloadProgress { file: filename, bytes: downloaded, total_bytes });
+            }
+        }
+        file.flush().await?;
+        Ok(hasher.finish())
+    }
+
+    /// Downloads an asset into `target_dir`, returning the file and its sha256
+    /// digest. The digest is computed while the file is written, so checking
+    /// it takes no second pass over a large download
+    pub async fn download_asset(
+        &self, 
+        url: &str, 
+        filename: &str,
+        target_dir: &Path
+    ) -> Result<(PathBuf, String)> {
+        if let Some(path) = url.strip_prefix("file://") {
+            ui::status!("Copying {}", filename.cyan());
+            tokio::fs::create_dir_all(target_dir).await?;
+            let final_path = target_dir.join(filename);
+            let sha256 = checksum::copy_with_sha256(Path::new(path), &final_path)?;
+            ui::emit(ui::Event::DownloadFinished {
+                file: filename,
+                bytes: std::fs::metadata(&final_path)?.len(),
+            });
+            return Ok((final_path, sha256));
+        }
+
+        if self.offline {
+            return Err(GripError::Offline(format!("Download of {}", filename)));
+        }
+
+        ui::status!("Downloading {}", filename.cyan());
+
+        // Downloaded next to where it ends up, so it only needs renaming
+        tokio::fs::create_dir_all(target_dir).await?;
+        let temp_path = target_dir.join(format!(".{}.part", filename));
+
+        // A connection dropped mid-download restarts it, within the retry budget
+        let mut attempt = 0;
+        loop {
+            let response = self.send(url, false).await?;
+            self.check_rate_limit(&response)?;
+
+            if !response.status().is_success() {
+                return Err(GripError::DownloadError(format!(
+                    "Failed to download {} ({})",
+                    filename,
+                    response.status()
+                )));
+            }
+
+            ui::emit(ui::Event::DownloadStarted {
+                file: filename,
+                url,
+                total_bytes: response.content_length(),
+            });
+            let pb = self.progress_bar(filename, response.content_length());
+            match self.write_body(response, &temp_path, filename, &pb).await {
+                Ok(sha256) => {
+                    pb.finish_with_message("Download complete!");
+                    let final_path = target_dir.join(filename);
+                    tokio::fs::rename(&temp_path, &final_path).await?;
+                    ui::emit(ui::Event::DownloadFinished {
+                        file: filename,
+                        bytes: tokio::fs::metadata(&final_path).await?.len(),
+                    });
+                    return Ok((final_path, sha256));
+                }
+                Err(e) if attempt < self.network.retries => {
+                    pb.abandon();
+                    attempt += 1;
+                    self.wait_before_retry(&format!("Download of {}", filename), &e.to_string(), attempt)
+                        .await;
+                }
+                Err(e) => {
+                    let _ = tokio::fs::remove_file(&temp_path).await;
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+/// Splits a qualified `registry/name` into the registry's name and the
+/// package's. Repository specs such as `gh:owner/repo` aren't qualified names
+pub fn split_qualified(spec: &str) -> Option<(&str, &str)> {
+    if provider::is_repository_spec(spec) {
+        return None;
+    }
+    spec.split_once('/')
+        .filter(|(registry, name)| !registry.is_empty() && !name.is_empty() && !name.contains('/'))
+}
+
+/// Registries in the order packages are looked up in them: highest priority
+/// first, and by name among equal priorities, so the order never depends on
+/// the order of the config file
+pub fn by_priority(registries: &[Registry]) -> Vec<Registry> {
+    let mut sorted = registries.to_vec();
+    sorted.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.name.cmp(&b.name)));
+    sorted
+}
+
+/// The commit a clone is at
+async fn head(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .await
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The `packages` directory of a registry, or its root for a flat directory of
+/// definitions
+pub(crate) fn packages_dir(registry_path: &Path) -> PathBuf {
+    let packages = registry_path.join("packages");
+    if packages.is_dir() {
+        packages
+    } else {
+        registry_path.to_path_buf()
+    }
+}
+
+/// The `next` URL of a `Link` header, as in
+/// `<https://api.github.com/...&page=2>; rel="next", <...>; rel="last"`
+fn next_link(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        let is_next = params
+            .split(';')
+            .any(|param| matches!(param.trim(), "rel=\"next\"" | "rel=next"));
+        is_next.then(|| url.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+    })
+}
+
+/// Adds a page of releases to those already fetched, skipping tags seen on an
+/// earlier page, which happens when a release is published between requests
+pub fn append_releases(releases: &mut Vec<Release>, page: Vec<Release>) {
+    for release in page {
+        if !releases.iter().any(|r| r.tag_name == release.tag_name) {
+            releases.push(release);
+        }
+    }
+}
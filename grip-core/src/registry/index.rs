@@ -0,0 +1,84 @@
+use crate::error::{GripError, Result};
+use crate::{checksum, signature, utils};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The digest of every file in a registry, at its root
+pub const INDEX_FILE: &str = "index.json";
+
+/// The maintainers' minisign signature of the index, next to it
+pub const SIGNATURE_FILE: &str = "index.json.minisig";
+
+/// A registry's files and their sha256 digests. Signing the index signs the
+/// whole registry, so a key in the config vouches for every definition in it
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Index {
+    /// Paths relative to the registry root, with `/` separators
+    pub files: BTreeMap<String, String>,
+}
+
+impl Index {
+    /// Digests every file in `dir` but the index, its signature and git's
+    pub fn build(dir: &Path) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        for path in utils::walk_files(dir)? {
+            let relative = path.strip_prefix(dir).unwrap_or(&path);
+            let name = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if name == INDEX_FILE || name == SIGNATURE_FILE || name == ".git" || name.starts_with(".git/") {
+                continue;
+            }
+            files.insert(name, checksum::sha256_file(&path)?);
+        }
+        Ok(Self { files })
+    }
+
+    /// Writes the index of `dir` to its root, for the maintainers to sign
+    pub fn write(dir: &Path) -> Result<(PathBuf, usize)> {
+        let index = Self::build(dir)?;
+        let path = dir.join(INDEX_FILE);
+        std::fs::write(&path, serde_json::to_string_pretty(&index)?)?;
+        Ok((path, index.files.len()))
+    }
+}
+
+/// Checks that the registry in `dir` is exactly what its maintainers signed:
+/// the index's signature verifies against `key`, and the files on disk are
+/// the ones the index lists, with the same digests
+pub fn verify(dir: &Path, key: &str) -> Result<()> {
+    let index_path = dir.join(INDEX_FILE);
+    let signature_path = dir.join(SIGNATURE_FILE);
+    if !index_path.is_file() || !signature_path.is_file() {
+        return Err(GripError::SignatureError(format!(
+            "{} has no signed {}",
+            dir.display(),
+            INDEX_FILE
+        )));
+    }
+    signature::verify_minisign(&index_path, &std::fs::read(&signature_path)?, key)?;
+
+    let content = std::fs::read_to_string(&index_path)?;
+    let signed: Index = serde_json::from_str(&content)
+        .map_err(|e| GripError::SignatureError(format!("Failed to parse {}: {}", index_path.display(), e)))?;
+    let actual = Index::build(dir)?;
+
+    let mut problems = Vec::new();
+    for (name, digest) in &actual.files {
+        match signed.files.get(name) {
+            None => problems.push(format!("{} isn't in the signed index", name)),
+            Some(expected) if expected != digest => problems.push(format!("{} doesn't match the signed index", name)),
+            Some(_) => {}
+        }
+    }
+    for name in signed.files.keys().filter(|name| !actual.files.contains_key(*name)) {
+        problems.push(format!("{} is in the signed index but missing", name));
+    }
+    if !problems.is_empty() {
+        return Err(GripError::SignatureError(problems.join("; ")));
+    }
+    Ok(())
+}
@@ -0,0 +1,303 @@
+use crate::error::{GripError, Result};
+use crate::package::{Asset, Release};
+use serde::Deserialize;
+use std::sync::RwLock;
+
+/// The public GitHub API, used unless `github_api_url` points elsewhere
+pub const DEFAULT_GITHUB_API_URL: &str = "https://api.github.com";
+
+static GITHUB_API_URL: RwLock<Option<String>> = RwLock::new(None);
+
+/// Sends API requests for github.com repositories to `url` instead of the
+/// public API, such as a caching proxy or a mock server in tests. Web URLs
+/// still name github.com
+pub fn set_github_api_url(url: Option<&str>) {
+    *GITHUB_API_URL.write().unwrap_or_else(|e| e.into_inner()) = url.map(|url| url.trim_end_matches('/').to_string());
+}
+
+/// The API that github.com repositories are looked up through
+pub fn github_api_url() -> String {
+    GITHUB_API_URL
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| DEFAULT_GITHUB_API_URL.to_string())
+}
+
+/// A forge API that lists a project's releases and their downloadable assets
+pub trait ReleaseProvider {
+    /// Name used in error messages
+    fn name(&self) -> &'static str;
+
+    /// API endpoint listing the project's releases, newest first. Lists
+    /// longer than a page continue at the `Link` header's `next` URL
+    fn releases_url(&self) -> String;
+
+    /// Converts the API response into grip's release model
+    fn parse_releases(&self, body: &str) -> Result<Vec<Release>>;
+
+    /// The project's page on the forge
+    fn web_url(&self) -> String;
+
+    /// The web host and `owner/repo` whose GitHub artifact attestations cover
+    /// the project's assets, for providers that publish them
+    fn attestation_repo(&self) -> Option<(String, String)> {
+        None
+    }
+}
+
+pub struct GitHub {
+    api_base: String,
+    /// The instance's web host, `github.com` or a GitHub Enterprise host
+    host: String,
+    repo: String,
+}
+
+impl ReleaseProvider for GitHub {
+    fn name(&self) -> &'static str {
+        "GitHub"
+    }
+
+    fn releases_url(&self) -> String {
+        format!("{}/repos/{}/releases?per_page=100", self.api_base, self.repo)
+    }
+
+    fn parse_releases(&self, body: &str) -> Result<Vec<Release>> {
+        Ok(serde_json::from_str(body)?)
+    }
+
+    fn web_url(&self) -> String {
+        format!("https://{}/{}", self.host, self.repo)
+    }
+
+    fn attestation_repo(&self) -> Option<(String, String)> {
+        Some((self.host.clone(), self.repo.clone()))
+    }
+}
+
+/// Gitea and its forks (Forgejo, Codeberg), whose release JSON mirrors GitHub's
+pub struct Gitea {
+    host: String,
+    repo: String,
+}
+
+impl ReleaseProvider for Gitea {
+    fn name(&self) -> &'static str {
+        "Gitea"
+    }
+
+    fn releases_url(&self) -> String {
+        format!("https://{}/api/v1/repos/{}/releases?limit=50", self.host, self.repo)
+    }
+
+    fn parse_releases(&self, body: &str) -> Result<Vec<Release>> {
+        Ok(serde_json::from_str(body)?)
+    }
+
+    fn web_url(&self) -> String {
+        format!("https://{}/{}", self.host, self.repo)
+    }
+}
+
+pub struct GitLab {
+    host: String,
+    project: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabRelease {
+    tag_name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    released_at: Option<String>,
+    #[serde(default)]
+    upcoming_release: bool,
+    assets: GitLabAssets,
+}
+
+#[derive(Deserialize)]
+struct GitLabAssets {
+    #[serde(default)]
+    links: Vec<GitLabLink>,
+}
+
+#[derive(Deserialize)]
+struct GitLabLink {
+    name: String,
+    url: String,
+    #[serde(default)]
+    direct_asset_url: Option<String>,
+}
+
+impl ReleaseProvider for GitLab {
+    fn name(&self) -> &'static str {
+        "GitLab"
+    }
+
+    fn releases_url(&self) -> String {
+        format!(
+            "https://{}/api/v4/projects/{}/releases?per_page=100",
+            self.host,
+            self.project.replace('/', "%2F")
+        )
+    }
+
+    fn parse_releases(&self, body: &str) -> Result<Vec<Release>> {
+        let releases: Vec<GitLabRelease> = serde_json::from_str(body)?;
+
+        // Only release links are binaries; the generated source archives are skipped
+        Ok(releases
+            .into_iter()
+            .map(|r| Release {
+                tag_name: r.tag_name,
+                draft: false,
+                prerelease: r.upcoming_release,
+                body: r.description,
+                published_at: r.released_at,
+                assets: r
+                    .assets
+                    .links
+                    .into_iter()
+                    .map(|link| Asset {
+                        name: link.name,
+                        browser_download_url: link.direct_asset_url.unwrap_or(link.url),
+                        size: None,
+                    })
+                    .collect(),
+            })
+            .collect())
+    }
+
+    fn web_url(&self) -> String {
+        format!("https://{}/{}", self.host, self.project)
+    }
+}
+
+// Prefixes that name a provider explicitly, as in `gh:owner/repo`
+const PROVIDER_PREFIXES: &[&str] = &["gh", "github", "ghe", "gitlab", "gitea", "forgejo", "codeberg"];
+
+/// Whether a package argument is a provider-prefixed repository, like
+/// `gh:owner/repo`, rather than a registry package name
+pub fn is_repository_spec(spec: &str) -> bool {
+    spec.split_once(':')
+        .is_some_and(|(kind, rest)| PROVIDER_PREFIXES.contains(&kind) && rest.contains('/'))
+}
+
+/// Whether a recorded repository is a direct `url:` or `file:` source, which
+/// has no releases to check
+pub fn is_direct_source(repository: &str) -> bool {
+    repository.starts_with("url:") || repository.starts_with("file:")
+}
+
+/// The forge page of a recorded repository, if it is one grip knows how to reach
+pub fn web_url(repository: &str) -> Option<String> {
+    if is_direct_source(repository) {
+        return None;
+    }
+    for_repository(repository).ok().map(|p| p.web_url())
+}
+
+/// Whether two ways of writing a repository name the same one, such as
+/// `owner/repo`, `gh:owner/repo` and `https://github.com/owner/repo`
+pub fn same_repository(a: &str, b: &str) -> bool {
+    match (web_url(a), web_url(b)) {
+        (Some(a), Some(b)) => a.eq_ignore_ascii_case(&b),
+        _ => a.trim_end_matches('/') == b.trim_end_matches('/'),
+    }
+}
+
+/// The package name a repository spec installs under: its last path segment
+pub fn repository_name(spec: &str) -> Option<&str> {
+    if !is_repository_spec(spec) {
+        return None;
+    }
+    spec.trim_end_matches('/')
+        .trim_end_matches(".git")
+        .rsplit('/')
+        .next()
+}
+
+/// Picks the provider for a package's `repository`.
+///
+/// `owner/repo` and `github.com/owner/repo` use GitHub. Other hosts are GitHub
+/// Enterprise, GitLab or Gitea when the host says so (`github.example.com`,
+/// `gitlab.example.com`, `codeberg.org`, `forgejo.example.com`); a `ghe:`,
+/// `gitlab:`, `gitea:` or `forgejo:` prefix forces the provider for instances
+/// on any host.
+pub fn for_repository(repository: &str) -> Result<Box<dyn ReleaseProvider>> {
+    let (kind, rest) = match repository.split_once(':') {
+        Some((kind, rest)) if !rest.starts_with("//") => (Some(kind), rest),
+        _ => (None, repository),
+    };
+    let rest = rest
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_end_matches('/')
+        .trim_end_matches(".git");
+
+    let (host, path) = match rest.split_once('/') {
+        Some((host, path)) if host.contains('.') => (Some(host), path),
+        _ => (None, rest),
+    };
+
+    let invalid = || GripError::RegistryError(format!("Invalid repository '{}'", repository));
+    if path.split('/').count() < 2 || path.split('/').any(str::is_empty) {
+        return Err(invalid());
+    }
+
+    match (kind, host) {
+        (Some("gitlab"), Some(host)) => Ok(gitlab(host, path)),
+        (Some("gitlab"), None) => Ok(gitlab("gitlab.com", path)),
+        (Some("gh" | "github") | None, None | Some("github.com")) => {
+            Ok(github(&github_api_url(), "github.com", path))
+        }
+        // GitHub Enterprise Server serves its API under /api/v3 on the instance host
+        (Some("gh" | "github" | "ghe"), Some(host)) => {
+            Ok(github(&format!("https://{}/api/v3", host), host, path))
+        }
+        (Some("gitea" | "forgejo"), Some(host)) => Ok(gitea(host, path)),
+        (Some("codeberg"), None) => Ok(gitea("codeberg.org", path)),
+        (None, Some(host)) if has_label(host, &["gitlab"]) => Ok(gitlab(host, path)),
+        (None, Some(host)) if has_label(host, &["github"]) => {
+            Ok(github(&format!("https://{}/api/v3", host), host, path))
+        }
+        (None, Some(host)) if has_label(host, &["gitea", "forgejo", "codeberg"]) => {
+            Ok(gitea(host, path))
+        }
+        (Some(kind), _) => Err(GripError::RegistryError(format!(
+            "Unknown release provider '{}' in '{}'",
+            kind, repository
+        ))),
+        (None, Some(_)) => Err(GripError::RegistryError(format!(
+            "Cannot tell which release provider hosts '{}'; prefix it with `ghe:`, `gitlab:` or `gitea:`",
+            repository
+        ))),
+    }
+}
+
+fn has_label(host: &str, labels: &[&str]) -> bool {
+    host.split('.').any(|label| labels.contains(&label))
+}
+
+fn github(api_base: &str, host: &str, repo: &str) -> Box<dyn ReleaseProvider> {
+    Box::new(GitHub {
+        api_base: api_base.to_string(),
+        host: host.to_string(),
+        repo: repo.to_string(),
+    })
+}
+
+fn gitlab(host: &str, project: &str) -> Box<dyn ReleaseProvider> {
+    Box::new(GitLab {
+        host: host.to_string(),
+        project: project.to_string(),
+    })
+}
+
+fn gitea(host: &str, repo: &str) -> Box<dyn ReleaseProvider> {
+    Box::new(Gitea {
+        host: host.to_string(),
+        repo: repo.to_string(),
+    })
+}
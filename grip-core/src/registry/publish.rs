@@ -0,0 +1,357 @@
+use super::provider;
+use super::validate::{self, Severity};
+use super::{packages_dir, RegistryManager};
+use crate::config::{Registry, RegistrySource};
+use crate::error::{GripError, Result};
+use crate::ui;
+use base64::Engine;
+use colored::Colorize;
+use reqwest::{Method, StatusCode};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+
+// Forks are created in the background, so pushing to a new one may fail at first
+const FORK_PUSH_ATTEMPTS: u32 = 5;
+const FORK_PUSH_DELAY: Duration = Duration::from_secs(3);
+
+/// Where `grip publish` put a package definition
+#[derive(Debug)]
+pub enum Published {
+    /// Written into a registry directory on disk
+    Written(PathBuf),
+    /// Committed and pushed to the registry's repository
+    Pushed,
+    /// Proposed in a pull request, at this URL
+    PullRequest(String),
+}
+
+/// The `owner/repo` of a registry hosted on github.com, which publishing can
+/// open pull requests against
+pub fn github_repository(registry: &Registry) -> Option<String> {
+    let RegistrySource::Git(url) = registry.source() else {
+        return None;
+    };
+    let url = reqwest::Url::parse(&url).ok()?;
+    if url.host_str()? != "github.com" {
+        return None;
+    }
+    owner_repo(url.path())
+}
+
+/// The `owner/repo` of a GitHub repository given as a URL, `github.com/owner/repo`,
+/// `gh:owner/repo` or just `owner/repo`
+pub fn parse_github_url(url: &str) -> Option<String> {
+    let path = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(url);
+    let path = path
+        .strip_prefix("github.com/")
+        .or_else(|| path.strip_prefix("www.github.com/"))
+        .or_else(|| path.strip_prefix("gh:"))
+        .or_else(|| path.strip_prefix("github:"))
+        .unwrap_or(path);
+    owner_repo(path)
+}
+
+fn owner_repo(path: &str) -> Option<String> {
+    let path = path.trim_matches('/').trim_end_matches(".git");
+    match path.split('/').collect::<Vec<_>>().as_slice() {
+        [owner, repo] if !owner.is_empty() && !repo.is_empty() && !owner.contains(':') => {
+            Some(format!("{}/{}", owner, repo))
+        }
+        _ => None,
+    }
+}
+
+impl RegistryManager {
+    /// A GitHub repository's description, homepage and topics, among others
+    pub async fn github_metadata(&self, repo: &str) -> Result<Value> {
+        let body = self.fetch_text(&format!("{}/repos/{}", provider::github_api_url(), repo)).await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Adds or replaces `name`'s definition in a registry. A directory on disk
+    /// is written in place; a git registry gets a commit pushed to it when
+    /// `push` is set, or otherwise a pull request from a fork, which needs the
+    /// registry to be on GitHub. Nothing is submitted if the definition would
+    /// add problems to the registry
+    pub async fn publish(&self, registry: &Registry, name: &str, definition: &str, push: bool) -> Result<Published> {
+        let url = match registry.source() {
+            RegistrySource::Local(path) => {
+                let (file, _) = write_checked(&path, name, definition)?;
+                return Ok(Published::Written(file));
+            }
+            RegistrySource::Git(url) => url,
+        };
+        if self.offline {
+            return Err(GripError::Offline(format!("Publishing to {}", registry.name)));
+        }
+        let pull_request = match (push, github_repository(registry), &self.github_token) {
+            (true, ..) => None,
+            (false, Some(repo), Some(token)) => Some((repo, token.clone())),
+            (false, Some(_), None) => {
+                return Err(GripError::Usage(
+                    "Opening a pull request needs a GitHub token; set GITHUB_TOKEN or github_token in the config"
+                        .to_string(),
+                ))
+            }
+            (false, None, _) => {
+                return Err(GripError::Usage(format!(
+                    "Registry {} isn't on GitHub, so no pull request can be opened; pass --push if you can write to it",
+                    registry.name
+                )))
+            }
+        };
+
+        let checkout = tempfile::tempdir()?;
+        let path = checkout.path().to_string_lossy();
+        ui::status!("Cloning registry {}...", registry.name.cyan());
+        if !self
+            .git(registry, &["clone", "--quiet", "--depth", "1", &url, &path], None)
+            .await?
+        {
+            return Err(GripError::RegistryError(format!("Failed to clone registry {}", registry.name)));
+        }
+
+        let (file, replaced) = write_checked(checkout.path(), name, definition)?;
+        let message = format!("{} {}", if replaced { "Update" } else { "Add" }, name);
+
+        match pull_request {
+            Some((repo, token)) => self
+                .open_pull_request(&repo, &token, checkout.path(), &file, &message)
+                .await
+                .map(Published::PullRequest),
+            None => {
+                git_commit(checkout.path(), &file, &message, None).await?;
+                ui::status!("Pushing to {}...", registry.name.cyan());
+                if !self
+                    .git(registry, &["push", "--quiet", "origin", "HEAD"], Some(checkout.path()))
+                    .await?
+                {
+                    return Err(GripError::RegistryError(format!(
+                        "Failed to push to registry {}; check that you can write to it",
+                        registry.name
+                    )));
+                }
+                Ok(Published::Pushed)
+            }
+        }
+    }
+
+    /// Commits the definition and pushes it to a branch, of a fork unless the
+    /// token's user can push to the registry itself, then opens a pull request
+    /// for it. Publishing the same package again updates its open pull request
+    async fn open_pull_request(
+        &self,
+        repo: &str,
+        token: &str,
+        checkout: &Path,
+        file: &Path,
+        title: &str,
+    ) -> Result<String> {
+        let user = self.github_api(Method::GET, "/user", None).await?;
+        let login = user["login"].as_str().unwrap_or_default().to_string();
+        let upstream = self.github_api(Method::GET, &format!("/repos/{}", repo), None).await?;
+        let base = upstream["default_branch"].as_str().unwrap_or("main").to_string();
+
+        let head_repo = if upstream["permissions"]["push"].as_bool() == Some(true) {
+            repo.to_string()
+        } else {
+            ui::status!("Forking {}...", repo);
+            let fork = self
+                .github_api(
+                    Method::POST,
+                    &format!("/repos/{}/forks", repo),
+                    Some(serde_json::json!({ "default_branch_only": true })),
+                )
+                .await?;
+            fork["full_name"].as_str().unwrap_or_default().to_string()
+        };
+        let head_owner = head_repo.split('/').next().unwrap_or(&login).to_string();
+
+        // Credited to the token's user when git has no identity configured
+        let email = format!("{}+{}@users.noreply.github.com", user["id"], login);
+        git_commit(checkout, file, title, Some((&login, &email))).await?;
+
+        let name = file.file_stem().unwrap_or_default().to_string_lossy();
+        let branch = format!("grip-publish/{}", name);
+        let remote = format!("https://github.com/{}.git", head_repo);
+        let refspec = format!("HEAD:refs/heads/{}", branch);
+        let credentials = base64::engine::general_purpose::STANDARD.encode(format!("x-access-token:{}", token));
+        let config = self.git_args_with_auth(Some(format!("Basic {}", credentials)));
+        ui::status!("Pushing {} to {}...", branch, head_repo);
+        let mut attempt = 1;
+        loop {
+            match run_git(checkout, &config, &["push", "--quiet", "--force", &remote, &refspec]).await {
+                Ok(()) => break,
+                Err(e) if attempt >= FORK_PUSH_ATTEMPTS || head_repo == repo => return Err(e),
+                Err(e) => {
+                    tracing::debug!(attempt, error = %e, "fork not ready for pushing");
+                    attempt += 1;
+                    tokio::time::sleep(FORK_PUSH_DELAY).await;
+                }
+            }
+        }
+
+        let head = format!("{}:{}", head_owner, branch);
+        let (status, pull) = self
+            .github_send(
+                Method::POST,
+                &format!("/repos/{}/pulls", repo),
+                Some(serde_json::json!({
+                    "title": title,
+                    "head": head,
+                    "base": base,
+                    "body": "Submitted with `grip publish`.",
+                    "maintainer_can_modify": true,
+                })),
+            )
+            .await?;
+        if status.is_success() {
+            return Ok(pull["html_url"].as_str().unwrap_or_default().to_string());
+        }
+
+        // The force push already updated an open pull request for this branch
+        if status == StatusCode::UNPROCESSABLE_ENTITY {
+            let existing = self
+                .github_api(
+                    Method::GET,
+                    &format!("/repos/{}/pulls?state=open&head={}", repo, head),
+                    None,
+                )
+                .await?;
+            if let Some(url) = existing[0]["html_url"].as_str() {
+                return Ok(url.to_string());
+            }
+        }
+        Err(github_error(status, "/pulls", &pull))
+    }
+
+    /// Calls the GitHub API with the configured token, failing on error statuses
+    async fn github_api(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value> {
+        let (status, response) = self.github_send(method, path, body).await?;
+        if !status.is_success() {
+            return Err(github_error(status, path, &response));
+        }
+        Ok(response)
+    }
+
+    async fn github_send(&self, method: Method, path: &str, body: Option<Value>) -> Result<(StatusCode, Value)> {
+        let url = format!("{}{}", provider::github_api_url(), path);
+        let mut request = self
+            .client
+            .request(method.clone(), &url)
+            .header("Accept", "application/vnd.github+json")
+            .timeout(self.network.timeout());
+        if let Some(token) = &self.github_token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+
+        tracing::debug!(%method, url, "GitHub API");
+        let response = request.send().await?;
+        tracing::debug!(url, status = %response.status(), "response");
+        self.check_rate_limit(&response)?;
+        let status = response.status();
+        let body = response.json().await.unwrap_or(Value::Null);
+        Ok((status, body))
+    }
+}
+
+fn github_error(status: StatusCode, path: &str, body: &Value) -> GripError {
+    let mut message = body["message"].as_str().unwrap_or_default().to_string();
+    if let Some(detail) = body["errors"][0]["message"].as_str() {
+        message = format!("{}: {}", message, detail);
+    }
+    GripError::RegistryError(format!("GitHub API error ({}) on {}: {}", status, path, message))
+}
+
+/// Writes a definition into a registry directory, restoring what was there
+/// before if that introduces errors. Returns the file and whether it replaced
+/// an existing definition
+fn write_checked(dir: &Path, name: &str, definition: &str) -> Result<(PathBuf, bool)> {
+    let before = validate::validate(dir)?;
+    let file = packages_dir(dir).join(format!("{}.json", name));
+    let previous = std::fs::read(&file).ok();
+    if previous.as_deref() == Some(definition.as_bytes()) {
+        return Err(GripError::Usage(format!("The registry already has this definition of {}", name)));
+    }
+    std::fs::write(&file, definition)?;
+
+    // Problems the registry already had aren't this definition's to fix
+    let introduced: Vec<_> = validate::validate(dir)?
+        .into_iter()
+        .filter(|problem| !before.contains(problem))
+        .collect();
+    for problem in introduced.iter().filter(|p| p.severity == Severity::Warning) {
+        ui::warning!("{}", problem.message);
+    }
+    let errors: Vec<_> = introduced
+        .iter()
+        .filter(|p| p.severity == Severity::Error)
+        .map(|p| format!("\n  {}", p.message))
+        .collect();
+    if errors.is_empty() {
+        return Ok((file, previous.is_some()));
+    }
+
+    match previous {
+        Some(content) => std::fs::write(&file, content)?,
+        None => std::fs::remove_file(&file)?,
+    }
+    Err(GripError::RegistryError(format!(
+        "{} can't be added to the registry:{}",
+        name,
+        errors.concat()
+    )))
+}
+
+async fn git_has_identity(dir: &Path) -> bool {
+    Command::new("git")
+        .args(["config", "user.email"])
+        .current_dir(dir)
+        .output()
+        .await
+        .is_ok_and(|output| output.status.success() && !output.stdout.trim_ascii().is_empty())
+}
+
+/// Commits `file`, as `fallback_identity` when git has no user configured
+async fn git_commit(dir: &Path, file: &Path, message: &str, fallback_identity: Option<(&str, &str)>) -> Result<()> {
+    run_git(dir, &[], &["add", &file.to_string_lossy()]).await?;
+    let mut config = Vec::new();
+    if let Some((name, email)) = fallback_identity {
+        if !git_has_identity(dir).await {
+            config.extend([
+                "-c".to_string(),
+                format!("user.name={}", name),
+                "-c".to_string(),
+                format!("user.email={}", email),
+            ]);
+        }
+    }
+    run_git(dir, &config, &["commit", "--quiet", "-m", message]).await
+}
+
+/// Runs git with `-c` settings in `config`, failing with its error output
+async fn run_git(dir: &Path, config: &[String], args: &[&str]) -> Result<()> {
+    tracing::debug!(?args, ?dir, "running git");
+    let output = Command::new("git")
+        .args(config)
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let message = if stderr.trim().is_empty() { stdout } else { stderr };
+        return Err(GripError::RegistryError(format!("git {} failed: {}", args[0], message.trim())));
+    }
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use crate::dependency::Dependency;
+use crate::error::{GripError, Result};
+use crate::registry::{packages_dir, provider};
+use crate::template::{self, Template};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The JSON Schema package definitions are checked against, also written into
+/// new registries so editors can validate as you type
+pub const PACKAGE_SCHEMA: &str = include_str!("../../schema/package.schema.json");
+
+/// Optional registry metadata at the registry root
+pub const REGISTRY_FILE: &str = "registry.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Something wrong with one file of a registry
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Problem {
+    pub file: PathBuf,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Checks a single package definition on its own: against the schema, that
+/// grip can fetch releases from its repository, compile its attestation
+/// identity pattern and make sense of its asset patterns
+pub fn check_definition(definition: &Value) -> Result<Vec<String>> {
+    Ok(definition_problems(&package_validator()?, definition))
+}
+
+fn package_validator() -> Result<jsonschema::Validator> {
+    let schema: Value = serde_json::from_str(PACKAGE_SCHEMA)?;
+    jsonschema::validator_for(&schema)
+        .map_err(|e| GripError::Other(format!("Invalid package schema: {}", e)))
+}
+
+fn definition_problems(validator: &jsonschema::Validator, definition: &Value) -> Vec<String> {
+    let mut problems: Vec<String> = validator
+        .iter_errors(definition)
+        .map(|e| match e.instance_path().as_str() {
+            "" => e.to_string(),
+            path => format!("{}: {}", path, e),
+        })
+        .collect();
+
+    // Missing or mistyped fields were already reported by the schema
+    if let Some(repository) = definition["repository"].as_str() {
+        if let Err(e) = provider::for_repository(repository) {
+            problems.push(e.to_string());
+        }
+    }
+    if let Some(identity) = definition["attestations"]["identity"].as_str() {
+        if let Err(e) = regex::Regex::new(identity) {
+            problems.push(format!("attestations.identity is not a valid pattern: {}", e));
+        }
+    }
+    // Regex syntax would be matched literally and never find an asset
+    for (platform, settings) in definition["platforms"].as_object().into_iter().flatten() {
+        if let Some(asset) = settings["asset"].as_str() {
+            if asset.contains(['^', '$', '(', '[', '+', '|', '\\']) {
+                problems.push(format!(
+                    "platforms.{}.asset looks like a regular expression; asset patterns only support * and ?",
+                    platform
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Checks every package definition in a registry directory with
+/// [`check_definition`], and across definitions: file names matching package
+/// names, duplicate names and dependencies missing from the registry
+pub fn validate(dir: &Path) -> Result<Vec<Problem>> {
+    if !dir.is_dir() {
+        return Err(GripError::RegistryError(format!("{} is not a directory", dir.display())));
+    }
+    let validator = package_validator()?;
+
+    let mut problems = Vec::new();
+    let error = |file: &Path, message: String| Problem {
+        file: file.to_path_buf(),
+        severity: Severity::Error,
+        message,
+    };
+
+    let registry_file = dir.join(REGISTRY_FILE);
+    if registry_file.is_file() {
+        let metadata: Option<Value> = std::fs::read_to_string(&registry_file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        if !metadata.is_some_and(|m| m.get("name").is_some_and(Value::is_string)) {
+            problems.push(error(&registry_file, "must be a JSON object with a \"name\"".to_string()));
+        }
+    }
+
+    let packages_path = packages_dir(dir);
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&packages_path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<_>>()?;
+    files.retain(|path| {
+        path.extension().is_some_and(|ext| ext == "json")
+            && path.file_name().is_some_and(|name| name != REGISTRY_FILE)
+    });
+    files.sort();
+
+    let mut definitions = Vec::new();
+    for file in &files {
+        let definition: Value = match std::fs::read_to_string(file)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+        {
+            Ok(definition) => definition,
+            Err(e) => {
+                problems.push(error(file, format!("not valid JSON: {}", e)));
+                continue;
+            }
+        };
+
+        problems.extend(
+            definition_problems(&validator, &definition)
+                .into_iter()
+                .map(|message| error(file, message)),
+        );
+        definitions.push((file, definition));
+    }
+
+    let mut names: HashMap<String, &Path> = HashMap::new();
+    for (file, definition) in &definitions {
+        let Some(name) = definition["name"].as_str() else {
+            continue;
+        };
+        let stem = file.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        if name != stem {
+            problems.push(error(
+                file,
+                format!("name \"{}\" doesn't match the file name; grip looks packages up by file name", name),
+            ));
+        }
+        // Case-insensitive file systems can't hold both
+        if let Some(first) = names.insert(name.to_lowercase(), file) {
+            problems.push(error(
+                file,
+                format!(
+                    "duplicate package name \"{}\", also defined in {}",
+                    name,
+                    first.file_name().unwrap_or_default().to_string_lossy()
+                ),
+            ));
+        }
+    }
+
+    for (file, definition) in &definitions {
+        for spec in definition["dependencies"].as_array().into_iter().flatten() {
+            let Some(spec) = spec.as_str() else {
+                continue;
+            };
+            let dependency = Dependency::parse(spec);
+            if !names.contains_key(&dependency.name.to_lowercase()) {
+                problems.push(Problem {
+                    file: file.to_path_buf(),
+                    severity: Severity::Warning,
+                    message: format!(
+                        "dependency \"{}\" isn't in this registry; it must come from another one",
+                        dependency.name
+                    ),
+                });
+            }
+        }
+    }
+
+    let templates_path = dir.join(template::TEMPLATES_DIR);
+    if templates_path.is_dir() {
+        let mut templates: Vec<PathBuf> = std::fs::read_dir(&templates_path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<_>>()?;
+        templates.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+        templates.sort();
+        for file in templates {
+            let parsed = std::fs::read_to_string(&file)
+                .map_err(|e| e.to_string())
+                .and_then(|content| serde_json::from_str::<Template>(&content).map_err(|e| e.to_string()));
+            if let Err(e) = parsed {
+                problems.push(error(&file, format!("not a valid template: {}", e)));
+            }
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Lays out a new registry: a README, `registry.json`, the package schema and
+/// an example package to start from
+pub fn create(dir: &Path, name: &str) -> Result<()> {
+    if dir.exists() && std::fs::read_dir(dir)?.next().is_some() {
+        return Err(GripError::Usage(format!("{} already exists and is not empty", dir.display())));
+    }
+    std::fs::create_dir_all(dir.join("packages"))?;
+    std::fs::create_dir_all(dir.join("schema"))?;
+
+    std::fs::write(
+        dir.join("README.md"),
+        format!(
+            "# {name}\n\n\
+             A [grip](https://github.com/Grip-Packages/Grip) package registry.\n\n\
+             ```bash\n\
+             grip registry add {name} github.com/<owner>/<repo>\n\
+             ```\n\n\
+             Each package is a JSON file in `packages/` named after the package, described by\n\
+             `schema/package.schema.json`. Check changes with `grip registry validate` before\n\
+             publishing them.\n"
+        ),
+    )?;
+    std::fs::write(
+        dir.join(REGISTRY_FILE),
+        serde_json::to_string_pretty(&serde_json::json!({
+            "name": name,
+            "description": "",
+        }))?,
+    )?;
+    std::fs::write(dir.join("schema").join("package.schema.json"), PACKAGE_SCHEMA)?;
+    std::fs::write(
+        dir.join("packages").join("example.json"),
+        serde_json::to_string_pretty(&serde_json::json!({
+            "$schema": "../schema/package.schema.json",
+            "name": "example",
+            "description": "An example package; replace or delete it",
+            "repository": "owner/example",
+            "executable_name": "example",
+            "tags": ["example"],
+        }))?,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use crate::error::{GripError, Result};
+use crate::package::Release;
+use semver::{Version, VersionReq};
+
+/// Parses a release tag into a semantic version, tolerating `v` prefixes,
+/// project-name prefixes (`tool-1.2.0`) and missing minor or patch numbers
+pub fn parse_tag(tag: &str) -> Option<Version> {
+    let start = tag.char_indices().find_map(|(i, c)| {
+        let at_boundary = tag[..i]
+            .chars()
+            .next_back()
+            .is_none_or(|prev| !prev.is_ascii_alphanumeric() || prev == 'v' || prev == 'V');
+        (c.is_ascii_digit() && at_boundary).then_some(i)
+    })?;
+    let candidate = &tag[start..];
+
+    if let Ok(version) = Version::parse(candidate) {
+        return Some(version);
+    }
+
+    // Pad `1` and `1.2` out to full versions, keeping any pre-release suffix
+    let (core, rest) = match candidate.find(['-', '+']) {
+        Some(i) => candidate.split_at(i),
+        None => (candidate, ""),
+    };
+    let parts = core.split('.').count();
+    if parts >= 3 || !core.split('.').all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    let padded = format!("{}{}{}", core, ".0".repeat(3 - parts), rest);
+    Version::parse(&padded).ok()
+}
+
+fn strip_v(requirement: &str) -> String {
+    let mut normalized = String::with_capacity(requirement.len());
+    let mut chars = requirement.chars().peekable();
+    while let Some(c) = chars.next() {
+        let next_is_digit = chars.peek().is_some_and(|n| n.is_ascii_digit());
+        if (c == 'v' || c == 'V') && next_is_digit {
+            continue;
+        }
+        normalized.push(c);
+    }
+    normalized
+}
+
+fn matches(requirement: &VersionReq, version: &Version, pre: bool) -> bool {
+    if requirement.matches(version) {
+        return true;
+    }
+    // semver only lets pre-releases satisfy comparators naming the same
+    // pre-release, so with --pre compare them by their release version
+    pre && !version.pre.is_empty() && {
+        let mut release = version.clone();
+        release.pre = semver::Prerelease::EMPTY;
+        requirement.matches(&release)
+    }
+}
+
+/// Whether an installed tag satisfies `requirement`, by the same rules
+/// [`resolve`] uses to pick a release (without pre-releases)
+pub fn satisfies(tag: &str, requirement: &str) -> bool {
+    if tag == requirement {
+        return true;
+    }
+    let Some(version) = parse_tag(tag) else {
+        return false;
+    };
+    let normalized = strip_v(requirement.trim());
+    if let Ok(exact) = Version::parse(&normalized) {
+        return version == exact;
+    }
+    VersionReq::parse(&normalized).is_ok_and(|req| matches(&req, &version, false))
+}
+
+/// Picks the release satisfying `requirement`.
+///
+/// An exact tag name always wins. A plain version such as `1.2.3` selects that
+/// version regardless of tag prefix, and a range such as `^1.2`, `~0.5.3` or
+/// `>=2,<3` selects the highest matching release. Pre-releases are only
+/// considered when `pre` is set.
+pub fn resolve<'a>(releases: &'a [Release], requirement: &str, pre: bool) -> Result<&'a Release> {
+    if let Some(release) = releases.iter().find(|r| r.tag_name == requirement) {
+        return Ok(release);
+    }
+
+    let normalized = strip_v(requirement.trim());
+    let not_found = || GripError::VersionNotFound(requirement.to_string());
+
+    let candidates = releases
+        .iter()
+        .filter(|r| !r.draft)
+        .filter_map(|r| parse_tag(&r.tag_name).map(|v| (r, v)));
+
+    if let Ok(exact) = Version::parse(&normalized) {
+        return candidates
+            .into_iter()
+            .find(|(_, v)| *v == exact)
+            .map(|(r, _)| r)
+            .ok_or_else(&not_found);
+    }
+
+    let version_req = VersionReq::parse(&normalized)
+        .map_err(|e| GripError::Usage(format!("Invalid version requirement '{}': {}", requirement, e)))?;
+
+    candidates
+        .filter(|(r, v)| (pre || (!r.prerelease && v.pre.is_empty())) && matches(&version_req, v, pre))
+        .max_by(|(_, a), (_, b)| a.cmp(b))
+        .map(|(r, _)| r)
+        .ok_or_else(not_found)
+}
@@ -0,0 +1,205 @@
+use crate::dependency::Dependency;
+use crate::registry::provider;
+use crate::state::InstalledPackage;
+use crate::utils;
+use serde_json::{json, Value};
+
+/// The bill of materials formats `grip sbom` writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5, as JSON
+    CycloneDx,
+    /// SPDX 2.3, as JSON
+    Spdx,
+}
+
+/// Describes the installed packages, sorted by name, as a software bill of
+/// materials: each package's active version, where it came from and the
+/// digest of the asset it was installed from
+pub fn generate(packages: &[(&String, &InstalledPackage)], format: SbomFormat, timestamp: u64) -> Value {
+    let mut packages = packages.to_vec();
+    packages.sort_by(|a, b| a.0.cmp(b.0));
+    match format {
+        SbomFormat::CycloneDx => cyclonedx(&packages, timestamp),
+        SbomFormat::Spdx => spdx(&packages, timestamp),
+    }
+}
+
+fn cyclonedx(packages: &[(&String, &InstalledPackage)], timestamp: u64) -> Value {
+    let components: Vec<Value> = packages
+        .iter()
+        .map(|(name, package)| {
+            let mut component = json!({
+                "type": "application",
+                "bom-ref": name,
+                "name": name,
+                "version": package.version,
+                "purl": purl(name, package),
+            });
+            if let Some(sha256) = &package.sha256 {
+                component["hashes"] = json!([{ "alg": "SHA-256", "content": sha256 }]);
+            }
+            if let Some(license) = &package.license {
+                component["licenses"] = json!([{ "expression": license }]);
+            }
+            let mut references = Vec::new();
+            if let Some(url) = &package.url {
+                references.push(json!({ "type": "distribution", "url": url }));
+            }
+            if let Some(web_url) = web_url(package) {
+                references.push(json!({ "type": "vcs", "url": web_url }));
+            }
+            if !references.is_empty() {
+                component["externalReferences"] = Value::Array(references);
+            }
+            component
+        })
+        .collect();
+
+    let dependencies: Vec<Value> = packages
+        .iter()
+        .map(|(name, package)| {
+            json!({ "ref": name, "dependsOn": installed_dependencies(package, packages) })
+        })
+        .collect();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "timestamp": rfc3339(timestamp),
+            "tools": [{ "name": "grip", "version": env!("CARGO_PKG_VERSION") }],
+        },
+        "components": components,
+        "dependencies": dependencies,
+    })
+}
+
+fn spdx(packages: &[(&String, &InstalledPackage)], timestamp: u64) -> Value {
+    let spdx_id = |name: &str| {
+        let id: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '-' })
+            .collect();
+        format!("SPDXRef-Package-{}", id)
+    };
+
+    let spdx_packages: Vec<Value> = packages
+        .iter()
+        .map(|(name, package)| {
+            let mut spdx_package = json!({
+                "SPDXID": spdx_id(name),
+                "name": name,
+                "versionInfo": package.version,
+                "downloadLocation": package.url.as_deref().unwrap_or("NOASSERTION"),
+                "filesAnalyzed": false,
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": package.license.as_deref().unwrap_or("NOASSERTION"),
+                "copyrightText": "NOASSERTION",
+                "externalRefs": [{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": purl(name, package),
+                }],
+            });
+            if let Some(sha256) = &package.sha256 {
+                spdx_package["checksums"] = json!([{ "algorithm": "SHA256", "checksumValue": sha256 }]);
+            }
+            if let Some(web_url) = web_url(package) {
+                spdx_package["homepage"] = json!(web_url);
+            }
+            spdx_package
+        })
+        .collect();
+
+    let mut relationships = Vec::new();
+    for (name, package) in packages {
+        relationships.push(json!({
+            "spdxElementId": "SPDXRef-DOCUMENT",
+            "relationshipType": "DESCRIBES",
+            "relatedSpdxElement": spdx_id(name),
+        }));
+        for dependency in installed_dependencies(package, packages) {
+            relationships.push(json!({
+                "spdxElementId": spdx_id(name),
+                "relationshipType": "DEPENDS_ON",
+                "relatedSpdxElement": spdx_id(&dependency),
+            }));
+        }
+    }
+
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": "grip-installed-packages",
+        // Only has to be unique per document
+        "documentNamespace": format!(
+            "https://spdx.org/spdxdocs/grip-installed-packages-{}-{}",
+            timestamp,
+            std::process::id()
+        ),
+        "creationInfo": {
+            "created": rfc3339(timestamp),
+            "creators": [format!("Tool: grip-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": spdx_packages,
+        "relationships": relationships,
+    })
+}
+
+/// The package's registry dependencies that are themselves installed
+fn installed_dependencies(package: &InstalledPackage, packages: &[(&String, &InstalledPackage)]) -> Vec<String> {
+    package
+        .dependencies
+        .iter()
+        .map(|spec| Dependency::parse(spec).name)
+        .filter(|name| packages.iter().any(|(installed, _)| *installed == name))
+        .collect()
+}
+
+/// The forge page of the repository a package was installed from
+fn web_url(package: &InstalledPackage) -> Option<String> {
+    provider::web_url(package.repository.as_deref()?)
+}
+
+/// A package URL: `pkg:github/owner/repo@version` for GitHub projects, and a
+/// generic one pointing at the download otherwise
+fn purl(name: &str, package: &InstalledPackage) -> String {
+    let version = percent_encode(&package.version);
+    if let Some(web_url) = web_url(package) {
+        if let Some(repo) = web_url.strip_prefix("https://github.com/") {
+            return format!("pkg:github/{}@{}", repo.to_lowercase(), version);
+        }
+    }
+    let mut purl = format!("pkg:generic/{}@{}", percent_encode(name), version);
+    if let Some(url) = &package.url {
+        purl.push_str("?download_url=");
+        purl.push_str(&percent_encode(url));
+    }
+    purl
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Formats seconds since the Unix epoch as an RFC 3339 UTC timestamp, as in
+/// "2024-05-17T13:45:02Z"
+fn rfc3339(timestamp: u64) -> String {
+    let seconds = timestamp % 86_400;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        utils::format_date(timestamp),
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
@@ -0,0 +1,63 @@
+use crate::package::PackageInfo;
+
+/// Scores how well `candidate` matches `query` as a fuzzy subsequence, or
+/// returns `None` if the query's characters don't all appear in order.
+/// Consecutive and word-start matches score higher.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<u32> {
+    fuzzy_match(query, candidate).map(|(score, _)| score)
+}
+
+/// Like `fuzzy_score`, also returning the positions of the matched
+/// characters in `candidate`, for highlighting
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<(u32, Vec<usize>)> {
+    let query = query.to_lowercase();
+    let candidate = candidate.to_lowercase();
+    let candidate: Vec<char> = candidate.chars().collect();
+
+    let mut score = 0;
+    let mut position = 0;
+    let mut matched: Vec<usize> = Vec::new();
+
+    for q in query.chars() {
+        let found = candidate[position..].iter().position(|&c| c == q)? + position;
+
+        score += 1;
+        if matched.last().is_some_and(|&p| p + 1 == found) {
+            score += 3;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 2;
+        }
+
+        matched.push(found);
+        position = found + 1;
+    }
+
+    Some((score, matched))
+}
+
+/// Scores a package against a search query, favouring name matches over tag
+/// and description matches
+pub fn score_package(query: &str, info: &PackageInfo) -> Option<u32> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let name = info.name.to_lowercase();
+    let description = info.description.as_deref().unwrap_or("").to_lowercase();
+
+    if name == query {
+        Some(1000)
+    } else if name.starts_with(&query) {
+        Some(800)
+    } else if name.contains(&query) {
+        Some(600)
+    } else if info.tags.iter().any(|t| t.to_lowercase() == query) {
+        Some(500)
+    } else if description.contains(&query) {
+        Some(400)
+    } else {
+        fuzzy_score(&query, &name).map(|s| 100 + s)
+    }
+}
@@ -0,0 +1,162 @@
+use crate::config::{Config, Locations, Registry, Scope};
+use crate::error::Result;
+use crate::path::{self, Shell};
+use crate::{shim, ui, utils};
+use colored::Colorize;
+use dialoguer::{Input, Password, Select};
+use std::path::{Path, PathBuf};
+
+/// Walks through the settings a new user should know about: where packages
+/// are installed, how they get on PATH, a GitHub token and registries. Each
+/// question starts from the current setting, so running it again changes
+/// them. Nothing is written until every question is answered
+pub fn run(locations: &Locations) -> Result<()> {
+    let mut config = Config::read(&locations.config_file)?;
+    ui::status!("Setting grip up; the answers are saved to {}", config.file().display());
+
+    choose_install_root(&mut config, &locations.data_dir)?;
+    let root = config.install_root(Scope::User, &locations.data_dir)?;
+    let shell_setup = choose_path_strategy(&mut config, &root)?;
+    choose_github_token(&mut config)?;
+    choose_registries(&mut config)?;
+
+    config.save()?;
+    if let Some((rc_file, line)) = shell_setup {
+        path::add_setup(&rc_file, &line)?;
+        ui::success!("Added `{}` to {}; open a new terminal for it to take effect", line, rc_file.display());
+    }
+    ui::success!(
+        "Saved to {}; `grip setup` asks again, and `grip config set` changes single settings",
+        config.file().display()
+    );
+    Ok(())
+}
+
+/// Asks where packages go, keeping the data directory unless told otherwise
+fn choose_install_root(config: &mut Config, data_dir: &Path) -> Result<()> {
+    if std::env::var_os("GRIP_HOME").is_some() {
+        ui::status!("Packages are installed in {}, as GRIP_HOME says", data_dir.display());
+        return Ok(());
+    }
+    let current = config.install_root(Scope::User, data_dir)?;
+    let answer: String = Input::new()
+        .with_prompt("Where should packages be installed?")
+        .default(current.display().to_string())
+        .interact_text()?;
+    let answer = answer.trim();
+    let root = PathBuf::from(utils::expand_path(answer));
+    config.install_dir = if root == data_dir {
+        None
+    } else if root.is_absolute() {
+        Some(answer.to_string())
+    } else {
+        Some(std::path::absolute(root)?.display().to_string())
+    };
+    Ok(())
+}
+
+/// Asks how installed packages get on PATH: through a `grip shellenv` line
+/// in the shell's startup file, through PATH entries grip adds as installs
+/// need them, or not at all. Returns the startup file and line to add for
+/// the first
+fn choose_path_strategy(config: &mut Config, root: &Path) -> Result<Option<(PathBuf, String)>> {
+    let shell = Shell::detect().and_then(|shell| Some((shell, shell.rc_file()?)));
+    if let Some((_, rc_file)) = &shell {
+        if Shell::is_set_up(&std::fs::read_to_string(rc_file).unwrap_or_default()) {
+            ui::status!("{} already puts packages on PATH through `grip shellenv`", rc_file.display());
+            return Ok(None);
+        }
+    }
+
+    let shellenv = shell.map(|(shell, rc_file)| (rc_file, shell.setup_line(&[shim::bin_dir(root)])));
+    let mut choices = Vec::new();
+    if let Some((rc_file, line)) = &shellenv {
+        choices.push(format!(
+            "Add `{}` to {}, which puts grip's shims and package completions on PATH (recommended)",
+            line,
+            rc_file.display()
+        ));
+    }
+    choices.push(if cfg!(windows) {
+        "Add directories to the user PATH as installs need them".to_string()
+    } else {
+        "Add a PATH line to the shell's startup file for each directory installs need".to_string()
+    });
+    choices.push("Leave PATH to me".to_string());
+
+    let manual = choices.len() - 1;
+    let choice = Select::new()
+        .with_prompt("How should installed packages get on PATH?")
+        .items(&choices)
+        .default(if config.settings.modify_path { 0 } else { manual })
+        .interact()?;
+    config.settings.modify_path = choice != manual;
+    Ok(shellenv.filter(|_| choice == 0))
+}
+
+/// Asks for a GitHub token unless the environment provides one
+fn choose_github_token(config: &mut Config) -> Result<()> {
+    let from_env = ["GITHUB_TOKEN", "GH_TOKEN"]
+        .into_iter()
+        .find(|var| std::env::var(var).is_ok_and(|token| !token.is_empty()));
+    if let Some(var) = from_env {
+        ui::status!("Using the GitHub token in {}", var);
+        return Ok(());
+    }
+    let prompt = match config.github_token {
+        Some(_) => "GitHub token (leave empty to keep the current one)",
+        None => "GitHub token, which raises GitHub's API rate limit (leave empty to skip)",
+    };
+    let token = Password::new().with_prompt(prompt).allow_empty_password(true).interact()?;
+    if !token.trim().is_empty() {
+        config.github_token = Some(token.trim().to_string());
+    }
+    Ok(())
+}
+
+/// Shows the configured registries and asks for more
+fn choose_registries(config: &mut Config) -> Result<()> {
+    for registry in &config.registries {
+        ui::status!("Packages come from registry {} ({})", registry.name.cyan(), registry.url);
+    }
+    loop {
+        let url: String = Input::new()
+            .with_prompt("Add a registry? Its URL, or leave empty to finish")
+            .allow_empty(true)
+            .interact_text()?;
+        let url = url.trim();
+        if url.is_empty() {
+            return Ok(());
+        }
+        let registries = &config.registries;
+        let name: String = Input::new()
+            .with_prompt("Name for it")
+            .default(registry_name(url))
+            .validate_with(|name: &String| {
+                if registries.iter().any(|r| r.name == *name) {
+                    Err("a registry has that name already")
+                } else {
+                    Ok(())
+                }
+            })
+            .interact_text()?;
+        match Registry::new(&name, url, 0, None) {
+            Ok(registry) => {
+                ui::success!("Added registry {} ({})", name.cyan(), registry.url);
+                config.registries.push(registry);
+            }
+            Err(e) => ui::warning!("Couldn't add {}: {}", url, e),
+        }
+    }
+}
+
+/// A name for a registry, from the last part of its URL
+fn registry_name(url: &str) -> String {
+    url.trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .map(|name| name.trim_end_matches(".git"))
+        .filter(|name| !name.is_empty())
+        .unwrap_or("registry")
+        .to_string()
+}
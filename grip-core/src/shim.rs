@@ -0,0 +1,416 @@
+use crate::error::{GripError, Result};
+use std::path::{Path, PathBuf};
+
+/// Where Windows keeps hard links of multi-call executables under the names
+/// of their aliases, in the bin directory
+#[cfg(windows)]
+const MULTI_CALL_DIR: &str = ".multicall";
+
+/// The single directory grip adds to PATH; it holds a link or shim for every
+/// installed executable
+pub fn bin_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("bin")
+}
+
+/// Returns the name an executable is exposed as in the bin directory
+pub fn shim_name(executable: &Path) -> String {
+    let name = executable
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    #[cfg(windows)]
+    {
+        let stem = executable
+            .file_stem()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or(name);
+        format!("{}.cmd", stem)
+    }
+
+    #[cfg(not(windows))]
+    name
+}
+
+/// Points `bin_dir/<name>` at `executable`, replacing any existing link so
+/// switching versions is a single rename
+#[cfg(unix)]
+pub fn link(bin_dir: &Path, name: &str, executable: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(bin_dir)?;
+    let shim = bin_dir.join(name);
+    let temp = bin_dir.join(format!(".{}.tmp", name));
+
+    if temp.symlink_metadata().is_ok() {
+        std::fs::remove_file(&temp)?;
+    }
+    crate::utils::create_symlink(executable, &temp)?;
+    std::fs::rename(&temp, &shim)?;
+
+    Ok(shim)
+}
+
+/// Writes a `.cmd` shim forwarding to `executable`; symlinks on Windows need
+/// elevated privileges, while a batch file works from cmd.exe and PowerShell.
+/// The active `executable` is named in a comment for [`is_linked`], since an
+/// alias runs a hard link of it instead
+#[cfg(windows)]
+pub fn link(bin_dir: &Path, name: &str, executable: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(bin_dir)?;
+    let shim = bin_dir.join(name);
+    let temp = bin_dir.join(format!(".{}.tmp", name));
+    let program = multi_call_program(bin_dir, executable, Some(name));
+
+    std::fs::write(
+        &temp,
+        format!(
+            "@echo off\r\nrem grip shim, active version {}\r\n\"{}\" %*\r\n",
+            executable.display(),
+            program.display()
+        ),
+    )?;
+    std::fs::rename(&temp, &shim)?;
+
+    Ok(shim)
+}
+
+/// Writes a shim that runs `grip exec-shim`, which picks the version a
+/// version file pins for the current directory and otherwise the active
+/// one. The active `executable` is named in a comment for [`is_linked`].
+/// The shim passes the name it was run as, which grip gives the executable
+/// as its own, and replaces itself with grip, so grip's exit code and the
+/// signals sent to it are the executable's
+#[cfg(unix)]
+pub fn link_versioned(bin_dir: &Path, name: &str, package: &str, executable: &Path, grip: &Path) -> Result<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::create_dir_all(bin_dir)?;
+    let shim = bin_dir.join(name);
+    let temp = bin_dir.join(format!(".{}.tmp", name));
+    let quote = |s: &str| format!("'{}'", s.replace('\'', r"'\''"));
+    let file_name = executable.file_name().unwrap_or_default().to_string_lossy();
+
+    if temp.symlink_metadata().is_ok() {
+        std::fs::remove_file(&temp)?;
+    }
+    std::fs::write(
+        &temp,
+        format!(
+            "#!/bin/sh\n# grip shim for {}, active version {}\nexec {} exec-shim --arg0 \"$0\" {} {} \"$@\"\n",
+            package,
+            executable.display(),
+            quote(&grip.to_string_lossy()),
+            quote(package),
+            quote(&file_name)
+        ),
+    )?;
+    std::fs::set_permissions(&temp, std::fs::Permissions::from_mode(0o755))?;
+    std::fs::rename(&temp, &shim)?;
+
+    Ok(shim)
+}
+
+#[cfg(windows)]
+pub fn link_versioned(bin_dir: &Path, name: &str, package: &str, executable: &Path, grip: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(bin_dir)?;
+    let shim = bin_dir.join(name);
+    let temp = bin_dir.join(format!(".{}.tmp", name));
+    let file_name = executable.file_name().unwrap_or_default().to_string_lossy();
+
+    std::fs::write(
+        &temp,
+        format!(
+            "@echo off\r\nrem grip shim for {}, active version {}\r\n\"{}\" exec-shim --arg0 \"%~n0\" \"{}\" \"{}\" %*\r\n",
+            package,
+            executable.display(),
+            grip.display(),
+            package,
+            file_name
+        ),
+    )?;
+    std::fs::rename(&temp, &shim)?;
+
+    Ok(shim)
+}
+
+/// Whether `bin_dir/<name>` currently forwards to `executable`
+pub fn is_linked(bin_dir: &Path, name: &str, executable: &Path) -> bool {
+    let shim = bin_dir.join(name);
+
+    #[cfg(unix)]
+    return match std::fs::read_link(&shim) {
+        Ok(target) => target == executable,
+        Err(_) => std::fs::read_to_string(shim)
+            .is_ok_and(|content| content.contains(&format!("active version {}\n", executable.display()))),
+    };
+
+    #[cfg(windows)]
+    return std::fs::read_to_string(shim)
+        .is_ok_and(|content| content.contains(&executable.display().to_string()));
+}
+
+/// Whether `bin_dir/<name>` forwards to an executable under `dir`, so a
+/// package only removes links it still owns
+pub fn points_into(bin_dir: &Path, name: &str, dir: &Path) -> bool {
+    let shim = bin_dir.join(name);
+    if let Ok(target) = std::fs::read_link(&shim) {
+        return target.starts_with(dir);
+    }
+    let prefix = format!("{}{}", dir.display(), std::path::MAIN_SEPARATOR);
+    std::fs::read_to_string(shim).is_ok_and(|content| content.contains(&prefix))
+}
+
+/// Removes a link or shim from the bin directory if present
+pub fn unlink(bin_dir: &Path, name: &str) -> Result<()> {
+    let shim = bin_dir.join(name);
+    if shim.symlink_metadata().is_ok() {
+        std::fs::remove_file(shim)?;
+    }
+    #[cfg(windows)]
+    if let Ok(entries) = std::fs::read_dir(bin_dir.join(MULTI_CALL_DIR)) {
+        let stem = Path::new(name).file_stem();
+        for entry in entries.flatten() {
+            if entry.path().file_stem() == stem {
+                std::fs::remove_file(entry.path())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs `executable` in place of grip, for shims and `grip run`, with grip's
+/// standard streams, and returns its exit code for grip to exit with.
+///
+/// On Unix grip becomes the executable, so SIGINT, SIGTERM and the rest reach
+/// it directly and its exit status, a signal included, is the shim's. `arg0`
+/// is the name it sees itself run as, for multi-call executables such as
+/// busybox that act on it.
+#[cfg(unix)]
+pub fn exec(executable: &Path, arg0: Option<&str>, args: &[String]) -> Result<i32> {
+    use std::os::unix::process::CommandExt;
+
+    let mut command = std::process::Command::new(executable);
+    command.args(args);
+    if let Some(arg0) = arg0 {
+        command.arg0(arg0);
+    }
+    let error = command.exec();
+    Err(GripError::InstallError(format!("Failed to run {}: {}", executable.display(), error)))
+}
+
+/// Runs `executable` as a child of grip, which can't be replaced on Windows,
+/// for shims and `grip run`. Ctrl+C and Ctrl+Break reach every process on the
+/// console, so grip ignores them and leaves the executable to decide what
+/// they mean, then exits with its exit code. Windows has no name to run an
+/// executable under apart from its path; see [`multi_call_program`]
+#[cfg(windows)]
+pub fn exec(executable: &Path, _arg0: Option<&str>, args: &[String]) -> Result<i32> {
+    use winapi::shared::minwindef::{BOOL, DWORD, TRUE};
+
+    unsafe extern "system" fn leave_to_child(_: DWORD) -> BOOL {
+        TRUE
+    }
+    // A handler, unlike ignoring the events outright, isn't inherited
+    unsafe {
+        winapi::um::consoleapi::SetConsoleCtrlHandler(Some(leave_to_child), TRUE);
+    }
+    let status = std::process::Command::new(executable)
+        .args(args)
+        .status()
+        .map_err(|e| GripError::InstallError(format!("Failed to run {}: {}", executable.display(), e)))?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// The program to run for `executable` when it is run as `arg0`. Unix passes
+/// the name itself, so that is the executable
+#[cfg(unix)]
+pub fn multi_call_program(_bin_dir: &Path, executable: &Path, _arg0: Option<&str>) -> PathBuf {
+    executable.to_path_buf()
+}
+
+/// The program to run for `executable` when it is run as `arg0`: for an
+/// alias, such as `ls` for busybox, a hard link of the executable named after
+/// it, since multi-call executables on Windows read their name from their
+/// path. Falls back to the executable when the link can't be made, e.g.
+/// across drives
+#[cfg(windows)]
+pub fn multi_call_program(bin_dir: &Path, executable: &Path, arg0: Option<&str>) -> PathBuf {
+    let stem = |path: &Path| path.file_stem().map(|stem| stem.to_string_lossy().to_lowercase());
+    let Some(name) = arg0.map(Path::new).and_then(Path::file_stem) else {
+        return executable.to_path_buf();
+    };
+    if stem(executable) == stem(Path::new(name)) {
+        return executable.to_path_buf();
+    }
+    let mut link = bin_dir.join(MULTI_CALL_DIR).join(name);
+    link.set_extension(executable.extension().unwrap_or_default());
+    match refresh_hard_link(&link, executable) {
+        Ok(()) => link,
+        Err(e) => {
+            tracing::debug!(link = %link.display(), error = %e, "running the executable instead of a hard link");
+            executable.to_path_buf()
+        }
+    }
+}
+
+/// Points `link` at `executable` unless it already is. Hard links share the
+/// file's size and modification time, so those tell when it is
+#[cfg(windows)]
+fn refresh_hard_link(link: &Path, executable: &Path) -> std::io::Result<()> {
+    let stamp = |path: &Path| path.metadata().map(|m| (m.len(), m.modified().ok()));
+    if stamp(link).ok() == Some(stamp(executable)?) {
+        return Ok(());
+    }
+    if let Some(dir) = link.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let temp = link.with_extension("tmp");
+    let _ = std::fs::remove_file(&temp);
+    std::fs::hard_link(executable, &temp)?;
+    std::fs::rename(&temp, link)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn script(path: &Path, body: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::write(path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(unix)]
+    fn run(program: &Path, args: &[&str], stdin: &str) -> std::process::Output {
+        use std::io::Write;
+        let mut child = std::process::Command::new(program)
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_pass_through_name_arguments_streams_and_exit_code() {
+        let dir = tempfile::tempdir().unwrap();
+        let executable = dir.path().join("tool");
+        script(&executable, r#"echo "$(basename "$0") $*"; cat; exit 7"#);
+        let bin_dir = dir.path().join("bin");
+
+        let shim = link(&bin_dir, "alias", &executable).unwrap();
+        let output = run(&shim, &["a b", "-c"], "from stdin\n");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "alias a b -c\nfrom stdin\n");
+        assert_eq!(output.status.code(), Some(7));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn versioned_shims_hand_grip_their_name_arguments_and_streams() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let bin_dir = dir.path().join("bin");
+        let executable = dir.path().join("busybox");
+        script(&executable, "exit 1");
+        // Stands in for grip, showing how exec-shim is called
+        let grip = dir.path().join("grip's copy");
+        script(&grip, r#"printf '%s\n' "$@"; cat; [ "$6" = term ] && kill -TERM $$; exit 9"#);
+
+        let shim = link_versioned(&bin_dir, "ls", "busybox", &executable, &grip).unwrap();
+        let output = run(&shim, &["x y", "$HOME"], "from stdin\n");
+        let expected = format!(
+            "exec-shim\n--arg0\n{}\nbusybox\nbusybox\nx y\n$HOME\nfrom stdin\n",
+            shim.display()
+        );
+        assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+        assert_eq!(output.status.code(), Some(9));
+        assert!(is_linked(&bin_dir, "ls", &executable));
+
+        // No shell is left waiting in between to turn a signal into an exit code
+        let output = run(&shim, &["term"], "");
+        assert_eq!(output.status.signal(), Some(15));
+    }
+
+    /// Runs `exec` when started by the tests below, which it replaces. Not a
+    /// test of its own, so it only runs when asked for by name
+    #[cfg(unix)]
+    #[test]
+    #[ignore = "run by exec_in_helper in a child process"]
+    fn exec_helper() {
+        let Ok(args) = std::env::var("GRIP_TEST_EXEC") else {
+            return;
+        };
+        let args: Vec<String> = args.split('\n').map(str::to_string).collect();
+        let result = exec(Path::new("/bin/sh"), Some("applet"), &args);
+        panic!("exec returned {:?}", result);
+    }
+
+    #[cfg(unix)]
+    fn exec_in_helper(args: &[&str]) -> std::process::Output {
+        std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--ignored", "--exact", "shim::tests::exec_helper", "--nocapture", "--test-threads=1"])
+            .env("GRIP_TEST_EXEC", args.join("\n"))
+            .output()
+            .unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exec_replaces_grip_and_names_the_executable_arg0() {
+        use std::os::unix::process::ExitStatusExt;
+
+        // `sh -c` sets $0 to the name it was run as
+        let output = exec_in_helper(&["-c", r#"echo "ran as $0 with $1"; exit 5"#, "applet", "one"]);
+        assert!(String::from_utf8_lossy(&output.stdout).contains("ran as applet with one"));
+        assert_eq!(output.status.code(), Some(5));
+
+        let output = exec_in_helper(&["-c", "kill -INT $$; sleep 5"]);
+        assert_eq!(output.status.signal(), Some(2));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn cmd_shims_pass_through_exit_codes() {
+        let dir = tempfile::tempdir().unwrap();
+        let executable = dir.path().join("tool.cmd");
+        std::fs::write(&executable, "@echo %*\r\n@exit /b 7\r\n").unwrap();
+        let bin_dir = dir.path().join("bin");
+
+        let shim = link(&bin_dir, "tool.cmd", &executable).unwrap();
+        let output = std::process::Command::new(&shim).arg("one").output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "one");
+        assert_eq!(output.status.code(), Some(7));
+        assert!(is_linked(&bin_dir, "tool.cmd", &executable));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn exec_returns_the_exit_code() {
+        let args = ["/c".to_string(), "exit 5".to_string()];
+        assert_eq!(exec(Path::new("cmd"), None, &args).unwrap(), 5);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn aliases_run_a_hard_link_named_after_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let executable = dir.path().join("busybox.exe");
+        std::fs::write(&executable, b"MZ multi-call").unwrap();
+        let bin_dir = dir.path().join("bin");
+
+        assert_eq!(multi_call_program(&bin_dir, &executable, Some("busybox.cmd")), executable);
+        let program = multi_call_program(&bin_dir, &executable, Some("ls.cmd"));
+        assert_eq!(program, bin_dir.join(MULTI_CALL_DIR).join("ls.exe"));
+        assert_eq!(std::fs::read(&program).unwrap(), b"MZ multi-call");
+
+        link(&bin_dir, "ls.cmd", &executable).unwrap();
+        assert!(is_linked(&bin_dir, "ls.cmd", &executable));
+        assert!(points_into(&bin_dir, "ls.cmd", dir.path()));
+        unlink(&bin_dir, "ls.cmd").unwrap();
+        assert!(!program.exists());
+    }
+}
@@ -0,0 +1,129 @@
+use crate::error::{GripError, Result};
+use crate::package::{Asset, PublicKeys, Release};
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureKind {
+    Minisign,
+    Gpg,
+}
+
+impl SignatureKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SignatureKind::Minisign => "minisign",
+            SignatureKind::Gpg => "GPG",
+        }
+    }
+
+    /// The trusted key for this kind of signature, if the package declares one
+    pub fn key<'a>(&self, keys: &'a PublicKeys) -> Option<&'a str> {
+        match self {
+            SignatureKind::Minisign => keys.minisign.as_deref(),
+            SignatureKind::Gpg => keys.gpg.as_deref(),
+        }
+    }
+}
+
+/// Finds a detached signature for `asset_name` in the release, preferring kinds
+/// the package has a key for
+pub fn find_signature_asset<'a>(
+    release: &'a Release,
+    asset_name: &str,
+    keys: &PublicKeys,
+) -> Option<(&'a Asset, SignatureKind)> {
+    let candidates = [
+        (format!("{}.minisig", asset_name), SignatureKind::Minisign),
+        (format!("{}.sig", asset_name), SignatureKind::Gpg),
+        (format!("{}.asc", asset_name), SignatureKind::Gpg),
+    ];
+
+    let found: Vec<_> = candidates
+        .iter()
+        .filter_map(|(name, kind)| {
+            release
+                .assets
+                .iter()
+                .find(|a| a.name.eq_ignore_ascii_case(name))
+                .map(|a| (a, *kind))
+        })
+        .collect();
+
+    found
+        .iter()
+        .find(|(_, kind)| kind.key(keys).is_some())
+        .or_else(|| found.first())
+        .copied()
+}
+
+/// Parses a minisign public key, given either as the bare base64 key or the
+/// contents of a `minisign.pub` file
+pub fn minisign_public_key(key: &str) -> Result<minisign_verify::PublicKey> {
+    let key = key.trim();
+    if key.lines().count() > 1 {
+        minisign_verify::PublicKey::decode(key)
+    } else {
+        minisign_verify::PublicKey::from_base64(key)
+    }
+    .map_err(|e| GripError::SignatureError(format!("invalid minisign public key: {}", e)))
+}
+
+/// Checks a minisign signature against a public key, given either as the bare
+/// base64 key or the contents of a `minisign.pub` file
+pub fn verify_minisign(file: &Path, signature: &[u8], key: &str) -> Result<()> {
+    let public_key = minisign_public_key(key)?;
+
+    let signature = minisign_verify::Signature::decode(&String::from_utf8_lossy(signature))
+        .map_err(|e| GripError::SignatureError(format!("invalid minisign signature: {}", e)))?;
+
+    let data = std::fs::read(file)?;
+    public_key
+        .verify(&data, &signature, false)
+        .map_err(|e| GripError::SignatureError(format!("{}: {}", file.display(), e)))
+}
+
+/// Checks a detached GPG signature with `gpg`, trusting only `key` (an
+/// ASCII-armored public key) through a throwaway keyring
+pub fn verify_gpg(file: &Path, signature: &[u8], key: &str) -> Result<()> {
+    let home = tempfile::tempdir()?;
+    let key_file = home.path().join("key.asc");
+    let signature_file = home.path().join("asset.sig");
+    std::fs::write(&key_file, key)?;
+    std::fs::write(&signature_file, signature)?;
+
+    let gpg = |args: &[&std::ffi::OsStr]| {
+        Command::new("gpg")
+            .arg("--batch")
+            .arg("--homedir")
+            .arg(home.path())
+            .args(args)
+            .output()
+            .map_err(|e| GripError::SignatureError(format!("failed to run gpg: {}", e)))
+    };
+
+    let import = gpg(&["--import".as_ref(), key_file.as_os_str()])?;
+    if !import.status.success() {
+        return Err(GripError::SignatureError(format!(
+            "invalid GPG public key: {}",
+            String::from_utf8_lossy(&import.stderr).trim()
+        )));
+    }
+
+    let verify = gpg(&["--verify".as_ref(), signature_file.as_os_str(), file.as_os_str()])?;
+    if !verify.status.success() {
+        return Err(GripError::SignatureError(format!(
+            "{}: {}",
+            file.display(),
+            String::from_utf8_lossy(&verify.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+pub fn verify(kind: SignatureKind, file: &Path, signature: &[u8], key: &str) -> Result<()> {
+    match kind {
+        SignatureKind::Minisign => verify_minisign(file, signature, key),
+        SignatureKind::Gpg => verify_gpg(file, signature, key),
+    }
+}
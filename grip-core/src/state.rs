@@ -0,0 +1,392 @@
+use crate::attestation::Attestation;
+use crate::dependency::Dependency;
+use crate::error::{bail, GripError, Result};
+use crate::installer::InstallerRecord;
+use crate::lock::{self, FileLock};
+use crate::migrations;
+use crate::shim;
+use crate::storage::StateStore;
+use crate::ui;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{rename, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InstalledPackage {
+    pub version: String,
+    pub install_path: PathBuf,
+    /// The active version's linked executables, the package's main one first
+    #[serde(default)]
+    pub executables: Vec<PathBuf>,
+    pub installed_versions: Vec<String>,
+    #[serde(default)]
+    pub asset_name: Option<String>,
+    /// Repository the package was installed from
+    #[serde(default)]
+    pub repository: Option<String>,
+    /// Registry the package's definition came from, which upgrades look it up
+    /// in again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Held packages are skipped by `upgrade --all`
+    #[serde(default)]
+    pub pinned: bool,
+    /// How the most recently installed version's provenance was verified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<Attestation>,
+    /// The registry dependencies of the most recently installed version
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+    /// What a Windows installer the package ran registered, to undo on uninstall
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installer: Option<InstallerRecord>,
+    /// When the package was last installed, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installed_at: Option<u64>,
+    /// Disk space the installed versions take, in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Digest of the asset the active version was installed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Where the asset the active version was installed from was downloaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Extra names the main executable is linked as in the bin directory
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// SPDX license expression of the active version, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// The active version's completions and man pages linked into the share
+    /// directory, relative to it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extras: Vec<PathBuf>,
+}
+
+impl InstalledPackage {
+    /// The main executable, which `grip run` and `grip which` use
+    pub fn executable(&self) -> Option<&Path> {
+        self.executables.first().map(PathBuf::as_path)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackageState {
+    #[serde(default)]
+    schema_version: u32,
+    pub(crate) packages: HashMap<String, InstalledPackage>,
+}
+
+impl Default for PackageState {
+    fn default() -> Self {
+        Self {
+            schema_version: migrations::STATE_SCHEMA_VERSION,
+            packages: HashMap::new(),
+        }
+    }
+}
+
+impl PackageState {
+    /// Loads the state, upgrading files written by older versions of grip.
+    /// The original is kept as `package_state.json.v<N>.bak` before migrating.
+    /// A file that doesn't parse is replaced by the backup `save` keeps
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        let state_file = data_dir.join("package_state.json");
+        if !state_file.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&state_file)?;
+        let parse_error = |e| GripError::ConfigError(format!("Failed to parse {}: {}", state_file.display(), e));
+        let (content, mut value) = match serde_json::from_str::<serde_json::Value>(&content) {
+            Ok(value) => (content, value),
+            Err(e) => Self::recover(data_dir).ok_or_else(|| parse_error(e))?,
+        };
+
+        let version = migrations::state_version(&value);
+        if version != migrations::STATE_SCHEMA_VERSION {
+            let backup = data_dir.join(format!("package_state.json.v{}.bak", version));
+            if !backup.exists() {
+                std::fs::write(&backup, &content)?;
+            }
+            if version > migrations::STATE_SCHEMA_VERSION {
+                bail!(
+                    ConfigError,
+                    "{} was written by a newer grip (schema {}, this build understands {}); \
+                     upgrade grip to use it. A copy was saved to {}",
+                    state_file.display(),
+                    version,
+                    migrations::STATE_SCHEMA_VERSION,
+                    backup.display()
+                );
+            }
+            migrations::migrate_state(&mut value)?;
+        }
+
+        serde_json::from_value(value).map_err(parse_error)
+    }
+
+    /// The state saved before the last one, for when `package_state.json`
+    /// doesn't parse, as after a crash or a bad edit. Later changes are lost
+    fn recover(data_dir: &Path) -> Option<(String, serde_json::Value)> {
+        let backup = data_dir.join("package_state.json.bak");
+        let content = std::fs::read_to_string(&backup).ok()?;
+        let value = serde_json::from_str(&content).ok()?;
+        ui::warning!(
+            "{} is damaged; using the previous copy from {}. Changes made since may be missing",
+            data_dir.join("package_state.json").display(),
+            backup.display()
+        );
+        Some((content, value))
+    }
+
+    /// Writes the state through a temporary file that is flushed to disk
+    /// before replacing the original, so neither readers nor a crash can leave
+    /// it half written. The previous state is kept as `package_state.json.bak`,
+    /// written the same way
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        let state_file = data_dir.join("package_state.json");
+        let content = serde_json::to_string_pretty(self)?;
+
+        // A damaged state isn't worth keeping over the last good backup
+        if let Ok(previous) = std::fs::read(&state_file) {
+            if serde_json::from_slice::<serde::de::IgnoredAny>(&previous).is_ok() {
+                write_synced(&data_dir.join("package_state.json.bak"), &previous)?;
+            }
+        }
+        write_synced(&state_file, content.as_bytes())?;
+        Ok(())
+    }
+
+    /// Applies `f` to the state in `store` while holding the state lock, so
+    /// changes other grip processes made since this one loaded it are kept.
+    /// Only the packages `f` added, changed or removed are written. Returns
+    /// the new state; on failure nothing is written
+    pub fn update<T>(store: &dyn StateStore, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<(Self, T)> {
+        let _lock = FileLock::acquire(&lock::state_lock_path(store.data_dir()), "the package state")?;
+        let current = store.load()?;
+
+        let mut next = current.clone();
+        let value = f(&mut next)?;
+        for (name, package) in &next.packages {
+            if current.packages.get(name) != Some(package) {
+                store.put_package(name, package)?;
+            }
+        }
+        for name in current.packages.keys() {
+            if !next.packages.contains_key(name) {
+                store.remove_package(name)?;
+            }
+        }
+        Ok((next, value))
+    }
+
+    pub fn add_package(
+        &mut self,
+        name: String,
+        version: String,
+        install_path: PathBuf,
+        executables: Vec<PathBuf>,
+        asset_name: Option<String>,
+        repository: Option<String>,
+    ) {
+        if let Some(existing_package) = self.packages.get_mut(&name) {
+            if !existing_package.installed_versions.contains(&version) {
+                existing_package.installed_versions.push(version.clone());
+            }
+            existing_package.version = version;
+            existing_package.install_path = install_path;
+            existing_package.executables = executables;
+            existing_package.asset_name = asset_name;
+            existing_package.repository = repository;
+        } else {
+            self.packages.insert(
+                name,
+                InstalledPackage {
+                    version: version.clone(),
+                    install_path,
+                    executables,
+                    installed_versions: vec![version],
+                    asset_name,
+                    repository,
+                    registry: None,
+                    pinned: false,
+                    attestation: None,
+                    dependencies: Vec::new(),
+                    installer: None,
+                    installed_at: None,
+                    size: None,
+                    sha256: None,
+                    url: None,
+                    aliases: Vec::new(),
+                    license: None,
+                    extras: Vec::new(),
+                },
+            );
+        }
+    }
+
+    /// Makes an already installed version the active one
+    pub fn set_active(
+        &mut self,
+        name: &str,
+        version: &str,
+        install_path: PathBuf,
+        executables: Vec<PathBuf>,
+    ) -> Result<()> {
+        let package = self
+            .packages
+            .get_mut(name)
+            .ok_or_else(|| GripError::InstallError(format!("Package '{}' is not installed", name)))?;
+        package.version = version.to_string();
+        package.install_path = install_path;
+        package.executables = executables;
+        Ok(())
+    }
+
+    pub fn set_pinned(&mut self, name: &str, pinned: bool) -> Result<()> {
+        let package = self
+            .packages
+            .get_mut(name)
+            .ok_or_else(|| GripError::InstallError(format!("Package '{}' is not installed", name)))?;
+        package.pinned = pinned;
+        Ok(())
+    }
+
+    pub fn set_attestation(&mut self, name: &str, attestation: Option<Attestation>) {
+        if let Some(package) = self.packages.get_mut(name) {
+            package.attestation = attestation;
+        }
+    }
+
+    pub fn set_registry(&mut self, name: &str, registry: Option<String>) {
+        if let Some(package) = self.packages.get_mut(name) {
+            package.registry = registry;
+        }
+    }
+
+    pub fn set_installer(&mut self, name: &str, installer: Option<InstallerRecord>) {
+        if let Some(package) = self.packages.get_mut(name) {
+            package.installer = installer;
+        }
+    }
+
+    /// Records when the package was installed and the space it now takes
+    pub fn set_installed_at(&mut self, name: &str, installed_at: u64, size: u64) {
+        if let Some(package) = self.packages.get_mut(name) {
+            package.installed_at = Some(installed_at);
+            package.size = Some(size);
+        }
+    }
+
+    pub fn set_download(&mut self, name: &str, url: String, sha256: String) {
+        if let Some(package) = self.packages.get_mut(name) {
+            package.url = Some(url);
+            package.sha256 = Some(sha256);
+        }
+    }
+
+    pub fn set_license(&mut self, name: &str, license: Option<String>) {
+        if let Some(package) = self.packages.get_mut(name) {
+            package.license = license;
+        }
+    }
+
+    pub fn set_aliases(&mut self, name: &str, aliases: Vec<String>) {
+        if let Some(package) = self.packages.get_mut(name) {
+            package.aliases = aliases;
+        }
+    }
+
+    pub fn set_extras(&mut self, name: &str, extras: Vec<PathBuf>) {
+        if let Some(package) = self.packages.get_mut(name) {
+            package.extras = extras;
+        }
+    }
+
+    /// The installed package whose executable or alias is linked as `shim`
+    /// in the bin directory
+    pub fn shim_owner(&self, shim: &str) -> Option<&String> {
+        self.packages
+            .iter()
+            .find(|(_, package)| {
+                package.executables.iter().any(|e| shim::shim_name(e) == shim)
+                    || package.aliases.iter().any(|a| shim::shim_name(Path::new(a)) == shim)
+            })
+            .map(|(name, _)| name)
+    }
+
+    pub fn set_size(&mut self, name: &str, size: u64) {
+        if let Some(package) = self.packages.get_mut(name) {
+            package.size = Some(size);
+        }
+    }
+
+    pub fn set_dependencies(&mut self, name: &str, dependencies: Vec<String>) {
+        if let Some(package) = self.packages.get_mut(name) {
+            package.dependencies = dependencies;
+        }
+    }
+
+    /// Installed packages that depend on `name`
+    pub fn dependents(&self, name: &str) -> Vec<&String> {
+        let mut dependents: Vec<&String> = self
+            .packages
+            .iter()
+            .filter(|(other, package)| {
+                *other != name
+                    && package
+                        .dependencies
+                        .iter()
+                        .any(|d| Dependency::parse(d).name == name)
+            })
+            .map(|(other, _)| other)
+            .collect();
+        dependents.sort();
+        dependents
+    }
+
+    /// Forgets a version of a package that is no longer on disk
+    pub fn remove_version(&mut self, name: &str, version: &str) {
+        if let Some(package) = self.packages.get_mut(name) {
+            if package.version != version {
+                package.installed_versions.retain(|v| v != version);
+            }
+        }
+    }
+
+    pub fn remove_package(&mut self, name: &str) -> Option<InstalledPackage> {
+        self.packages.remove(name)
+    }
+
+    pub fn get_package(&self, name: &str) -> Option<&InstalledPackage> {
+        self.packages.get(name)
+    }
+
+    pub fn list_packages(&self) -> Vec<(&String, &InstalledPackage)> {
+        self.packages.iter().collect()
+    }
+}
+
+/// Replaces `path` with `content` through a flushed temporary file, then
+/// flushes the directory so the rename itself survives a power loss
+fn write_synced(path: &Path, content: &[u8]) -> Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_file = path.with_file_name(temp_name);
+    let mut file = File::create(&temp_file)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    drop(file);
+
+    rename(temp_file, path)?;
+    // Windows has no way to sync a directory
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,457 @@
+use crate::error::{bail, GripError, Result};
+use crate::history::{self, Action, Transaction};
+use crate::lock::{self, FileLock};
+use crate::migrations;
+use crate::state::{InstalledPackage, PackageState};
+use crate::ui;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+const DB_FILE: &str = "state.db";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS packages (
+    name TEXT PRIMARY KEY,
+    version TEXT NOT NULL,
+    record TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    package TEXT NOT NULL,
+    from_version TEXT,
+    to_version TEXT
+);
+CREATE INDEX IF NOT EXISTS history_package ON history (package, id);
+";
+
+/// Which [`StateStore`] keeps the package state and history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StateBackend {
+    /// `package_state.json` and `history.json`
+    #[default]
+    Json,
+    /// One SQLite database, `state.db`, which lookups query for the rows they
+    /// need rather than reading every package
+    Sqlite,
+}
+
+/// Keeps the record of installed packages and the log of changes to them.
+/// Changes to the state go through [`PackageState::update`], which holds the
+/// state lock around `load` and writes the packages that changed
+pub trait StateStore: Send + Sync {
+    /// The install root the state belongs to, where its lock is kept
+    fn data_dir(&self) -> &Path;
+
+    /// The file the state is kept in
+    fn path(&self) -> PathBuf;
+
+    fn load(&self) -> Result<PackageState>;
+
+    /// Adds or replaces the record of one package
+    fn put_package(&self, name: &str, package: &InstalledPackage) -> Result<()>;
+
+    /// Drops the record of one package
+    fn remove_package(&self, name: &str) -> Result<()>;
+
+    /// A `package_state.json` holding the current state, for plugins to read
+    fn export(&self) -> Result<PathBuf>;
+
+    fn package(&self, name: &str) -> Result<Option<InstalledPackage>>;
+
+    /// Installed packages whose name contains `filter`, ignoring case, by name
+    fn packages(&self, filter: Option<&str>) -> Result<Vec<(String, InstalledPackage)>>;
+
+    /// The log of `package`, or of every package, oldest first
+    fn history(&self, package: Option<&str>) -> Result<Vec<Transaction>>;
+
+    /// Appends a transaction to the log, dropping the oldest past the limit
+    fn record(&self, transaction: Transaction) -> Result<()>;
+}
+
+/// Opens the state of the install root `data_dir` kept by `backend`. After
+/// switching back to `json`, the state and history in `state.db` are first
+/// written back to the JSON files
+pub fn open(backend: StateBackend, data_dir: &Path) -> Result<Box<dyn StateStore>> {
+    Ok(match backend {
+        StateBackend::Json => {
+            if data_dir.join(DB_FILE).exists() {
+                SqliteStore::open(data_dir)?.hand_back()?;
+            }
+            Box::new(JsonStore::new(data_dir))
+        }
+        StateBackend::Sqlite => Box::new(SqliteStore::open(data_dir)?),
+    })
+}
+
+fn matches(name: &str, filter: Option<&str>) -> bool {
+    filter.is_none_or(|f| name.to_lowercase().contains(&f.to_lowercase()))
+}
+
+/// The state in `package_state.json` and the log in `history.json`. Every
+/// lookup reads the whole file
+pub struct JsonStore {
+    data_dir: PathBuf,
+}
+
+impl JsonStore {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            data_dir: data_dir.to_path_buf(),
+        }
+    }
+}
+
+impl StateStore for JsonStore {
+    fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    fn path(&self) -> PathBuf {
+        self.data_dir.join("package_state.json")
+    }
+
+    fn load(&self) -> Result<PackageState> {
+        PackageState::load(&self.data_dir)
+    }
+
+    fn put_package(&self, name: &str, package: &InstalledPackage) -> Result<()> {
+        let mut state = self.load()?;
+        state.packages.insert(name.to_string(), package.clone());
+        state.save(&self.data_dir)
+    }
+
+    fn remove_package(&self, name: &str) -> Result<()> {
+        let mut state = self.load()?;
+        state.packages.remove(name);
+        state.save(&self.data_dir)
+    }
+
+    fn export(&self) -> Result<PathBuf> {
+        Ok(self.path())
+    }
+
+    fn package(&self, name: &str) -> Result<Option<InstalledPackage>> {
+        Ok(self.load()?.packages.remove(name))
+    }
+
+    fn packages(&self, filter: Option<&str>) -> Result<Vec<(String, InstalledPackage)>> {
+        let mut packages: Vec<_> = self
+            .load()?
+            .packages
+            .into_iter()
+            .filter(|(name, _)| matches(name, filter))
+            .collect();
+        packages.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(packages)
+    }
+
+    fn history(&self, package: Option<&str>) -> Result<Vec<Transaction>> {
+        Ok(history::load(&self.data_dir)?
+            .into_iter()
+            .filter(|t| package.is_none_or(|name| t.package == name))
+            .collect())
+    }
+
+    fn record(&self, transaction: Transaction) -> Result<()> {
+        history::record(&self.data_dir, transaction)
+    }
+}
+
+/// The state and log in `state.db`, one row per package and per transaction.
+/// Packages are kept as their JSON record beside the columns lookups use, so
+/// the state migrations apply to them unchanged.
+///
+/// A new database starts with whatever `package_state.json` and
+/// `history.json` hold. Those files are only written again to export the state
+/// for plugins, or when switching back to `json` hands the state back to them
+pub struct SqliteStore {
+    data_dir: PathBuf,
+    path: PathBuf,
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(DB_FILE);
+        let connection = Connection::open(&path)?;
+        // Other grip processes hold the database for a moment at most
+        connection.busy_timeout(std::time::Duration::from_secs(30))?;
+        connection.execute_batch(SCHEMA)?;
+        let store = Self {
+            data_dir: data_dir.to_path_buf(),
+            path,
+            connection: Mutex::new(connection),
+        };
+
+        if store.schema_version()?.is_none() {
+            let _lock = FileLock::acquire(&lock::state_lock_path(data_dir), "the package state")?;
+            // Another process may have imported while this one waited
+            if store.schema_version()?.is_none() {
+                store.import()?;
+            }
+        }
+        Ok(store)
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The schema of the package records; `None` until the database holds a state
+    fn schema_version(&self) -> Result<Option<u32>> {
+        let version: Option<String> = self
+            .connection()
+            .query_row("SELECT value FROM meta WHERE key = 'schema_version'", [], |row| row.get(0))
+            .optional()?;
+        version
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| GripError::ConfigError(format!("{} has an invalid schema version '{}'", self.path.display(), v)))
+            })
+            .transpose()
+    }
+
+    fn import(&self) -> Result<()> {
+        let state = PackageState::load(&self.data_dir)?;
+        let transactions = history::load(&self.data_dir)?;
+        let mut connection = self.connection();
+        let tx = connection.transaction()?;
+        for transaction in &transactions {
+            insert_transaction(&tx, transaction)?;
+        }
+        write_packages(&tx, &state)?;
+        tx.commit()?;
+
+        if !state.packages.is_empty() || !transactions.is_empty() {
+            ui::status!("Copied the package state and history into {}", self.path.display());
+        }
+        Ok(())
+    }
+
+    /// Writes the state and history back to `package_state.json` and
+    /// `history.json`, then moves the database aside to `state.db.bak` so the
+    /// next switch to `sqlite` imports them afresh
+    fn hand_back(self) -> Result<()> {
+        let _lock = FileLock::acquire(&lock::state_lock_path(&self.data_dir), "the package state")?;
+        let state = self.load()?;
+        let transactions = self.history(None)?;
+        state.save(&self.data_dir)?;
+        {
+            let _lock = FileLock::acquire(&lock::history_lock_path(&self.data_dir), "the history")?;
+            history::save(&self.data_dir, &transactions)?;
+        }
+
+        let Self { data_dir, path, connection } = self;
+        drop(connection);
+        std::fs::rename(&path, data_dir.join(format!("{}.bak", DB_FILE)))?;
+        ui::status!(
+            "Copied the package state and history in {} back to {}",
+            path.display(),
+            data_dir.join("package_state.json").display()
+        );
+        Ok(())
+    }
+
+    /// Replaces every package in one transaction, so readers see the old
+    /// state or the new one and a crash leaves the old one
+    fn replace_packages(&self, state: &PackageState) -> Result<()> {
+        let mut connection = self.connection();
+        let tx = connection.transaction()?;
+        tx.execute("DELETE FROM packages", [])?;
+        write_packages(&tx, state)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Whether the records need migrating before they can be read one by one
+    fn outdated(&self) -> Result<bool> {
+        Ok(self.schema_version()? != Some(migrations::STATE_SCHEMA_VERSION))
+    }
+}
+
+impl StateStore for SqliteStore {
+    fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    /// Reads every package, upgrading records written by older versions of
+    /// grip and writing them back. A copy of the database is kept as
+    /// `state.db.v<N>.bak` first
+    fn load(&self) -> Result<PackageState> {
+        let version = self.schema_version()?.unwrap_or(migrations::STATE_SCHEMA_VERSION);
+        let records: Vec<(String, String)> = {
+            let connection = self.connection();
+            let mut statement = connection.prepare("SELECT name, record FROM packages")?;
+            let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        let parse_error = |e| GripError::ConfigError(format!("Failed to parse {}: {}", self.path.display(), e));
+        let mut packages = serde_json::Map::new();
+        for (name, record) in records {
+            packages.insert(name, serde_json::from_str(&record).map_err(parse_error)?);
+        }
+        let mut value = serde_json::json!({ "schema_version": version, "packages": packages });
+
+        if version != migrations::STATE_SCHEMA_VERSION {
+            let backup = self.data_dir.join(format!("{}.v{}.bak", DB_FILE, version));
+            if !backup.exists() {
+                self.connection()
+                    .execute("VACUUM INTO ?1", params![backup.to_string_lossy()])?;
+            }
+            if version > migrations::STATE_SCHEMA_VERSION {
+                bail!(
+                    ConfigError,
+                    "{} was written by a newer grip (schema {}, this build understands {}); \
+                     upgrade grip to use it. A copy was saved to {}",
+                    self.path.display(),
+                    version,
+                    migrations::STATE_SCHEMA_VERSION,
+                    backup.display()
+                );
+            }
+            migrations::migrate_state(&mut value)?;
+            let state = serde_json::from_value(value).map_err(parse_error)?;
+            self.replace_packages(&state)?;
+            return Ok(state);
+        }
+        serde_json::from_value(value).map_err(parse_error)
+    }
+
+    fn put_package(&self, name: &str, package: &InstalledPackage) -> Result<()> {
+        self.connection().execute(
+            "INSERT OR REPLACE INTO packages (name, version, record) VALUES (?1, ?2, ?3)",
+            params![name, package.version, serde_json::to_string(package)?],
+        )?;
+        Ok(())
+    }
+
+    fn remove_package(&self, name: &str) -> Result<()> {
+        self.connection().execute("DELETE FROM packages WHERE name = ?1", params![name])?;
+        Ok(())
+    }
+
+    /// Writes the state to `package_state.json`, replacing the copy made when
+    /// the database took over or at the last export
+    fn export(&self) -> Result<PathBuf> {
+        self.load()?.save(&self.data_dir)?;
+        Ok(self.data_dir.join("package_state.json"))
+    }
+
+    fn package(&self, name: &str) -> Result<Option<InstalledPackage>> {
+        if self.outdated()? {
+            return Ok(self.load()?.packages.remove(name));
+        }
+        let record: Option<String> = self
+            .connection()
+            .query_row("SELECT record FROM packages WHERE name = ?1", params![name], |row| row.get(0))
+            .optional()?;
+        record
+            .map(|record| serde_json::from_str(&record))
+            .transpose()
+            .map_err(|e| GripError::ConfigError(format!("Failed to parse {}: {}", self.path.display(), e)))
+    }
+
+    fn packages(&self, filter: Option<&str>) -> Result<Vec<(String, InstalledPackage)>> {
+        if self.outdated()? {
+            let mut packages: Vec<_> = self
+                .load()?
+                .packages
+                .into_iter()
+                .filter(|(name, _)| matches(name, filter))
+                .collect();
+            packages.sort_by(|(a, _), (b, _)| a.cmp(b));
+            return Ok(packages);
+        }
+        // SQLite's lower() only folds ASCII, so names are matched here the
+        // way the JSON store matches them and only those records are parsed
+        let connection = self.connection();
+        let mut statement = connection.prepare("SELECT name, record FROM packages ORDER BY name")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        let mut packages = Vec::new();
+        for row in rows {
+            let (name, record) = row?;
+            if !matches(&name, filter) {
+                continue;
+            }
+            let package = serde_json::from_str(&record)
+                .map_err(|e| GripError::ConfigError(format!("Failed to parse {}: {}", self.path.display(), e)))?;
+            packages.push((name, package));
+        }
+        Ok(packages)
+    }
+
+    fn history(&self, package: Option<&str>) -> Result<Vec<Transaction>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT timestamp, action, package, from_version, to_version FROM history \
+             WHERE ?1 IS NULL OR package = ?1 ORDER BY id",
+        )?;
+        let rows = statement.query_map(params![package], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        })?;
+        rows.map(|row| {
+            let (timestamp, action, package, from, to) = row?;
+            let action: Action = serde_json::from_value(serde_json::Value::String(action))
+                .map_err(|e| GripError::ConfigError(format!("Failed to parse {}: {}", self.path.display(), e)))?;
+            Ok(Transaction {
+                timestamp: timestamp as u64,
+                action,
+                package,
+                from,
+                to,
+            })
+        })
+        .collect()
+    }
+
+    fn record(&self, transaction: Transaction) -> Result<()> {
+        let mut connection = self.connection();
+        let tx = connection.transaction()?;
+        insert_transaction(&tx, &transaction)?;
+        tx.execute(
+            "DELETE FROM history WHERE id <= (SELECT MAX(id) FROM history) - ?1",
+            params![history::MAX_ENTRIES as i64],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn write_packages(tx: &rusqlite::Transaction, state: &PackageState) -> Result<()> {
+    let mut statement = tx.prepare("INSERT INTO packages (name, version, record) VALUES (?1, ?2, ?3)")?;
+    for (name, package) in &state.packages {
+        statement.execute(params![name, package.version, serde_json::to_string(package)?])?;
+    }
+    tx.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', ?1)",
+        params![migrations::STATE_SCHEMA_VERSION.to_string()],
+    )?;
+    Ok(())
+}
+
+fn insert_transaction(tx: &rusqlite::Transaction, transaction: &Transaction) -> Result<()> {
+    tx.execute(
+        "INSERT INTO history (timestamp, action, package, from_version, to_version) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            transaction.timestamp as i64,
+            transaction.action.name(),
+            transaction.package,
+            transaction.from,
+            transaction.to
+        ],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use crate::error::Result;
+use std::path::{Path, PathBuf};
+
+/// Installed files shared between package directories. Each distinct file is
+/// kept once under `store/objects`, named after its blake3 digest (with `.x`
+/// for executables), and package directories hard link to it, so the same
+/// version installed in several projects, or a file repeated within an
+/// archive, takes the space of one copy. blake3 rather than sha256, as every
+/// installed file is hashed on the way in and the digest is only a name here,
+/// never compared with one a publisher gave.
+///
+/// Objects are made read-only, since writing through one link would change
+/// every package sharing it. An object nothing links to any more is garbage,
+/// which is how `gc` finds what to remove without tracking references
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            dir: data_dir.join("store").join("objects"),
+        }
+    }
+
+    /// Replaces each file under `dir` with a hard link to the store's copy,
+    /// adding the files the store doesn't have yet. Returns the bytes saved
+    /// by files it already had. Directories on another filesystem than the
+    /// store are left alone, as hard links can't cross filesystems
+    #[cfg(unix)]
+    pub fn dedupe(&self, dir: &Path) -> Result<u64> {
+        use std::os::unix::fs::MetadataExt;
+
+        let mut saved = 0;
+        for path in crate::utils::walk_files(dir)? {
+            let metadata = std::fs::symlink_metadata(&path)?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let executable = metadata.mode() & 0o111 != 0;
+            let object = self.object_path(&crate::checksum::blake3_file(&path)?, executable);
+
+            let linked = match std::fs::symlink_metadata(&object) {
+                Ok(existing) if existing.ino() == metadata.ino() && existing.dev() == metadata.dev() => Ok(()),
+                Ok(_) => {
+                    // Link beside the file first, so it is never missing
+                    let temp = path.with_file_name(".grip-store-link");
+                    std::fs::hard_link(&object, &temp)
+                        .and_then(|()| std::fs::rename(&temp, &path))
+                        .map(|()| saved += metadata.len())
+                }
+                Err(_) => {
+                    if let Some(parent) = object.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::fs::hard_link(&path, &object).and_then(|()| set_read_only(&object, executable))
+                }
+            };
+            match linked {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                    tracing::debug!(dir = %dir.display(), "not on the store's filesystem, keeping copies");
+                    return Ok(saved);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        tracing::debug!(dir = %dir.display(), saved, "deduplicated files");
+        Ok(saved)
+    }
+
+    #[cfg(not(unix))]
+    pub fn dedupe(&self, _dir: &Path) -> Result<u64> {
+        Ok(0)
+    }
+
+    /// Removes objects no package directory links to any more, returning how
+    /// many were removed and the bytes freed
+    pub fn gc(&self) -> Result<(usize, u64)> {
+        if !self.dir.exists() {
+            return Ok((0, 0));
+        }
+
+        let (mut removed, mut freed) = (0, 0);
+        for path in crate::utils::walk_files(&self.dir)? {
+            let metadata = std::fs::symlink_metadata(&path)?;
+            if metadata.is_file() && link_count(&metadata) <= 1 {
+                std::fs::remove_file(&path)?;
+                removed += 1;
+                freed += metadata.len();
+            }
+        }
+        // Leave the fan-out directories emptied above tidy
+        for entry in std::fs::read_dir(&self.dir)? {
+            let _ = std::fs::remove_dir(entry?.path());
+        }
+        Ok((removed, freed))
+    }
+
+    fn object_path(&self, digest: &str, executable: bool) -> PathBuf {
+        let name = if executable {
+            format!("{}.x", &digest[2..])
+        } else {
+            digest[2..].to_string()
+        };
+        self.dir.join(&digest[..2]).join(name)
+    }
+}
+
+#[cfg(unix)]
+fn set_read_only(path: &Path, executable: bool) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = if executable { 0o555 } else { 0o444 };
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(unix)]
+fn link_count(metadata: &std::fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::nlink(metadata)
+}
+
+#[cfg(not(unix))]
+fn link_count(_metadata: &std::fs::Metadata) -> u64 {
+    // Nothing is stored where there are no hard links to count
+    u64::MAX
+}
@@ -0,0 +1,210 @@
+use crate::error::{GripError, Result};
+use crate::utils::{link_target, Unpacker};
+use std::collections::HashMap;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Unpacks the files of a `.deb` through `unpacker`. Only the `data.tar`
+/// member is read; maintainer scripts never run and dpkg's database is left
+/// alone
+pub(crate) fn extract_deb(path: &Path, unpacker: &mut Unpacker) -> Result<()> {
+    let fail = |cause: String| GripError::ExtractionError(format!("{}: {}", path.display(), cause));
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic).map_err(|e| fail(e.to_string()))?;
+    if &magic != b"!<arch>\n" {
+        return Err(fail("not an ar archive".to_string()));
+    }
+
+    loop {
+        let mut header = [0u8; 60];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Err(fail("no data.tar member".to_string()));
+            }
+            Err(e) => return Err(fail(e.to_string())),
+        }
+        let name = String::from_utf8_lossy(&header[..16]).trim_end().trim_end_matches('/').to_string();
+        let size: u64 = String::from_utf8_lossy(&header[48..58])
+            .trim()
+            .parse()
+            .map_err(|_| fail(format!("bad size for member {}", name)))?;
+
+        let mut member = (&mut reader).take(size);
+        if name.starts_with("data.tar") {
+            let data = decompress(&mut member).map_err(|e| fail(e.to_string()))?;
+            unpacker.unpack_tar(data).map_err(|e| fail(e.to_string()))?;
+            return Ok(());
+        }
+        std::io::copy(&mut member, &mut std::io::sink()).map_err(|e| fail(e.to_string()))?;
+        // Members are padded to an even length
+        if size % 2 == 1 {
+            reader.read_exact(&mut [0u8; 1]).map_err(|e| fail(e.to_string()))?;
+        }
+    }
+}
+
+/// Unpacks the files of a `.rpm` through `unpacker`. The cpio payload is read
+/// directly; scriptlets never run and the rpm database is left alone
+pub(crate) fn extract_rpm(path: &Path, unpacker: &mut Unpacker) -> Result<()> {
+    let fail = |cause: String| GripError::ExtractionError(format!("{}: {}", path.display(), cause));
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+
+    // The lead is fixed-size; the signature header is padded to 8 bytes and
+    // the main header follows it directly
+    skip(&mut reader, 96).map_err(|e| fail(e.to_string()))?;
+    let signature_len = header_len(&mut reader).map_err(|e| fail(e.to_string()))?;
+    skip(&mut reader, signature_len + (8 - signature_len % 8) % 8).map_err(|e| fail(e.to_string()))?;
+    let header_len = header_len(&mut reader).map_err(|e| fail(e.to_string()))?;
+    skip(&mut reader, header_len).map_err(|e| fail(e.to_string()))?;
+
+    let mut payload = decompress(reader).map_err(|e| fail(e.to_string()))?;
+    unpack_cpio(&mut payload, unpacker).map_err(|e| fail(e.to_string()))
+}
+
+/// Reads an rpm header's preamble and returns how many bytes the rest of it,
+/// index and data, takes
+fn header_len(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut preamble = [0u8; 16];
+    reader.read_exact(&mut preamble)?;
+    if preamble[..3] != [0x8e, 0xad, 0xe8] {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "bad rpm header"));
+    }
+    let entries = u32::from_be_bytes([preamble[8], preamble[9], preamble[10], preamble[11]]) as u64;
+    let data = u32::from_be_bytes([preamble[12], preamble[13], preamble[14], preamble[15]]) as u64;
+    Ok(entries * 16 + data)
+}
+
+fn skip(reader: &mut impl Read, len: u64) -> std::io::Result<()> {
+    let skipped = std::io::copy(&mut reader.take(len), &mut std::io::sink())?;
+    if skipped < len {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+/// Wraps `reader` in the decompressor its leading bytes call for
+fn decompress<'a>(reader: impl Read + 'a) -> std::io::Result<Box<dyn Read + 'a>> {
+    let mut reader = BufReader::new(reader);
+    let magic = std::io::BufRead::fill_buf(&mut reader)?.to_vec();
+    Ok(if magic.starts_with(b"\x1f\x8b") {
+        Box::new(flate2::read::GzDecoder::new(reader))
+    } else if magic.starts_with(b"\xfd7zXZ\x00") {
+        Box::new(xz2::read::XzDecoder::new(reader))
+    } else if magic.starts_with(b"BZh") {
+        Box::new(bzip2::read::BzDecoder::new(reader))
+    } else if magic.starts_with(b"\x28\xb5\x2f\xfd") {
+        Box::new(zstd::stream::read::Decoder::with_buffer(reader)?)
+    } else {
+        Box::new(reader)
+    })
+}
+
+/// Unpacks a cpio archive in the "newc" format rpm uses. Files hard linked
+/// together come as one entry per name, where only the last carries the
+/// data; the names before it are linked to that one once it arrives
+fn unpack_cpio(reader: &mut impl Read, unpacker: &mut Unpacker) -> std::io::Result<()> {
+    let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+    let mut offset: u64 = 0;
+    // Names waiting for their data, by device and inode
+    let mut links: HashMap<(u32, u32, u32), Vec<PathBuf>> = HashMap::new();
+
+    loop {
+        let mut header = [0u8; 110];
+        reader.read_exact(&mut header)?;
+        if &header[..6] != b"070701" && &header[..6] != b"070702" {
+            return Err(invalid("bad cpio header".to_string()));
+        }
+        let field = |index: usize| {
+            let start = 6 + index * 8;
+            u32::from_str_radix(&String::from_utf8_lossy(&header[start..start + 8]), 16)
+                .map_err(|_| invalid("bad cpio header".to_string()))
+        };
+        let inode = (field(7)?, field(8)?, field(0)?);
+        let mode = field(1)?;
+        let nlink = field(4)?;
+        let size = field(6)? as u64;
+        let name_len = field(11)? as u64;
+
+        let mut name = vec![0u8; name_len as usize];
+        reader.read_exact(&mut name)?;
+        let name = String::from_utf8_lossy(&name).trim_end_matches('\0').to_string();
+        offset += 110 + name_len;
+        skip(reader, (4 - offset % 4) % 4)?;
+        offset += (4 - offset % 4) % 4;
+
+        if name == "TRAILER!!!" {
+            return Ok(());
+        }
+
+        let mut data = reader.take(size);
+        let path = unpacker.entry_path(Path::new(&name))?;
+        match mode & 0o170000 {
+            0o040000 => std::fs::create_dir_all(path)?,
+            0o100000 => {
+                unpacker.write_file(&path, &mut data, Some(mode))?;
+                if nlink > 1 && size == 0 {
+                    links.entry(inode).or_default().push(path);
+                } else if nlink > 1 {
+                    for name in links.remove(&inode).unwrap_or_default() {
+                        std::fs::remove_file(&name)?;
+                        if std::fs::hard_link(&path, &name).is_err() {
+                            std::fs::copy(&path, &name)?;
+                        }
+                    }
+                }
+            }
+            0o120000 => {
+                let target = link_target(Path::new(&name), &mut data)?;
+                unpacker.symlink(&path, &target)?;
+            }
+            // Device files and FIFOs
+            _ => {}
+        }
+        std::io::copy(&mut data, &mut std::io::sink())?;
+        offset += size;
+        skip(reader, (4 - offset % 4) % 4)?;
+        offset += (4 - offset % 4) % 4;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Appends a newc entry, padded as cpio pads names and data
+    fn cpio_entry(archive: &mut Vec<u8>, name: &str, inode: u32, mode: u32, nlink: u32, data: &[u8]) {
+        let name = format!("{}\0", name);
+        let fields = [inode, mode, 0, 0, nlink, 0, data.len() as u32, 0, 0, 0, 0, name.len() as u32, 0];
+        archive.extend_from_slice(b"070701");
+        for field in fields {
+            archive.extend_from_slice(format!("{:08x}", field).as_bytes());
+        }
+        archive.extend_from_slice(name.as_bytes());
+        archive.resize(archive.len().next_multiple_of(4), 0);
+        archive.extend_from_slice(data);
+        archive.resize(archive.len().next_multiple_of(4), 0);
+    }
+
+    #[test]
+    fn links_cpio_hard_link_sets_to_the_entry_with_the_data() {
+        let mut archive = Vec::new();
+        cpio_entry(&mut archive, "usr/bin/tool", 7, 0o100755, 3, b"");
+        cpio_entry(&mut archive, "usr/bin/tool-alias", 7, 0o100755, 3, b"");
+        cpio_entry(&mut archive, "usr/bin/tool-data", 7, 0o100755, 3, b"#!/bin/sh\n");
+        cpio_entry(&mut archive, "usr/share/empty", 8, 0o100644, 1, b"");
+        cpio_entry(&mut archive, "TRAILER!!!", 0, 0, 1, b"");
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut unpacker = Unpacker::new(dir.path(), 0);
+        unpack_cpio(&mut archive.as_slice(), &mut unpacker).unwrap();
+
+        for name in ["tool", "tool-alias", "tool-data"] {
+            let content = std::fs::read(dir.path().join("usr/bin").join(name)).unwrap();
+            assert_eq!(content, b"#!/bin/sh\n", "{} is missing its data", name);
+        }
+        assert!(std::fs::read(dir.path().join("usr/share/empty")).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+use crate::error::{GripError, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Where a registry keeps its project templates, as `<name>.json`
+pub const TEMPLATES_DIR: &str = "templates";
+
+/// The template at the root of a repository used as one
+pub const TEMPLATE_FILE: &str = "grip-template.json";
+
+/// A starting point for `grip init`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Template {
+    /// Shown when the template is used
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The project's first version, `0.1.0` when unset
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Package names mapped to the version to install, as in grip.json
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+    /// Settings for the project's `.grip.toml`
+    #[serde(default)]
+    pub config: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Template {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| GripError::ConfigError(format!("Failed to parse {}: {}", path.display(), e)))
+    }
+}
+
+/// Whether `--template` names a repository, as a URL, `file://` path or
+/// `host/owner/repo`, rather than a template in the registries
+pub fn is_location(template: &str) -> bool {
+    template.contains('/') || template.contains(':')
+}
@@ -0,0 +1,207 @@
+use crate::error::Result;
+use crate::ui;
+use crate::utils::format_size;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The steps of an install that are timed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    /// Finding the package in the registries, pulling them if they are stale
+    Resolve,
+    /// Requests to release provider APIs, such as release lists
+    Api,
+    /// Fetching the asset, or copying it out of the cache
+    Download,
+    /// Checksums, signatures and attestations
+    Verify,
+    Extract,
+    /// Moving the install into place, linking executables and updating PATH
+    Link,
+    Hooks,
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Resolve => "resolve",
+            Phase::Api => "api",
+            Phase::Download => "download",
+            Phase::Verify => "verify",
+            Phase::Extract => "extract",
+            Phase::Link => "link",
+            Phase::Hooks => "hooks",
+        }
+    }
+}
+
+/// One timed step
+#[derive(Debug, Clone, Serialize)]
+pub struct Span {
+    pub phase: Phase,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// What the step worked on, such as the URL of an API request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub seconds: f64,
+    /// Bytes downloaded or unpacked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+}
+
+impl Span {
+    /// A step of `phase` that started at `started` and ends now
+    pub fn new(phase: Phase, started: Instant) -> Self {
+        Self {
+            phase,
+            package: None,
+            detail: None,
+            seconds: started.elapsed().as_secs_f64(),
+            bytes: None,
+        }
+    }
+
+    pub fn package(mut self, package: &str) -> Self {
+        self.package = Some(package.to_string());
+        self
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn bytes(mut self, bytes: u64) -> Self {
+        self.bytes = Some(bytes);
+        self
+    }
+}
+
+/// Where a run's time went, for `--timings`. Steps are recorded whether or
+/// not they are shown, since recording one costs next to nothing. Packages
+/// installed together overlap, so the steps can add up to more than the run
+#[derive(Debug)]
+pub struct Timings {
+    started: Instant,
+    spans: Mutex<Vec<Span>>,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            spans: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+/// The steps of one phase for one package, added up
+#[derive(Debug, Serialize)]
+struct Total {
+    phase: Phase,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
+    steps: usize,
+    seconds: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<u64>,
+}
+
+impl Timings {
+    pub fn record(&self, span: Span) {
+        tracing::debug!(phase = span.phase.name(), package = ?span.package, seconds = span.seconds, "timed");
+        self.spans.lock().unwrap_or_else(|e| e.into_inner()).push(span);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    fn totals(&self) -> Vec<Total> {
+        let spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        let mut totals: Vec<Total> = Vec::new();
+        for span in spans.iter() {
+            let index = totals
+                .iter()
+                .position(|t| t.phase == span.phase && t.package == span.package)
+                .unwrap_or_else(|| {
+                    totals.push(Total {
+                        phase: span.phase,
+                        package: span.package.clone(),
+                        steps: 0,
+                        seconds: 0.0,
+                        bytes: None,
+                    });
+                    totals.len() - 1
+                });
+            let total = &mut totals[index];
+            total.steps += 1;
+            total.seconds += span.seconds;
+            if let Some(bytes) = span.bytes {
+                total.bytes = Some(total.bytes.unwrap_or(0) + bytes);
+            }
+        }
+        totals.sort_by(|a, b| (&a.package, a.phase).cmp(&(&b.package, b.phase)));
+        totals
+    }
+
+    /// Prints a table of the time spent in each phase of each package, with
+    /// transfer rates for downloads
+    pub fn print(&self) {
+        let mut table = ui::Table::new(&["PACKAGE", "PHASE", "TIME", "DETAIL"]).align_right(2);
+        for total in self.totals() {
+            let detail = match (total.phase, total.bytes) {
+                (Phase::Download, Some(bytes)) if total.seconds > 0.0 => format!(
+                    "{} at {}/s",
+                    format_size(bytes),
+                    format_size((bytes as f64 / total.seconds) as u64)
+                ),
+                (_, Some(bytes)) => format_size(bytes),
+                (Phase::Api, None) if total.steps == 1 => "1 request".to_string(),
+                (Phase::Api, None) => format!("{} requests", total.steps),
+                (_, None) => String::new(),
+            };
+            table.add_row(vec![
+                total.package.unwrap_or_else(|| "-".to_string()),
+                total.phase.name().to_string(),
+                format_seconds(total.seconds),
+                detail,
+            ]);
+        }
+        table.add_row(vec![
+            "total".to_string(),
+            String::new(),
+            format_seconds(self.elapsed().as_secs_f64()),
+            String::new(),
+        ]);
+        if ui::status_to_stderr() {
+            table.eprint();
+        } else {
+            table.print();
+        }
+    }
+
+    /// Writes every step, the per-phase totals and the run's wall time as JSON,
+    /// for benchmarks to compare
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::json!({
+            "total_seconds": self.elapsed().as_secs_f64(),
+            "phases": self.totals(),
+            "spans": *self.spans.lock().unwrap_or_else(|e| e.into_inner()),
+        });
+        std::fs::write(path, serde_json::to_string_pretty(&json)?)?;
+        Ok(())
+    }
+}
+
+fn format_seconds(seconds: f64) -> String {
+    if seconds < 1.0 {
+        format!("{:.0}ms", seconds * 1000.0)
+    } else {
+        format!("{:.2}s", seconds)
+    }
+}
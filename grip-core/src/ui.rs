@@ -0,0 +1,320 @@
+use colored::{ColoredString, Colorize};
+use dialoguer::console::{self, Alignment};
+use serde::Serialize;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+static QUIET: AtomicBool = AtomicBool::new(false);
+static PROGRESS_EVENTS: AtomicBool = AtomicBool::new(false);
+
+/// Switches commands to JSON output, moving status messages to stderr so
+/// stdout carries only the JSON document
+pub fn set_json_output(enabled: bool) {
+    JSON_OUTPUT.store(enabled, Ordering::Relaxed);
+}
+
+pub fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Moves status messages to stderr so stdout belongs to another program, as
+/// with `grip run`
+pub fn set_status_to_stderr(enabled: bool) {
+    STATUS_TO_STDERR.store(enabled, Ordering::Relaxed);
+}
+
+/// Hides progress and success messages; warnings and failures still print
+pub fn set_quiet(enabled: bool) {
+    QUIET.store(enabled, Ordering::Relaxed);
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Reports progress as JSON events on stderr instead of status messages and
+/// progress bars, for programs that draw their own
+pub fn set_progress_events(enabled: bool) {
+    PROGRESS_EVENTS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn progress_events() -> bool {
+    PROGRESS_EVENTS.load(Ordering::Relaxed)
+}
+
+/// Version of the `--progress json` event format. Fields may be added to
+/// events without changing it; it changes when fields are renamed or removed
+pub const EVENT_SCHEMA: u32 = 1;
+
+/// What `--progress json` reports, one event per line of stderr
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// A status message: `info`, `success`, `warning`, `failure` or `detail`
+    Message { level: &'a str, message: &'a str },
+    /// Looking a package up in the registries
+    ResolveStarted { package: &'a str },
+    /// The release and asset to install were chosen
+    Resolved { package: &'a str, version: &'a str, asset: &'a str },
+    DownloadStarted {
+        file: &'a str,
+        url: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        total_bytes: Option<u64>,
+    },
+    /// Sent a few times a second while a download runs
+    DownloadProgress {
+        file: &'a str,
+        bytes: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        total_bytes: Option<u64>,
+    },
+    DownloadFinished { file: &'a str, bytes: u64 },
+    ExtractStarted { package: &'a str, file: &'a str },
+    /// A package was installed or upgraded
+    Completed { package: &'a str, version: &'a str, path: &'a str },
+    /// The command failed; it exits with `exit_code`
+    Error { message: &'a str, kind: &'a str, exit_code: i32 },
+}
+
+/// Writes an event when `--progress json` asked for them
+pub fn emit(event: Event) {
+    if !progress_events() {
+        return;
+    }
+    #[derive(Serialize)]
+    struct Line<'a> {
+        schema: u32,
+        #[serde(flatten)]
+        event: Event<'a>,
+    }
+    if let Ok(line) = serde_json::to_string(&Line { schema: EVENT_SCHEMA, event }) {
+        // One write per line, so events from concurrent downloads don't interleave
+        use std::io::Write;
+        let _ = writeln!(std::io::stderr().lock(), "{}", line);
+    }
+}
+
+/// When output is colored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// When writing to a terminal, unless `NO_COLOR` is set
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether a stream gets colors, given whether it is a terminal
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_terminal && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+        }
+    }
+}
+
+/// Applies `choice` to colored messages and tables, prompts and progress
+/// bars alike. Messages follow the stream status messages go to, so call
+/// this after [`set_json_output`] and [`set_status_to_stderr`]
+pub fn set_color(choice: ColorChoice) {
+    let stdout = choice.enabled(std::io::stdout().is_terminal());
+    let stderr = choice.enabled(std::io::stderr().is_terminal());
+    colored::control::set_override(if status_to_stderr() { stderr } else { stdout });
+    console::set_colors_enabled(stdout);
+    console::set_colors_enabled_stderr(stderr);
+}
+
+/// The width of the terminal stdout writes to, or `None` when it is piped
+pub fn terminal_width() -> Option<usize> {
+    console::Term::stdout().size_checked().map(|(_, columns)| columns as usize)
+}
+
+/// Rows printed in columns under a header line. Cells may be colored; lines
+/// longer than the terminal is wide are cut short rather than wrapped
+pub struct Table {
+    headers: Vec<&'static str>,
+    right_aligned: Vec<usize>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&'static str]) -> Self {
+        Self {
+            headers: headers.to_vec(),
+            right_aligned: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Lines a column up on the right, as for sizes
+    pub fn align_right(mut self, column: usize) -> Self {
+        self.right_aligned.push(column);
+        self
+    }
+
+    pub fn add_row(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    pub fn print(&self) {
+        for line in self.lines() {
+            println!("{}", line);
+        }
+    }
+
+    /// Prints to stderr, for tables that accompany a command's output rather
+    /// than being it
+    pub fn eprint(&self) {
+        for line in self.lines() {
+            eprintln!("{}", line);
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let widths: Vec<usize> = (0..self.headers.len())
+            .map(|column| {
+                self.rows
+                    .iter()
+                    .filter_map(|row| row.get(column))
+                    .map(|cell| console::measure_text_width(cell))
+                    .chain([self.headers[column].len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let max_width = terminal_width();
+
+        let headers = self.headers.iter().map(|header| header.to_string()).collect::<Vec<_>>();
+        let mut lines = Vec::new();
+        for row in std::iter::once(&headers).chain(&self.rows) {
+            let mut line = String::new();
+            for (column, cell) in row.iter().enumerate() {
+                // The last column isn't padded, so lines carry no trailing spaces
+                if column + 1 == row.len() && !self.right_aligned.contains(&column) {
+                    line.push_str(cell);
+                    break;
+                }
+                let alignment = match self.right_aligned.contains(&column) {
+                    true => Alignment::Right,
+                    false => Alignment::Left,
+                };
+                line.push_str(&console::pad_str(cell, widths[column], alignment, None));
+                if column + 1 < row.len() {
+                    line.push_str("  ");
+                }
+            }
+            let line = line.trim_end();
+            lines.push(match max_width {
+                Some(max_width) => console::truncate_str(line, max_width, "…").into_owned(),
+                None => line.to_string(),
+            });
+        }
+        lines
+    }
+}
+
+/// Prints a progress or success message unless `--quiet` was given
+pub fn print_progress(symbol: ColoredString, message: &str) {
+    tracing::info!(target: "grip_core::ui", "{}", message);
+    if !quiet() {
+        write_status(symbol, message);
+    }
+}
+
+/// Prints a warning or failure, which `--quiet` never hides
+pub fn print_status(symbol: ColoredString, message: &str) {
+    tracing::warn!(target: "grip_core::ui", "{}", message);
+    write_status(symbol, message);
+}
+
+fn write_status(symbol: ColoredString, message: &str) {
+    if progress_events() {
+        let level = match &*symbol {
+            "✓" => "success",
+            "!" => "warning",
+            "✗" => "failure",
+            _ => "info",
+        };
+        emit(Event::Message { level, message: &console::strip_ansi_codes(message) });
+    } else if status_to_stderr() {
+        eprintln!("{} {}", symbol, message);
+    } else {
+        println!("{} {}", symbol, message);
+    }
+}
+
+/// Prints an indented line under the previous message, on the same stream
+pub fn print_detail(message: &str) {
+    if progress_events() {
+        emit(Event::Message { level: "detail", message });
+    } else if status_to_stderr() {
+        eprintln!("    {}", message);
+    } else {
+        println!("    {}", message);
+    }
+}
+
+/// Whether stdout is reserved for something other than status messages
+pub fn status_to_stderr() -> bool {
+    json_output() || STATUS_TO_STDERR.load(Ordering::Relaxed)
+}
+
+pub fn arrow() -> ColoredString {
+    "→".blue()
+}
+
+pub fn check() -> ColoredString {
+    "✓".green()
+}
+
+pub fn bang() -> ColoredString {
+    "!".yellow()
+}
+
+pub fn cross() -> ColoredString {
+    "✗".red()
+}
+
+/// Prints a value as pretty JSON on stdout
+pub fn print_json<T: Serialize>(value: &T) -> crate::error::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Reports progress, as in "→ Downloading ripgrep"
+#[macro_export]
+macro_rules! status {
+    ($($arg:tt)*) => {
+        $crate::ui::print_progress($crate::ui::arrow(), &format!($($arg)*))
+    };
+}
+
+/// Reports a completed step, as in "✓ Installation complete!"
+#[macro_export]
+macro_rules! success {
+    ($($arg:tt)*) => {
+        $crate::ui::print_progress($crate::ui::check(), &format!($($arg)*))
+    };
+}
+
+/// Reports something the user should know about but that isn't fatal
+#[macro_export]
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::ui::print_status($crate::ui::bang(), &format!($($arg)*))
+    };
+}
+
+/// Reports a failed step that doesn't abort the command
+#[macro_export]
+macro_rules! failure {
+    ($($arg:tt)*) => {
+        $crate::ui::print_status($crate::ui::cross(), &format!($($arg)*))
+    };
+}
+
+pub use crate::{failure, status, success, warning};
@@ -0,0 +1,73 @@
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+const FILE: &str = "update-check.json";
+
+/// What the last background update check found, in `update-check.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Record {
+    /// When a check was last started, in seconds since the Unix epoch
+    pub checked_at: u64,
+    /// The latest release of each installed package, as of the last check
+    /// that finished
+    #[serde(default)]
+    pub latest: BTreeMap<String, String>,
+}
+
+/// The last check's record; a missing or unreadable one counts as never checked
+pub fn load(root: &Path) -> Record {
+    std::fs::read_to_string(root.join(FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(root: &Path, record: &Record) -> Result<()> {
+    let temp_file = root.join(format!("{}.tmp.{}", FILE, std::process::id()));
+    std::fs::write(&temp_file, serde_json::to_string_pretty(record)?)?;
+    std::fs::rename(temp_file, root.join(FILE))?;
+    Ok(())
+}
+
+/// Whether the last check started longer than `interval` ago
+pub fn is_due(record: &Record, interval: Duration) -> bool {
+    now().saturating_sub(record.checked_at) >= interval.as_secs()
+}
+
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Starts `grip update-check` detached, so the command that triggered it
+/// neither waits for it nor takes it down when interrupted. It is pointed at
+/// the same data directory and config file
+pub fn spawn(data_dir: &Path, config_file: &Path) -> Result<()> {
+    let mut command = std::process::Command::new(std::env::current_exe()?);
+    command
+        .arg("update-check")
+        .env("GRIP_DATA_DIR", data_dir)
+        .env("GRIP_CONFIG", config_file)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(DETACHED_PROCESS | CREATE_NO_WINDOW);
+    }
+    command.spawn()?;
+    Ok(())
+}
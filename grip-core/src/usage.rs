@@ -0,0 +1,68 @@
+use crate::error::{GripError, Result};
+use crate::lock::{self, FileLock};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const FILE: &str = "usage.json";
+
+/// How often and when each package was last run, in `usage.json`. Only kept
+/// with `usage_stats` on, and never leaves the machine
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Record {
+    /// When runs started being counted, in seconds since the Unix epoch, so a
+    /// package isn't taken for unused before there was a chance to see it run
+    pub since: u64,
+    #[serde(default)]
+    pub packages: BTreeMap<String, PackageUsage>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PackageUsage {
+    pub runs: u64,
+    /// Seconds since the Unix epoch
+    pub last_run: u64,
+}
+
+/// The usage counted so far; `None` before anything was
+pub fn load(root: &Path) -> Result<Option<Record>> {
+    let path = root.join(FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| GripError::ConfigError(format!("Failed to parse {}: {}", path.display(), e)))
+}
+
+/// Counts a run of `package`. A run that finds another one updating the
+/// record isn't counted, rather than holding up the program it starts
+pub fn record_run(root: &Path, package: &str) -> Result<()> {
+    let Some(_lock) = FileLock::try_acquire(&lock::usage_lock_path(root))? else {
+        return Ok(());
+    };
+    let now = crate::update_check::now();
+    let mut record = load(root)?.unwrap_or(Record {
+        since: now,
+        packages: BTreeMap::new(),
+    });
+    let usage = record.packages.entry(package.to_string()).or_default();
+    usage.runs += 1;
+    usage.last_run = now;
+
+    let temp_file = root.join(format!("{}.tmp.{}", FILE, std::process::id()));
+    std::fs::write(&temp_file, serde_json::to_string_pretty(&record)?)?;
+    std::fs::rename(temp_file, root.join(FILE))?;
+    Ok(())
+}
+
+/// Forgets everything counted, starting over from the next run
+pub fn reset(root: &Path) -> Result<()> {
+    let _lock = FileLock::acquire(&lock::usage_lock_path(root), "the usage statistics")?;
+    let path = root.join(FILE);
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
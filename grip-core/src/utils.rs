@@ -0,0 +1,1277 @@
+use crate::error::{GripError, Result};
+use crate::system_package;
+use std::ffi::OsString;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+/// Archive formats grip can unpack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarBz2,
+    TarZst,
+    SevenZip,
+    /// A Debian package; only its files are unpacked
+    Deb,
+    /// An RPM package; only its files are unpacked
+    Rpm,
+    /// A single gzip-compressed file, usually a bare executable (`tool.gz`)
+    Gzip,
+    /// A single xz-compressed file
+    Xz,
+    /// A single bzip2-compressed file
+    Bzip2,
+    /// A single zstd-compressed file
+    Zstd,
+}
+
+impl ArchiveFormat {
+    /// Whether this is a Linux distribution package, whose executables live
+    /// in `usr/bin`
+    pub fn is_system_package(&self) -> bool {
+        matches!(self, ArchiveFormat::Deb | ArchiveFormat::Rpm)
+    }
+
+    /// Whether this is a compressed file rather than an archive, which
+    /// decompresses to the one file it holds
+    pub fn is_single_file(&self) -> bool {
+        matches!(
+            self,
+            ArchiveFormat::Gzip | ArchiveFormat::Xz | ArchiveFormat::Bzip2 | ArchiveFormat::Zstd
+        )
+    }
+}
+
+const MAGIC_BYTES: &[(&[u8], ArchiveFormat)] = &[
+    (b"PK\x03\x04", ArchiveFormat::Zip),
+    (b"PK\x05\x06", ArchiveFormat::Zip),
+    (b"7z\xbc\xaf\x27\x1c", ArchiveFormat::SevenZip),
+    (b"!<arch>\ndebian-binary", ArchiveFormat::Deb),
+    (b"\xed\xab\xee\xdb", ArchiveFormat::Rpm),
+];
+
+/// Compression formats, with the format of a tarball compressed that way and
+/// of any other file compressed that way
+const COMPRESSION_MAGIC_BYTES: &[(&[u8], ArchiveFormat, ArchiveFormat)] = &[
+    (b"\x1f\x8b", ArchiveFormat::TarGz, ArchiveFormat::Gzip),
+    (b"\xfd7zXZ\x00", ArchiveFormat::TarXz, ArchiveFormat::Xz),
+    (b"BZh", ArchiveFormat::TarBz2, ArchiveFormat::Bzip2),
+    (b"\x28\xb5\x2f\xfd", ArchiveFormat::TarZst, ArchiveFormat::Zstd),
+];
+
+/// How many archives may be nested in one another, as in a zip holding a
+/// tarball, before extraction gives up
+pub const MAX_NESTED_ARCHIVES: usize = 3;
+
+/// Detects an archive's format from its leading bytes, returning `None` for
+/// files that aren't a supported archive (such as a bare executable).
+/// Compressed files are told apart from compressed tarballs by decompressing
+/// their first block
+pub fn detect_archive_format(path: &Path) -> Result<Option<ArchiveFormat>> {
+    let header = read_block(std::fs::File::open(path)?)?;
+
+    if let Some((_, format)) = MAGIC_BYTES.iter().find(|(magic, _)| header.starts_with(magic)) {
+        return Ok(Some(*format));
+    }
+
+    if let Some((_, tar, single)) = COMPRESSION_MAGIC_BYTES
+        .iter()
+        .find(|(magic, _, _)| header.starts_with(magic))
+    {
+        // A stream that doesn't decompress is left for extraction to report
+        let is_tar = decompress(*tar, std::fs::File::open(path)?)
+            .and_then(read_block)
+            .map_or(true, |block| is_tar_header(&block));
+        return Ok(Some(if is_tar { *tar } else { *single }));
+    }
+
+    if is_tar_header(&header) {
+        return Ok(Some(ArchiveFormat::Tar));
+    }
+
+    Ok(None)
+}
+
+/// Reads up to one tar block from the start of `reader`
+fn read_block(reader: impl Read) -> std::io::Result<Vec<u8>> {
+    let mut block = Vec::with_capacity(512);
+    reader.take(512).read_to_end(&mut block)?;
+    Ok(block)
+}
+
+/// Whether `block` is a tar header: POSIX tarballs carry a magic string, and
+/// older ones are recognised by the header's checksum
+fn is_tar_header(block: &[u8]) -> bool {
+    if block.len() < 512 {
+        return false;
+    }
+    if &block[257..262] == b"ustar" {
+        return true;
+    }
+    let recorded = std::str::from_utf8(&block[148..156])
+        .ok()
+        .map(|field| field.trim_matches(|c: char| c == '\0' || c == ' '))
+        .and_then(|field| u32::from_str_radix(field, 8).ok());
+    // The checksum is taken with its own field counted as spaces
+    let actual: u32 = block[..512]
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| if (148..156).contains(&i) { u32::from(b' ') } else { u32::from(*byte) })
+        .sum();
+    recorded == Some(actual)
+}
+
+/// A reader decompressing `file` as the compression of `format` says, or
+/// reading it as is for formats that aren't compressed as a whole
+fn decompress(format: ArchiveFormat, file: std::fs::File) -> std::io::Result<Box<dyn Read>> {
+    Ok(match format {
+        ArchiveFormat::TarGz | ArchiveFormat::Gzip => Box::new(flate2::read::GzDecoder::new(file)),
+        ArchiveFormat::TarXz | ArchiveFormat::Xz => Box::new(xz2::read::XzDecoder::new(file)),
+        ArchiveFormat::TarBz2 | ArchiveFormat::Bzip2 => Box::new(bzip2::read::BzDecoder::new(file)),
+        ArchiveFormat::TarZst | ArchiveFormat::Zstd => Box::new(zstd::stream::read::Decoder::new(file)?),
+        _ => Box::new(file),
+    })
+}
+
+/// The name of the file a compressed file holds: `tool.gz` holds `tool`
+fn decompressed_name(name: &str) -> String {
+    [".gz", ".xz", ".bz2", ".zst", ".zstd"]
+        .iter()
+        .find_map(|extension| {
+            let split = name.len().checked_sub(extension.len())?;
+            let (stem, suffix) = name.split_at_checked(split)?;
+            (!stem.is_empty() && suffix.eq_ignore_ascii_case(extension)).then(|| stem.to_string())
+        })
+        // Never the name of the compressed file, which may be beside it
+        .unwrap_or_else(|| format!("{}.out", name))
+}
+
+/// Extracts a supported archive into `target_dir`, returning the format of
+/// what was unpacked. Archives come from the internet, so every entry must
+/// land inside `target_dir`: entries named with `..` or an absolute path,
+/// symlinks pointing outside, directly or through other symlinks in the
+/// archive, and entries written through a symlink are rejected. Unpacking
+/// more than `max_size` bytes fails, so a small download can't fill the disk;
+/// 0 means no limit.
+///
+/// A compressed file that isn't a tarball, such as `tool.gz`, decompresses to
+/// the file it holds. An archive whose only file is another archive, such as
+/// a zip holding a tarball, has that one unpacked in its place, up to
+/// [`MAX_NESTED_ARCHIVES`] deep; the innermost format is returned
+pub async fn extract_archive(archive_path: &Path, target_dir: &Path, max_size: u64) -> Result<ArchiveFormat> {
+    let fail = |cause: String| GripError::ExtractionError(format!("{}: {}", archive_path.display(), cause));
+
+    let mut unpacker = Unpacker::new(target_dir, max_size);
+    std::fs::create_dir_all(target_dir).map_err(|e| fail(e.to_string()))?;
+
+    let name = archive_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut format = unpack(archive_path, &name, &mut unpacker)?;
+    for depth in 1.. {
+        let Some((inner, inner_format)) = nested_archive(target_dir, archive_path).map_err(|e| fail(e.to_string()))?
+        else {
+            break;
+        };
+        if depth > MAX_NESTED_ARCHIVES {
+            return Err(fail(format!("nests archives more than {} deep", MAX_NESTED_ARCHIVES)));
+        }
+        tracing::debug!(archive = %inner.display(), format = ?inner_format, depth, "unpacking a nested archive");
+        let inner_name = inner
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        // Moved out of the way, with the directories it was in, so its
+        // contents land at the top like the outer archive's would
+        let moved = target_dir.join(NESTED_ARCHIVE);
+        let clear = || -> std::io::Result<u64> {
+            let size = inner.metadata()?.len();
+            std::fs::rename(&inner, &moved)?;
+            for entry in std::fs::read_dir(target_dir)? {
+                let path = entry?.path();
+                if path.is_dir() && !path.is_symlink() {
+                    std::fs::remove_dir_all(path)?;
+                }
+            }
+            Ok(size)
+        };
+        let size = clear().map_err(|e| fail(e.to_string()))?;
+        // Its bytes were counted when it was unpacked; its contents count instead
+        unpacker.written = unpacker.written.saturating_sub(size);
+        format = unpack(&moved, &inner_name, &mut unpacker)?;
+        std::fs::remove_file(&moved).map_err(|e| fail(e.to_string()))?;
+    }
+
+    unpacker.check_links(target_dir).map_err(|e| fail(e.to_string()))?;
+    tracing::debug!(archive = %archive_path.display(), bytes = unpacker.written, "extracted");
+    Ok(format)
+}
+
+/// What a nested archive is renamed to while it is unpacked
+const NESTED_ARCHIVE: &str = ".grip-nested-archive";
+
+/// Unpacks one archive, named `name`, into the unpacker's target directory
+fn unpack(archive_path: &Path, name: &str, unpacker: &mut Unpacker) -> Result<ArchiveFormat> {
+    let fail = |cause: String| GripError::ExtractionError(format!("{}: {}", name, cause));
+
+    let format = detect_archive_format(archive_path)
+        .map_err(|e| fail(e.to_string()))?
+        .ok_or_else(|| fail("unsupported archive format".to_string()))?;
+    tracing::debug!(archive = %archive_path.display(), ?format, target = %unpacker.target_dir.display(), "extracting");
+
+    let open = || std::fs::File::open(archive_path).map_err(|e| fail(e.to_string()));
+    let decompressed = || decompress(format, open()?).map_err(|e| fail(e.to_string()));
+
+    match format {
+        ArchiveFormat::Zip => unpack_zip(open()?, unpacker).map_err(|e| fail(e.to_string()))?,
+        ArchiveFormat::Tar | ArchiveFormat::TarGz | ArchiveFormat::TarXz | ArchiveFormat::TarBz2 | ArchiveFormat::TarZst => {
+            unpacker.unpack_tar(decompressed()?).map_err(|e| fail(e.to_string()))?
+        }
+        ArchiveFormat::Gzip | ArchiveFormat::Xz | ArchiveFormat::Bzip2 | ArchiveFormat::Zstd => {
+            let path = unpacker.target_dir.join(decompressed_name(name));
+            unpacker
+                .write_file(&path, &mut decompressed()?, Some(0o755))
+                .map_err(|e| fail(e.to_string()))?
+        }
+        ArchiveFormat::SevenZip => unpack_7z(archive_path, unpacker).map_err(|e| fail(e.to_string()))?,
+        ArchiveFormat::Deb => system_package::extract_deb(archive_path, unpacker)?,
+        ArchiveFormat::Rpm => system_package::extract_rpm(archive_path, unpacker)?,
+    }
+    Ok(format)
+}
+
+/// Extensions of archives that are unpacked when nested in another. Formats
+/// built on zip, such as `.jar`, are files to install rather than unpack
+const NESTED_EXTENSIONS: &[&str] = &[
+    ".zip", ".tar", ".tgz", ".tbz", ".tbz2", ".txz", ".tzst", ".gz", ".xz", ".bz2", ".zst", ".7z", ".deb", ".rpm",
+];
+
+/// The archive unpacked into `target_dir` when it is the only file there,
+/// besides `outer`, the archive it came from
+fn nested_archive(target_dir: &Path, outer: &Path) -> Result<Option<(PathBuf, ArchiveFormat)>> {
+    let files: Vec<PathBuf> = walk_files(target_dir)?
+        .into_iter()
+        .filter(|file| file != outer && !file.ends_with(NESTED_ARCHIVE))
+        .collect();
+    let [file] = files.as_slice() else {
+        return Ok(None);
+    };
+    let name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if !NESTED_EXTENSIONS.iter().any(|extension| name.ends_with(extension)) || !file.symlink_metadata()?.is_file() {
+        return Ok(None);
+    }
+    Ok(detect_archive_format(file)?.map(|format| (file.clone(), format)))
+}
+
+/// Writes archive entries under a target directory, refusing any that would
+/// end up outside it and stopping once the size limit is reached
+pub(crate) struct Unpacker<'a> {
+    target_dir: &'a Path,
+    max_size: u64,
+    written: u64,
+}
+
+impl<'a> Unpacker<'a> {
+    pub(crate) fn new(target_dir: &'a Path, max_size: u64) -> Self {
+        Self {
+            target_dir,
+            max_size,
+            written: 0,
+        }
+    }
+
+    /// Where the entry `name` goes. Names are taken as relative to the
+    /// target, and may use `..` only within it; a leading `/` is refused
+    /// rather than stripped, since no honest release archive has one
+    pub(crate) fn entry_path(&self, name: &Path) -> std::io::Result<PathBuf> {
+        let mut path = self.target_dir.to_path_buf();
+        let mut depth = 0;
+        for component in name.components() {
+            match component {
+                Component::Normal(part) => {
+                    if path.symlink_metadata().is_ok_and(|m| m.file_type().is_symlink()) {
+                        return Err(unsafe_entry(name, "is inside a symlink"));
+                    }
+                    path.push(part);
+                    depth += 1;
+                }
+                Component::CurDir => {}
+                Component::ParentDir if depth > 0 => {
+                    path.pop();
+                    depth -= 1;
+                }
+                Component::ParentDir => return Err(unsafe_entry(name, "escapes the target directory")),
+                Component::RootDir | Component::Prefix(_) => return Err(unsafe_entry(name, "has an absolute path")),
+            }
+        }
+        Ok(path)
+    }
+
+    /// Checks that a symlink at `path` pointing at `target` stays inside,
+    /// following the symlinks already unpacked, so a chain of links that each
+    /// look harmless can't lead out either
+    fn check_link(&self, path: &Path, target: &Path) -> std::io::Result<()> {
+        if target.has_root() || target.components().any(|c| matches!(c, Component::Prefix(_))) {
+            return Err(unsafe_entry(path, "is a symlink to an absolute path"));
+        }
+        let relative = path.strip_prefix(self.target_dir).unwrap_or(path);
+        let dir = relative
+            .parent()
+            .map(|parent| parent.components().map(|c| c.as_os_str().to_os_string()).collect())
+            .unwrap_or_default();
+        match self.resolve_link(dir, target, &mut 0) {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(unsafe_entry(relative, "is a symlink pointing outside the target directory")),
+            Err(LinkLoop) => Err(unsafe_entry(relative, "is a symlink loop")),
+        }
+    }
+
+    /// Where `target`, followed from the directory `dir` of the target, leads:
+    /// its components relative to the target, or `None` once a `..` or an
+    /// absolute link leaves it. Symlinks on the way are followed; parts that
+    /// don't exist yet count as directories
+    fn resolve_link(
+        &self,
+        mut dir: Vec<OsString>,
+        target: &Path,
+        hops: &mut u32,
+    ) -> std::result::Result<Option<Vec<OsString>>, LinkLoop> {
+        if target.has_root() || target.components().any(|c| matches!(c, Component::Prefix(_))) {
+            return Ok(None);
+        }
+        for component in target.components() {
+            match component {
+                Component::Normal(part) => {
+                    dir.push(part.to_os_string());
+                    let on_disk = self.target_dir.join(dir.iter().collect::<PathBuf>());
+                    if let Ok(next) = std::fs::read_link(&on_disk) {
+                        *hops += 1;
+                        if *hops > MAX_LINK_HOPS {
+                            return Err(LinkLoop);
+                        }
+                        dir.pop();
+                        match self.resolve_link(dir, &next, hops)? {
+                            Some(resolved) => dir = resolved,
+                            None => return Ok(None),
+                        }
+                    }
+                }
+                Component::ParentDir if dir.pop().is_none() => return Ok(None),
+                _ => {}
+            }
+        }
+        Ok(Some(dir))
+    }
+
+    /// Checks every unpacked symlink again once the whole archive is out, as
+    /// a link made later can change where an earlier one leads
+    fn check_links(&self, dir: &Path) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let metadata = path.symlink_metadata()?;
+            if metadata.file_type().is_symlink() {
+                self.check_link(&path, &std::fs::read_link(&path)?)?;
+            } else if metadata.is_dir() {
+                self.check_links(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a file's contents, counting them against the size limit
+    pub(crate) fn write_file(&mut self, path: &Path, reader: &mut dyn Read, mode: Option<u32>) -> std::io::Result<()> {
+        if path == self.target_dir {
+            return Err(unsafe_entry(path, "has no name"));
+        }
+        let mut file = create_entry_file(path)?;
+        let written = std::io::copy(&mut reader.take(self.remaining()), &mut file)?;
+        self.count(written)?;
+        if let Some(mode) = mode {
+            set_mode(path, mode & 0o777)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a file's contents into memory for a writer thread, counting them
+    /// against the size limit
+    fn read_file(&mut self, path: &Path, reader: &mut dyn Read, size: u64) -> std::io::Result<Vec<u8>> {
+        if path == self.target_dir {
+            return Err(unsafe_entry(path, "has no name"));
+        }
+        let mut data = Vec::with_capacity(size.min(self.remaining()) as usize);
+        reader.take(self.remaining()).read_to_end(&mut data)?;
+        self.count(data.len() as u64)?;
+        Ok(data)
+    }
+
+    /// How many more bytes may be unpacked, plus one so going over shows
+    fn remaining(&self) -> u64 {
+        match self.max_size {
+            0 => u64::MAX,
+            max => (max - self.written).saturating_add(1),
+        }
+    }
+
+    fn count(&mut self, bytes: u64) -> std::io::Result<()> {
+        self.written += bytes;
+        if self.max_size > 0 && self.written > self.max_size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "unpacks to more than {}; raise settings.max_extracted_size_mb to allow it",
+                    format_size(self.max_size)
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Creates a symlink, provided it points inside the target directory.
+    /// Where symlinks aren't available the entry is skipped
+    pub(crate) fn symlink(&self, path: &Path, target: &Path) -> std::io::Result<()> {
+        self.check_link(path, target)?;
+        #[cfg(unix)]
+        {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            if path.symlink_metadata().is_ok() {
+                std::fs::remove_file(path)?;
+            }
+            std::os::unix::fs::symlink(target, path)?;
+        }
+        Ok(())
+    }
+
+    /// Unpacks a tarball as the decompressor produces it. Small files are
+    /// read into memory and written by a pool of threads while decompression
+    /// carries on; large ones are written as they are read
+    pub(crate) fn unpack_tar(&mut self, reader: impl Read) -> std::io::Result<()> {
+        let mut archive = tar::Archive::new(reader);
+        let mut writers = WriterPool::default();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.into_owned();
+            let path = self.entry_path(&name)?;
+            let kind = entry.header().entry_type();
+            match kind {
+                tar::EntryType::Directory => {
+                    writers.wait_for(&path)?;
+                    std::fs::create_dir_all(&path)?
+                }
+                tar::EntryType::Regular | tar::EntryType::Continuous | tar::EntryType::GNUSparse => {
+                    let mode = entry.header().mode().ok();
+                    let size = entry.size();
+                    if size <= BUFFERED_ENTRY_SIZE {
+                        let data = self.read_file(&path, &mut entry, size)?;
+                        writers.write(BufferedFile { path, data, mode })?;
+                    } else {
+                        writers.wait_for(&path)?;
+                        self.write_file(&path, &mut entry, mode)?
+                    }
+                }
+                // Links depend on what is already on disk, so pending writes
+                // finish first
+                tar::EntryType::Symlink => {
+                    writers.finish()?;
+                    let target = entry
+                        .link_name()?
+                        .ok_or_else(|| unsafe_entry(&name, "is a symlink without a target"))?;
+                    self.symlink(&path, &target)?
+                }
+                tar::EntryType::Link => {
+                    writers.finish()?;
+                    let target = entry
+                        .link_name()?
+                        .ok_or_else(|| unsafe_entry(&name, "is a hard link without a target"))?;
+                    let source = self.entry_path(&target)?;
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    if path.symlink_metadata().is_ok() {
+                        std::fs::remove_file(&path)?;
+                    }
+                    std::fs::hard_link(source, &path)?
+                }
+                // Devices, FIFOs and metadata-only entries
+                _ => tracing::debug!(entry = %name.display(), ?kind, "skipped archive entry"),
+            }
+        }
+        writers.finish()
+    }
+}
+
+/// How many symlinks resolving one may pass through, as on Linux
+const MAX_LINK_HOPS: u32 = 40;
+
+/// A symlink that leads back to itself
+struct LinkLoop;
+
+/// The longest symlink target an archive entry may give, Linux's `PATH_MAX`
+const MAX_LINK_TARGET: u64 = 4096;
+
+/// Tar entries up to this size are handed to the writer threads; larger ones
+/// are streamed to disk rather than held in memory
+const BUFFERED_ENTRY_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The most threads writing tar entries at once
+const MAX_WRITERS: usize = 4;
+
+/// A tar entry read into memory, waiting to be written
+struct BufferedFile {
+    path: PathBuf,
+    data: Vec<u8>,
+    mode: Option<u32>,
+}
+
+/// Threads writing buffered tar entries while the archive is still being
+/// read. The queue is bounded, so memory use is too. Writes only run
+/// concurrently with each other between links and repeated paths, which
+/// wait for them to finish
+#[derive(Default)]
+struct WriterPool {
+    queue: Option<std::sync::mpsc::SyncSender<BufferedFile>>,
+    workers: Vec<std::thread::JoinHandle<std::io::Result<()>>>,
+    /// Paths queued since the writers last finished
+    queued: std::collections::HashSet<PathBuf>,
+}
+
+impl WriterPool {
+    fn write(&mut self, file: BufferedFile) -> std::io::Result<()> {
+        self.wait_for(&file.path)?;
+        self.queued.insert(file.path.clone());
+        let queue = self.queue.get_or_insert_with(|| {
+            let (queue, workers) = start_writers();
+            self.workers = workers;
+            queue
+        });
+        if queue.send(file).is_err() {
+            // Every writer stopped, so one failed; finishing says why
+            self.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Finishes the queued writes first if one of them is to `path`, so the
+    /// last entry for a path is the one left on disk
+    fn wait_for(&mut self, path: &Path) -> std::io::Result<()> {
+        if self.queued.contains(path) {
+            self.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Waits for every queued write, returning the first that failed
+    fn finish(&mut self) -> std::io::Result<()> {
+        self.queue = None;
+        self.queued.clear();
+        let mut result = Ok(());
+        for worker in self.workers.drain(..) {
+            let outcome = worker
+                .join()
+                .unwrap_or_else(|_| Err(std::io::Error::other("an archive writer thread panicked")));
+            if result.is_ok() {
+                result = outcome;
+            }
+        }
+        result
+    }
+}
+
+impl Drop for WriterPool {
+    /// Nothing is left writing into a directory that is about to be removed
+    /// after a failed extraction
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+fn start_writers() -> (std::sync::mpsc::SyncSender<BufferedFile>, Vec<std::thread::JoinHandle<std::io::Result<()>>>) {
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(MAX_WRITERS);
+    let (queue, receiver) = std::sync::mpsc::sync_channel::<BufferedFile>(threads * 2);
+    let receiver = std::sync::Arc::new(std::sync::Mutex::new(receiver));
+    let workers = (0..threads)
+        .map(|_| {
+            let receiver = receiver.clone();
+            std::thread::spawn(move || loop {
+                let next = receiver
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .recv();
+                let Ok(file) = next else {
+                    return Ok(());
+                };
+                let mut out = create_entry_file(&file.path)?;
+                std::io::Write::write_all(&mut out, &file.data)?;
+                if let Some(mode) = file.mode {
+                    set_mode(&file.path, mode & 0o777)?;
+                }
+            })
+        })
+        .collect();
+    (queue, workers)
+}
+
+/// Creates the file for an archive entry along with its directory, replacing
+/// a symlink unpacked earlier rather than writing through it
+fn create_entry_file(path: &Path) -> std::io::Result<std::fs::File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if path.symlink_metadata().is_ok_and(|m| m.file_type().is_symlink()) {
+        std::fs::remove_file(path)?;
+    }
+    std::fs::File::create(path)
+}
+
+fn unsafe_entry(name: &Path, problem: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("entry {} {}", name.display(), problem),
+    )
+}
+
+/// Reads the target of the symlink entry `name` from its data. The data
+/// isn't counted against the size limit, so it is read no further than a
+/// path can be long
+pub(crate) fn link_target(name: &Path, data: &mut impl Read) -> std::io::Result<PathBuf> {
+    let mut target = String::new();
+    data.take(MAX_LINK_TARGET + 1).read_to_string(&mut target)?;
+    if target.len() as u64 > MAX_LINK_TARGET {
+        return Err(unsafe_entry(name, "has a symlink target longer than a path can be"));
+    }
+    Ok(PathBuf::from(target))
+}
+
+fn unpack_zip(file: std::fs::File, unpacker: &mut Unpacker) -> std::io::Result<()> {
+    let mut archive = zip::ZipArchive::new(file)?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let name = PathBuf::from(entry.name());
+        let path = unpacker.entry_path(&name)?;
+        let mode = entry.unix_mode();
+        if entry.is_dir() {
+            std::fs::create_dir_all(&path)?;
+        } else if mode.is_some_and(|mode| mode & 0o170000 == 0o120000) {
+            let target = link_target(&name, &mut entry)?;
+            unpacker.symlink(&path, &target)?;
+        } else {
+            unpacker.write_file(&path, &mut entry, mode)?;
+        }
+    }
+    Ok(())
+}
+
+fn unpack_7z(archive_path: &Path, unpacker: &mut Unpacker) -> std::result::Result<(), sevenz_rust::Error> {
+    sevenz_rust::decompress_file_with_extract_fn(archive_path, unpacker.target_dir, |entry, reader, _| {
+        let path = unpacker.entry_path(Path::new(entry.name())).map_err(sevenz_rust::Error::io)?;
+        if entry.is_directory() {
+            std::fs::create_dir_all(&path).map_err(sevenz_rust::Error::io)?;
+        } else {
+            unpacker.write_file(&path, reader, None).map_err(sevenz_rust::Error::io)?;
+        }
+        Ok(true)
+    })
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+pub fn get_platform() -> &'static str {
+    #[cfg(target_os = "windows")]
+    return "windows";
+
+    #[cfg(target_os = "macos")]
+    return "macos";
+
+    #[cfg(target_os = "linux")]
+    return "linux";
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    return "unknown";
+}
+
+pub fn get_arch() -> &'static str {
+    #[cfg(target_arch = "x86_64")]
+    return "x86_64";
+
+    #[cfg(target_arch = "aarch64")]
+    return "aarch64";
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    return "unknown";
+}
+
+pub fn get_binary_extension() -> &'static str {
+    #[cfg(target_os = "windows")]
+    return "exe";
+
+    #[cfg(not(target_os = "windows"))]
+    return "";
+}
+
+/// Returns whether the given path points to a binary file
+pub fn is_binary(path: &Path) -> bool {
+    #[cfg(target_os = "windows")]
+    return path.extension().is_some_and(|ext| ext == "exe");
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        // On Unix systems, check if the file is executable
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+}
+
+/// Lists every file under `dir`, shallowest first, without following symlinked
+/// directories
+pub fn walk_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = std::collections::VecDeque::from([dir.to_path_buf()]);
+
+    while let Some(current) = pending.pop_front() {
+        let mut entries: Vec<_> = std::fs::read_dir(&current)?.collect::<std::io::Result<_>>()?;
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push_back(entry.path());
+            } else {
+                files.push(entry.path());
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Finds the executable for a package in an extracted tree: the shallowest
+/// file called `name` (with or without the platform's binary extension), or
+/// failing that the only executable file in the tree
+pub fn find_executable(dir: &Path, name: &str) -> Result<Option<PathBuf>> {
+    let files = walk_files(dir)?;
+    let extension = get_binary_extension();
+    let with_extension = format!("{}.{}", name, extension);
+
+    let by_name = files.iter().find(|path| {
+        path.file_name().is_some_and(|file_name| {
+            file_name == name || (!extension.is_empty() && file_name == with_extension.as_str())
+        })
+    });
+    if let Some(path) = by_name {
+        return Ok(Some(path.clone()));
+    }
+
+    let mut executables = files.into_iter().filter(|path| is_binary(path));
+    match (executables.next(), executables.next()) {
+        (Some(only), None) => Ok(Some(only)),
+        _ => Ok(None),
+    }
+}
+
+/// Moves the contents of a lone top-level directory (as in
+/// `tool-v1.2.0-linux-x86_64/tool`) up into `dir`
+pub fn flatten_single_dir(dir: &Path) -> Result<()> {
+    let entries: Vec<_> = std::fs::read_dir(dir)?.collect::<std::io::Result<_>>()?;
+    let [entry] = entries.as_slice() else {
+        return Ok(());
+    };
+    if !entry.file_type()?.is_dir() {
+        return Ok(());
+    }
+
+    // Move the wrapper aside first in case it contains an entry of the same name
+    let wrapper = dir.join(".grip-flatten");
+    std::fs::rename(entry.path(), &wrapper)?;
+    for child in std::fs::read_dir(&wrapper)? {
+        let child = child?;
+        std::fs::rename(child.path(), dir.join(child.file_name()))?;
+    }
+    std::fs::remove_dir(wrapper)?;
+
+    Ok(())
+}
+
+/// Formats a byte count for humans, as in "12.3 MB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Total size of the files under `path`, in bytes. Symlinks aren't followed
+/// and entries that can't be read are skipped
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| dir_size(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Formats seconds since the Unix epoch as a UTC date, as in "2024-05-17"
+pub fn format_date(timestamp: u64) -> String {
+    // Howard Hinnant's days-to-civil conversion
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time, as in
+/// "2024-05-17 13:45"
+pub fn format_time(timestamp: u64) -> String {
+    let seconds = timestamp % 86_400;
+    format!("{} {:02}:{:02}", format_date(timestamp), seconds / 3600, seconds % 3600 / 60)
+}
+
+/// Formats how long ago something happened, as in "5m ago" or "3d ago"
+pub fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", seconds / 60),
+        3600..86_400 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86_400),
+    }
+}
+
+/// Parses a duration like "30m", "24h", "7d" or "1w"; a bare number is seconds
+pub fn parse_duration(text: &str) -> Option<std::time::Duration> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let seconds = match unit.trim() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => return None,
+    };
+    let number: u64 = number.parse().ok()?;
+    Some(std::time::Duration::from_secs(number.checked_mul(seconds)?))
+}
+
+/// Make a file executable
+pub fn make_executable(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = std::fs::metadata(path)?.permissions();
+        perms.set_mode(0o755);
+        std::fs::set_permissions(path, perms)?;
+    }
+    Ok(())
+}
+
+/// Reads a file, or stdin when `path` is `-`
+pub fn read_input(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        Ok(std::io::read_to_string(std::io::stdin())?)
+    } else {
+        Ok(std::fs::read_to_string(path)?)
+    }
+}
+
+/// Expand environment variables in a path string
+pub fn expand_path(path: &str) -> String {
+    let mut result = path.to_string();
+
+    if let Ok(home) = std::env::var("HOME") {
+        result = result.replace("$HOME", &home);
+        result = result.replace("~", &home);
+    }
+
+    #[cfg(windows)]
+    {
+        use std::env;
+        for (key, value) in env::vars() {
+            result = result.replace(&format!("%{}%", key), &value);
+        }
+    }
+
+    result
+}
+
+/// Renames the last component of `path` to `name`, keeping an extension
+/// Windows needs to run the file. Others, such as `.AppImage` or the version
+/// in `tool-1.2.0`, are dropped
+pub fn change_file_name(path: impl AsRef<Path>, name: String) -> PathBuf {
+    let path = path.as_ref();
+    let mut result = path.to_owned();
+    result.set_file_name(name);
+    if let Some(ext) = path.extension() {
+        let runnable = ["exe", "com", "bat", "cmd", "ps1"]
+            .iter()
+            .any(|runnable| ext.eq_ignore_ascii_case(runnable));
+        if cfg!(windows) && runnable {
+            result.set_extension(ext);
+        }
+    }
+    result
+}
+
+/// Create a symlink
+#[cfg(unix)]
+pub fn create_symlink(src: &Path, dst: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(src, dst)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+#[allow(dead_code)]
+pub fn create_symlink(src: &Path, dst: &Path) -> Result<()> {
+    if is_binary(src) {
+        std::os::windows::fs::symlink_file(src, dst)?;
+    } else {
+        std::os::windows::fs::symlink_dir(src, dst)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    enum Entry<'a> {
+        File(&'a str, &'a [u8]),
+        Symlink(&'a str, &'a str),
+    }
+
+    /// A tarball whose entry names are written as given, `..` and all
+    fn tarball(entries: &[Entry]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for entry in entries {
+            let mut header = tar::Header::new_gnu();
+            let (name, data) = match entry {
+                Entry::File(name, data) => {
+                    header.set_entry_type(tar::EntryType::Regular);
+                    (name, *data)
+                }
+                Entry::Symlink(name, target) => {
+                    header.set_entry_type(tar::EntryType::Symlink);
+                    header.set_link_name(target).unwrap();
+                    (name, &[][..])
+                }
+            };
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(data.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder.append(&header, data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn zip(entries: &[Entry]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        for entry in entries {
+            match entry {
+                Entry::File(name, data) => {
+                    writer.start_file(*name, options).unwrap();
+                    writer.write_all(data).unwrap();
+                }
+                Entry::Symlink(name, target) => writer.add_symlink(*name, *target, options).unwrap(),
+            }
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// Extracts `archive` into `out` in a fresh directory, so entries that
+    /// escape it land next to `out` where the tests can look for them
+    async fn extract(archive: &[u8], max_size: u64) -> (tempfile::TempDir, Result<ArchiveFormat>) {
+        extract_named("archive", archive, max_size).await
+    }
+
+    async fn extract_named(name: &str, archive: &[u8], max_size: u64) -> (tempfile::TempDir, Result<ArchiveFormat>) {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join(name);
+        std::fs::write(&archive_path, archive).unwrap();
+        let result = extract_archive(&archive_path, &dir.path().join("out"), max_size).await;
+        (dir, result)
+    }
+
+    fn files(dir: &Path) -> Vec<String> {
+        walk_files(dir)
+            .unwrap()
+            .iter()
+            .map(|file| file.strip_prefix(dir).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    fn assert_rejected(result: Result<ArchiveFormat>, problem: &str) {
+        match result {
+            Err(GripError::ExtractionError(message)) => {
+                assert!(message.contains(problem), "unexpected error: {}", message)
+            }
+            other => panic!("expected an extraction error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn unpacks_a_well_formed_tarball() {
+        let archive = gzip(&tarball(&[
+            Entry::File("tool/bin/tool", b"#!/bin/sh\n"),
+            Entry::File("tool/docs/../README", b"readme"),
+            Entry::Symlink("tool/bin/alias", "tool"),
+        ]));
+        let (dir, result) = extract(&archive, 0).await;
+        result.unwrap();
+
+        let out = dir.path().join("out");
+        assert!(is_binary(&out.join("tool/bin/tool")));
+        assert_eq!(std::fs::read(out.join("tool/README")).unwrap(), b"readme");
+        #[cfg(unix)]
+        assert_eq!(std::fs::read_link(out.join("tool/bin/alias")).unwrap(), Path::new("tool"));
+    }
+
+    #[tokio::test]
+    async fn unpacks_many_tar_entries_in_order() {
+        let names: Vec<String> = (0..200).map(|i| format!("tool/share/{}/file", i % 20)).collect();
+        let mut entries: Vec<Entry> = names.iter().map(|name| Entry::File(name, name.as_bytes())).collect();
+        // A later entry for the same path replaces the earlier one
+        entries.push(Entry::File("tool/share/3/file", b"last"));
+        let large = vec![7u8; BUFFERED_ENTRY_SIZE as usize + 1];
+        entries.push(Entry::File("tool/share/4/file", &large));
+        let (dir, result) = extract(&gzip(&tarball(&entries)), 0).await;
+        result.unwrap();
+
+        let out = dir.path().join("out/tool/share");
+        assert_eq!(std::fs::read(out.join("0/file")).unwrap(), b"tool/share/0/file");
+        assert_eq!(std::fs::read(out.join("3/file")).unwrap(), b"last");
+        assert_eq!(std::fs::read(out.join("4/file")).unwrap(), large);
+    }
+
+    #[tokio::test]
+    async fn recognises_tarballs_without_the_ustar_magic() {
+        let mut header = tar::Header::new_old();
+        header.set_path("tool").unwrap();
+        header.set_size(4);
+        header.set_mode(0o755);
+        header.set_cksum();
+        let mut builder = tar::Builder::new(Vec::new());
+        builder.append(&header, &b"tool"[..]).unwrap();
+        let archive = builder.into_inner().unwrap();
+
+        let (dir, result) = extract(&archive, 0).await;
+        assert_eq!(result.unwrap(), ArchiveFormat::Tar);
+        assert_eq!(files(&dir.path().join("out")), ["tool"]);
+    }
+
+    #[tokio::test]
+    async fn decompresses_a_compressed_executable() {
+        let (dir, result) = extract_named("tool-linux-amd64.gz", &gzip(b"\x7fELF binary"), 0).await;
+        assert_eq!(result.unwrap(), ArchiveFormat::Gzip);
+        let out = dir.path().join("out");
+        assert_eq!(files(&out), ["tool-linux-amd64"]);
+        assert_eq!(std::fs::read(out.join("tool-linux-amd64")).unwrap(), b"\x7fELF binary");
+        assert!(is_binary(&out.join("tool-linux-amd64")));
+
+        let compressed = zstd::encode_all(&b"#!/bin/sh\n"[..], 0).unwrap();
+        let (dir, result) = extract_named("tool.ZST", &compressed, 0).await;
+        assert_eq!(result.unwrap(), ArchiveFormat::Zstd);
+        assert_eq!(files(&dir.path().join("out")), ["tool"]);
+
+        // Without a compression extension the name still differs from the download's
+        let (dir, result) = extract_named("tool", &gzip(b"data"), 0).await;
+        assert_eq!(result.unwrap(), ArchiveFormat::Gzip);
+        assert_eq!(files(&dir.path().join("out")), ["tool.out"]);
+    }
+
+    #[tokio::test]
+    async fn unpacks_archives_nested_in_another() {
+        let inner = gzip(&tarball(&[Entry::File("tool/bin/tool", b"#!/bin/sh\n")]));
+        let (dir, result) = extract(&zip(&[Entry::File("dist/tool.tar.gz", &inner)]), 0).await;
+        assert_eq!(result.unwrap(), ArchiveFormat::TarGz);
+        assert_eq!(files(&dir.path().join("out")), ["tool/bin/tool"]);
+
+        // A compressed executable inside a zip
+        let (dir, result) = extract(&zip(&[Entry::File("tool.gz", &gzip(b"binary"))]), 0).await;
+        assert_eq!(result.unwrap(), ArchiveFormat::Gzip);
+        assert_eq!(files(&dir.path().join("out")), ["tool"]);
+    }
+
+    #[tokio::test]
+    async fn leaves_nested_archives_that_are_not_alone_or_are_packages() {
+        let inner = gzip(&tarball(&[Entry::File("tool", b"x")]));
+        let archive = zip(&[Entry::File("tool.tar.gz", &inner), Entry::File("README", b"readme")]);
+        let (dir, result) = extract(&archive, 0).await;
+        assert_eq!(result.unwrap(), ArchiveFormat::Zip);
+        assert_eq!(files(&dir.path().join("out")), ["README", "tool.tar.gz"]);
+
+        // A jar is a zip, but one to install rather than unpack
+        let jar = zip(&[Entry::File("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\n")]);
+        let (dir, result) = extract(&gzip(&tarball(&[Entry::File("tool.jar", &jar)])), 0).await;
+        assert_eq!(result.unwrap(), ArchiveFormat::TarGz);
+        assert_eq!(files(&dir.path().join("out")), ["tool.jar"]);
+    }
+
+    #[tokio::test]
+    async fn limits_how_deep_archives_nest() {
+        let mut archive = gzip(b"binary");
+        for depth in 0..MAX_NESTED_ARCHIVES {
+            archive = zip(&[Entry::File(&format!("level{}.{}", depth, if depth == 0 { "gz" } else { "zip" }), &archive)]);
+        }
+        let (dir, result) = extract(&archive, 0).await;
+        assert_eq!(result.unwrap(), ArchiveFormat::Gzip);
+        assert_eq!(files(&dir.path().join("out")), ["level0"]);
+
+        let archive = zip(&[Entry::File("level.zip", &archive)]);
+        let (_dir, result) = extract(&archive, 0).await;
+        assert_rejected(result, "nests archives more than 3 deep");
+    }
+
+    #[tokio::test]
+    async fn rejects_tar_entries_outside_the_target() {
+        let (dir, result) = extract(&tarball(&[Entry::File("../outside", b"x")]), 0).await;
+        assert_rejected(result, "escapes the target directory");
+        assert!(!dir.path().join("outside").exists());
+
+        let (_dir, result) = extract(&tarball(&[Entry::File("a/../../outside", b"x")]), 0).await;
+        assert_rejected(result, "escapes the target directory");
+
+        let (_dir, result) = extract(&tarball(&[Entry::File("/tmp/grip-outside", b"x")]), 0).await;
+        assert_rejected(result, "has an absolute path");
+        assert!(!Path::new("/tmp/grip-outside").exists());
+    }
+
+    #[tokio::test]
+    async fn rejects_tar_symlinks_outside_the_target() {
+        let (_dir, result) = extract(&tarball(&[Entry::Symlink("link", "../outside")]), 0).await;
+        assert_rejected(result, "pointing outside the target directory");
+
+        let (_dir, result) = extract(&tarball(&[Entry::Symlink("a/b/link", "../../../outside")]), 0).await;
+        assert_rejected(result, "pointing outside the target directory");
+
+        let (_dir, result) = extract(&tarball(&[Entry::Symlink("link", "/etc")]), 0).await;
+        assert_rejected(result, "symlink to an absolute path");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn rejects_tar_symlinks_chained_outside_the_target() {
+        // `y` looks like it stays inside, but `x` is the target itself
+        let archive = tarball(&[Entry::Symlink("x", "."), Entry::Symlink("y", "x/..")]);
+        let (_dir, result) = extract(&archive, 0).await;
+        assert_rejected(result, "pointing outside the target directory");
+
+        let archive = tarball(&[
+            Entry::Symlink("a", "."),
+            Entry::Symlink("b", "a/a/a"),
+            Entry::Symlink("c", "b/../etc"),
+        ]);
+        let (_dir, result) = extract(&archive, 0).await;
+        assert_rejected(result, "pointing outside the target directory");
+
+        // Only a link made afterwards makes `y` lead outside
+        let archive = tarball(&[Entry::Symlink("y", "x/.."), Entry::Symlink("x", ".")]);
+        let (_dir, result) = extract(&archive, 0).await;
+        assert_rejected(result, "pointing outside the target directory");
+
+        let archive = tarball(&[Entry::Symlink("loop", "loop")]);
+        let (_dir, result) = extract(&archive, 0).await;
+        assert_rejected(result, "symlink loop");
+
+        // Chains that stay inside are fine
+        let archive = tarball(&[
+            Entry::File("bin/tool", b"x"),
+            Entry::Symlink("current", "bin"),
+            Entry::Symlink("alias", "current/../current/tool"),
+        ]);
+        let (dir, result) = extract(&archive, 0).await;
+        result.unwrap();
+        assert_eq!(std::fs::read(dir.path().join("out/alias")).unwrap(), b"x");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn rejects_entries_written_through_a_symlink() {
+        // Each symlink stays inside, but a link made through `here` would
+        // resolve from the wrong directory
+        let archive = tarball(&[
+            Entry::Symlink("sub/here", ".."),
+            Entry::Symlink("sub/here/escape", "../outside"),
+        ]);
+        let (_dir, result) = extract(&archive, 0).await;
+        assert_rejected(result, "is inside a symlink");
+    }
+
+    #[tokio::test]
+    async fn rejects_zip_entries_outside_the_target() {
+        let (dir, result) = extract(&zip(&[Entry::File("../outside", b"x")]), 0).await;
+        assert_rejected(result, "escapes the target directory");
+        assert!(!dir.path().join("outside").exists());
+
+        let (_dir, result) = extract(&zip(&[Entry::Symlink("link", "../../etc")]), 0).await;
+        assert_rejected(result, "pointing outside the target directory");
+    }
+
+    #[tokio::test]
+    async fn rejects_symlink_targets_longer_than_a_path() {
+        let target = "a/".repeat(MAX_LINK_TARGET as usize);
+        let (_dir, result) = extract(&zip(&[Entry::Symlink("link", &target)]), 0).await;
+        assert_rejected(result, "has a symlink target longer than a path can be");
+    }
+
+    #[tokio::test]
+    async fn enforces_the_size_limit() {
+        let zeros = vec![0u8; 2 * 1024 * 1024];
+        let limit = 1024 * 1024;
+
+        let (_dir, result) = extract(&zip(&[Entry::File("bomb", &zeros)]), limit).await;
+        assert_rejected(result, "unpacks to more than 1.0 MB");
+
+        let archive = gzip(&tarball(&[Entry::File("a", &zeros[..limit as usize]), Entry::File("b", b"x")]));
+        let (_dir, result) = extract(&archive, limit).await;
+        assert_rejected(result, "unpacks to more than 1.0 MB");
+
+        let (_dir, result) = extract(&archive, 0).await;
+        result.unwrap();
+    }
+}
@@ -0,0 +1,56 @@
+use std::path::{Path, PathBuf};
+
+/// The files read for pinned versions, in the order they are read within a
+/// directory: grip's own, then asdf's
+pub const VERSION_FILES: [&str; 2] = [".grip-versions", ".tool-versions"];
+
+/// The versions a version file pins a package to, most preferred first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pin {
+    pub versions: Vec<String>,
+    pub file: PathBuf,
+}
+
+/// The pin for `package` in the nearest version file in `dir` or its parents
+/// that mentions it. `system`, as asdf writes it, counts as no pin, leaving the
+/// active version in charge
+pub fn find(dir: &Path, package: &str) -> Option<Pin> {
+    for dir in dir.ancestors() {
+        for name in VERSION_FILES {
+            let file = dir.join(name);
+            let Ok(content) = std::fs::read_to_string(&file) else {
+                continue;
+            };
+            let Some(versions) = parse(&content).into_iter().find(|(name, _)| name == package).map(|(_, v)| v)
+            else {
+                continue;
+            };
+            if versions.first().is_none_or(|v| v == "system") {
+                return None;
+            }
+            return Some(Pin { versions, file });
+        }
+    }
+    None
+}
+
+/// Reads `<package> <version> [<fallback>...]` lines, skipping blank lines
+/// and `#` comments
+fn parse(content: &str) -> Vec<(String, Vec<String>)> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?.to_string();
+            let versions: Vec<String> = fields.map(str::to_string).collect();
+            (!versions.is_empty()).then_some((name, versions))
+        })
+        .collect()
+}
+
+/// Whether an installed tag is the version a pin names, with or without a
+/// leading `v`
+pub fn matches(tag: &str, version: &str) -> bool {
+    tag == version || tag.trim_start_matches('v') == version.trim_start_matches('v')
+}
@@ -0,0 +1,233 @@
+use crate::package::Package;
+use crate::server::{MockServer, Response};
+use serde_json::{json, Value};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+
+/// Variables that would point grip at the user's own setup or credentials
+const CLEARED_VARS: &[&str] = &["GITHUB_TOKEN", "GH_TOKEN", "GH_HOST", "GH_ENTERPRISE_TOKEN", "GITLAB_TOKEN"];
+
+/// A grip that only sees a temporary directory and a [`MockServer`]: its home,
+/// data directory and config live in the directory, its one registry is a git
+/// repository the server serves, and github.com API requests go to the server.
+/// Nothing outside is read or changed, so tests can run side by side
+pub struct TestEnv {
+    dir: tempfile::TempDir,
+    server: MockServer,
+    grip: PathBuf,
+}
+
+/// How a grip run ended and what it printed
+#[derive(Debug)]
+pub struct Run {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Run {
+    pub fn code(&self) -> Option<i32> {
+        self.status.code()
+    }
+
+    /// Panics with the output unless grip succeeded
+    #[track_caller]
+    pub fn success(&self) -> &Self {
+        assert!(self.status.success(), "grip failed with {}:\n{}\n{}", self.status, self.stdout, self.stderr);
+        self
+    }
+
+    /// Panics with the output unless grip exited with `code`
+    #[track_caller]
+    pub fn failure(&self, code: i32) -> &Self {
+        assert_eq!(self.code(), Some(code), "grip exited unexpectedly:\n{}\n{}", self.stdout, self.stderr);
+        self
+    }
+
+    /// stdout as JSON, for runs with `--output json`
+    #[track_caller]
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.stdout).unwrap_or_else(|e| panic!("stdout isn't JSON ({}):\n{}", e, self.stdout))
+    }
+}
+
+impl TestEnv {
+    /// Sets up an empty registry and a config using it for the grip binary at
+    /// `grip`, which integration tests get from `env!("CARGO_BIN_EXE_grip")`
+    pub fn new(grip: impl Into<PathBuf>) -> Self {
+        let env = Self {
+            dir: tempfile::tempdir().expect("failed to create a temporary directory"),
+            server: MockServer::start(),
+            grip: grip.into(),
+        };
+        std::fs::create_dir_all(env.home()).unwrap();
+        env.server.mount_git("/git", env.dir.path().join("git"));
+
+        let registry = env.registry_dir();
+        std::fs::create_dir_all(registry.join("packages")).unwrap();
+        std::fs::write(registry.join("README.md"), "A registry for grip's tests\n").unwrap();
+        env.git(&["init", "--quiet", "--initial-branch", "main"]);
+        env.commit("Create the registry");
+
+        let config = json!({
+            "registries": [{ "name": "mock", "url": format!("{}/git/registry", env.server.url()), "priority": 100 }],
+            "default_registry": format!("{}/git/registry", env.server.url()),
+            "cache_ttl": 0,
+            "github_api_url": env.server.url(),
+            "settings": { "modify_path": false },
+            "network": { "retries": 0, "timeout_secs": 10, "backoff_ms": 0 },
+        });
+        std::fs::write(env.config_file(), serde_json::to_string_pretty(&config).unwrap()).unwrap();
+        env
+    }
+
+    pub fn server(&self) -> &MockServer {
+        &self.server
+    }
+
+    /// The directory grip is given as its home
+    pub fn home(&self) -> PathBuf {
+        self.dir.path().join("home")
+    }
+
+    pub fn data_dir(&self) -> PathBuf {
+        self.dir.path().join("data")
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.dir.path().join("config.json")
+    }
+
+    /// Where the shims of installed packages are linked
+    pub fn bin_dir(&self) -> PathBuf {
+        self.data_dir().join("bin")
+    }
+
+    /// The working copy of the registry the server serves
+    pub fn registry_dir(&self) -> PathBuf {
+        self.dir.path().join("git").join("registry")
+    }
+
+    /// Changes a setting of the config, given as a dotted key
+    pub fn set_config(&self, key: &str, value: Value) {
+        let mut config: Value = serde_json::from_str(&std::fs::read_to_string(self.config_file()).unwrap()).unwrap();
+        let mut target = &mut config;
+        let mut parts: Vec<&str> = key.split('.').collect();
+        let last = parts.pop().unwrap();
+        for part in parts {
+            target = target
+                .as_object_mut()
+                .unwrap()
+                .entry(part)
+                .or_insert_with(|| json!({}));
+        }
+        target[last] = value;
+        std::fs::write(self.config_file(), serde_json::to_string_pretty(&config).unwrap()).unwrap();
+    }
+
+    /// Adds `package` to the registry, or replaces it, and serves its releases
+    /// and their assets. Publishing a package again with another release is
+    /// how a new version comes out
+    pub fn publish(&self, package: &Package) {
+        let file = self.registry_dir().join("packages").join(format!("{}.json", package.name));
+        std::fs::write(&file, serde_json::to_string_pretty(&package.definition()).unwrap()).unwrap();
+        self.commit(&format!("Publish {}", package.name));
+
+        let releases: Vec<Value> = package
+            .releases
+            .iter()
+            .rev()
+            .map(|release| {
+                let assets: Vec<Value> = release
+                    .served_assets()
+                    .into_iter()
+                    .map(|asset| {
+                        let path = format!("/download/{}/{}/{}", package.repository, release.tag, asset.name);
+                        let value = json!({
+                            "name": asset.name,
+                            "size": asset.content.len(),
+                            "browser_download_url": format!("{}{}", self.server.url(), path),
+                        });
+                        self.server.route(&path, Response::new(200, asset.content));
+                        value
+                    })
+                    .collect();
+                json!({
+                    "tag_name": release.tag,
+                    "draft": false,
+                    "prerelease": release.prerelease,
+                    "body": format!("Release {}", release.tag),
+                    "published_at": "2024-01-01T00:00:00Z",
+                    "assets": assets,
+                })
+            })
+            .collect();
+        let repository = format!("/repos/{}", package.repository);
+        self.server.route(
+            &repository,
+            Response::json(&json!({ "full_name": package.repository, "license": { "spdx_id": "MIT" } })),
+        );
+        self.server.route(&format!("{}/releases", repository), Response::json(&Value::Array(releases)));
+    }
+
+    /// Runs grip with `args`, confined to the environment and never prompting
+    pub fn grip<I, S>(&self, args: I) -> Run
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut command = Command::new(&self.grip);
+        command.arg("--non-interactive").args(args);
+        self.run_command(command)
+    }
+
+    /// Runs a shim or another program the way a user of the environment would
+    pub fn run(&self, program: &Path, args: &[&str]) -> Run {
+        let mut command = Command::new(program);
+        command.args(args);
+        self.run_command(command)
+    }
+
+    fn run_command(&self, mut command: Command) -> Run {
+        for (var, _) in std::env::vars_os() {
+            let name = var.to_string_lossy();
+            if name.starts_with("GRIP_") || CLEARED_VARS.contains(&name.as_ref()) {
+                command.env_remove(&var);
+            }
+        }
+        command
+            .current_dir(self.dir.path())
+            .env("HOME", self.home())
+            .env("USERPROFILE", self.home())
+            .env("XDG_CONFIG_HOME", self.home().join(".config"))
+            .env("XDG_DATA_HOME", self.home().join(".local").join("share"))
+            .env("GRIP_CONFIG", self.config_file())
+            .env("GRIP_DATA_DIR", self.data_dir())
+            .env("GRIP_SYSTEM_CONFIG", self.dir.path().join("system.toml"))
+            .env("NO_COLOR", "1");
+        let output = command.output().expect("failed to start the program");
+        Run {
+            status: output.status,
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+
+    fn commit(&self, message: &str) {
+        self.git(&["add", "--all"]);
+        self.git(&["commit", "--quiet", "--allow-empty", "-m", message]);
+    }
+
+    fn git(&self, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=grip tests", "-c", "user.email=tests@example.com", "-c", "commit.gpgsign=false"])
+            .args(args)
+            .current_dir(self.registry_dir())
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .status()
+            .expect("failed to run git");
+        assert!(status.success(), "git {} failed", args.join(" "));
+    }
+}
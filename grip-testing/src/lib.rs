@@ -0,0 +1,24 @@
+//! Hermetic end-to-end tests for grip: a [`MockServer`] stands in for the
+//! GitHub API, release downloads and a git registry, and a [`TestEnv`] runs
+//! the grip binary against it in a temporary directory. Integration tests of
+//! the `grip` package find the binary through `env!("CARGO_BIN_EXE_grip")`:
+//!
+//! ```no_run
+//! use grip_testing::{platform_asset_name, script, Asset, Package, Release, TestEnv};
+//!
+//! # let grip = "target/debug/grip";
+//! let env = TestEnv::new(grip);
+//! let archive = platform_asset_name("tool", "1.0.0", "tar.gz");
+//! env.publish(&Package::new("tool").release(
+//!     Release::new("v1.0.0").asset(Asset::tar_gz(&archive, &[("tool", &script("tool 1.0.0"))])),
+//! ));
+//! env.grip(["install", "tool"]).success();
+//! ```
+
+mod env;
+mod package;
+mod server;
+
+pub use env::{Run, TestEnv};
+pub use package::{platform_asset_name, script, Asset, Package, Release};
+pub use server::{MockServer, Response};
@@ -0,0 +1,183 @@
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+
+/// A downloadable file of a release
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub name: String,
+    pub content: Vec<u8>,
+    /// The digest `SHA256SUMS` gives for the asset, when it should lie
+    published_sha256: Option<String>,
+}
+
+impl Asset {
+    pub fn new(name: &str, content: impl Into<Vec<u8>>) -> Self {
+        Self {
+            name: name.to_string(),
+            content: content.into(),
+            published_sha256: None,
+        }
+    }
+
+    /// A gzipped tarball holding `files`, all executable
+    pub fn tar_gz(name: &str, files: &[(&str, &[u8])]) -> Self {
+        let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        let mut builder = tar::Builder::new(encoder);
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *content).expect("failed to build a tarball");
+        }
+        let content = builder
+            .into_inner()
+            .and_then(|encoder| encoder.finish())
+            .expect("failed to build a tarball");
+        Self::new(name, content)
+    }
+
+    /// A zip archive holding `files`, all executable
+    pub fn zip(name: &str, files: &[(&str, &[u8])]) -> Self {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default().unix_permissions(0o755);
+        for (path, content) in files {
+            writer.start_file(*path, options).expect("failed to build a zip");
+            writer.write_all(content).expect("failed to build a zip");
+        }
+        let content = writer.finish().expect("failed to build a zip").into_inner();
+        Self::new(name, content)
+    }
+
+    /// Makes the release's `SHA256SUMS` give `digest` for the asset rather
+    /// than its real one, as a tampered download would look
+    pub fn published_sha256(mut self, digest: &str) -> Self {
+        self.published_sha256 = Some(digest.to_string());
+        self
+    }
+
+    pub fn sha256(&self) -> String {
+        format!("{:x}", Sha256::digest(&self.content))
+    }
+}
+
+/// A release of a package, served as the GitHub API describes one
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub tag: String,
+    pub prerelease: bool,
+    pub assets: Vec<Asset>,
+    /// Whether a `SHA256SUMS` asset is published with the others
+    pub checksums: bool,
+}
+
+impl Release {
+    pub fn new(tag: &str) -> Self {
+        Self {
+            tag: tag.to_string(),
+            prerelease: false,
+            assets: Vec::new(),
+            checksums: true,
+        }
+    }
+
+    pub fn asset(mut self, asset: Asset) -> Self {
+        self.assets.push(asset);
+        self
+    }
+
+    pub fn prerelease(mut self) -> Self {
+        self.prerelease = true;
+        self
+    }
+
+    /// Publishes no `SHA256SUMS`, so installs can't verify the assets
+    pub fn without_checksums(mut self) -> Self {
+        self.checksums = false;
+        self
+    }
+
+    /// The assets as served, with `SHA256SUMS` added when published
+    pub fn served_assets(&self) -> Vec<Asset> {
+        let mut assets = self.assets.clone();
+        if self.checksums {
+            let sums: String = self
+                .assets
+                .iter()
+                .map(|asset| {
+                    let digest = asset.published_sha256.clone().unwrap_or_else(|| asset.sha256());
+                    format!("{}  {}\n", digest, asset.name)
+                })
+                .collect();
+            assets.push(Asset::new("SHA256SUMS", sums));
+        }
+        assets
+    }
+}
+
+/// A registry package and the releases of the repository it points at
+#[derive(Debug, Clone)]
+pub struct Package {
+    pub name: String,
+    /// The `owner/repo` on github.com the releases are served for
+    pub repository: String,
+    /// Oldest first; the API lists them the other way round
+    pub releases: Vec<Release>,
+    /// Further fields of the registry entry, such as `dependencies`
+    pub fields: Map<String, Value>,
+}
+
+impl Package {
+    /// A package whose executable is named after it, in repository `mock/<name>`
+    pub fn new(name: &str) -> Self {
+        let mut fields = Map::new();
+        fields.insert("executable_name".to_string(), json!(name));
+        fields.insert("license".to_string(), json!("MIT"));
+        Self {
+            name: name.to_string(),
+            repository: format!("mock/{}", name),
+            releases: Vec::new(),
+            fields,
+        }
+    }
+
+    pub fn release(mut self, release: Release) -> Self {
+        self.releases.push(release);
+        self
+    }
+
+    /// Sets a field of the registry entry
+    pub fn field(mut self, key: &str, value: Value) -> Self {
+        self.fields.insert(key.to_string(), value);
+        self
+    }
+
+    /// The registry entry, as `packages/<name>.json` holds it
+    pub fn definition(&self) -> Value {
+        let mut definition = Map::new();
+        definition.insert("name".to_string(), json!(self.name));
+        definition.insert("description".to_string(), json!(format!("{} for tests", self.name)));
+        definition.insert("repository".to_string(), json!(self.repository));
+        definition.extend(self.fields.clone());
+        Value::Object(definition)
+    }
+}
+
+/// The name of an asset built for this machine, e.g.
+/// `tool-1.0.0-x86_64-linux.tar.gz`, which grip picks over other platforms'
+pub fn platform_asset_name(name: &str, version: &str, extension: &str) -> String {
+    format!(
+        "{}-{}-{}-{}.{}",
+        name,
+        version,
+        grip_core::utils::get_arch(),
+        grip_core::utils::get_platform(),
+        extension
+    )
+}
+
+/// A shell script that prints `output`, to stand in for a package's executable
+pub fn script(output: &str) -> Vec<u8> {
+    format!("#!/bin/sh\necho '{}'\n", output.replace('\'', "'\\''")).into_bytes()
+}
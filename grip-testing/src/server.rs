@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// What the server answers a request with
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn json(value: &serde_json::Value) -> Self {
+        Self::new(200, value.to_string()).header("Content-Type", "application/json")
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// One request, as far as the routes need it
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Default)]
+struct Routes {
+    /// Exact paths, without the query string
+    fixed: HashMap<String, Response>,
+    /// Path prefixes under which the git repositories in a directory are
+    /// served through `git http-backend`
+    git: Vec<(String, PathBuf)>,
+}
+
+impl Routes {
+    fn respond(&self, request: &Request) -> Response {
+        if let Some(response) = self.fixed.get(&request.path) {
+            return response.clone();
+        }
+        for (prefix, root) in &self.git {
+            if let Some(path_info) = request.path.strip_prefix(prefix.as_str()) {
+                return git_http_backend(root, path_info, request)
+                    .unwrap_or_else(|e| Response::new(500, e.to_string()));
+            }
+        }
+        Response::new(404, "not found")
+    }
+}
+
+/// Runs `git http-backend` as a CGI script for a request to a repository
+/// under `root`, so git's smart HTTP protocol, shallow clones included, works
+/// against the mock server
+fn git_http_backend(root: &Path, path_info: &str, request: &Request) -> std::io::Result<Response> {
+    let mut command = Command::new("git");
+    command
+        .arg("http-backend")
+        .env("GIT_PROJECT_ROOT", root)
+        .env("GIT_HTTP_EXPORT_ALL", "1")
+        .env("PATH_INFO", path_info)
+        .env("REQUEST_METHOD", &request.method)
+        .env("QUERY_STRING", &request.query)
+        .env("CONTENT_LENGTH", request.body.len().to_string())
+        .env("REMOTE_ADDR", "127.0.0.1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    for (header, var) in [
+        ("Content-Type", "CONTENT_TYPE"),
+        ("Content-Encoding", "HTTP_CONTENT_ENCODING"),
+        ("Git-Protocol", "GIT_PROTOCOL"),
+    ] {
+        if let Some(value) = request.header(header) {
+            command.env(var, value);
+        }
+    }
+    let mut child = command.spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let body = request.body.clone();
+    let writer = std::thread::spawn(move || stdin.write_all(&body));
+    let output = child.wait_with_output()?;
+    let _ = writer.join();
+
+    // CGI output: headers, with the status in a `Status` one, then the body
+    let split = output
+        .stdout
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map(|i| (i, i + 4))
+        .or_else(|| output.stdout.windows(2).position(|w| w == b"\n\n").map(|i| (i, i + 2)))
+        .unwrap_or((output.stdout.len(), output.stdout.len()));
+    let mut response = Response::new(200, output.stdout[split.1..].to_vec());
+    for line in String::from_utf8_lossy(&output.stdout[..split.0]).lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("Status") {
+            response.status = value.split_whitespace().next().and_then(|s| s.parse().ok()).unwrap_or(500);
+        } else {
+            response.headers.push((name.to_string(), value.to_string()));
+        }
+    }
+    Ok(response)
+}
+
+/// A plain HTTP/1.1 server on a local port that answers with canned
+/// responses, standing in for the GitHub API, asset downloads and registry
+/// remotes. Every request is logged, so tests can check what grip fetched.
+/// It stops when dropped
+pub struct MockServer {
+    addr: SocketAddr,
+    routes: Arc<Mutex<Routes>>,
+    requests: Arc<Mutex<Vec<String>>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl MockServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind the mock server");
+        let addr = listener.local_addr().expect("mock server has no address");
+        let server = Self {
+            addr,
+            routes: Arc::default(),
+            requests: Arc::default(),
+            stopped: Arc::default(),
+        };
+
+        let routes = server.routes.clone();
+        let requests = server.requests.clone();
+        let stopped = server.stopped.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let routes = routes.clone();
+                let requests = requests.clone();
+                std::thread::spawn(move || {
+                    let _ = serve(stream, &routes, &requests);
+                });
+            }
+        });
+        server
+    }
+
+    /// `http://127.0.0.1:<port>`
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Answers requests for `path` with `response` from now on
+    pub fn route(&self, path: &str, response: Response) {
+        self.routes.lock().unwrap().fixed.insert(path.to_string(), response);
+    }
+
+    /// Serves the git repositories in `root` at `prefix`, e.g. `root/registry`
+    /// at `<url>/<prefix>/registry`
+    pub fn mount_git(&self, prefix: &str, root: impl Into<PathBuf>) {
+        self.routes.lock().unwrap().git.push((prefix.to_string(), root.into()));
+    }
+
+    /// The requests made so far, as `METHOD /path?query`
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// How many requests were made for `path`, with any query string
+    pub fn hits(&self, path: &str) -> usize {
+        self.requests()
+            .iter()
+            .filter_map(|request| request.split_once(' '))
+            .filter(|(_, target)| target.split('?').next() == Some(path))
+            .count()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Wakes the accept loop up so it sees the flag
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+/// Answers the requests on one connection, until the client closes it
+fn serve(stream: TcpStream, routes: &Mutex<Routes>, requests: &Mutex<Vec<String>>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut stream = stream;
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line)? == 0 {
+            return Ok(());
+        }
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or_default().to_string();
+        requests.lock().unwrap().push(format!("{} {}", method, target));
+        let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+        let mut request = Request {
+            method: method.clone(),
+            path: path.to_string(),
+            query: query.to_string(),
+            headers,
+            body: Vec::new(),
+        };
+        request.body = read_body(&mut reader, &request)?;
+        let close = request.header("Connection").is_some_and(|c| c.eq_ignore_ascii_case("close"));
+
+        let response = routes.lock().unwrap().respond(&request);
+        write!(stream, "HTTP/1.1 {} {}\r\n", response.status, reason(response.status))?;
+        for (name, value) in &response.headers {
+            write!(stream, "{}: {}\r\n", name, value)?;
+        }
+        write!(stream, "Content-Length: {}\r\n\r\n", response.body.len())?;
+        if method != "HEAD" {
+            stream.write_all(&response.body)?;
+        }
+        stream.flush()?;
+        if close {
+            return Ok(());
+        }
+    }
+}
+
+/// Reads a request body sent with a `Content-Length` or chunked, as git
+/// sends larger ones
+fn read_body(reader: &mut impl BufRead, request: &Request) -> std::io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    if request.header("Transfer-Encoding").is_some_and(|t| t.eq_ignore_ascii_case("chunked")) {
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size)?;
+            let size = usize::from_str_radix(size.trim().split(';').next().unwrap_or_default(), 16)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk)?;
+            if size == 0 {
+                return Ok(body);
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    }
+    let length = request.header("Content-Length").and_then(|l| l.parse().ok()).unwrap_or(0);
+    reader.take(length).read_to_end(&mut body)?;
+    Ok(body)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        304 => "Not Modified",
+        403 => "Forbidden",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        _ => "",
+    }
+}
@@ -1,34 +1,478 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, ArgGroup, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
 #[command(author, version, about = "GitHub Release Package Manager")]
 pub struct Cli {
+    /// Never prompt; pick the latest version and best matching asset automatically
+    #[arg(short = 'y', long, global = true)]
+    pub non_interactive: bool,
+    /// Hide progress bars and status messages; warnings and errors still print
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+    /// Log to stderr: -v for info, -vv for debug, -vvv for trace (GRIP_LOG overrides)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+    /// Output format for command results
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+    /// How to report progress; json writes one event per line to stderr and never prompts
+    #[arg(long, global = true, value_enum, value_name = "FORMAT", default_value_t = ProgressFormat::Text)]
+    pub progress: ProgressFormat,
+    /// When to color output; auto colors a terminal unless NO_COLOR is set
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorMode::Auto)]
+    pub color: ColorMode,
+    /// Network timeout in seconds, overriding the config's network.timeout_secs
+    #[arg(long, global = true, value_name = "SECS")]
+    pub timeout: Option<u64>,
+    /// Use only cached registries, release lists and assets (also GRIP_OFFLINE=1)
+    #[arg(long, global = true)]
+    pub offline: bool,
+    /// Revalidate registries and cached release lists even if they are recent
+    #[arg(long, global = true, conflicts_with = "offline")]
+    pub refresh: bool,
+    /// Don't run post-install hooks or registry post-install steps
+    #[arg(long, global = true)]
+    pub no_hooks: bool,
+    /// Show what install, upgrade or uninstall would download, write and link, without changing anything
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    /// Show where the time went: resolution, API calls, downloads, extraction, linking
+    #[arg(long, global = true)]
+    pub timings: bool,
+    /// Write the time spent in each step as JSON to FILE, for benchmarks
+    #[arg(long, global = true, value_name = "FILE")]
+    pub timings_file: Option<PathBuf>,
+    /// Install into and manage ./.grip instead of the user's install root
+    #[arg(long, global = true)]
+    pub local: bool,
+    /// Keep the cache, registries, logs and by default packages here (also GRIP_DATA_DIR)
+    #[arg(long, global = true, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Read and write settings in this config file (also GRIP_CONFIG)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
     #[command(subcommand)]
     pub command: Commands,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable, colored text
+    Text,
+    /// Structured JSON on stdout, with status messages on stderr
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProgressFormat {
+    /// Status messages and progress bars
+    Text,
+    /// Newline-delimited JSON events on stderr, for programs drawing their own progress
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    /// A Homebrew Brewfile
+    Brewfile,
+    /// The output of `scoop export`
+    Scoop,
+    /// The output of `winget export`
+    Winget,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ShellKind {
+    Bash,
+    Zsh,
+    Fish,
+    #[value(alias = "pwsh")]
+    Powershell,
+    #[value(alias = "nushell")]
+    Nu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListSortKind {
+    Name,
+    /// Largest first
+    Size,
+    /// Most recently installed first
+    #[value(alias = "date")]
+    Installed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SbomFormatKind {
+    /// CycloneDX 1.5 JSON
+    Cyclonedx,
+    /// SPDX 2.3 JSON
+    Spdx,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum Commands {
-    /// Install a package
+    /// Install a package, or every dependency in grip.json when no package is given
+    #[command(group(ArgGroup::new("target").args(["packages", "repo"]).multiple(true)))]
     Install {
-        /// Package name to install
-        package: String,
-        /// Specific version to install
-        #[arg(short, long)]
+        /// Package names (optionally registry/name or name@version), or repositories such as gh:owner/repo
+        packages: Vec<String>,
+        /// Install from a GitHub repository's releases without a registry entry
+        #[arg(long, value_name = "OWNER/REPO")]
+        repo: Option<String>,
+        /// Install an archive or binary from a URL, as PACKAGE at --version
+        #[arg(long, requires_all = ["packages", "version"], conflicts_with_all = ["repo", "asset", "file"])]
+        url: Option<String>,
+        /// Install a local archive or binary, as PACKAGE at --version
+        #[arg(long, requires_all = ["packages", "version"], conflicts_with_all = ["repo", "asset"])]
+        file: Option<PathBuf>,
+        /// Version or semver range to install (e.g. v1.2.3, ^1.2, ">=2,<3")
+        #[arg(long, requires = "target")]
         version: Option<String>,
         /// Specific asset to install
-        #[arg(short, long)]
+        #[arg(short, long, requires = "target")]
         asset: Option<String>,
+        /// Always prompt for the asset instead of selecting one automatically
+        #[arg(short, long)]
+        interactive: bool,
+        /// Allow pre-releases to satisfy --version
+        #[arg(long)]
+        pre: bool,
+        /// Install exactly what grip.lock records, failing if it is out of date
+        #[arg(long, conflicts_with = "target")]
+        frozen: bool,
+        /// How many packages to download at once
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Look packages up only in this registry
+        #[arg(long, conflicts_with_all = ["url", "file", "frozen"])]
+        registry: Option<String>,
+        /// Install again even if the version is already installed, replacing its directory
+        #[arg(long, visible_alias = "force")]
+        reinstall: bool,
+    },
+    /// Make the project's .grip match grip.json and grip.lock, installing what
+    /// is missing and removing what no dependency needs
+    Sync {
+        /// Only report the differences (exits with 1 if there are any)
+        #[arg(long)]
+        check: bool,
+    },
+    /// Uninstall a package and all of its installed versions
+    Uninstall {
+        /// Package name to uninstall
+        package: String,
+        /// Uninstall even if other installed packages depend on it
+        #[arg(long)]
+        force: bool,
+    },
+    /// Switch the active version of an installed package
+    #[command(alias = "switch")]
+    Use {
+        /// Package name
+        package: String,
+        /// Installed version to activate
+        version: String,
+    },
+    /// Upgrade installed packages to their latest release
+    Upgrade {
+        /// Package name to upgrade
+        #[arg(required_unless_present_any = ["all", "interactive"])]
+        package: Option<String>,
+        /// Upgrade all installed packages
+        #[arg(long, conflicts_with = "package")]
+        all: bool,
+        /// Choose which outdated packages to upgrade from a checklist
+        #[arg(short, long, conflicts_with_all = ["package", "all"])]
+        interactive: bool,
+        /// How many packages to download at once with --interactive
+        #[arg(short, long, requires = "interactive")]
+        jobs: Option<usize>,
+    },
+    /// Show a package's release notes
+    Changelog {
+        /// Package name
+        package: String,
+        /// Only show releases after this version; defaults to the installed one
+        #[arg(long, value_name = "VERSION")]
+        since: Option<String>,
+    },
+    /// Run a package's executable, installing it first if needed
+    #[command(alias = "exec")]
+    Run {
+        /// Package name, optionally with a version (name@version)
+        package: String,
+        /// Arguments passed to the executable
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Run what a version shim stands for; shims call this when the
+    /// `version_shims` setting is on
+    #[command(hide = true)]
+    ExecShim {
+        /// The name the shim was run as, passed on to the executable
+        #[arg(long, value_name = "NAME")]
+        arg0: Option<String>,
+        /// Package the shim belongs to
+        package: String,
+        /// File name of the executable to run
+        executable: String,
+        /// Arguments passed to the executable
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Link every installed package's shims again, e.g. after changing the
+    /// `version_shims` setting
+    Reshim,
+    /// Manage the downloaded asset cache
+    Cache {
+        #[command(subcommand)]
+        cmd: CacheCommands,
+    },
+    /// Show installed and outdated packages, the cache, registries, the current
+    /// project and problems at a glance; outdated packages come from cached
+    /// release lists unless --refresh is given
+    Status,
+    /// Diagnose broken installs, shims, PATH and registries
+    Doctor,
+    /// Print the path of a package's active executable
+    Which {
+        /// Package name
+        package: String,
+    },
+    /// Hold a package at its current version so `upgrade --all` skips it
+    #[command(alias = "hold")]
+    Pin {
+        /// Package name
+        package: String,
+    },
+    /// Allow a pinned package to be upgraded again
+    #[command(alias = "unhold")]
+    Unpin {
+        /// Package name
+        package: String,
+    },
+    /// Show how often and when each package was run, counted locally with
+    /// the `usage_stats` setting on
+    Stats {
+        /// Forget the runs counted so far
+        #[arg(long)]
+        reset: bool,
+    },
+    /// Show the log of installs, upgrades and uninstalls
+    History {
+        /// Only show this package's history
+        package: Option<String>,
+    },
+    /// Switch a package back to the version its most recent upgrade replaced
+    Rollback {
+        /// Package name
+        package: String,
+    },
+    /// Link a package's main executable under another name as well, e.g. `grip alias k kubectl`
+    Alias {
+        /// Name to link the executable as
+        name: String,
+        /// Installed package whose main executable the alias runs
+        package: String,
+    },
+    /// Remove an alias set up by `grip alias` or the registry
+    Unalias {
+        /// Alias to remove
+        name: String,
     },
     /// Initialize grip in current directory
-    Init,
+    Init {
+        /// Start from a template: a name from the registries, or a repository
+        /// URL, `file://` path or host/owner/repo with a grip-template.json
+        #[arg(long)]
+        template: Option<String>,
+        /// Replace an existing grip.json and .grip.toml
+        #[arg(long)]
+        force: bool,
+    },
+    /// Add a dependency to grip.json
+    Add {
+        /// Package to add, optionally as <package>@<version>
+        package: String,
+    },
     /// Manage registries
     Registry {
         #[command(subcommand)]
         cmd: RegistryCommands,
     },
-    /// List installed packages
-    List,
+    /// Submit a package definition to a registry by pull request, or push it
+    /// to a registry you can write to
+    Publish {
+        /// Package definition file, or - for stdin
+        #[arg(required_unless_present = "from", conflicts_with = "from")]
+        file: Option<PathBuf>,
+        /// Draft the definition from a GitHub repository's releases instead
+        #[arg(long, value_name = "REPOSITORY")]
+        from: Option<String>,
+        /// Registry to publish to
+        #[arg(short, long, default_value = "default")]
+        registry: String,
+        /// Push a commit to the registry instead of opening a pull request
+        #[arg(long)]
+        push: bool,
+    },
+    /// Print the installed packages and versions as a bundle for `grip import`
+    Export,
+    /// Install every package in a bundle written by `grip export`, or in
+    /// another package manager's export
+    Import {
+        /// Bundle or export file, or - for stdin
+        file: PathBuf,
+        /// Read a Brewfile, `scoop export` or `winget export` file instead of a
+        /// grip bundle; entries are installed at their latest release
+        #[arg(long, value_enum)]
+        from: Option<ImportFormat>,
+        /// How many packages to download at once
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
+    /// Choose where packages go, how they get on PATH, a GitHub token and
+    /// registries, as grip asks the first time it runs
+    Setup,
+    /// View and change settings in the config file
+    Config {
+        #[command(subcommand)]
+        cmd: ConfigCommands,
+    },
+    /// List installed packages with their size, install date and other versions
+    List {
+        /// Only list packages whose name contains this text
+        #[arg(short, long, value_name = "TEXT")]
+        filter: Option<String>,
+        /// Order to list packages in
+        #[arg(short, long, value_enum, default_value_t = ListSortKind::Name)]
+        sort: ListSortKind,
+        /// Print JSON, the same as --output json
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check installed files against the hashes recorded at install time
+    /// (exits with 1 if any changed)
+    Verify {
+        /// Only verify this package
+        package: Option<String>,
+        /// Reinstall packages whose files changed, from the cached asset when there is one
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Show the license of every installed package
+    Licenses,
+    /// Print a software bill of materials for the installed packages
+    Sbom {
+        /// Bill of materials format
+        #[arg(short, long, value_enum, default_value_t = SbomFormatKind::Cyclonedx)]
+        format: SbomFormatKind,
+    },
+    /// Delete stored files that no installed version uses any more
+    Gc,
+    /// Delete package directories and versions grip no longer records, as
+    /// interrupted installs leave behind, and unused stored files
+    Clean {
+        /// Also empty the download cache, and drop cached registry responses
+        /// and clones of registries that are no longer configured
+        #[arg(long)]
+        all: bool,
+    },
+    /// Uninstall every package and remove grip's PATH changes, download cache
+    /// and registry clones, keeping only the config and logs
+    Purge {
+        /// Don't ask for confirmation
+        #[arg(long)]
+        force: bool,
+    },
+    /// Delete installed versions other than the active one
+    Prune {
+        /// Only prune this package's versions
+        package: Option<String>,
+        /// Keep this many of the newest inactive versions
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        keep: usize,
+        /// Instead, suggest packages to uninstall that haven't been run for
+        /// this long, e.g. 90d; needs the `usage_stats` setting
+        #[arg(long, value_name = "DURATION", conflicts_with_all = ["package", "keep"])]
+        unused: Option<String>,
+    },
+    /// Show installed packages with their dependencies
+    Tree {
+        /// Only show this package's dependencies
+        package: Option<String>,
+    },
+    /// List installed packages with newer releases (exits with 1 if any are found)
+    Outdated,
+    /// Record the latest release of every installed package for the update
+    /// notice; normal commands start this in the background when
+    /// `update_check.interval` is set
+    #[command(hide = true)]
+    UpdateCheck,
+    /// Check installed versions for known vulnerabilities in the OSV database
+    /// (exits with 1 if any are found)
+    Audit,
+    /// Print a shell completion script
+    Completions {
+        /// Shell to generate completions for
+        shell: Shell,
+    },
+    /// Manage the PATH entries grip adds
+    Path {
+        #[command(subcommand)]
+        cmd: PathCommands,
+    },
+    /// Print the shell statements that put grip's packages on PATH, e.g. for
+    /// `eval "$(grip shellenv)"` in ~/.bashrc or `grip shellenv | source` in fish
+    Shellenv {
+        /// Shell to print statements for; detected from the environment by default
+        #[arg(long, value_enum)]
+        shell: Option<ShellKind>,
+    },
+    /// Print the shell statements that put the current project's `.grip/bin`
+    /// in front of PATH, taking off a project activated before; prints nothing
+    /// when there is nothing to change
+    Env {
+        /// Shell to print statements for; detected from the environment by default
+        #[arg(long, value_enum)]
+        shell: Option<ShellKind>,
+    },
+    /// Print code for a shell's startup file that runs `grip env` at every
+    /// prompt, e.g. `eval "$(grip hook bash)"` in ~/.bashrc
+    Hook {
+        /// Shell to print the hook for
+        #[arg(value_enum)]
+        shell: ShellKind,
+    },
+    /// Search packages across configured registries
+    Search {
+        /// Text to match against package names, tags and descriptions
+        query: String,
+        /// Maximum number of results to show
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Show package metadata, versions and release notes
+    Info {
+        /// Package name
+        package: String,
+        /// Number of versions to list
+        #[arg(long, default_value_t = 10)]
+        versions: usize,
+    },
+    /// Any other subcommand runs a `grip-<name>` executable from PATH
+    #[command(external_subcommand)]
+    External(Vec<String>),
 }
 
 #[derive(Debug, Subcommand)]
@@ -37,17 +481,112 @@ pub enum RegistryCommands {
     Add {
         /// Registry name
         name: String,
-        /// Registry URL (github.com/owner/repo)
+        /// Registry URL (github.com/owner/repo, file://<dir> or git+<url>)
         url: String,
-        /// Priority (higher numbers are checked first)
-        #[arg(short, long)]
+        /// Priority (higher numbers are checked first; ties go by name)
+        #[arg(short, long, allow_negative_numbers = true)]
         priority: Option<i32>,
+        /// Bearer token sent to the registry host
+        #[arg(long, conflicts_with_all = ["username", "token_command"])]
+        token: Option<String>,
+        /// Username for basic auth
+        #[arg(long, requires = "password", conflicts_with = "token_command")]
+        username: Option<String>,
+        /// Password for basic auth
+        #[arg(long, requires = "username")]
+        password: Option<String>,
+        /// Command that prints a bearer token, e.g. for SSO helpers
+        #[arg(long)]
+        token_command: Option<String>,
+        /// Minisign public key the registry's index is signed with, or a
+        /// minisign.pub file; every refresh is then verified against it
+        #[arg(long, value_name = "KEY")]
+        public_key: Option<String>,
     },
     /// Remove a registry
     Remove {
         /// Registry name
         name: String,
     },
+    /// Change the priority a registry's packages are looked up with
+    SetPriority {
+        /// Registry name
+        name: String,
+        /// New priority (higher numbers are checked first; ties go by name)
+        #[arg(allow_negative_numbers = true)]
+        priority: i32,
+    },
+    /// Rename a registry, keeping its clone and installed packages' records
+    Rename {
+        /// Current registry name
+        old: String,
+        /// New registry name
+        new: String,
+    },
+    /// Refresh the local copy of one or all registries
+    Update {
+        /// Registry name; every registry is refreshed when omitted
+        name: Option<String>,
+    },
     /// List configured registries
     List,
-}
\ No newline at end of file
+    /// Scaffold a new registry: layout, registry.json, schema and an example package
+    Create {
+        /// Directory to create
+        dir: PathBuf,
+        /// Registry name; defaults to the directory name
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Check a registry's package definitions before publishing it
+    Validate {
+        /// Registry directory
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Write index.json, the digest of every file in a registry, for its
+    /// maintainers to sign with minisign
+    Index {
+        /// Registry directory
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommands {
+    /// Print a setting, e.g. `network.retries`
+    Get {
+        /// Dotted setting key
+        key: String,
+    },
+    /// Change a setting; values are read as JSON when they parse as such
+    Set {
+        /// Dotted setting key
+        key: String,
+        /// New value, e.g. 8, true, null or a path
+        value: String,
+    },
+    /// List every setting except registries
+    List,
+    /// Open the config file in $VISUAL or $EDITOR, validating it before saving
+    Edit,
+    /// Show the config files and environment variables settings are read
+    /// from, lowest precedence first
+    Path,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommands {
+    /// List cached assets
+    List,
+    /// Remove every cached asset
+    Clean,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PathCommands {
+    /// Remove PATH entries for packages that are no longer installed, and
+    /// repeated entries, from shell startup files (the user PATH on Windows)
+    Prune,
+}
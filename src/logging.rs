@@ -0,0 +1,56 @@
+use std::path::Path;
+
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// How many days of debug logs to keep under `data_dir/logs`
+const KEPT_LOG_FILES: usize = 7;
+
+/// Sets up logging: stderr at the `-v` level (or whatever `GRIP_LOG` asks
+/// for), colored when `color` is set, plus a daily debug log under
+/// `data_dir/logs` that's always on so a failed run can be diagnosed after
+/// the fact
+pub fn init(verbosity: u8, color: bool, data_dir: Option<&Path>) {
+    let console_filter = match std::env::var("GRIP_LOG") {
+        Ok(directives) if !directives.is_empty() => EnvFilter::new(directives),
+        _ => {
+            let level = match verbosity {
+                0 => LevelFilter::OFF,
+                1 => LevelFilter::INFO,
+                2 => LevelFilter::DEBUG,
+                _ => LevelFilter::TRACE,
+            };
+            // Status messages are already on screen, so don't echo them
+            EnvFilter::new(format!("grip={level},grip_core={level},grip_core::ui=off"))
+        }
+    };
+    let console = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(color)
+        .with_filter(console_filter);
+
+    // Written synchronously so nothing is lost when grip exits with an error
+    let file = data_dir
+        .and_then(|dir| {
+            let logs = dir.join("logs");
+            std::fs::create_dir_all(&logs).ok()?;
+            RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix("grip")
+                .filename_suffix("log")
+                .max_log_files(KEPT_LOG_FILES)
+                .build(logs)
+                .ok()
+        })
+        .map(|appender| {
+            tracing_subscriber::fmt::layer()
+                .with_writer(appender)
+                .with_ansi(false)
+                .with_filter(EnvFilter::new("grip=debug,grip_core=debug"))
+        });
+
+    let _ = tracing_subscriber::registry().with(console).with(file).try_init();
+}
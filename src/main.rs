@@ -1,364 +1,426 @@
 mod cli;
-mod config;
-mod error;
-mod package;
-mod path;
-mod registry;
-mod utils;
+mod logging;
 
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs::rename;
-use std::path::PathBuf;
+use std::io::IsTerminal;
 
-use clap::Parser;
-use cli::{Cli, Commands, RegistryCommands};
+use clap::{CommandFactory, Parser};
+use cli::{
+    CacheCommands, Cli, ColorMode, Commands, ConfigCommands, ImportFormat, ListSortKind, OutputFormat, PathCommands,
+    ProgressFormat, RegistryCommands, SbomFormatKind, ShellKind,
+};
 use colored::Colorize;
-use config::Config;
-use dialoguer::Select;
-use error::Result;
-use registry::RegistryManager;
-use tokio::fs::{set_permissions, File};
+use grip_core::activation::{self, Activation};
+use grip_core::config::{Locations, RegistryAuth, Scope};
+use grip_core::foreign::ForeignFormat;
+use grip_core::path::Shell;
+use grip_core::sbom::SbomFormat;
+use grip_core::ui::ColorChoice;
+use grip_core::{bail, installed_name, project, ui, Grip, ListSort, Result};
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct InstalledPackage {
-    pub version: String,
-    pub install_path: PathBuf,
-    pub executable_path: Option<PathBuf>,
-    pub installed_versions: Vec<String>,
-}
-
-#[derive(Serialize, Deserialize, Debug, Default)]
-pub struct PackageState {
-    packages: HashMap<String, InstalledPackage>,
-}
-
-impl PackageState {
-    pub fn load(data_dir: &PathBuf) -> Result<Self> {
-        let state_file = data_dir.join("package_state.json");
-        if state_file.exists() {
-            let content = std::fs::read_to_string(state_file)?;
-            Ok(serde_json::from_str(&content)?)
-        } else {
-            Ok(Self::default())
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        tracing::error!(kind = e.kind(), "{}", e);
+        ui::emit(ui::Event::Error {
+            message: &e.to_string(),
+            kind: e.kind(),
+            exit_code: e.exit_code(),
+        });
+        if ui::json_output() {
+            let _ = ui::print_json(&serde_json::json!({
+                "error": e.to_string(),
+                "kind": e.kind(),
+                "exit_code": e.exit_code(),
+            }));
+        } else if !ui::progress_events() {
+            eprintln!("{} {}", "error:".red().bold(), e);
         }
+        std::process::exit(e.exit_code());
     }
+}
 
-    pub fn save(&self, data_dir: &PathBuf) -> Result<()> {
-        let state_file = data_dir.join("package_state.json");
-        let content = serde_json::to_string_pretty(self)?;
-        std::fs::write(state_file, content)?;
-        Ok(())
+async fn run(cli: Cli) -> Result<()> {
+    ui::set_json_output(cli.output == OutputFormat::Json || matches!(cli.command, Commands::List { json: true, .. }));
+    ui::set_quiet(cli.quiet);
+    ui::set_progress_events(cli.progress == ProgressFormat::Json);
+    ui::set_status_to_stderr(matches!(cli.command, Commands::Run { .. } | Commands::ExecShim { .. }));
+    let color = match cli.color {
+        ColorMode::Auto => ColorChoice::Auto,
+        ColorMode::Always => ColorChoice::Always,
+        ColorMode::Never => ColorChoice::Never,
+    };
+    ui::set_color(color);
+    let locations = Locations::resolve(cli.config.clone(), cli.data_dir.clone());
+    logging::init(
+        cli.verbose,
+        color.enabled(std::io::stderr().is_terminal()),
+        locations.as_ref().ok().map(|locations| locations.data_dir.as_path()),
+    );
+    tracing::debug!("grip {} starting", env!("CARGO_PKG_VERSION"));
+    let locations = locations?;
+    let non_interactive = cli.non_interactive
+        || ui::progress_events()
+        || !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal();
+    let offline = cli.offline
+        || std::env::var("GRIP_OFFLINE").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+    // Sync only makes sense for a project's install, so it implies --local
+    let scope = if cli.local || matches!(cli.command, Commands::Sync { .. }) {
+        Scope::Project
+    } else {
+        Scope::User
+    };
+    // Editing is how a config that no longer parses gets fixed, so it can't
+    // depend on loading one
+    if matches!(cli.command, Commands::Config { cmd: ConfigCommands::Edit }) {
+        return grip_core::config::edit(&locations.config_file, non_interactive);
     }
-
-    pub fn add_package(
-        &mut self,
-        name: String,
-        version: String,
-        install_path: PathBuf,
-        executable_path: Option<PathBuf>,
-    ) {
-        if let Some(existing_package) = self.packages.get_mut(&name) {
-            if !existing_package.installed_versions.contains(&version) {
-                existing_package.installed_versions.push(version.clone());
-            }
-            existing_package.version = version;
-            existing_package.install_path = install_path;
-            existing_package.executable_path = executable_path;
-        } else {
-            self.packages.insert(
-                name,
-                InstalledPackage {
-                    version: version.clone(),
-                    install_path,
-                    executable_path,
-                    installed_versions: vec![version],
-                },
-            );
+    // Hooks run these at every prompt, so they skip loading anything
+    match cli.command {
+        Commands::Env { shell } => return print_env(shell.map(shell_from_kind)),
+        Commands::Hook { shell } => {
+            print!("{}", activation::hook_script(shell_from_kind(shell)));
+            return Ok(());
         }
+        _ => {}
     }
-
-    pub fn remove_package(&mut self, name: &str) -> Option<InstalledPackage> {
-        self.packages.remove(name)
-    }
-
-    pub fn get_package(&self, name: &str) -> Option<&InstalledPackage> {
-        self.packages.get(name)
+    if cli.dry_run && !matches!(cli.command, Commands::Install { .. } | Commands::Upgrade { .. } | Commands::Uninstall { .. }) {
+        bail!(Usage, "--dry-run only applies to install, upgrade and uninstall");
     }
-
-    pub fn list_packages(&self) -> Vec<(&String, &InstalledPackage)> {
-        self.packages.iter().collect()
+    let first_run = !locations.config_file.exists();
+    if matches!(cli.command, Commands::Setup) {
+        if non_interactive {
+            bail!(Usage, "grip setup asks questions; use `grip config set` to change settings non-interactively");
+        }
+        return grip_core::setup::run(&locations);
     }
-}
-
-struct Grip {
-    config: Config,
-    registry_manager: RegistryManager,
-    package_state: PackageState,
-}
-
-impl Grip {
-    async fn new() -> Result<Self> {
-        let data_dir = dirs::data_local_dir()
-            .ok_or_else(|| anyhow::anyhow!("Failed to get local data directory"))?
-            .join("grip");
-
-        std::fs::create_dir_all(&data_dir)?;
-
-        let config = Config::load()?;
-        let registry_manager = RegistryManager::new(data_dir.clone());
-        let package_state = PackageState::load(&data_dir)?;
-
-        Ok(Self {
-            config,
-            registry_manager,
-            package_state,
-        })
+    if first_run && !non_interactive && !ui::json_output() && !cli.dry_run && !matches!(
+            cli.command,
+            Commands::Shellenv { .. } | Commands::Completions { .. } | Commands::ExecShim { .. }
+        ) {
+        grip_core::setup::run(&locations)?;
     }
-
-    async fn install(
-        &mut self,
-        package_name: &str,
-        version: Option<String>,
-        asset: Option<String>,
-    ) -> Result<()> {
-        println!("{} Looking up package {}", "→".blue(), package_name.cyan());
-
-        let package = self
-            .registry_manager
-            .find_package(&self.config.registries, package_name)
-            .await?;
-
-        println!(
-            "{} Found package in repository: {}",
-            "→".blue(),
-            package.info.repository.cyan()
-        );
-
-        let releases = self
-            .registry_manager
-            .get_releases(&package.info.repository)
-            .await?;
-
-        if releases.is_empty() {
-            anyhow::bail!("No releases found for package '{}'", package_name);
+    let mut grip = match Grip::new(non_interactive, cli.quiet, offline, cli.timeout, scope, &locations).await {
+        Ok(grip) => grip,
+        // A config or state file that doesn't parse is exactly what doctor is for
+        Err(e) if matches!(cli.command, Commands::Doctor) => {
+            ui::failure!("Failed to load grip's config or package state: {}", e);
+            println!(
+                "    {} fix or move aside the file named above; grip recreates it on the next run",
+                "fix:".yellow()
+            );
+            std::process::exit(1);
         }
+        Err(e) => return Err(e),
+    };
+    grip.no_hooks = cli.no_hooks;
+    grip.dry_run = cli.dry_run;
+    grip.set_refresh(cli.refresh);
+
+    // Commands whose output is read by other programs or shells, that
+    // already report updates, or that delete grip's data don't show the
+    // update notice
+    let update_notice = !ui::json_output()
+        && !cli.quiet
+        && std::io::stderr().is_terminal()
+        && !matches!(
+            cli.command,
+            Commands::Run { .. }
+                | Commands::ExecShim { .. }
+                | Commands::Shellenv { .. }
+                | Commands::Completions { .. }
+                | Commands::External(_)
+                | Commands::Outdated
+                | Commands::Status
+                | Commands::Upgrade { .. }
+                | Commands::UpdateCheck
+                | Commands::Purge { .. }
+        );
 
-        let release = match version {
-            Some(ref v) => releases
-                .iter()
-                .find(|r| r["tag_name"].as_str().unwrap_or("") == v)
-                .ok_or_else(|| anyhow::anyhow!("Version {} not found", v))?,
-            None => {
-                let versions: Vec<&str> = releases
-                    .iter()
-                    .filter_map(|r| r["tag_name"].as_str())
-                    .collect();
-
-                println!("{} Available versions:", "→".blue());
-                let selection = Select::new()
-                    .with_prompt("Select version")
-                    .items(&versions)
-                    .default(0)
-                    .interact()?;
-
-                &releases[selection]
+    match cli.command {
+        Commands::Install {
+            mut packages,
+            repo,
+            url,
+            file,
+            version,
+            asset,
+            interactive,
+            pre,
+            frozen,
+            jobs,
+            registry,
+            reinstall,
+        } => {
+            grip.reinstall = reinstall;
+            packages.extend(repo.map(|repo| format!("gh:{}", repo)));
+            match packages.as_slice() {
+                [] => grip.install_project(frozen).await?,
+                [package] => {
+                    let (package, spec_version) = project::parse_spec(package);
+                    let outcome = match version {
+                        Some(version) if url.is_some() || file.is_some() => {
+                            grip.install_direct(&package, &version, url, file).await?
+                        }
+                        version => {
+                            grip.install(
+                                &package,
+                                version.or(spec_version),
+                                asset,
+                                interactive,
+                                pre,
+                                registry.as_deref(),
+                            )
+                            .await?
+                        }
+                    };
+                    if ui::json_output() {
+                        let mut json = serde_json::to_value(&outcome)?;
+                        json["name"] = serde_json::json!(installed_name(&package));
+                        ui::print_json(&json)?;
+                    }
+                }
+                _ => {
+                    if version.is_some() || asset.is_some() || url.is_some() || file.is_some() {
+                        bail!(
+                            Usage,
+                            "--version, --asset, --url and --file take a single package; use name@version for several"
+                        );
+                    }
+                    let jobs = jobs.unwrap_or(grip.config.settings.jobs);
+                    let installed = grip
+                        .install_many(&packages, jobs, interactive, pre, registry.as_deref())
+                        .await?;
+                    if ui::json_output() {
+                        let json: Vec<_> = installed
+                            .iter()
+                            .map(|(name, outcome)| {
+                                let mut json = serde_json::to_value(outcome)?;
+                                json["name"] = serde_json::json!(name);
+                                Ok(json)
+                            })
+                            .collect::<Result<_>>()?;
+                        ui::print_json(&json)?;
+                    }
+                }
             }
-        };
-
-        let assets = release["assets"]
-            .as_array()
-            .ok_or_else(|| anyhow::anyhow!("No assets found in release"))?;
-
-        let asset_obj = match asset {
-            Some(ref a) => assets
-                .iter()
-                .find(|asset| asset["name"].as_str().unwrap_or("") == a)
-                .ok_or_else(|| anyhow::anyhow!("Asset {} not found", a))?,
-            None => {
-                let asset_names: Vec<&str> =
-                    assets.iter().filter_map(|a| a["name"].as_str()).collect();
-
-                println!("{} Available assets:", "→".blue());
-                let selection = Select::new()
-                    .with_prompt("Select asset")
-                    .items(&asset_names)
-                    .default(0)
-                    .interact()?;
-
-                &assets[selection]
+        }
+        Commands::Uninstall { package, force } => {
+            grip.uninstall(&package, force).await?;
+        }
+        Commands::Use { package, version } => {
+            grip.use_version(&package, &version).await?;
+        }
+        Commands::Run { package, args } => {
+            let code = grip.run(&package, &args).await?;
+            std::process::exit(code);
+        }
+        Commands::ExecShim { arg0, package, executable, args } => {
+            let code = grip.run_shim(&package, &executable, arg0.as_deref(), &args)?;
+            std::process::exit(code);
+        }
+        Commands::Reshim => grip.reshim()?,
+        Commands::Cache { cmd } => match cmd {
+            CacheCommands::List => grip.cache_list()?,
+            CacheCommands::Clean => grip.cache_clean()?,
+        },
+        Commands::Status => grip.status().await?,
+        Commands::Doctor => {
+            if !grip.doctor().await? {
+                std::process::exit(1);
             }
-        };
-
-        let download_url = asset_obj["browser_download_url"]
-            .as_str()
-            .ok_or_else(|| anyhow::anyhow!("Invalid download URL"))?;
-
-        let filename = asset_obj["name"]
-            .as_str()
-            .ok_or_else(|| anyhow::anyhow!("Invalid asset name"))?;
-
-        let target_dir = self
-            .registry_manager
-            .data_dir
-            .join("packages")
-            .join(package_name)
-            .join(release["tag_name"].as_str().unwrap_or("unknown"));
-
-        let mut downloaded_file = self
-            .registry_manager
-            .download_asset(download_url, filename, &target_dir)
-            .await?;
-
-        if filename.ends_with(".zip") || filename.ends_with(".tar.gz") || filename.ends_with(".tgz") {
-            println!("{} Extracting archive...", "→".blue());
-            utils::extract_archive(&downloaded_file, &target_dir);
-            println!("{} Extracted to {:?}", "✓".green(), target_dir);
-            std::fs::remove_file(downloaded_file)?;
-        } else {
-            if let Some(executable_name) = package.info.executable_name.clone() {
-                let mut new_pathbuf = downloaded_file.clone();
-                new_pathbuf.set_file_name(executable_name);
-                if let Some(extension) = downloaded_file.extension() {
-                    new_pathbuf.set_extension(extension);
-                }
-                rename(downloaded_file, new_pathbuf)?;
+        }
+        Commands::Which { package } => {
+            grip.which(&package)?;
+        }
+        Commands::Pin { package } => {
+            grip.pin(&package, true)?;
+        }
+        Commands::Unpin { package } => {
+            grip.pin(&package, false)?;
+        }
+        Commands::Stats { reset } => grip.stats(reset)?,
+        Commands::History { package } => grip.history(package.as_deref())?,
+        Commands::Rollback { package } => grip.rollback(&package).await?,
+        Commands::Alias { name, package } => grip.alias(&name, &package)?,
+        Commands::Unalias { name } => grip.unalias(&name)?,
+        Commands::Upgrade {
+            package,
+            all,
+            interactive,
+            jobs,
+        } => {
+            if interactive {
+                let jobs = jobs.unwrap_or(grip.config.settings.jobs);
+                grip.upgrade_interactive(jobs).await?;
+            } else {
+                grip.upgrade(package, all).await?;
             }
         }
-
-        path::add_to_path(&target_dir).await?;
-
-        let executable_path = if let Some(executable_name) = package.info.executable_name {
-            Some(target_dir.join(executable_name))
-        } else {
-            None
-        };
-
-        self.package_state.add_package(
-            package_name.to_string(),
-            release["tag_name"].as_str().unwrap_or("unknown").to_string(),
-            target_dir.clone(),
-            executable_path,
-        );
-
-        self.package_state.save(&self.registry_manager.data_dir)?;
-
-        println!("{} Installation complete!", "✓".green());
-        Ok(())
-    }
-
-    async fn handle_registry_command(&mut self, cmd: RegistryCommands) -> Result<()> {
-        match cmd {
+        Commands::Changelog { package, since } => grip.changelog(&package, since.as_deref()).await?,
+        Commands::Registry { cmd } => match cmd {
             RegistryCommands::Add {
                 name,
                 url,
                 priority,
+                token,
+                username,
+                password,
+                token_command,
+                public_key,
             } => {
-                if self.config.registries.iter().any(|r| r.name == name) {
-                    anyhow::bail!("Registry '{}' already exists", name);
-                }
-
-                self.config.registries.push(config::Registry {
-                    name: name.clone(),
-                    url: url.clone(),
-                    priority: priority.unwrap_or(0),
-                });
-
-                self.config.save()?;
-                println!("{} Added registry {} ({})", "✓".green(), name.cyan(), url);
+                let auth = match (token, username, password, token_command) {
+                    (Some(token), ..) => Some(RegistryAuth::Bearer { token }),
+                    (_, Some(username), Some(password), _) => {
+                        Some(RegistryAuth::Basic { username, password })
+                    }
+                    (.., Some(command)) => Some(RegistryAuth::Command { command }),
+                    _ => None,
+                };
+                grip.registry_add(&name, &url, priority.unwrap_or(0), auth, public_key.as_deref())?;
             }
-            RegistryCommands::Remove { name } => {
-                if name == "default" {
-                    anyhow::bail!("Cannot remove default registry");
-                }
-
-                let original_len = self.config.registries.len();
-                self.config.registries.retain(|r| r.name != name);
-
-                if self.config.registries.len() == original_len {
-                    anyhow::bail!("Registry '{}' not found", name);
-                }
-
-                self.config.save()?;
-
-                let registry_path = self
-                    .registry_manager
-                    .data_dir
-                    .join("registries")
-                    .join(&name);
-                if registry_path.exists() {
-                    std::fs::remove_dir_all(registry_path)?;
-                }
-
-                println!("{} Removed registry {}", "✓".green(), name.cyan());
+            RegistryCommands::Remove { name } => grip.registry_remove(&name)?,
+            RegistryCommands::SetPriority { name, priority } => grip.registry_set_priority(&name, priority)?,
+            RegistryCommands::Rename { old, new } => grip.registry_rename(&old, &new)?,
+            RegistryCommands::Update { name } => grip.registry_update(name.as_deref()).await?,
+            RegistryCommands::List => grip.registry_list()?,
+            RegistryCommands::Create { dir, name } => grip.registry_create(&dir, name.as_deref())?,
+            RegistryCommands::Validate { dir } => grip.registry_validate(&dir)?,
+            RegistryCommands::Index { dir } => grip.registry_index(&dir)?,
+        },
+        Commands::Publish {
+            file,
+            from,
+            registry,
+            push,
+        } => {
+            grip.publish(file.as_deref(), from.as_deref(), &registry, push)
+                .await?;
+        }
+        Commands::Export => grip.export()?,
+        Commands::Import { file, from, jobs } => {
+            let jobs = jobs.unwrap_or(grip.config.settings.jobs);
+            let format = from.map(|from| match from {
+                ImportFormat::Brewfile => ForeignFormat::Brewfile,
+                ImportFormat::Scoop => ForeignFormat::Scoop,
+                ImportFormat::Winget => ForeignFormat::Winget,
+            });
+            match format {
+                Some(format) => grip.import_foreign(format, &file, jobs).await?,
+                None => grip.import(&file, jobs).await?,
             }
-            RegistryCommands::List => {
-                println!("{} Configured registries:", "→".blue());
-                for registry in &self.config.registries {
-                    println!(
-                        "  {} {} (priority: {}, url: {})",
-                        "→".blue(),
-                        registry.name.cyan(),
-                        registry.priority,
-                        registry.url
-                    );
-                }
+        }
+        Commands::Config { cmd } => match cmd {
+            ConfigCommands::Get { key } => grip.config_get(&key)?,
+            ConfigCommands::Set { key, value } => grip.config_set(&key, &value)?,
+            ConfigCommands::List => grip.config_list()?,
+            ConfigCommands::Path => grip.config_path()?,
+            ConfigCommands::Edit => unreachable!("handled before loading the config"),
+        },
+        Commands::Init { template, force } => {
+            grip.init(template.as_deref(), force).await?;
+        }
+        Commands::Add { package } => {
+            grip.add(&package).await?;
+        }
+        Commands::List { filter, sort, .. } => {
+            let sort = match sort {
+                ListSortKind::Name => ListSort::Name,
+                ListSortKind::Size => ListSort::Size,
+                ListSortKind::Installed => ListSort::Installed,
+            };
+            grip.list_packages(filter.as_deref(), sort).await?;
+        }
+        Commands::Verify { package, repair } => {
+            if !grip.verify(package.as_deref(), repair).await? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Licenses => grip.licenses().await?,
+        Commands::Sbom { format } => grip.sbom(match format {
+            SbomFormatKind::Cyclonedx => SbomFormat::CycloneDx,
+            SbomFormatKind::Spdx => SbomFormat::Spdx,
+        })?,
+        Commands::Prune { unused: Some(period), .. } => grip.suggest_unused(&period)?,
+        Commands::Prune { package, keep, .. } => grip.prune(package.as_deref(), keep)?,
+        Commands::Gc => grip.gc()?,
+        Commands::Clean { all } => grip.clean(all)?,
+        Commands::Purge { force } => grip.purge(force).await?,
+        Commands::Tree { package } => grip.tree(package.as_deref())?,
+        Commands::UpdateCheck => grip.check_for_updates().await?,
+        Commands::Sync { check } => {
+            if grip.sync(check).await? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Outdated => {
+            if grip.outdated().await? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Audit => {
+            if grip.audit().await? {
+                std::process::exit(1);
             }
         }
-        Ok(())
+        Commands::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "grip", &mut std::io::stdout());
+        }
+        Commands::Path { cmd } => match cmd {
+            PathCommands::Prune => grip.path_prune()?,
+        },
+        Commands::Shellenv { shell } => {
+            grip.shellenv(shell.map(shell_from_kind))?;
+        }
+        Commands::Env { .. } | Commands::Hook { .. } | Commands::Setup => {
+            unreachable!("handled before loading the config")
+        }
+        Commands::Search { query, limit } => {
+            grip.search(&query, limit).await?;
+        }
+        Commands::Info { package, versions } => {
+            grip.info(&package, versions).await?;
+        }
+        Commands::External(args) => {
+            let (name, args) = args.split_first().expect("clap passes the subcommand name");
+            let code = grip.run_plugin(name, args)?;
+            std::process::exit(code);
+        }
     }
 
-    async fn init(&self) -> Result<()> {
-        let config = serde_json::json!({
-            "name": "grip-project",
-            "version": "0.1.0",
-            "dependencies": {}
-        });
-
-        std::fs::write("grip.json", serde_json::to_string_pretty(&config)?)?;
-
-        println!("{} Created grip.json", "✓".green());
-        Ok(())
+    if cli.timings {
+        grip.timings.print();
     }
-
-    async fn list_packages(&self) -> Result<()> {
-        println!("{} Installed packages:", "→".blue());
-        for (name, package) in self.package_state.list_packages() {
-            println!(
-                "  {} {} (current: {}, all versions: {})",
-                "→".blue(),
-                name.cyan(),
-                package.version,
-                package.installed_versions.join(", ")
-            );
-        }
-        Ok(())
+    if let Some(file) = &cli.timings_file {
+        grip.timings.save(file)?;
+    }
+    if update_notice {
+        grip.update_notice();
     }
+    Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let mut grip = Grip::new().await?;
-
-    match cli.command {
-        Commands::Install {
-            package,
-            version,
-            asset,
-        } => {
-            grip.install(&package, version, asset).await?;
-        }
-        Commands::Registry { cmd } => {
-            grip.handle_registry_command(cmd).await?;
-        }
-        Commands::Init => {
-            grip.init().await?;
-        }
-        Commands::List => {
-            grip.list_packages().await?;
-        }
+fn shell_from_kind(kind: ShellKind) -> Shell {
+    match kind {
+        ShellKind::Bash => Shell::Bash,
+        ShellKind::Zsh => Shell::Zsh,
+        ShellKind::Fish => Shell::Fish,
+        ShellKind::Powershell => Shell::PowerShell,
+        ShellKind::Nu => Shell::Nu,
     }
+}
 
+fn print_env(shell: Option<Shell>) -> Result<()> {
+    let activation = Activation::from_env()?;
+    if ui::json_output() {
+        return ui::print_json(&activation.to_json());
+    }
+    let Some(shell) = shell.or_else(Shell::detect) else {
+        bail!(Usage, "Couldn't tell which shell you use; pass --shell");
+    };
+    if shell != Shell::Nu && activation.is_current() {
+        return Ok(());
+    }
+    print!("{}", activation.script(shell));
     Ok(())
-}
\ No newline at end of file
+}
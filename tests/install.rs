@@ -0,0 +1,183 @@
+//! Installs, upgrades and uninstalls against a mock registry and GitHub API
+#![cfg(unix)]
+
+use grip_testing::{platform_asset_name, script, Asset, Package, Release, TestEnv};
+use std::os::unix::fs::PermissionsExt;
+
+fn env() -> TestEnv {
+    TestEnv::new(env!("CARGO_BIN_EXE_grip"))
+}
+
+/// A release of `tool` with an archive for this machine holding an executable
+/// that prints its version
+fn release(version: &str) -> Release {
+    let archive = platform_asset_name("tool", version, "tar.gz");
+    let executable = script(&format!("tool {}", version));
+    Release::new(&format!("v{}", version)).asset(Asset::tar_gz(&archive, &[("tool", &executable)]))
+}
+
+#[test]
+fn install_links_the_executable_and_records_the_package() {
+    let env = env();
+    env.publish(&Package::new("tool").release(release("1.0.0")));
+
+    env.grip(["install", "tool"]).success();
+
+    let shim = env.bin_dir().join("tool");
+    assert_eq!(env.run(&shim, &[]).success().stdout.trim(), "tool 1.0.0");
+    let listed = env.grip(["--output", "json", "list"]).success().json();
+    assert_eq!(listed[0]["name"], "tool");
+    assert_eq!(listed[0]["version"], "v1.0.0");
+    assert_eq!(env.server().hits("/repos/mock/tool/releases"), 1);
+    assert!(env.server().hits("/git/registry/info/refs") > 0, "the registry wasn't cloned from the server");
+}
+
+#[test]
+fn install_picks_the_asset_for_this_platform() {
+    let env = env();
+    let other = if grip_core::utils::get_platform() == "linux" { "windows" } else { "linux" };
+    let foreign = Asset::zip(&format!("tool-1.0.0-x86_64-{}.zip", other), &[("tool.exe", b"MZ")]);
+    env.publish(&Package::new("tool").release(release("1.0.0").asset(foreign)));
+
+    env.grip(["install", "tool"]).success();
+
+    let native = platform_asset_name("tool", "1.0.0", "tar.gz");
+    assert_eq!(env.server().hits(&format!("/download/mock/tool/v1.0.0/{}", native)), 1);
+    assert!(env
+        .server()
+        .requests()
+        .iter()
+        .all(|request| !request.contains(&format!("-{}.zip", other))));
+}
+
+#[test]
+fn install_refuses_an_asset_whose_checksum_does_not_match() {
+    let env = env();
+    let archive = platform_asset_name("tool", "1.0.0", "tar.gz");
+    let tampered = Asset::tar_gz(&archive, &[("tool", &script("tool"))]).published_sha256(&"0".repeat(64));
+    env.publish(&Package::new("tool").release(Release::new("v1.0.0").asset(tampered)));
+
+    env.grip(["install", "tool"]).failure(11);
+
+    assert!(!env.bin_dir().join("tool").exists());
+}
+
+#[test]
+fn upgrade_switches_to_the_newest_release() {
+    let env = env();
+    env.publish(&Package::new("tool").release(release("1.0.0")));
+    env.grip(["install", "tool"]).success();
+
+    env.publish(&Package::new("tool").release(release("1.0.0")).release(release("1.1.0")));
+    env.grip(["upgrade", "tool"]).success();
+
+    let shim = env.bin_dir().join("tool");
+    assert_eq!(env.run(&shim, &[]).success().stdout.trim(), "tool 1.1.0");
+    let history = env.grip(["--output", "json", "history", "tool"]).success().json();
+    assert_eq!(history[1]["action"], "upgrade");
+    assert_eq!(history[1]["from"], "v1.0.0");
+    assert_eq!(history[1]["to"], "v1.1.0");
+}
+
+#[test]
+fn uninstall_removes_the_shim_and_the_package() {
+    let env = env();
+    env.publish(&Package::new("tool").release(release("1.0.0")));
+    env.grip(["install", "tool"]).success();
+
+    env.grip(["uninstall", "tool"]).success();
+
+    assert!(!env.bin_dir().join("tool").exists());
+    assert!(!env.data_dir().join("packages").join("tool").exists());
+    assert_eq!(env.grip(["--output", "json", "list"]).success().json(), serde_json::json!([]));
+}
+
+#[test]
+fn install_resolves_registry_dependencies_first() {
+    let env = env();
+    let lib = |version: &str| {
+        let archive = platform_asset_name("lib", version, "tar.gz");
+        Release::new(&format!("v{}", version)).asset(Asset::tar_gz(&archive, &[("lib", &script("lib"))]))
+    };
+    env.publish(&Package::new("lib").release(lib("2.0.0")));
+    env.publish(
+        &Package::new("tool")
+            .release(release("1.0.0"))
+            .field("dependencies", serde_json::json!(["lib >=2"])),
+    );
+
+    env.grip(["install", "tool"]).success();
+
+    assert!(env.bin_dir().join("lib").exists());
+    assert!(env.bin_dir().join("tool").exists());
+}
+
+#[test]
+fn install_refuses_executable_names_that_leave_the_package() {
+    let env = env();
+    let bare = Asset::new(&platform_asset_name("tool", "1.0.0", "bin"), script("tool"));
+    for name in ["../../escaped", "/tmp/escaped", ".."] {
+        env.publish(
+            &Package::new("tool")
+                .release(Release::new("v1.0.0").asset(bare.clone()))
+                .field("executable_name", serde_json::json!(name)),
+        );
+
+        env.grip(["install", "tool"]).failure(7);
+
+        assert!(!env.data_dir().join("escaped").exists());
+        assert!(!env.data_dir().join("packages").join("escaped").exists());
+        assert_eq!(env.grip(["--output", "json", "list"]).success().json(), serde_json::json!([]));
+    }
+}
+
+#[test]
+fn sqlite_state_backend_takes_over_the_json_state() {
+    let env = env();
+    env.publish(&Package::new("tool").release(release("1.0.0")));
+    env.grip(["install", "tool"]).success();
+
+    env.set_config("settings.state_backend", serde_json::json!("sqlite"));
+    env.publish(&Package::new("tool").release(release("1.0.0")).release(release("1.1.0")));
+    env.grip(["upgrade", "tool"]).success();
+
+    assert!(env.data_dir().join("state.db").is_file());
+    let listed = env.grip(["--output", "json", "list", "--filter", "TO"]).success().json();
+    assert_eq!(listed[0]["version"], "v1.1.0");
+    let which = env.grip(["--output", "json", "which", "tool"]).success().json();
+    assert!(which["executable"].as_str().unwrap().contains("v1.1.0"));
+    let history = env.grip(["--output", "json", "history", "tool"]).success().json();
+    assert_eq!(history[0]["action"], "install");
+    assert_eq!(history[1]["to"], "v1.1.0");
+    env.grip(["verify", "tool"]).success();
+
+    // Plugins still get the state as JSON
+    let plugin = env.bin_dir().join("grip-state");
+    std::fs::write(&plugin, "#!/bin/sh\ncat \"$GRIP_STATE\"\n").unwrap();
+    std::fs::set_permissions(&plugin, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let exported: serde_json::Value = serde_json::from_str(&env.grip(["state"]).success().stdout).unwrap();
+    assert_eq!(exported["packages"]["tool"]["version"], "v1.1.0");
+}
+
+#[test]
+fn json_state_backend_takes_the_state_back_from_sqlite() {
+    let env = env();
+    env.set_config("settings.state_backend", serde_json::json!("sqlite"));
+    env.publish(&Package::new("tool").release(release("1.0.0")).release(release("1.1.0")));
+    env.grip(["install", "tool", "--version", "v1.0.0"]).success();
+    env.grip(["upgrade", "tool"]).success();
+
+    env.set_config("settings.state_backend", serde_json::json!("json"));
+    let listed = env.grip(["--output", "json", "list"]).success().json();
+
+    assert_eq!(listed[0]["version"], "v1.1.0");
+    assert!(!env.data_dir().join("state.db").exists());
+    assert!(env.data_dir().join("state.db.bak").is_file());
+    let history = env.grip(["--output", "json", "history", "tool"]).success().json();
+    assert_eq!(history[1]["to"], "v1.1.0");
+
+    // Changes made since carry over on the next switch
+    env.grip(["uninstall", "tool"]).success();
+    env.set_config("settings.state_backend", serde_json::json!("sqlite"));
+    assert_eq!(env.grip(["--output", "json", "list"]).success().json(), serde_json::json!([]));
+}